@@ -209,7 +209,12 @@ async function run() {
   const scriptsDir = resolveScriptsDir(installDir);
   const pythonCmd = detectPython();
   ensurePythonDeps(pythonCmd, scriptsDir);
-  const env = { ...process.env, WATERMARK_SCRIPTS_DIR: scriptsDir };
+  // Run the scripts with the interpreter the dependencies were installed into
+  const env = {
+    ...process.env,
+    WATERMARK_SCRIPTS_DIR: scriptsDir,
+    WATERMARK_PYTHON: process.env.WATERMARK_PYTHON || pythonCmd,
+  };
   const child = spawn(binPath, [], { stdio: "inherit", env });
 
   child.on("exit", (code, signal) => {
@@ -93,10 +93,9 @@ if (-not $env:WATERMARK_SCRIPTS_DIR) {
         $env:WATERMARK_SCRIPTS_DIR = $repoScripts
     } else {
         $cachedScripts = Join-Path (Split-Path -Parent $BinPath) "../scripts"
+        # Without either, the binary falls back to its embedded copies
         if (Test-Path $cachedScripts) {
             $env:WATERMARK_SCRIPTS_DIR = $cachedScripts
-        } else {
-            throw "Cannot find scripts directory. Set WATERMARK_SCRIPTS_DIR explicitly."
         }
     }
 }
@@ -145,11 +145,9 @@ elif [[ -d "${REPO_DIR}/scripts" ]]; then
   export WATERMARK_SCRIPTS_DIR="${REPO_DIR}/scripts"
 else
   BIN_DIR="$(cd "$(dirname "${BIN_PATH}")" && pwd)"
+  # Without either, the binary falls back to its embedded copies
   if [[ -d "${BIN_DIR}/../scripts" ]]; then
     export WATERMARK_SCRIPTS_DIR="${BIN_DIR}/../scripts"
-  else
-    echo "Cannot find scripts directory. Set WATERMARK_SCRIPTS_DIR explicitly." >&2
-    exit 1
   fi
 fi
 
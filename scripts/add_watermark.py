@@ -0,0 +1,177 @@
+#!/usr/bin/env python3
+"""
+Add Watermark - Stamp a text or logo watermark onto images or PDFs (test fixtures)
+Usage: python add_watermark.py (--image <path> | --dir <path> | --pdf <path>) --output <path>
+                               [--text <text>] [--logo <path>] [--position <pos>]
+                               [--opacity <0-1>] [--scale <fraction>] [--dpi <dpi>]
+"""
+
+import sys
+import os
+import argparse
+from pathlib import Path
+
+POSITIONS = ['bottom_right', 'bottom_left', 'top_right', 'top_left', 'center']
+
+def stamp(image, text, logo_path, position, opacity, scale):
+    """Return a copy of a PIL image with the watermark composited on top."""
+    from PIL import Image, ImageDraw, ImageFont
+
+    base = image.convert("RGBA")
+    width, height = base.size
+    layer = Image.new("RGBA", base.size, (0, 0, 0, 0))
+    alpha = int(255 * opacity)
+
+    if logo_path:
+        logo = Image.open(logo_path).convert("RGBA")
+        target_w = max(1, int(width * scale * 5))
+        target_h = max(1, int(logo.height * target_w / logo.width))
+        logo = logo.resize((target_w, target_h))
+        logo_alpha = logo.getchannel("A").point(lambda a: a * opacity)
+        logo.putalpha(logo_alpha)
+        mark_w, mark_h = logo.size
+    else:
+        font_size = max(8, int(height * scale))
+        try:
+            font = ImageFont.truetype("DejaVuSans.ttf", font_size)
+        except OSError:
+            try:
+                font = ImageFont.load_default(size=font_size)
+            except TypeError:
+                font = ImageFont.load_default()
+        draw = ImageDraw.Draw(layer)
+        left, top, right, bottom = draw.textbbox((0, 0), text, font=font)
+        mark_w, mark_h = right - left, bottom - top
+
+    margin = int(min(width, height) * 0.02)
+    if position == 'bottom_right':
+        x, y = width - mark_w - margin, height - mark_h - margin
+    elif position == 'bottom_left':
+        x, y = margin, height - mark_h - margin
+    elif position == 'top_right':
+        x, y = width - mark_w - margin, margin
+    elif position == 'top_left':
+        x, y = margin, margin
+    else:
+        x, y = (width - mark_w) // 2, (height - mark_h) // 2
+
+    if logo_path:
+        layer.paste(logo, (x, y), logo)
+    else:
+        draw.text((x - left, y - top), text, font=font, fill=(160, 160, 160, alpha))
+
+    return Image.alpha_composite(base, layer).convert("RGB")
+
+def stamp_file(input_path, output_path, args):
+    from PIL import Image
+
+    with Image.open(input_path) as image:
+        result = stamp(image, args.text, args.logo, args.position, args.opacity, args.scale)
+    result.save(output_path)
+
+def main():
+    parser = argparse.ArgumentParser(description='Add watermarks to images or PDFs')
+    parser.add_argument('--image', help='Single image path')
+    parser.add_argument('--dir', help='Directory containing images')
+    parser.add_argument('--pdf', help='PDF file path')
+    parser.add_argument('--output', required=True, help='Output image/PDF path or directory')
+    parser.add_argument('--text', default='NotebookLM', help='Watermark text')
+    parser.add_argument('--logo', help='Logo image to stamp instead of text')
+    parser.add_argument('--position', default='bottom_right', choices=POSITIONS)
+    parser.add_argument('--opacity', type=float, default=0.6)
+    parser.add_argument('--scale', type=float, default=0.025, help='Text height as a fraction of image height')
+    parser.add_argument('--dpi', type=int, default=200, help='Render DPI for PDF input')
+
+    args = parser.parse_args()
+
+    if not (args.image or args.dir or args.pdf):
+        print("Error: One of --image, --dir or --pdf must be provided", file=sys.stderr)
+        sys.exit(1)
+
+    try:
+        from PIL import Image
+    except ImportError:
+        print("Error: Pillow not installed. Run: pip install Pillow", file=sys.stderr)
+        sys.exit(1)
+
+    if args.logo and not os.path.exists(args.logo):
+        print(f"Error: Logo not found: {args.logo}", file=sys.stderr)
+        sys.exit(1)
+
+    outputs = []
+
+    if args.image:
+        if not os.path.exists(args.image):
+            print(f"Error: Image not found: {args.image}", file=sys.stderr)
+            sys.exit(1)
+        output_dir = os.path.dirname(args.output)
+        if output_dir:
+            Path(output_dir).mkdir(parents=True, exist_ok=True)
+        stamp_file(args.image, args.output, args)
+        outputs.append(args.output)
+        print(f"  ✓ Watermarked: {args.output}")
+
+    elif args.dir:
+        if not os.path.isdir(args.dir):
+            print(f"Error: Directory not found: {args.dir}", file=sys.stderr)
+            sys.exit(1)
+        Path(args.output).mkdir(parents=True, exist_ok=True)
+        image_extensions = {'.png', '.jpg', '.jpeg', '.webp'}
+        image_files = sorted([
+            f for f in os.listdir(args.dir)
+            if os.path.isfile(os.path.join(args.dir, f))
+            and Path(f).suffix.lower() in image_extensions
+        ])
+        print(f"Found {len(image_files)} images in {args.dir}")
+        for image_file in image_files:
+            output_path = os.path.join(args.output, image_file)
+            stamp_file(os.path.join(args.dir, image_file), output_path, args)
+            outputs.append(output_path)
+            print(f"  ✓ Watermarked: {image_file}")
+
+    else:
+        if not os.path.exists(args.pdf):
+            print(f"Error: PDF file not found: {args.pdf}", file=sys.stderr)
+            sys.exit(1)
+        try:
+            from rasterize import render_pages
+            import img2pdf
+        except ImportError as e:
+            print(f"Error: Missing dependency: {e}", file=sys.stderr)
+            print("Run: pip install pdf2image img2pdf", file=sys.stderr)
+            sys.exit(1)
+
+        import io
+        try:
+            images = render_pages(args.pdf, dpi=args.dpi)
+        except Exception as e:
+            print(f"Error converting PDF: {e}", file=sys.stderr)
+            print("Note: Make sure poppler or Ghostscript is installed (brew install poppler)", file=sys.stderr)
+            sys.exit(1)
+
+        pages = []
+        for i, image in enumerate(images):
+            buffer = io.BytesIO()
+            stamp(image, args.text, args.logo, args.position, args.opacity, args.scale).save(buffer, "PNG")
+            pages.append(buffer.getvalue())
+            print(f"  ✓ Watermarked page {i+1}")
+
+        output_dir = os.path.dirname(args.output)
+        if output_dir:
+            Path(output_dir).mkdir(parents=True, exist_ok=True)
+        with open(args.output, "wb") as f:
+            f.write(img2pdf.convert(pages))
+        outputs.append(args.output)
+
+    print(f"\nComplete! Watermarked: {len(outputs)}")
+
+    # Output JSON for easy parsing
+    import json
+    result = {
+        "outputs": outputs,
+        "count": len(outputs)
+    }
+    print(f"JSON_RESULT:{json.dumps(result)}")
+
+if __name__ == "__main__":
+    main()
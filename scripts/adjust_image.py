@@ -0,0 +1,177 @@
+#!/usr/bin/env python3
+"""
+Adjust Image - Deskew, rotate and crop scanned page images
+Usage: python adjust_image.py (--image <path> | --dir <path>) [--output <dir>]
+                              [--deskew] [--rotate <degrees>] [--crop x,y,w,h] [--auto-crop]
+"""
+
+import sys
+import os
+import argparse
+from pathlib import Path
+
+def estimate_skew(img):
+    """Estimate the page skew angle in degrees from near-horizontal Hough lines."""
+    import cv2
+    import numpy as np
+
+    gray = cv2.cvtColor(img, cv2.COLOR_BGR2GRAY)
+    edges = cv2.Canny(gray, 50, 150, apertureSize=3)
+    min_length = max(20, img.shape[1] // 8)
+    lines = cv2.HoughLinesP(edges, 1, np.pi / 1800, threshold=100,
+                            minLineLength=min_length, maxLineGap=20)
+    if lines is None:
+        return 0.0
+
+    angles = []
+    for x1, y1, x2, y2 in lines[:, 0]:
+        angle = np.degrees(np.arctan2(y2 - y1, x2 - x1))
+        # Only consider text baselines / rules, not vertical strokes
+        if abs(angle) < 15:
+            angles.append(angle)
+
+    if not angles:
+        return 0.0
+    return float(np.median(angles))
+
+def rotate(img, angle):
+    """Rotate around the centre, expanding the canvas and filling with white."""
+    import cv2
+    import numpy as np
+
+    if abs(angle) < 0.01:
+        return img
+    height, width = img.shape[:2]
+    center = (width / 2, height / 2)
+    matrix = cv2.getRotationMatrix2D(center, angle, 1.0)
+    cos, sin = abs(matrix[0, 0]), abs(matrix[0, 1])
+    new_w = int(height * sin + width * cos)
+    new_h = int(height * cos + width * sin)
+    matrix[0, 2] += new_w / 2 - center[0]
+    matrix[1, 2] += new_h / 2 - center[1]
+    return cv2.warpAffine(img, matrix, (new_w, new_h), flags=cv2.INTER_CUBIC,
+                          borderMode=cv2.BORDER_CONSTANT, borderValue=(255, 255, 255))
+
+def auto_crop(img, padding=10):
+    """Trim uniform light margins around the page content."""
+    import cv2
+
+    gray = cv2.cvtColor(img, cv2.COLOR_BGR2GRAY)
+    _, content = cv2.threshold(gray, 240, 255, cv2.THRESH_BINARY_INV)
+    coords = cv2.findNonZero(content)
+    if coords is None:
+        return img
+    x, y, w, h = cv2.boundingRect(coords)
+    height, width = img.shape[:2]
+    x0, y0 = max(0, x - padding), max(0, y - padding)
+    x1, y1 = min(width, x + w + padding), min(height, y + h + padding)
+    return img[y0:y1, x0:x1]
+
+def adjust(image_path, output_path, args):
+    """Apply the requested adjustments to one image. Returns a summary dict."""
+    import cv2
+
+    img = cv2.imread(image_path)
+    if img is None:
+        print(f"  Error: Cannot read image: {image_path}", file=sys.stderr)
+        return None
+
+    summary = {"input": image_path, "output": output_path}
+
+    if args.deskew:
+        skew = estimate_skew(img)
+        img = rotate(img, skew)
+        summary["skew_corrected"] = round(skew, 3)
+
+    if args.rotate:
+        img = rotate(img, -args.rotate)
+        summary["rotated"] = args.rotate
+
+    if args.crop:
+        x, y, w, h = args.crop
+        img = img[y:y + h, x:x + w]
+        summary["cropped"] = [x, y, w, h]
+
+    if args.auto_crop:
+        img = auto_crop(img)
+        summary["auto_cropped"] = True
+
+    cv2.imwrite(output_path, img)
+    summary["width"] = img.shape[1]
+    summary["height"] = img.shape[0]
+    return summary
+
+def parse_crop(value):
+    parts = [int(p) for p in value.split(',')]
+    if len(parts) != 4:
+        raise argparse.ArgumentTypeError("crop must be x,y,width,height")
+    return parts
+
+def main():
+    parser = argparse.ArgumentParser(description='Deskew, rotate and crop images')
+    parser.add_argument('--image', help='Single image path')
+    parser.add_argument('--dir', help='Directory containing images')
+    parser.add_argument('--output', help='Output directory (optional)')
+    parser.add_argument('--deskew', action='store_true', help='Automatically straighten skewed scans')
+    parser.add_argument('--rotate', type=float, default=0.0, help='Rotate clockwise by degrees')
+    parser.add_argument('--crop', type=parse_crop, help='Crop rectangle x,y,width,height')
+    parser.add_argument('--auto-crop', action='store_true', help='Trim light page margins')
+
+    args = parser.parse_args()
+
+    if not args.image and not args.dir:
+        print("Error: Either --image or --dir must be provided", file=sys.stderr)
+        sys.exit(1)
+
+    # Import OpenCV here to provide better error messages
+    try:
+        import cv2
+        import numpy as np
+    except ImportError:
+        print("Error: opencv-python not installed. Run: pip install opencv-python-headless numpy", file=sys.stderr)
+        sys.exit(1)
+
+    if args.image:
+        if not os.path.exists(args.image):
+            print(f"Error: Image not found: {args.image}", file=sys.stderr)
+            sys.exit(1)
+        inputs = [args.image]
+        output_dir = args.output or os.path.dirname(args.image)
+    else:
+        if not os.path.isdir(args.dir):
+            print(f"Error: Directory not found: {args.dir}", file=sys.stderr)
+            sys.exit(1)
+        image_extensions = {'.png', '.jpg', '.jpeg', '.webp', '.tif', '.tiff'}
+        inputs = sorted([
+            os.path.join(args.dir, f) for f in os.listdir(args.dir)
+            if os.path.isfile(os.path.join(args.dir, f))
+            and Path(f).suffix.lower() in image_extensions
+        ])
+        output_dir = args.output or args.dir
+        print(f"Found {len(inputs)} images in {args.dir}")
+
+    Path(output_dir).mkdir(parents=True, exist_ok=True)
+
+    results = []
+    for input_path in inputs:
+        output_path = os.path.join(output_dir, os.path.basename(input_path))
+        summary = adjust(input_path, output_path, args)
+        if summary is None:
+            continue
+        results.append(summary)
+        details = ", ".join(f"{k}={v}" for k, v in summary.items() if k not in ("input", "output"))
+        print(f"  ✓ {os.path.basename(input_path)}: {details}")
+
+    print(f"\nComplete! Adjusted: {len(results)}")
+
+    # Output JSON for easy parsing
+    import json
+    result = {
+        "output_dir": output_dir,
+        "adjusted": results,
+        "count": len(results)
+    }
+    print(f"JSON_RESULT:{json.dumps(result)}")
+
+if __name__ == "__main__":
+    main()
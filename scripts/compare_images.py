@@ -0,0 +1,91 @@
+#!/usr/bin/env python3
+"""
+Compare Images - Produce a visual diff between an original and processed image
+Usage: python compare_images.py <original> <processed> <diff_output> [threshold]
+"""
+
+import sys
+import os
+from pathlib import Path
+
+def main():
+    if len(sys.argv) < 4:
+        print("Usage: python compare_images.py <original> <processed> <diff_output> [threshold]", file=sys.stderr)
+        sys.exit(1)
+
+    original_path = sys.argv[1]
+    processed_path = sys.argv[2]
+    diff_path = sys.argv[3]
+    threshold = int(sys.argv[4]) if len(sys.argv) > 4 else 10
+
+    for path in (original_path, processed_path):
+        if not os.path.exists(path):
+            print(f"Error: Image not found: {path}", file=sys.stderr)
+            sys.exit(1)
+
+    # Import OpenCV here to provide better error messages
+    try:
+        import cv2
+        import numpy as np
+    except ImportError:
+        print("Error: opencv-python not installed. Run: pip install opencv-python-headless numpy", file=sys.stderr)
+        sys.exit(1)
+
+    original = cv2.imread(original_path)
+    processed = cv2.imread(processed_path)
+    if original is None or processed is None:
+        print("Error: Cannot read one of the input images", file=sys.stderr)
+        sys.exit(1)
+
+    if original.shape != processed.shape:
+        print(f"Error: Image sizes differ: {original.shape[1]}x{original.shape[0]} vs {processed.shape[1]}x{processed.shape[0]}", file=sys.stderr)
+        sys.exit(1)
+
+    height, width = original.shape[:2]
+
+    # Per-pixel maximum channel difference
+    diff = cv2.absdiff(original, processed).max(axis=2)
+    changed = diff > threshold
+    changed_pixels = int(np.count_nonzero(changed))
+    total_pixels = height * width
+
+    # Highlight changed pixels in red over a dimmed copy of the original
+    overlay = (original * 0.4).astype(np.uint8)
+    overlay[changed] = (0, 0, 255)
+
+    Path(os.path.dirname(os.path.abspath(diff_path))).mkdir(parents=True, exist_ok=True)
+    cv2.imwrite(diff_path, overlay)
+
+    bbox = None
+    if changed_pixels > 0:
+        ys, xs = np.nonzero(changed)
+        bbox = {
+            "x": int(xs.min()),
+            "y": int(ys.min()),
+            "width": int(xs.max() - xs.min() + 1),
+            "height": int(ys.max() - ys.min() + 1)
+        }
+
+    print(f"Changed pixels: {changed_pixels} / {total_pixels} ({changed_pixels / total_pixels:.4%})")
+    if bbox:
+        print(f"Changed region: x={bbox['x']} y={bbox['y']} w={bbox['width']} h={bbox['height']}")
+    print(f"Diff image: {diff_path}")
+
+    # Output JSON for easy parsing
+    import json
+    result = {
+        "diff_path": diff_path,
+        "width": width,
+        "height": height,
+        "threshold": threshold,
+        "changed_pixels": changed_pixels,
+        "total_pixels": total_pixels,
+        "changed_ratio": changed_pixels / total_pixels,
+        "mean_abs_diff": float(diff.mean()),
+        "max_abs_diff": int(diff.max()),
+        "changed_bbox": bbox
+    }
+    print(f"JSON_RESULT:{json.dumps(result)}")
+
+if __name__ == "__main__":
+    main()
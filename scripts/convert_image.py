@@ -0,0 +1,119 @@
+#!/usr/bin/env python3
+"""
+Convert Image - Convert images between PNG/JPEG/WebP/TIFF with optional resize
+Usage: python convert_image.py (--image <path> | --dir <path>) --output <path> --format <fmt>
+                               [--quality <1-100>] [--max-width <px>] [--max-height <px>] [--scale <factor>]
+"""
+
+import sys
+import os
+import argparse
+from pathlib import Path
+
+FORMATS = {
+    'png': ('PNG', '.png'),
+    'jpeg': ('JPEG', '.jpg'),
+    'jpg': ('JPEG', '.jpg'),
+    'webp': ('WEBP', '.webp'),
+    'tiff': ('TIFF', '.tiff'),
+    'tif': ('TIFF', '.tiff'),
+}
+
+def convert(input_path, output_path, args):
+    """Convert a single image, returning its (width, height) after resizing."""
+    from PIL import Image
+
+    pil_format = FORMATS[args.format][0]
+    with Image.open(input_path) as image:
+        image.load()
+        width, height = image.size
+
+        factor = args.scale if args.scale else 1.0
+        if args.max_width and width * factor > args.max_width:
+            factor = args.max_width / width
+        if args.max_height and height * factor > args.max_height:
+            factor = args.max_height / height
+        if factor != 1.0:
+            image = image.resize((max(1, round(width * factor)), max(1, round(height * factor))), Image.LANCZOS)
+
+        if pil_format == 'JPEG' and image.mode not in ('RGB', 'L'):
+            image = image.convert('RGB')
+
+        save_kwargs = {}
+        if pil_format in ('JPEG', 'WEBP'):
+            save_kwargs['quality'] = args.quality
+        image.save(output_path, pil_format, **save_kwargs)
+        return image.size
+
+def main():
+    parser = argparse.ArgumentParser(description='Convert images between formats')
+    parser.add_argument('--image', help='Single image path')
+    parser.add_argument('--dir', help='Directory containing images')
+    parser.add_argument('--output', required=True, help='Output file (single image) or directory')
+    parser.add_argument('--format', required=True, choices=sorted(FORMATS.keys()))
+    parser.add_argument('--quality', type=int, default=90, help='JPEG/WebP quality (1-100)')
+    parser.add_argument('--max-width', type=int, help='Downscale to at most this width')
+    parser.add_argument('--max-height', type=int, help='Downscale to at most this height')
+    parser.add_argument('--scale', type=float, help='Resize factor (e.g. 0.5)')
+
+    args = parser.parse_args()
+
+    if not args.image and not args.dir:
+        print("Error: Either --image or --dir must be provided", file=sys.stderr)
+        sys.exit(1)
+
+    try:
+        from PIL import Image
+    except ImportError:
+        print("Error: Pillow not installed. Run: pip install Pillow", file=sys.stderr)
+        sys.exit(1)
+
+    extension = FORMATS[args.format][1]
+    converted = []
+
+    if args.image:
+        if not os.path.exists(args.image):
+            print(f"Error: Image not found: {args.image}", file=sys.stderr)
+            sys.exit(1)
+        output_path = args.output
+        if os.path.isdir(output_path):
+            output_path = os.path.join(output_path, Path(args.image).stem + extension)
+        output_dir = os.path.dirname(output_path)
+        if output_dir:
+            Path(output_dir).mkdir(parents=True, exist_ok=True)
+        size = convert(args.image, output_path, args)
+        converted.append({"input": args.image, "output": output_path, "width": size[0], "height": size[1]})
+        print(f"  ✓ {args.image} -> {output_path} ({size[0]}x{size[1]})")
+
+    else:
+        if not os.path.isdir(args.dir):
+            print(f"Error: Directory not found: {args.dir}", file=sys.stderr)
+            sys.exit(1)
+        Path(args.output).mkdir(parents=True, exist_ok=True)
+        image_extensions = {'.png', '.jpg', '.jpeg', '.webp', '.tif', '.tiff', '.bmp', '.gif'}
+        image_files = sorted([
+            f for f in os.listdir(args.dir)
+            if os.path.isfile(os.path.join(args.dir, f))
+            and Path(f).suffix.lower() in image_extensions
+        ])
+        print(f"Found {len(image_files)} images in {args.dir}")
+        for image_file in image_files:
+            input_path = os.path.join(args.dir, image_file)
+            output_path = os.path.join(args.output, Path(image_file).stem + extension)
+            size = convert(input_path, output_path, args)
+            converted.append({"input": input_path, "output": output_path, "width": size[0], "height": size[1]})
+            print(f"  ✓ {image_file} -> {os.path.basename(output_path)} ({size[0]}x{size[1]})")
+
+    print(f"\nComplete! Converted: {len(converted)}")
+
+    # Output JSON for easy parsing
+    import json
+    result = {
+        "format": args.format,
+        "converted": converted,
+        "count": len(converted)
+    }
+    print(f"JSON_RESULT:{json.dumps(result)}")
+
+if __name__ == "__main__":
+    main()
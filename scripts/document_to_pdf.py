@@ -0,0 +1,61 @@
+#!/usr/bin/env python3
+"""
+Document to PDF - Convert EPUB and DjVu documents to PDF so they can go through the PDF pipeline
+Usage: python document_to_pdf.py <input_document> <output_pdf>
+"""
+
+import sys
+import os
+import json
+import shutil
+import subprocess
+from pathlib import Path
+
+# extension -> (program, command builder, install hint)
+CONVERTERS = {
+    '.djvu': ('ddjvu', lambda src, dst: ['ddjvu', '-format=pdf', src, dst],
+              'macOS: brew install djvulibre; Ubuntu: sudo apt install djvulibre-bin'),
+    '.djv': ('ddjvu', lambda src, dst: ['ddjvu', '-format=pdf', src, dst],
+             'macOS: brew install djvulibre; Ubuntu: sudo apt install djvulibre-bin'),
+    '.epub': ('ebook-convert', lambda src, dst: ['ebook-convert', src, dst],
+              'Install calibre (https://calibre-ebook.com), which provides ebook-convert'),
+}
+
+def main():
+    if len(sys.argv) < 3:
+        print("Usage: python document_to_pdf.py <input_document> <output_pdf>", file=sys.stderr)
+        sys.exit(1)
+
+    input_path = sys.argv[1]
+    output_pdf = sys.argv[2]
+
+    if not os.path.exists(input_path):
+        print(f"Error: Input document not found: {input_path}", file=sys.stderr)
+        sys.exit(1)
+
+    ext = Path(input_path).suffix.lower()
+    if ext not in CONVERTERS:
+        print(f"Error: Unsupported document type: {ext} (expected .epub, .djvu or .djv)", file=sys.stderr)
+        sys.exit(1)
+
+    program, build_command, hint = CONVERTERS[ext]
+    if shutil.which(program) is None:
+        print(f"Error: {program} not found, needed to convert {ext} files", file=sys.stderr)
+        print(f"Note: {hint}", file=sys.stderr)
+        sys.exit(1)
+
+    Path(output_pdf).parent.mkdir(parents=True, exist_ok=True)
+
+    print(f"Converting {ext[1:].upper()} to PDF with {program}...")
+    result = subprocess.run(build_command(input_path, output_pdf), capture_output=True, text=True)
+    if result.returncode != 0 or not os.path.exists(output_pdf):
+        print(f"Error converting document: {result.stderr.strip() or result.stdout.strip()}", file=sys.stderr)
+        sys.exit(1)
+
+    print(f"  Output: {output_pdf}")
+
+    # Output JSON for easy parsing
+    print(f"JSON_RESULT:{json.dumps({'input': input_path, 'output': output_pdf, 'format': ext[1:]})}")
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,211 @@
+#!/usr/bin/env python3
+"""
+Evaluate Algorithms - Run several removal algorithms on one sample page and rank them
+Usage: python evaluate_algorithms.py <input> <output_dir> [--page <n>] [--dpi <n>]
+                                     [--algorithms telea,ns,solid_fill] [--size <px>]
+                                     [--region x,y,w,h] [--template <path>] [--mode corner|grid]
+                                     [--inpaint-radius <px>] [--mask-padding <px>] [--feather <px>]
+
+<input> is a PDF, whose --page is rendered at --dpi, or an image. The
+watermark is detected once and filled with each algorithm in turn. Each
+result is scored on:
+
+  residual            how much of the detection signal is left after
+                      cleaning: the template match score with --template,
+                      else the share of the region that still looks like
+                      watermark text, relative to the original (0 = gone)
+  boundary_artifacts  how much sharper the edge of the fill is than the
+                      image around it (0 = no visible seam)
+
+and `score` = 1 - (0.6 * residual + 0.4 * boundary_artifacts). A crop around
+the watermark is saved as `before.png` and `<algorithm>.png` in <output_dir>,
+no larger than --size pixels on a side.
+"""
+
+import sys
+import os
+import json
+import argparse
+import time
+from pathlib import Path
+
+sys.path.insert(0, os.path.dirname(os.path.abspath(__file__)))
+
+from remove_watermark import ALGORITHMS, add_removal_arguments, removal_options
+
+# Weights of the two penalties in the score
+RESIDUAL_WEIGHT = 0.6
+BOUNDARY_WEIGHT = 0.4
+
+# Gradient difference (0-255 scale) between the edge of the fill and its
+# surroundings that counts as a fully visible seam
+SEAM_GRADIENT = 64
+
+def parse_algorithms(value):
+    algorithms = [a.strip() for a in value.split(',') if a.strip()]
+    unknown = [a for a in algorithms if a not in ALGORITHMS]
+    if not algorithms or unknown:
+        raise argparse.ArgumentTypeError(
+            f"algorithms must be a comma-separated list of {', '.join(ALGORITHMS)}")
+    return list(dict.fromkeys(algorithms))
+
+def load_sample(input_path, page, dpi):
+    """The page of a PDF, or the image, as a BGR array."""
+    if Path(input_path).suffix.lower() != '.pdf':
+        from remove_watermark import read_image
+        img = read_image(input_path)
+        if img is None:
+            raise ValueError(f"Cannot read image: {input_path}")
+        return img
+
+    from rasterize import render_pages, to_bgr
+    images = render_pages(input_path, dpi=dpi, first_page=page, last_page=page)
+    if not images:
+        raise ValueError(f"Page {page} does not exist in {input_path}")
+    return to_bgr(images[0])
+
+def detection_signal(img, options):
+    """Confidence the detector reports for `img` with the given settings."""
+    from remove_watermark import build_grid_mask, build_mask
+
+    report = {}
+    if options['mode'] == 'grid':
+        build_grid_mask(img, report)
+    else:
+        build_mask(img, options['region'], options['template'], report)
+    return report.get('confidence', 0.0)
+
+def boundary_artifacts(result, mask):
+    """How much sharper the edge of the fill is than the image just outside it."""
+    import cv2
+    import numpy as np
+
+    gray = cv2.cvtColor(result, cv2.COLOR_BGR2GRAY).astype(np.float32)
+    gradient = cv2.magnitude(cv2.Sobel(gray, cv2.CV_32F, 1, 0), cv2.Sobel(gray, cv2.CV_32F, 0, 1))
+
+    kernel = cv2.getStructuringElement(cv2.MORPH_RECT, (3, 3))
+    edge = cv2.subtract(cv2.dilate(mask, kernel, iterations=2), cv2.erode(mask, kernel, iterations=2))
+    outside = cv2.subtract(cv2.dilate(mask, kernel, iterations=10), cv2.dilate(mask, kernel, iterations=4))
+    if not np.any(edge) or not np.any(outside):
+        return 0.0
+    seam = float(gradient[edge > 0].mean() - gradient[outside > 0].mean())
+    return min(1.0, max(0.0, seam / SEAM_GRADIENT))
+
+def crop_box(img, mask, options):
+    """Bounds around the watermark, with a margin, to show in the thumbnails."""
+    import cv2
+
+    height, width = img.shape[:2]
+    if mask is not None:
+        x, y, w, h = cv2.boundingRect(mask)
+    elif options['mode'] == 'grid':
+        x, y, w, h = 0, 0, width, height
+    else:
+        rx, ry, rw, rh = options['region']
+        x, y, w, h = int(rx * width), int(ry * height), max(1, int(rw * width)), max(1, int(rh * height))
+    margin = max(16, int(max(w, h) * 0.2))
+    return (max(0, x - margin), max(0, y - margin),
+            min(width, x + w + margin), min(height, y + h + margin))
+
+def save_thumbnail(img, box, size, path):
+    import cv2
+
+    x1, y1, x2, y2 = box
+    crop = img[y1:y2, x1:x2]
+    scale = size / max(crop.shape[:2])
+    interpolation = cv2.INTER_AREA if scale < 1 else cv2.INTER_NEAREST
+    crop = cv2.resize(crop, None, fx=scale, fy=scale, interpolation=interpolation)
+    if not cv2.imwrite(path, crop):
+        raise OSError(f"Cannot write {path}")
+
+def main():
+    parser = argparse.ArgumentParser(description='Rank removal algorithms on one sample page')
+    parser.add_argument('input')
+    parser.add_argument('output_dir')
+    parser.add_argument('--page', type=int, default=1, help='Page of a PDF input (default 1)')
+    parser.add_argument('--dpi', type=int, default=200, help='Render DPI of a PDF input (default 200)')
+    parser.add_argument('--algorithms', type=parse_algorithms, default=list(ALGORITHMS),
+                        help='Comma-separated algorithms to compare (default all)')
+    parser.add_argument('--size', type=int, default=256,
+                        help='Longest side of the thumbnails in pixels (default 256)')
+    add_removal_arguments(parser)
+    args = parser.parse_args()
+
+    if not os.path.exists(args.input):
+        print(f"Error: Input not found: {args.input}", file=sys.stderr)
+        sys.exit(1)
+
+    try:
+        import cv2  # noqa: F401
+        import numpy as np  # noqa: F401
+    except ImportError as e:
+        print(f"Error: Missing dependency: {e}", file=sys.stderr)
+        print("Run: pip install pdf2image opencv-python-headless numpy", file=sys.stderr)
+        sys.exit(1)
+
+    from remove_watermark import detect_mask, fill_mask
+
+    options = removal_options(args)
+    try:
+        img = load_sample(args.input, args.page, args.dpi)
+    except Exception as e:
+        print(f"Error loading sample: {e}", file=sys.stderr)
+        sys.exit(1)
+
+    Path(args.output_dir).mkdir(parents=True, exist_ok=True)
+    mask = detect_mask(img, options['region'], options['template'], options['mode'],
+                       options['mask_padding'])
+    original_signal = detection_signal(img, options)
+    box = crop_box(img, mask, options)
+    before_path = os.path.join(args.output_dir, "before.png")
+    save_thumbnail(img, box, args.size, before_path)
+
+    if mask is None:
+        print("  ○ No watermark detected; every algorithm leaves the page unchanged")
+
+    results = []
+    for algorithm in args.algorithms:
+        started = time.monotonic()
+        if mask is None:
+            result = img
+        else:
+            result = fill_mask(img, mask, algorithm, options['inpaint_radius'], options['feather'])
+        duration = time.monotonic() - started
+
+        if mask is None or original_signal <= 0:
+            residual = 0.0
+        else:
+            residual = min(1.0, detection_signal(result, options) / original_signal)
+        boundary = 0.0 if mask is None else boundary_artifacts(result, mask)
+        score = 1 - (RESIDUAL_WEIGHT * residual + BOUNDARY_WEIGHT * boundary)
+
+        thumbnail = os.path.join(args.output_dir, f"{algorithm}.png")
+        save_thumbnail(result, box, args.size, thumbnail)
+        results.append({
+            "algorithm": algorithm,
+            "score": round(score, 3),
+            "residual": round(residual, 3),
+            "boundary_artifacts": round(boundary, 3),
+            "duration_ms": round(duration * 1000),
+            "thumbnail": thumbnail,
+        })
+
+    # Best score first; the faster algorithm wins a tie
+    results.sort(key=lambda r: (-r["score"], r["duration_ms"]))
+    for rank, r in enumerate(results, 1):
+        print(f"  {rank}. {r['algorithm']}: score {r['score']:.3f} "
+              f"(residual {r['residual']:.3f}, boundary {r['boundary_artifacts']:.3f}, "
+              f"{r['duration_ms']} ms)")
+
+    result = {
+        "input": args.input,
+        "page": args.page if Path(args.input).suffix.lower() == '.pdf' else None,
+        "watermark_found": mask is not None,
+        "before": before_path,
+        "best": results[0]["algorithm"],
+        "results": results,
+    }
+    print(f"JSON_RESULT:{json.dumps(result)}")
+
+if __name__ == "__main__":
+    main()
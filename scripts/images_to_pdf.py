@@ -1,26 +1,135 @@
 #!/usr/bin/env python3
 """
 Images to PDF - Merge images into a PDF file
-Usage: python images_to_pdf.py <image_dir> <output_path> [pattern]
+Usage: python images_to_pdf.py <image_dir> <output_path> [pattern] [--sort name|natural|mtime]
+                               [--max-width <px>] [--max-height <px>] [--scale <factor>]
+                               [--deterministic]
+
+Resized pages keep their physical size: the image's DPI is scaled along with
+its pixels.
+
+With --deterministic the PDF dates come from SOURCE_DATE_EPOCH (default 0)
+and no random document ID is written, so the same images always give the
+same bytes.
 """
 
+import argparse
+import datetime
 import sys
 import os
+import re
 from pathlib import Path
 import glob
-
-def main():
-    if len(sys.argv) < 3:
-        print("Usage: python images_to_pdf.py <image_dir> <output_path> [pattern]", file=sys.stderr)
+import io
+
+# Formats img2pdf cannot embed; they are decoded with pillow-heif and
+# embedded as PNG
+HEIF_EXTENSIONS = ('.heic', '.heif', '.avif')
+
+# img2pdf's page size for images without DPI metadata
+DEFAULT_DPI = 96.0
+
+def natural_key(path):
+    """Sort key comparing digit runs as numbers, so page_2 comes before page_10."""
+    name = os.path.basename(path)
+    return [(0, int(part), '') if part.isdigit() else (1, 0, part.lower())
+            for part in re.split(r'(\d+)', name)]
+
+def sort_images(image_files, order):
+    if order == 'name':
+        return sorted(image_files)
+    if order == 'mtime':
+        return sorted(image_files, key=lambda f: (os.path.getmtime(f), natural_key(f)))
+    return sorted(image_files, key=lambda f: (natural_key(f), f))
+
+def resize_factor(width, height, args):
+    """Factor to resize a width x height image by, from --scale/--max-width/--max-height."""
+    factor = args.scale if args.scale else 1.0
+    if args.max_width and width * factor > args.max_width:
+        factor = args.max_width / width
+    if args.max_height and height * factor > args.max_height:
+        factor = args.max_height / height
+    return factor
+
+def pdf_input(path, args):
+    """What to hand img2pdf for `path`: the path itself, or encoded bytes for
+    HEIC/AVIF images (as PNG) and images that need resizing."""
+    heif = Path(path).suffix.lower() in HEIF_EXTENSIONS
+    resizing = args.scale or args.max_width or args.max_height
+    if not heif and not resizing:
+        return path
+    try:
+        from PIL import Image
+    except ImportError:
+        print("Error: Pillow not installed. Run: pip install Pillow", file=sys.stderr)
         sys.exit(1)
+    if heif:
+        try:
+            import pillow_heif
+        except ImportError:
+            print("Error: pillow-heif not installed. Run: pip install pillow-heif", file=sys.stderr)
+            sys.exit(1)
+        pillow_heif.register_heif_opener()
+        if hasattr(pillow_heif, 'register_avif_opener'):
+            pillow_heif.register_avif_opener()
+
+    with Image.open(path) as image:
+        image.load()
+        # JPEG pages stay JPEG so resizing does not inflate them
+        pil_format = 'JPEG' if image.format == 'JPEG' else 'PNG'
+        dpi = image.info.get('dpi') or (DEFAULT_DPI, DEFAULT_DPI)
+        width, height = image.size
+        factor = resize_factor(width, height, args)
+        if factor != 1.0:
+            image = image.resize((max(1, round(width * factor)), max(1, round(height * factor))),
+                                 Image.LANCZOS)
+        elif not heif:
+            return path
+        if image.mode not in ('RGB', 'L', '1'):
+            image = image.convert('RGB')
+        buffer = io.BytesIO()
+        save_kwargs = {'dpi': (float(dpi[0]) * factor, float(dpi[1]) * factor)}
+        if pil_format == 'JPEG':
+            save_kwargs['quality'] = 90
+        image.save(buffer, format=pil_format, **save_kwargs)
+    return buffer.getvalue()
+
+def reproducible_options(img2pdf):
+    """img2pdf.convert arguments that make the PDF depend only on its images."""
+    epoch = int(os.environ.get('SOURCE_DATE_EPOCH') or 0)
+    date = datetime.datetime.fromtimestamp(epoch, datetime.timezone.utc)
+    options = {'creationdate': date, 'moddate': date}
+    if hasattr(img2pdf, 'Engine'):
+        # pikepdf would add a random document ID
+        options['engine'] = img2pdf.Engine.internal
+    return options
 
-    image_dir = sys.argv[1]
-    output_path = sys.argv[2]
-    pattern = sys.argv[3] if len(sys.argv) > 3 else "*.png"
+def main():
+    parser = argparse.ArgumentParser(description='Merge images into a PDF file')
+    parser.add_argument('image_dir')
+    parser.add_argument('output_path')
+    parser.add_argument('pattern', nargs='?', default='*.png')
+    parser.add_argument('--sort', choices=['name', 'natural', 'mtime'], default='natural',
+                        help='Page order: plain file name, file name with numbers compared '
+                             'numerically (default), or modification time')
+    parser.add_argument('--max-width', type=int, help='Downscale pages to at most this width')
+    parser.add_argument('--max-height', type=int, help='Downscale pages to at most this height')
+    parser.add_argument('--scale', type=float, help='Resize factor for every page (e.g. 0.5)')
+    parser.add_argument('--deterministic', action='store_true',
+                        help='Fixed dates and no random document ID, for byte-identical output')
+    args = parser.parse_args()
+
+    image_dir = args.image_dir
+    output_path = args.output_path
+    pattern = args.pattern
 
     if not os.path.isdir(image_dir):
         print(f"Error: Directory not found: {image_dir}", file=sys.stderr)
         sys.exit(1)
+    # Patterns match file names in image_dir, as the server's merger does
+    if '/' in pattern or '\\' in pattern or '..' in pattern:
+        print(f"Error: pattern must match file names, not paths: {pattern}", file=sys.stderr)
+        sys.exit(1)
 
     # Import img2pdf here to provide better error messages
     try:
@@ -31,18 +140,19 @@ def main():
 
     # Find all matching images
     search_pattern = os.path.join(image_dir, pattern)
-    image_files = sorted(glob.glob(search_pattern))
+    image_files = glob.glob(search_pattern)
 
     if not image_files:
         # Try without pattern, just get all images
-        image_extensions = ['*.png', '*.jpg', '*.jpeg', '*.webp']
+        image_extensions = ['*.png', '*.jpg', '*.jpeg', '*.webp', '*.heic', '*.heif', '*.avif']
         for ext in image_extensions:
             image_files.extend(glob.glob(os.path.join(image_dir, ext)))
-        image_files = sorted(set(image_files))
+        image_files = list(set(image_files))
 
     if not image_files:
         print(f"Error: No images found in {image_dir}", file=sys.stderr)
         sys.exit(1)
+    image_files = sort_images(image_files, args.sort)
 
     print(f"Found {len(image_files)} images")
     for f in image_files:
@@ -58,7 +168,8 @@ def main():
 
     try:
         with open(output_path, "wb") as f:
-            f.write(img2pdf.convert(image_files))
+            options = reproducible_options(img2pdf) if args.deterministic else {}
+            f.write(img2pdf.convert([pdf_input(path, args) for path in image_files], **options))
     except Exception as e:
         print(f"Error creating PDF: {e}", file=sys.stderr)
         sys.exit(1)
@@ -0,0 +1,78 @@
+#!/usr/bin/env python3
+"""
+PDF Info - Report page count and per-page sizes of a PDF (via poppler's pdfinfo)
+Usage: python pdf_info.py <pdf_path>
+"""
+
+import sys
+import os
+import re
+import subprocess
+
+def main():
+    if len(sys.argv) < 2:
+        print("Usage: python pdf_info.py <pdf_path>", file=sys.stderr)
+        sys.exit(1)
+
+    pdf_path = sys.argv[1]
+    if not os.path.exists(pdf_path):
+        print(f"Error: PDF file not found: {pdf_path}", file=sys.stderr)
+        sys.exit(1)
+
+    try:
+        from pdf2image import pdfinfo_from_path
+    except ImportError:
+        print("Error: pdf2image not installed. Run: pip install pdf2image", file=sys.stderr)
+        sys.exit(1)
+
+    try:
+        info = pdfinfo_from_path(pdf_path)
+    except Exception as e:
+        print(f"Error reading PDF: {e}", file=sys.stderr)
+        print("Note: Make sure poppler or Ghostscript is installed (brew install poppler)", file=sys.stderr)
+        sys.exit(1)
+
+    page_count = int(info.get("Pages", 0))
+
+    # Per-page sizes need an explicit page range; fall back to the document size
+    pages = []
+    try:
+        output = subprocess.run(
+            ["pdfinfo", "-f", "1", "-l", str(page_count), pdf_path],
+            capture_output=True, text=True, check=True
+        ).stdout
+        for match in re.finditer(r"Page\s+(\d+) size:\s+([\d.]+) x ([\d.]+) pts", output):
+            pages.append({
+                "page": int(match.group(1)),
+                "width_pt": float(match.group(2)),
+                "height_pt": float(match.group(3))
+            })
+    except (OSError, subprocess.CalledProcessError):
+        pass
+
+    if not pages:
+        match = re.match(r"([\d.]+) x ([\d.]+) pts", info.get("Page size", ""))
+        if match:
+            pages = [{
+                "page": i + 1,
+                "width_pt": float(match.group(1)),
+                "height_pt": float(match.group(2))
+            } for i in range(page_count)]
+
+    print(f"Pages: {page_count}")
+    if pages:
+        print(f"First page size: {pages[0]['width_pt']} x {pages[0]['height_pt']} pts")
+
+    # Output JSON for easy parsing
+    import json
+    result = {
+        "pdf_path": pdf_path,
+        "page_count": page_count,
+        "file_size": os.path.getsize(pdf_path),
+        "encrypted": info.get("Encrypted", "no").startswith("yes"),
+        "pages": pages
+    }
+    print(f"JSON_RESULT:{json.dumps(result)}")
+
+if __name__ == "__main__":
+    main()
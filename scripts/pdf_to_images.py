@@ -1,7 +1,9 @@
 #!/usr/bin/env python3
 """
 PDF to Images - Convert PDF pages to PNG images
-Usage: python pdf_to_images.py <pdf_path> <output_dir> [dpi]
+Usage: python pdf_to_images.py <pdf_path> <output_dir> [dpi] [last_page]
+
+With last_page, only the pages up to it are rendered.
 """
 
 import sys
@@ -10,34 +12,36 @@ from pathlib import Path
 
 def main():
     if len(sys.argv) < 3:
-        print("Usage: python pdf_to_images.py <pdf_path> <output_dir> [dpi]", file=sys.stderr)
+        print("Usage: python pdf_to_images.py <pdf_path> <output_dir> [dpi] [last_page]", file=sys.stderr)
         sys.exit(1)
 
     pdf_path = sys.argv[1]
     output_dir = sys.argv[2]
     dpi = int(sys.argv[3]) if len(sys.argv) > 3 else 200
+    last_page = int(sys.argv[4]) if len(sys.argv) > 4 else None
 
     if not os.path.exists(pdf_path):
         print(f"Error: PDF file not found: {pdf_path}", file=sys.stderr)
         sys.exit(1)
 
-    # Import pdf2image here to provide better error messages
+    # Poppler (pdf2image) by default, Ghostscript when poppler is missing
+    from rasterize import choose_rasterizer, render_pages
     try:
-        from pdf2image import convert_from_path
-    except ImportError:
-        print("Error: pdf2image not installed. Run: pip install pdf2image", file=sys.stderr)
+        rasterizer = choose_rasterizer()
+    except Exception as e:
+        print(f"Error: {e}", file=sys.stderr)
         sys.exit(1)
 
     # Create output directory
     Path(output_dir).mkdir(parents=True, exist_ok=True)
 
-    print(f"Converting PDF to images with DPI={dpi}...")
+    print(f"Converting PDF to images with DPI={dpi} ({rasterizer})...")
 
     try:
-        images = convert_from_path(pdf_path, dpi=dpi)
+        images = render_pages(pdf_path, dpi=dpi, last_page=last_page)
     except Exception as e:
         print(f"Error converting PDF: {e}", file=sys.stderr)
-        print("Note: Make sure poppler is installed (brew install poppler)", file=sys.stderr)
+        print("Note: Make sure poppler or Ghostscript is installed (brew install poppler)", file=sys.stderr)
         sys.exit(1)
 
     print(f"Total pages: {len(images)}")
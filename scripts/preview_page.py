@@ -0,0 +1,84 @@
+#!/usr/bin/env python3
+"""
+Preview Page - Render one PDF page and show it before/after watermark removal
+Usage: python preview_page.py <pdf_path> <output_dir> <page> [dpi]
+                               [--region x,y,w,h] [--algorithm telea|ns|solid_fill] [--template <path>]
+                               [--mode corner|grid] [--steps <json>]
+"""
+
+import sys
+import os
+import argparse
+from pathlib import Path
+
+sys.path.insert(0, os.path.dirname(os.path.abspath(__file__)))
+
+from remove_watermark import add_removal_arguments, removal_options
+
+def main():
+    parser = argparse.ArgumentParser(description='Preview watermark removal on one PDF page')
+    parser.add_argument('pdf_path')
+    parser.add_argument('output_dir')
+    parser.add_argument('page', type=int)
+    parser.add_argument('dpi', nargs='?', type=int, default=72)
+    add_removal_arguments(parser)
+    args = parser.parse_args()
+
+    pdf_path = args.pdf_path
+    output_dir = args.output_dir
+    page = args.page
+    dpi = args.dpi
+
+    if not os.path.exists(pdf_path):
+        print(f"Error: PDF file not found: {pdf_path}", file=sys.stderr)
+        sys.exit(1)
+
+    # Import required libraries
+    try:
+        from rasterize import render_pages
+        import cv2
+        import numpy as np
+    except ImportError as e:
+        print(f"Error: Missing dependency: {e}", file=sys.stderr)
+        print("Run: pip install pdf2image opencv-python-headless numpy", file=sys.stderr)
+        sys.exit(1)
+
+    from remove_watermark import remove_watermark
+
+    Path(output_dir).mkdir(parents=True, exist_ok=True)
+
+    print(f"Rendering page {page} with DPI={dpi}...")
+    try:
+        images = render_pages(pdf_path, dpi=dpi, first_page=page, last_page=page)
+    except Exception as e:
+        print(f"Error converting PDF: {e}", file=sys.stderr)
+        print("Note: Make sure poppler or Ghostscript is installed (brew install poppler)", file=sys.stderr)
+        sys.exit(1)
+
+    if not images:
+        print(f"Error: Page {page} does not exist in {pdf_path}", file=sys.stderr)
+        sys.exit(1)
+
+    before_path = os.path.join(output_dir, f"page_{page:03d}_before.png")
+    after_path = os.path.join(output_dir, f"page_{page:03d}_after.png")
+    images[0].save(before_path, "PNG")
+
+    removed = remove_watermark(before_path, after_path, **removal_options(args))
+    if removed:
+        print(f"  ✓ Watermark removed")
+    else:
+        print(f"  ○ No watermark detected")
+
+    # Output JSON for easy parsing
+    import json
+    result = {
+        "page": page,
+        "dpi": dpi,
+        "before": before_path,
+        "after": after_path,
+        "watermark_removed": removed
+    }
+    print(f"JSON_RESULT:{json.dumps(result)}")
+
+if __name__ == "__main__":
+    main()
@@ -25,7 +25,7 @@ def main():
 
     # Import required libraries
     try:
-        from pdf2image import convert_from_path
+        from rasterize import render_pages
         import cv2
         import numpy as np
         import img2pdf
@@ -45,10 +45,10 @@ def main():
         # Step 1: Convert PDF to images
         print(f"Step 1/3: Converting PDF to images (DPI={dpi})...")
         try:
-            images = convert_from_path(input_pdf, dpi=dpi)
+            images = render_pages(input_pdf, dpi=dpi)
         except Exception as e:
             print(f"Error converting PDF: {e}", file=sys.stderr)
-            print("Note: Make sure poppler is installed (brew install poppler)", file=sys.stderr)
+            print("Note: Make sure poppler or Ghostscript is installed (brew install poppler)", file=sys.stderr)
             sys.exit(1)
 
         print(f"  Total pages: {len(images)}")
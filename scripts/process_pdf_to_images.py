@@ -2,20 +2,303 @@
 """
 Process PDF to Images - Convert PDF to images and remove watermarks
 Usage: python process_pdf_to_images.py <input_pdf> <output_dir> [dpi]
+                                       [--region x,y,w,h] [--algorithm telea|ns|solid_fill] [--template <path>]
+                                       [--mode corner|grid] [--steps <json>]
+                                       [--cache-dir <dir>] [--blank-pages process|skip|drop]
+                                       [--duplicate-pages keep|report|drop]
+                                       [--adaptive-dpi <max dpi>]
+                                       [--cmyk-pages <n,n,...>] [--cmyk-output]
+
+With --cache-dir, each cleaned page is stored under a hash of the PDF bytes,
+page number, DPI and removal settings, and reused on later runs instead of
+being rendered and cleaned again.
+
+A page that cannot be rendered or cleaned is reported as
+`page_NNN.png: ✗ Failed: <reason>` and skipped; the remaining pages are
+still processed.
+
+With --blank-pages skip, pages with next to no ink are written as rendered
+without running the removal step (`○ Blank page, not cleaned`); with drop
+they are left out of the output altogether (`○ Blank page, dropped`). A page
+with marks inside the watermark region is never taken for blank.
+
+With --duplicate-pages report or drop, each page is compared with the one
+before it by a perceptual hash of a low-resolution render. A repeated page is
+marked `(duplicate of page N)` with report, and with drop it is neither
+rendered nor cleaned but reported as `○ Duplicate of page N, dropped`.
+
+With --adaptive-dpi, a page whose detected watermark is under
+ADAPTIVE_MIN_HEIGHT pixels tall is rendered again at a higher DPI (up to the
+given one), cleaned there, and scaled back down; only the pixels under the
+watermark mask are taken from the higher-resolution result. Such pages are
+reported as `✓ Watermark removed at N DPI`.
+
+The pages listed in --cmyk-pages draw in CMYK and are rendered through
+Ghostscript's color management instead of poppler's fixed CMYK to RGB
+formula, which shifts print colors. With --cmyk-output every page is
+rendered in CMYK, cleaned there (the watermark is found on an RGB view) and
+written as a CMYK JPEG, `page_NNN.jpg`, for print; this needs Ghostscript.
+
+Each rendered page is preceded by a `Timing page_NNN: render <ms> ms,
+clean <ms> ms` line splitting its time between the two steps.
 """
 
 import sys
 import os
+import argparse
+import hashlib
+import json
+import shutil
+import time
 from pathlib import Path
 
+sys.path.insert(0, os.path.dirname(os.path.abspath(__file__)))
+
+from remove_watermark import add_removal_arguments, is_cmyk, removal_options
+
+# Bump when rendering or cleaning changes so stale cached pages are not reused
+CACHE_VERSION = 3
+
+BLANK_PAGES = ('process', 'skip', 'drop')
+
+# States a page can be cached in; dropped pages write no image to cache
+CACHED_STATES = ('removed', 'clean', 'blank')
+
+# A page is blank when fewer than this fraction of its pixels differ from the
+# paper colour by more than BLANK_INK_DELTA, measured on a copy at most
+# BLANK_SAMPLE pixels on its long side so scanner noise averages out
+BLANK_INK_RATIO = 0.001
+BLANK_INK_DELTA = 40
+BLANK_SAMPLE = 512
+
+DUPLICATE_PAGES = ('keep', 'report', 'drop')
+
+# Pages are hashed from a render at HASH_DPI shrunk to HASH_SIZE + 1 by
+# HASH_SIZE pixels; hashes at most DUPLICATE_DISTANCE bits apart are the same
+# page, allowing for rendering noise
+HASH_DPI = 36
+HASH_SIZE = 16
+DUPLICATE_DISTANCE = 2
+
+# With --adaptive-dpi, watermarks shorter than ADAPTIVE_MIN_HEIGHT pixels are
+# re-rendered so they are about ADAPTIVE_TARGET_HEIGHT pixels tall
+ADAPTIVE_MIN_HEIGHT = 16
+ADAPTIVE_TARGET_HEIGHT = 32
+
+# CMYK pages are JPEGs, PNG having no CMYK; at this quality and without
+# chroma subsampling they are as good as lossless for print
+CMYK_JPEG_QUALITY = 95
+
+def file_digest(path):
+    digest = hashlib.sha256()
+    with open(path, 'rb') as f:
+        for chunk in iter(lambda: f.read(1 << 20), b''):
+            digest.update(chunk)
+    return digest.hexdigest()
+
+def settings_digest(pdf_digest, dpi, options, blank_pages, adaptive_dpi, cmyk_output):
+    """Hash of everything but the page number that determines a cleaned page."""
+    options = dict(options)
+    if options.get('template'):
+        options['template'] = file_digest(options['template'])
+    key = json.dumps([CACHE_VERSION, pdf_digest, dpi, options, blank_pages, adaptive_dpi,
+                      cmyk_output], sort_keys=True, default=str)
+    return hashlib.sha256(key.encode()).hexdigest()
+
+def cached_page(cache_dir, key, page, extension):
+    """Return (path, state) of a cached page, or None."""
+    for state in CACHED_STATES:
+        path = os.path.join(cache_dir, f"{key}_{page}_{state}.{extension}")
+        if os.path.exists(path):
+            return path, state
+    return None
+
+def store_page(cache_dir, key, page, state, source, extension):
+    path = os.path.join(cache_dir, f"{key}_{page}_{state}.{extension}")
+    tmp = f"{path}.{os.getpid()}.tmp"
+    try:
+        shutil.copyfile(source, tmp)
+        os.replace(tmp, path)
+    except OSError as e:
+        print(f"  Warning: could not cache page {page}: {e}", file=sys.stderr)
+
+def is_blank(img, region=None):
+    """Whether a rendered page is effectively empty. With a `region`, marks
+    inside it must be just as sparse, so a page carrying only the watermark
+    is still cleaned."""
+    import cv2
+    import numpy as np
+    from remove_watermark import cmyk_to_bgr
+
+    if is_cmyk(img):
+        img = cmyk_to_bgr(img)
+    gray = cv2.cvtColor(img, cv2.COLOR_BGR2GRAY)
+    scale = BLANK_SAMPLE / max(gray.shape)
+    if scale < 1:
+        gray = cv2.resize(gray, None, fx=scale, fy=scale, interpolation=cv2.INTER_AREA)
+    paper = int(np.median(gray))
+    ink = np.abs(gray.astype(np.int16) - paper) > BLANK_INK_DELTA
+    if ink.mean() >= BLANK_INK_RATIO:
+        return False
+    if region:
+        height, width = ink.shape
+        x, y, w, h = region
+        area = ink[int(y * height):int((y + h) * height), int(x * width):int((x + w) * width)]
+        if area.size and area.mean() >= BLANK_INK_RATIO:
+            return False
+    return True
+
+def page_hash(input_pdf, page):
+    """Difference hash of a page: one bit per pair of horizontally adjacent
+    pixels of a tiny grayscale copy, set where the brightness drops."""
+    from rasterize import render_pages
+    from PIL import Image
+
+    image = render_pages(input_pdf, dpi=HASH_DPI, first_page=page, last_page=page)[0]
+    small = image.convert('L').resize((HASH_SIZE + 1, HASH_SIZE), Image.LANCZOS)
+    pixels = list(small.getdata())
+    value = 0
+    for y in range(HASH_SIZE):
+        row = pixels[y * (HASH_SIZE + 1):(y + 1) * (HASH_SIZE + 1)]
+        for left, right in zip(row, row[1:]):
+            value = (value << 1) | (left > right)
+    return value
+
+def is_duplicate(a, b):
+    return a is not None and b is not None and bin(a ^ b).count('1') <= DUPLICATE_DISTANCE
+
+def watermark_height(mask, mask_padding):
+    """Height in pixels of the watermark in a detected mask, before padding."""
+    import cv2
+
+    _, _, _, height = cv2.boundingRect(mask)
+    return max(height - 2 * mask_padding, 1)
+
+def page_color(page, args):
+    """How to render `page`: 'cmyk', 'managed' for CMYK content shown in RGB,
+    or 'rgb'; see rasterize.render_pages."""
+    if args.cmyk_output:
+        return 'cmyk'
+    return 'managed' if page in args.cmyk_pages else 'rgb'
+
+def render_page(input_pdf, page, dpi, color):
+    """One page as an OpenCV array: BGR, or CMYK when `color` is 'cmyk'."""
+    from rasterize import render_pages, to_bgr, to_cmyk
+
+    image = render_pages(input_pdf, dpi=dpi, first_page=page, last_page=page, color=color)[0]
+    # Handed to OpenCV in memory; only the cleaned page is written
+    return to_cmyk(image) if color == 'cmyk' else to_bgr(image)
+
+def write_page(output_path, img):
+    import cv2
+
+    if is_cmyk(img):
+        from PIL import Image
+        height, width = img.shape[:2]
+        Image.frombytes('CMYK', (width, height), img.tobytes()).save(
+            output_path, 'JPEG', quality=CMYK_JPEG_QUALITY, subsampling=0)
+    elif not cv2.imwrite(output_path, img):
+        raise OSError(f"cannot write {output_path}")
+
+def clean_adaptive(input_pdf, page, dpi, img, options, max_dpi, color):
+    """Clean a single-pass page, re-rendering it at up to `max_dpi` when the
+    watermark is too small at `dpi` to fill well. Returns (result, removed,
+    removal_dpi) where removal_dpi is None for pages cleaned at `dpi`."""
+    import cv2
+    from remove_watermark import detect_mask, fill_mask
+
+    detect = {key: options[key] for key in ('region', 'template', 'mode', 'mask_padding')}
+    mask = detect_mask(img, **detect)
+    if mask is None:
+        return img, False, None
+    height = watermark_height(mask, options['mask_padding'])
+    high_dpi = min(max_dpi, dpi * ADAPTIVE_TARGET_HEIGHT // height)
+    if height >= ADAPTIVE_MIN_HEIGHT or high_dpi <= dpi:
+        return fill_mask(img, mask, options['algorithm'], options['inpaint_radius'],
+                         options['feather']), True, None
+
+    # Pixel sizes scale with the render so the fill covers the same area
+    scale = high_dpi / dpi
+    high = render_page(input_pdf, page, high_dpi, color)
+    detect['mask_padding'] = round(options['mask_padding'] * scale)
+    high_mask = detect_mask(high, **detect)
+    if high_mask is None:
+        return fill_mask(img, mask, options['algorithm'], options['inpaint_radius'],
+                         options['feather']), True, None
+    cleaned = fill_mask(high, high_mask, options['algorithm'],
+                        round(options['inpaint_radius'] * scale), round(options['feather'] * scale))
+
+    # Only the watermark area comes from the downscaled render, so the rest
+    # of the page is pixel for pixel the same as at `dpi`
+    height, width = img.shape[:2]
+    cleaned = cv2.resize(cleaned, (width, height), interpolation=cv2.INTER_AREA)
+    result = img.copy()
+    result[mask > 0] = cleaned[mask > 0]
+    return result, True, high_dpi
+
+def render_and_clean(input_pdf, page, dpi, output_path, args):
+    """Render one page and write it cleaned to `output_path`; returns the
+    page's state ('removed', 'clean', 'blank' or 'dropped'), the DPI an
+    adaptive re-render removed the watermark at (or None), and the seconds
+    spent rendering and cleaning."""
+    from remove_watermark import apply_post_filters, clean_image
+
+    started = time.monotonic()
+    color = page_color(page, args)
+    img = render_page(input_pdf, page, dpi, color)
+    rendered = time.monotonic()
+    region = args.region if args.mode == 'corner' and not args.steps else None
+    removal_dpi = None
+    if args.blank_pages != 'process' and is_blank(img, region):
+        state = 'dropped' if args.blank_pages == 'drop' else 'blank'
+        result = img
+    else:
+        options = removal_options(args)
+        if args.adaptive_dpi and not args.steps:
+            result, removed, removal_dpi = clean_adaptive(
+                input_pdf, page, dpi, img, options, args.adaptive_dpi, color)
+            result = apply_post_filters(result, options['post_filters'])
+        else:
+            result, removed = clean_image(img, **options)
+        state = 'removed' if removed else 'clean'
+    if state != 'dropped':
+        write_page(output_path, result)
+    return state, removal_dpi, rendered - started, time.monotonic() - rendered
+
+def parse_pages(value):
+    """Parse a comma-separated list of page numbers into a set."""
+    try:
+        return {int(page) for page in value.split(',') if page.strip()}
+    except ValueError:
+        raise argparse.ArgumentTypeError("pages must be comma-separated numbers")
+
 def main():
-    if len(sys.argv) < 3:
-        print("Usage: python process_pdf_to_images.py <input_pdf> <output_dir> [dpi]", file=sys.stderr)
-        sys.exit(1)
+    parser = argparse.ArgumentParser(description='Convert PDF to images and remove watermarks')
+    parser.add_argument('input_pdf')
+    parser.add_argument('output_dir')
+    parser.add_argument('dpi', nargs='?', type=int, default=200)
+    add_removal_arguments(parser)
+    parser.add_argument('--cache-dir', help='Directory for cached cleaned pages')
+    parser.add_argument('--first-page', type=int, default=1, help='First page to process')
+    parser.add_argument('--last-page', type=int, help='Last page to process (default: last page)')
+    parser.add_argument('--blank-pages', choices=BLANK_PAGES, default='process',
+                        help='process: clean blank pages like any other (default); '
+                             'skip: keep them as rendered; drop: leave them out')
+    parser.add_argument('--duplicate-pages', choices=DUPLICATE_PAGES, default='keep',
+                        help='keep: do not look for repeated pages (default); '
+                             'report: mark pages identical to the one before; drop: leave them out')
+    parser.add_argument('--adaptive-dpi', type=int,
+                        help='Re-render pages whose watermark is only a few pixels tall at up '
+                             'to this DPI for removal (default: off)')
+    parser.add_argument('--cmyk-pages', type=parse_pages, default=set(),
+                        help='Comma-separated pages drawing in CMYK, rendered with color management')
+    parser.add_argument('--cmyk-output', action='store_true',
+                        help='Render, clean and write every page in CMYK (needs Ghostscript)')
+    args = parser.parse_args()
 
-    input_pdf = sys.argv[1]
-    output_dir = sys.argv[2]
-    dpi = int(sys.argv[3]) if len(sys.argv) > 3 else 200
+    input_pdf = args.input_pdf
+    output_dir = args.output_dir
+    dpi = args.dpi
 
     if not os.path.exists(input_pdf):
         print(f"Error: Input PDF not found: {input_pdf}", file=sys.stderr)
@@ -23,7 +306,7 @@ def main():
 
     # Import required libraries
     try:
-        from pdf2image import convert_from_path
+        import rasterize
         import cv2
         import numpy as np
     except ImportError as e:
@@ -31,73 +314,144 @@ def main():
         print("Run: pip install pdf2image opencv-python-headless numpy", file=sys.stderr)
         sys.exit(1)
 
+    if args.template and not os.path.exists(args.template):
+        print(f"Error: Template not found: {args.template}", file=sys.stderr)
+        sys.exit(1)
+
+    if args.cmyk_output and not rasterize.ghostscript_executable():
+        print("Error: CMYK output needs Ghostscript to render the pages in CMYK "
+              "(brew install ghostscript / sudo apt install ghostscript)", file=sys.stderr)
+        sys.exit(1)
+    if args.cmyk_pages and not args.cmyk_output and (
+            not rasterize.ghostscript_executable() or rasterize.configured_rasterizer() == 'poppler'):
+        print(f"  Warning: {len(args.cmyk_pages)} pages draw in CMYK; rendered without "
+              f"Ghostscript, their colors may shift", file=sys.stderr)
+
     # Create output directory
     Path(output_dir).mkdir(parents=True, exist_ok=True)
 
-    # Step 1: Convert PDF to images
-    print(f"Step 1/2: Converting PDF to images (DPI={dpi})...")
     try:
-        images = convert_from_path(input_pdf, dpi=dpi)
+        page_count = rasterize.page_count(input_pdf)
     except Exception as e:
         print(f"Error converting PDF: {e}", file=sys.stderr)
-        print("Note: Make sure poppler is installed (brew install poppler)", file=sys.stderr)
+        print("Note: Make sure poppler or Ghostscript is installed (brew install poppler)", file=sys.stderr)
         sys.exit(1)
 
-    print(f"  Total pages: {len(images)}")
+    cache_key = None
+    if args.cache_dir:
+        Path(args.cache_dir).mkdir(parents=True, exist_ok=True)
+        cache_key = settings_digest(file_digest(input_pdf), dpi, removal_options(args),
+                                    args.blank_pages, args.adaptive_dpi, args.cmyk_output)
 
-    # Step 2: Remove watermarks and save
+    # Pages are rendered one at a time so cached pages are never rendered
+    print(f"Step 1/2: Converting PDF to images (DPI={dpi})...")
+    print(f"  Total pages: {page_count}")
     print(f"\nStep 2/2: Removing watermarks and saving...")
     processed_count = 0
+    cached_count = 0
+    failed_count = 0
+    blank_count = 0
+    duplicate_count = 0
+    adaptive_count = 0
 
-    for i, image in enumerate(images):
-        # Save temporarily to process with OpenCV
-        temp_path = os.path.join(output_dir, f"_temp_{i}.png")
-        output_path = os.path.join(output_dir, f"page_{i+1:03d}.png")
+    first_page = max(args.first_page, 1)
+    last_page = min(args.last_page or page_count, page_count)
 
-        image.save(temp_path, "PNG")
+    # Hash of the previous page and the page it repeats, if any. A range that
+    # starts mid-document hashes the page before it to compare with.
+    previous_hash = None
+    original = None
+    if args.duplicate_pages != 'keep' and first_page > 1:
+        try:
+            previous_hash = page_hash(input_pdf, first_page - 1)
+            original = first_page - 1
+        except Exception as e:
+            print(f"  Warning: could not hash page {first_page - 1}: {e}", file=sys.stderr)
 
-        # Load with OpenCV
-        img = cv2.imread(temp_path)
-        height, width = img.shape[:2]
+    # CMYK pages are JPEGs, PNG having no CMYK
+    extension = 'jpg' if args.cmyk_output else 'png'
+    for page in range(first_page, last_page + 1):
+        name = f"page_{page:03d}.{extension}"
+        output_path = os.path.join(output_dir, name)
 
-        # Define watermark region - bottom right corner
-        roi_x = int(width * 0.80)
-        roi_y = int(height * 0.92)
-
-        # Extract ROI and detect watermark
-        roi = img[roi_y:height, roi_x:width]
-        gray_roi = cv2.cvtColor(roi, cv2.COLOR_BGR2GRAY)
-        mask_roi = cv2.inRange(gray_roi, 150, 240)
-
-        # Morphological operations
-        kernel = cv2.getStructuringElement(cv2.MORPH_RECT, (5, 5))
-        mask_roi = cv2.dilate(mask_roi, kernel, iterations=2)
-
-        # Create full image mask
-        mask = np.zeros((height, width), dtype=np.uint8)
-        mask[roi_y:height, roi_x:width] = mask_roi
-
-        # Apply inpainting if watermark detected
-        if np.sum(mask) > 100:
-            kernel_expand = cv2.getStructuringElement(cv2.MORPH_RECT, (7, 7))
-            mask = cv2.dilate(mask, kernel_expand, iterations=1)
-            result = cv2.inpaint(img, mask, inpaintRadius=5, flags=cv2.INPAINT_TELEA)
-            cv2.imwrite(output_path, result)
-            processed_count += 1
-            print(f"  page_{i+1:03d}.png: ✓ Watermark removed")
+        duplicate = ""
+        if args.duplicate_pages != 'keep':
+            try:
+                current_hash = page_hash(input_pdf, page)
+            except Exception as e:
+                print(f"  Warning: could not hash page {page}: {e}", file=sys.stderr)
+                current_hash = None
+            if is_duplicate(current_hash, previous_hash):
+                duplicate_count += 1
+                if args.duplicate_pages == 'drop':
+                    previous_hash = current_hash
+                    print(f"  {name}: ○ Duplicate of page {original}, dropped")
+                    continue
+                duplicate = f" (duplicate of page {original})"
+            else:
+                original = page
+            previous_hash = current_hash
+
+        cached = cached_page(args.cache_dir, cache_key, page, extension) if cache_key else None
+        if cached:
+            cached_path, state = cached
+            shutil.copyfile(cached_path, output_path)
+            os.utime(cached_path)
+            cached_count += 1
+            removal_dpi = None
+            suffix = f"{duplicate} (cached)"
         else:
-            cv2.imwrite(output_path, img)
-            print(f"  page_{i+1:03d}.png: ○ No watermark")
+            try:
+                state, removal_dpi, render_time, clean_time = render_and_clean(
+                    input_pdf, page, dpi, output_path, args)
+            except Exception as e:
+                failed_count += 1
+                reason = ' '.join(str(e).split()) or type(e).__name__
+                print(f"  {name}: ✗ Failed: {reason}")
+                continue
+            print(f"  Timing page_{page:03d}: render {render_time * 1000:.0f} ms, "
+                  f"clean {clean_time * 1000:.0f} ms")
 
-        # Remove temp file
-        os.remove(temp_path)
+            if cache_key and state != 'dropped':
+                store_page(args.cache_dir, cache_key, page, state, output_path, extension)
+            suffix = duplicate
+
+        if state == 'removed':
+            processed_count += 1
+            if removal_dpi:
+                adaptive_count += 1
+                suffix = f" at {removal_dpi} DPI{suffix}"
+            print(f"  {name}: ✓ Watermark removed{suffix}")
+        elif state == 'clean':
+            print(f"  {name}: ○ No watermark{suffix}")
+        elif state == 'blank':
+            blank_count += 1
+            print(f"  {name}: ○ Blank page, not cleaned{suffix}")
+        else:
+            blank_count += 1
+            print(f"  {name}: ○ Blank page, dropped")
 
     print(f"\n{'='*50}")
     print(f"Processing complete!")
     print(f"  Input:  {input_pdf}")
     print(f"  Output: {output_dir}")
-    print(f"  Pages:  {len(images)}")
+    if (first_page, last_page) == (1, page_count):
+        print(f"  Pages:  {page_count}")
+    else:
+        print(f"  Pages:  {first_page}-{last_page} of {page_count}")
     print(f"  Watermarks removed: {processed_count}")
+    if adaptive_count:
+        print(f"  Removed at a higher DPI: {adaptive_count}")
+    if blank_count:
+        action = 'dropped' if args.blank_pages == 'drop' else 'not cleaned'
+        print(f"  Blank pages {action}: {blank_count}")
+    if duplicate_count:
+        action = ' dropped' if args.duplicate_pages == 'drop' else ''
+        print(f"  Duplicate pages{action}: {duplicate_count}")
+    if failed_count:
+        print(f"  Failed pages: {failed_count}")
+    if cache_key:
+        print(f"  Reused from cache: {cached_count}")
     print(f"{'='*50}")
 
 if __name__ == "__main__":
@@ -0,0 +1,155 @@
+#!/usr/bin/env python3
+"""
+Process ZIP - Extract a ZIP of images and/or PDFs, remove watermarks from every entry, optionally re-zip
+Usage: python process_zip.py <zip_path> <work_dir> <output_dir> [--output-zip <path>] [--dpi N]
+                             [--region x,y,w,h] [--algorithm telea|ns|solid_fill] [--template <path>]
+                             [--mode corner|grid] [--steps <json>]
+"""
+
+import sys
+import os
+import json
+import zipfile
+import argparse
+from pathlib import Path
+
+sys.path.insert(0, os.path.dirname(os.path.abspath(__file__)))
+
+from remove_watermark import add_removal_arguments, removal_options
+
+IMAGE_EXTENSIONS = {'.png', '.jpg', '.jpeg', '.webp'}
+
+def safe_extract(archive, work_dir):
+    """Extract all members, refusing entries that would escape work_dir."""
+    root = os.path.realpath(work_dir)
+    for member in archive.infolist():
+        target = os.path.realpath(os.path.join(root, member.filename))
+        if target != root and not target.startswith(root + os.sep):
+            raise ValueError(f"Unsafe path in archive: {member.filename}")
+    archive.extractall(root)
+
+def clean_pdf(pdf_path, output_path, dpi, options):
+    """Render, clean and re-merge one PDF. Returns the number of pages cleaned."""
+    import cv2
+    import img2pdf
+    from rasterize import render_pages, to_bgr
+    from remove_watermark import clean_image
+
+    pages_dir = Path(str(output_path) + "_pages")
+    pages_dir.mkdir(parents=True, exist_ok=True)
+
+    page_files = []
+    removed_count = 0
+    for i, image in enumerate(render_pages(str(pdf_path), dpi=dpi)):
+        page_path = pages_dir / f"page_{i+1:03d}.png"
+        result, removed = clean_image(to_bgr(image), **options)
+        cv2.imwrite(str(page_path), result)
+        page_files.append(str(page_path))
+        if removed:
+            removed_count += 1
+
+    with open(output_path, "wb") as f:
+        f.write(img2pdf.convert(page_files))
+
+    for page_file in page_files:
+        os.remove(page_file)
+    pages_dir.rmdir()
+    return removed_count
+
+def main():
+    parser = argparse.ArgumentParser(description='Remove watermarks from every image and PDF in a ZIP')
+    parser.add_argument('zip_path')
+    parser.add_argument('work_dir', help='Scratch directory the archive is extracted into')
+    parser.add_argument('output_dir')
+    parser.add_argument('--output-zip', help='Also package the cleaned files into this ZIP')
+    parser.add_argument('--dpi', type=int, default=200, help='DPI used to render PDF entries')
+    add_removal_arguments(parser)
+    args = parser.parse_args()
+
+    if not zipfile.is_zipfile(args.zip_path):
+        print(f"Error: Not a ZIP archive: {args.zip_path}", file=sys.stderr)
+        sys.exit(1)
+
+    try:
+        import cv2
+        import img2pdf
+        from rasterize import render_pages
+    except ImportError as e:
+        print(f"Error: Missing dependency: {e}", file=sys.stderr)
+        print("Run: pip install -r scripts/requirements.txt", file=sys.stderr)
+        sys.exit(1)
+
+    from remove_watermark import remove_watermark
+
+    options = removal_options(args)
+
+    # Step 1: Extract
+    print(f"Step 1/2: Extracting {args.zip_path}...")
+    try:
+        with zipfile.ZipFile(args.zip_path) as archive:
+            safe_extract(archive, args.work_dir)
+    except (ValueError, zipfile.BadZipFile) as e:
+        print(f"Error extracting archive: {e}", file=sys.stderr)
+        sys.exit(1)
+
+    entries = []
+    for root, _dirs, files in os.walk(args.work_dir):
+        for name in files:
+            path = Path(root) / name
+            rel = path.relative_to(args.work_dir)
+            # Skip macOS resource forks and other hidden metadata
+            if any(part.startswith('.') or part == '__MACOSX' for part in rel.parts):
+                continue
+            entries.append(rel)
+    entries.sort()
+
+    # Step 2: Clean every supported entry, mirroring the archive layout
+    print(f"\nStep 2/2: Removing watermarks from {len(entries)} entries...")
+    results = []
+    for rel in entries:
+        src = Path(args.work_dir) / rel
+        dst = Path(args.output_dir) / rel
+        dst.parent.mkdir(parents=True, exist_ok=True)
+        ext = rel.suffix.lower()
+
+        try:
+            if ext in IMAGE_EXTENSIONS:
+                removed = remove_watermark(str(src), str(dst), **options)
+                status = "watermark_removed" if removed else "no_watermark"
+            elif ext == '.pdf':
+                removed = clean_pdf(src, dst, args.dpi, options)
+                status = f"{removed}_pages_cleaned"
+            else:
+                print(f"  {rel}: - skipped (unsupported type)")
+                results.append({"entry": str(rel), "status": "skipped"})
+                continue
+        except Exception as e:
+            print(f"  {rel}: ✗ {e}")
+            results.append({"entry": str(rel), "status": "failed", "error": str(e)})
+            continue
+
+        print(f"  {rel}: ✓ {status.replace('_', ' ')}")
+        results.append({"entry": str(rel), "status": status, "output": str(dst)})
+
+    written = [r for r in results if "output" in r]
+
+    if args.output_zip:
+        Path(args.output_zip).parent.mkdir(parents=True, exist_ok=True)
+        with zipfile.ZipFile(args.output_zip, "w", zipfile.ZIP_DEFLATED) as archive:
+            for r in written:
+                archive.write(r["output"], r["entry"])
+        print(f"\nPackaged {len(written)} files into {args.output_zip}")
+
+    failed = sum(1 for r in results if r["status"] == "failed")
+    print(f"\n{'='*50}")
+    print(f"Processing complete!")
+    print(f"  Input:   {args.zip_path}")
+    print(f"  Output:  {args.output_dir}")
+    print(f"  Cleaned: {len(written)}  Failed: {failed}  Skipped: {len(results) - len(written) - failed}")
+    print(f"{'='*50}")
+
+    # Output JSON for easy parsing
+    print(f"JSON_RESULT:{json.dumps({'output_dir': args.output_dir, 'output_zip': args.output_zip, 'entries': results})}")
+
+if __name__ == "__main__":
+    main()
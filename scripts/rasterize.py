@@ -0,0 +1,150 @@
+#!/usr/bin/env python3
+"""
+Rasterize - Render PDF pages to PIL images with poppler or Ghostscript
+
+The scripts render pages through `render_pages` and count them through
+`page_count` instead of calling pdf2image directly, so a server without
+poppler can use Ghostscript (`gs`) instead. The rasterizer is picked by
+WATERMARK_RASTERIZER:
+
+  auto         poppler when pdf2image and pdftoppm are available, else
+               Ghostscript when gs is on PATH (default)
+  poppler      always pdf2image/poppler
+  ghostscript  always Ghostscript
+
+Pages drawing in CMYK are better rendered by Ghostscript, which converts
+CMYK to RGB through ICC profiles where poppler uses a fixed formula that
+shifts print colors. `render_pages` takes `color='managed'` for those, and
+`color='cmyk'` for the CMYK samples themselves, which only Ghostscript can
+render.
+"""
+
+import os
+import re
+import shutil
+import subprocess
+import tempfile
+
+RASTERIZERS = ('poppler', 'ghostscript')
+
+# Ghostscript's console executable on Unix and Windows
+GHOSTSCRIPT_NAMES = ('gs', 'gswin64c', 'gswin32c')
+
+class RasterizerUnavailable(Exception):
+    pass
+
+def ghostscript_executable():
+    for name in GHOSTSCRIPT_NAMES:
+        path = shutil.which(name)
+        if path:
+            return path
+    return None
+
+def poppler_available():
+    try:
+        import pdf2image  # noqa: F401
+    except ImportError:
+        return False
+    return shutil.which('pdftoppm') is not None
+
+def configured_rasterizer():
+    return os.environ.get('WATERMARK_RASTERIZER', 'auto').strip().lower() or 'auto'
+
+def choose_rasterizer():
+    """The rasterizer to use, per WATERMARK_RASTERIZER."""
+    choice = configured_rasterizer()
+    if choice in RASTERIZERS:
+        return choice
+    if choice != 'auto':
+        raise RasterizerUnavailable(
+            f"WATERMARK_RASTERIZER must be auto, {' or '.join(RASTERIZERS)}, not {choice}")
+    if poppler_available():
+        return 'poppler'
+    if ghostscript_executable():
+        return 'ghostscript'
+    raise RasterizerUnavailable(
+        "No PDF rasterizer found: install poppler (pip install pdf2image, plus "
+        "brew install poppler / sudo apt install poppler-utils) or Ghostscript")
+
+def render_pages(pdf_path, dpi=200, first_page=None, last_page=None, color='rgb'):
+    """PIL images of the pages from `first_page` to `last_page` (1-based,
+    inclusive; default the whole document).
+
+    `color` is 'rgb' for RGB from the configured rasterizer, 'managed' for
+    RGB from Ghostscript's color-managed conversion unless poppler was
+    chosen explicitly or Ghostscript is missing, or 'cmyk' for CMYK images,
+    which need Ghostscript.
+    """
+    if color == 'cmyk':
+        return render_with_ghostscript(pdf_path, dpi, first_page, last_page, cmyk=True)
+    if color == 'managed' and ghostscript_executable() and configured_rasterizer() != 'poppler':
+        return render_with_ghostscript(pdf_path, dpi, first_page, last_page)
+    if choose_rasterizer() == 'poppler':
+        from pdf2image import convert_from_path
+        return convert_from_path(pdf_path, dpi=dpi, first_page=first_page, last_page=last_page)
+    return render_with_ghostscript(pdf_path, dpi, first_page, last_page)
+
+def to_bgr(image):
+    """A rendered page as an OpenCV BGR array, converted in memory rather
+    than through a temporary PNG."""
+    import cv2
+    import numpy as np
+    return cv2.cvtColor(np.array(image.convert('RGB')), cv2.COLOR_RGB2BGR)
+
+def to_cmyk(image):
+    """A CMYK render as an array of C, M, Y and K channels."""
+    import numpy as np
+    return np.array(image.convert('CMYK'))
+
+def page_count(pdf_path):
+    if choose_rasterizer() == 'poppler':
+        from pdf2image import pdfinfo_from_path
+        return int(pdfinfo_from_path(pdf_path).get("Pages", 0))
+    return ghostscript_page_count(pdf_path)
+
+def run_ghostscript(args):
+    gs = ghostscript_executable()
+    if gs is None:
+        raise RasterizerUnavailable(
+            "Ghostscript not found: install it (brew install ghostscript / "
+            "sudo apt install ghostscript) or poppler")
+    result = subprocess.run([gs, '-q', '-dNOPAUSE', '-dBATCH'] + args,
+                            capture_output=True, text=True)
+    if result.returncode != 0:
+        detail = (result.stderr or result.stdout).strip()
+        raise RuntimeError(f"Ghostscript failed: {detail}")
+    return result.stdout
+
+def render_with_ghostscript(pdf_path, dpi, first_page, last_page, cmyk=False):
+    from PIL import Image
+
+    # tiff32nc keeps the four ink channels; PNG has no CMYK
+    device, extension, mode = ('tiff32nc', 'tif', 'CMYK') if cmyk else ('png16m', 'png', 'RGB')
+    args = ['-dSAFER', f'-sDEVICE={device}', f'-r{dpi}',
+            '-dTextAlphaBits=4', '-dGraphicsAlphaBits=4']
+    if first_page:
+        args.append(f'-dFirstPage={first_page}')
+    if last_page:
+        args.append(f'-dLastPage={last_page}')
+
+    with tempfile.TemporaryDirectory(prefix='gs-') as tmp:
+        run_ghostscript(args + [f'-sOutputFile={os.path.join(tmp, "page_%05d." + extension)}',
+                                pdf_path])
+        images = []
+        for name in sorted(os.listdir(tmp)):
+            # convert() reads the file now, before the directory goes away
+            with Image.open(os.path.join(tmp, name)) as image:
+                images.append(image.convert(mode))
+    return images
+
+def ghostscript_page_count(pdf_path):
+    path = os.path.abspath(pdf_path)
+    literal = path.replace('\\', '\\\\').replace('(', '\\(').replace(')', '\\)')
+    output = run_ghostscript([
+        '-dNODISPLAY', '-dSAFER', f'--permit-file-read={path}',
+        '-c', f'({literal}) (r) file runpdfbegin pdfpagecount = quit',
+    ])
+    match = re.search(r'\d+', output)
+    if not match:
+        raise RuntimeError(f"Ghostscript reported no page count for {pdf_path}")
+    return int(match.group())
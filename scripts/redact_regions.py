@@ -0,0 +1,176 @@
+#!/usr/bin/env python3
+"""
+Redact Regions - Fill rectangles on images or PDF pages with a solid color (no inpainting)
+Usage: python redact_regions.py (--image <path> | --dir <path> | --pdf <path>) --output <path>
+                                --regions <json> [--color black|white|#rrggbb] [--dpi <dpi>]
+"""
+
+import sys
+import os
+import json
+import argparse
+from pathlib import Path
+
+NAMED_COLORS = {'black': (0, 0, 0), 'white': (255, 255, 255)}
+
+def parse_color(value):
+    if value in NAMED_COLORS:
+        return NAMED_COLORS[value]
+    if len(value) == 7 and value.startswith('#'):
+        try:
+            return tuple(int(value[i:i + 2], 16) for i in (1, 3, 5))
+        except ValueError:
+            pass
+    raise argparse.ArgumentTypeError("color must be black, white or #rrggbb")
+
+def parse_regions(value):
+    """Parse [{"region": [x, y, w, h], "page": n?}, ...] with fractional coordinates."""
+    try:
+        regions = json.loads(value)
+    except json.JSONDecodeError as e:
+        raise argparse.ArgumentTypeError(f"regions must be a JSON array: {e}")
+    if not isinstance(regions, list) or not regions:
+        raise argparse.ArgumentTypeError("regions must be a non-empty JSON array")
+    for entry in regions:
+        rect = entry.get('region') if isinstance(entry, dict) else None
+        if (not isinstance(rect, list) or len(rect) != 4
+                or not all(isinstance(v, (int, float)) and 0.0 <= v <= 1.0 for v in rect)):
+            raise argparse.ArgumentTypeError(f"invalid region entry: {entry}")
+    return regions
+
+def redact(image, regions, color, page=None):
+    """Return an RGB copy of a PIL image with the matching regions filled.
+
+    Entries without a page apply everywhere; entries with one only apply when
+    `page` matches (images in a directory are numbered from 1 in sorted order).
+    """
+    from PIL import ImageDraw
+
+    result = image.convert("RGB")
+    width, height = result.size
+    draw = ImageDraw.Draw(result)
+    count = 0
+    for entry in regions:
+        if entry.get('page') is not None and entry['page'] != page:
+            continue
+        x, y, w, h = entry['region']
+        left, top = int(width * x), int(height * y)
+        right = min(width, left + max(1, round(width * w)))
+        bottom = min(height, top + max(1, round(height * h)))
+        draw.rectangle([left, top, right - 1, bottom - 1], fill=color)
+        count += 1
+    return result, count
+
+def redact_file(input_path, output_path, regions, color, page=None):
+    from PIL import Image
+
+    with Image.open(input_path) as image:
+        result, count = redact(image, regions, color, page)
+    result.save(output_path)
+    return count
+
+def main():
+    parser = argparse.ArgumentParser(description='Redact rectangles on images or PDFs with a solid fill')
+    parser.add_argument('--image', help='Single image path')
+    parser.add_argument('--dir', help='Directory containing images')
+    parser.add_argument('--pdf', help='PDF file path')
+    parser.add_argument('--output', required=True, help='Output image/PDF path or directory')
+    parser.add_argument('--regions', required=True, type=parse_regions,
+                        help='JSON array of {"region": [x, y, w, h], "page": n} with 0-1 fractions')
+    parser.add_argument('--color', type=parse_color, default=NAMED_COLORS['black'])
+    parser.add_argument('--dpi', type=int, default=200, help='Render DPI for PDF input')
+
+    args = parser.parse_args()
+
+    if not (args.image or args.dir or args.pdf):
+        print("Error: One of --image, --dir or --pdf must be provided", file=sys.stderr)
+        sys.exit(1)
+
+    try:
+        from PIL import Image
+    except ImportError:
+        print("Error: Pillow not installed. Run: pip install Pillow", file=sys.stderr)
+        sys.exit(1)
+
+    outputs = []
+    filled = 0
+
+    if args.image:
+        if not os.path.exists(args.image):
+            print(f"Error: Image not found: {args.image}", file=sys.stderr)
+            sys.exit(1)
+        output_dir = os.path.dirname(args.output)
+        if output_dir:
+            Path(output_dir).mkdir(parents=True, exist_ok=True)
+        filled += redact_file(args.image, args.output, args.regions, args.color, page=1)
+        outputs.append(args.output)
+        print(f"  ✓ Redacted: {args.output}")
+
+    elif args.dir:
+        if not os.path.isdir(args.dir):
+            print(f"Error: Directory not found: {args.dir}", file=sys.stderr)
+            sys.exit(1)
+        Path(args.output).mkdir(parents=True, exist_ok=True)
+        image_extensions = {'.png', '.jpg', '.jpeg', '.webp'}
+        image_files = sorted([
+            f for f in os.listdir(args.dir)
+            if os.path.isfile(os.path.join(args.dir, f))
+            and Path(f).suffix.lower() in image_extensions
+        ])
+        print(f"Found {len(image_files)} images in {args.dir}")
+        for i, image_file in enumerate(image_files):
+            output_path = os.path.join(args.output, image_file)
+            filled += redact_file(os.path.join(args.dir, image_file), output_path,
+                                  args.regions, args.color, page=i + 1)
+            outputs.append(output_path)
+            print(f"  ✓ Redacted: {image_file}")
+
+    else:
+        if not os.path.exists(args.pdf):
+            print(f"Error: PDF file not found: {args.pdf}", file=sys.stderr)
+            sys.exit(1)
+        try:
+            from rasterize import render_pages
+            import img2pdf
+        except ImportError as e:
+            print(f"Error: Missing dependency: {e}", file=sys.stderr)
+            print("Run: pip install pdf2image img2pdf", file=sys.stderr)
+            sys.exit(1)
+
+        import io
+        try:
+            images = render_pages(args.pdf, dpi=args.dpi)
+        except Exception as e:
+            print(f"Error converting PDF: {e}", file=sys.stderr)
+            print("Note: Make sure poppler or Ghostscript is installed (brew install poppler)", file=sys.stderr)
+            sys.exit(1)
+
+        # Pages are rasterized so no text or vector content survives under the fill
+        pages = []
+        for i, image in enumerate(images):
+            result, count = redact(image, args.regions, args.color, page=i + 1)
+            buffer = io.BytesIO()
+            result.save(buffer, "PNG")
+            pages.append(buffer.getvalue())
+            filled += count
+            print(f"  ✓ Page {i+1}: {count} regions redacted")
+
+        output_dir = os.path.dirname(args.output)
+        if output_dir:
+            Path(output_dir).mkdir(parents=True, exist_ok=True)
+        with open(args.output, "wb") as f:
+            f.write(img2pdf.convert(pages))
+        outputs.append(args.output)
+
+    print(f"\nComplete! Redacted {filled} regions in {len(outputs)} outputs")
+
+    # Output JSON for easy parsing
+    result = {
+        "outputs": outputs,
+        "count": len(outputs),
+        "regions_filled": filled
+    }
+    print(f"JSON_RESULT:{json.dumps(result)}")
+
+if __name__ == "__main__":
+    main()
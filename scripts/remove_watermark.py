@@ -2,74 +2,555 @@
 """
 Remove Watermark - Remove watermarks from images using OpenCV
 Usage: python remove_watermark.py --image <path> | --dir <path> [--output <dir>]
+                                  [--recursive] [--extensions png,jpg,...]
+                                  [--include <glob>]... [--exclude <glob>]...
+                                  [--region x,y,w,h] [--algorithm telea|ns|solid_fill] [--template <path>]
+                                  [--mode corner|grid] [--steps <json>]
+                                  [--inpaint-radius <px>] [--mask-padding <px>] [--feather <px>]
 """
 
 import sys
 import os
+import json
 import argparse
+import fnmatch
 from pathlib import Path
 
-def remove_watermark(image_path, output_path):
-    """Remove watermark from a single image."""
+# Default watermark region as fractions of the image: x, y, width, height.
+# NotebookLM watermark is typically in the bottom-right 20% x 8% area
+DEFAULT_REGION = (0.80, 0.92, 0.20, 0.08)
+
+ALGORITHMS = ('telea', 'ns', 'solid_fill')
+
+# corner: light text inside `region` (NotebookLM style)
+# grid:   stock-photo overlay - a large semi-transparent logo plus repeated
+#         text across the whole image
+MODES = ('corner', 'grid')
+
+# Extensions cleaned in a --dir run unless --extensions says otherwise
+DEFAULT_EXTENSIONS = ('png', 'jpg', 'jpeg', 'webp', 'gif', 'heic', 'heif', 'avif')
+
+# Formats OpenCV cannot read or write. They are decoded through Pillow with
+# the pillow-heif plugin and cleaned images are saved as PNG next to them.
+HEIF_EXTENSIONS = ('heic', 'heif', 'avif')
+
+_template_cache = {}
+
+def parse_region(value):
+    """Parse an "x,y,w,h" string of fractions into a tuple."""
+    parts = [float(p) for p in value.split(',')]
+    if len(parts) != 4 or not all(0.0 <= p <= 1.0 for p in parts):
+        raise argparse.ArgumentTypeError("region must be x,y,w,h fractions between 0 and 1")
+    return tuple(parts)
+
+def parse_extensions(value):
+    """Parse a comma-separated extension list such as "png,.JPG" into a set."""
+    extensions = {e.strip().lstrip('.').lower() for e in value.split(',') if e.strip()}
+    if not extensions:
+        raise argparse.ArgumentTypeError("extensions must name at least one extension")
+    return extensions
+
+def is_heif(path):
+    return Path(path).suffix.lower().lstrip('.') in HEIF_EXTENSIONS
+
+def output_name(path):
+    """Name of the cleaned image for `path`: the same, except PNG for HEIC/AVIF."""
+    return str(Path(path).with_suffix('.png')) if is_heif(path) else path
+
+def read_image(image_path):
+    """Load an image as a BGR array, or None if it cannot be decoded."""
     import cv2
-    import numpy as np
 
-    img = cv2.imread(image_path)
-    if img is None:
-        print(f"  Error: Cannot read image: {image_path}", file=sys.stderr)
+    if not is_heif(image_path):
+        return cv2.imread(image_path)
+    try:
+        import numpy as np
+        import pillow_heif
+        from PIL import Image
+    except ImportError:
+        print("  Error: pillow-heif not installed. Run: pip install pillow-heif", file=sys.stderr)
+        return None
+    pillow_heif.register_heif_opener()
+    if hasattr(pillow_heif, 'register_avif_opener'):
+        pillow_heif.register_avif_opener()
+    try:
+        with Image.open(image_path) as image:
+            rgb = np.array(image.convert('RGB'))
+    except Exception as e:
+        print(f"  Error: Cannot decode {image_path}: {e}", file=sys.stderr)
+        return None
+    return cv2.cvtColor(rgb, cv2.COLOR_RGB2BGR)
+
+def matches_patterns(rel_path, include=(), exclude=()):
+    """Apply --include/--exclude globs to a path relative to --dir."""
+    rel_path = rel_path.replace(os.sep, '/')
+    if include and not any(fnmatch.fnmatchcase(rel_path, p) for p in include):
         return False
+    return not any(fnmatch.fnmatchcase(rel_path, p) for p in exclude)
+
+def find_images(image_dir, extensions, recursive=False, output_dir=None,
+                include=(), exclude=()):
+    """Relative paths of the images to clean in `image_dir`, sorted.
+
+    With `recursive`, subdirectories are searched too, except `output_dir`
+    when it lies inside `image_dir`, so earlier results are not cleaned again.
+    `include` and `exclude` are glob patterns matched against the relative
+    path with `/` separators.
+    """
+    skip = os.path.realpath(output_dir) if output_dir else None
+    image_files = []
+    for root, dirs, files in os.walk(image_dir):
+        if recursive:
+            dirs[:] = sorted(d for d in dirs
+                             if os.path.realpath(os.path.join(root, d)) != skip)
+        else:
+            dirs[:] = []
+        rel_root = os.path.relpath(root, image_dir)
+        for f in files:
+            if (Path(f).suffix.lower().lstrip('.') in extensions
+                    and not f.endswith('_processed.png')):  # Skip already processed
+                rel_path = os.path.normpath(os.path.join(rel_root, f))
+                if matches_patterns(rel_path, include, exclude):
+                    image_files.append(rel_path)
+    return sorted(image_files)
+
+def load_template(template_path):
+    import cv2
+
+    if template_path not in _template_cache:
+        template = cv2.imread(template_path, cv2.IMREAD_GRAYSCALE)
+        if template is None:
+            raise ValueError(f"Cannot read template image: {template_path}")
+        _template_cache[template_path] = template
+    return _template_cache[template_path]
+
+def record_confidence(report, confidence):
+    """Keep the highest detection confidence seen for one image in `report`."""
+    if report is not None:
+        report['confidence'] = round(max(report.get('confidence', 0.0), float(confidence)), 3)
+
+def build_mask(img, region=DEFAULT_REGION, template=None, report=None):
+    """Build the inpainting mask for the watermark inside `region`.
+
+    The detection confidence recorded in `report` is the template match
+    score with a template, otherwise the share of `region` that looks like
+    watermark text.
+    """
+    import cv2
+    import numpy as np
 
     height, width = img.shape[:2]
 
-    # Define watermark region - bottom right corner
-    # NotebookLM watermark is typically in the bottom-right 20% x 8% area
-    roi_x = int(width * 0.80)
-    roi_y = int(height * 0.92)
-    roi_w = width - roi_x
-    roi_h = height - roi_y
+    # Define watermark region
+    roi_x = int(width * region[0])
+    roi_y = int(height * region[1])
+    roi_x2 = min(width, roi_x + max(1, int(width * region[2])))
+    roi_y2 = min(height, roi_y + max(1, int(height * region[3])))
 
     # Extract ROI
-    roi = img[roi_y:height, roi_x:width]
+    roi = img[roi_y:roi_y2, roi_x:roi_x2]
 
     # Convert to grayscale
     gray_roi = cv2.cvtColor(roi, cv2.COLOR_BGR2GRAY)
 
+    mask = np.zeros((height, width), dtype=np.uint8)
+
+    if template is not None:
+        # Locate the known watermark shape and mask its footprint
+        tmpl = load_template(template)
+        th, tw = tmpl.shape[:2]
+        if th > gray_roi.shape[0] or tw > gray_roi.shape[1]:
+            record_confidence(report, 0.0)
+            return mask
+        scores = cv2.matchTemplate(gray_roi, tmpl, cv2.TM_CCOEFF_NORMED)
+        _, best, _, (mx, my) = cv2.minMaxLoc(scores)
+        record_confidence(report, max(best, 0.0))
+        if best < 0.6:
+            return mask
+        match = gray_roi[my:my + th, mx:mx + tw]
+        mask_roi = cv2.inRange(match, 150, 240)
+        kernel = cv2.getStructuringElement(cv2.MORPH_RECT, (5, 5))
+        mask_roi = cv2.dilate(mask_roi, kernel, iterations=2)
+        mask[roi_y + my:roi_y + my + th, roi_x + mx:roi_x + mx + tw] = mask_roi
+        return mask
+
     # Detect light-colored text (watermarks are usually light gray)
     # Watermark text color is approximately in the 150-240 range
     mask_roi = cv2.inRange(gray_roi, 150, 240)
+    record_confidence(report, np.count_nonzero(mask_roi) / max(1, mask_roi.size))
 
     # Use morphological operations to connect watermark text parts
     kernel = cv2.getStructuringElement(cv2.MORPH_RECT, (5, 5))
     mask_roi = cv2.dilate(mask_roi, kernel, iterations=2)
 
     # Create full image mask
-    mask = np.zeros((height, width), dtype=np.uint8)
-    mask[roi_y:height, roi_x:width] = mask_roi
+    mask[roi_y:roi_y2, roi_x:roi_x2] = mask_roi
+    return mask
+
+def build_grid_mask(img, report=None):
+    """Estimate a whole-image mask for semi-transparent stock-photo overlays.
+
+    Overlay strokes are thin, brighter than their surroundings and close to
+    neutral gray, so a white top-hat restricted to low-saturation pixels
+    picks them out while leaving colourful highlights alone. The detection
+    confidence recorded in `report` is the share of the image masked.
+    """
+    import cv2
+    import numpy as np
+
+    height, width = img.shape[:2]
+    gray = cv2.cvtColor(img, cv2.COLOR_BGR2GRAY)
+    saturation = cv2.cvtColor(img, cv2.COLOR_BGR2HSV)[:, :, 1]
+
+    # Kernel wider than a stroke but narrower than the logo/text blocks
+    size = max(15, int(min(height, width) * 0.02)) | 1
+    kernel = cv2.getStructuringElement(cv2.MORPH_ELLIPSE, (size, size))
+    tophat = cv2.morphologyEx(gray, cv2.MORPH_TOPHAT, kernel)
+
+    # Otsu adapts to the overlay opacity; the floor keeps texture noise out
+    otsu, _ = cv2.threshold(tophat, 0, 255, cv2.THRESH_BINARY + cv2.THRESH_OTSU)
+    _, strokes = cv2.threshold(tophat, max(otsu, 20), 255, cv2.THRESH_BINARY)
+
+    neutral = cv2.inRange(saturation, 0, 60)
+    mask = cv2.bitwise_and(strokes, neutral)
+
+    # Drop isolated specks, then close small gaps inside letters
+    speck = cv2.getStructuringElement(cv2.MORPH_RECT, (3, 3))
+    mask = cv2.morphologyEx(mask, cv2.MORPH_OPEN, speck)
+    mask = cv2.dilate(mask, speck, iterations=1)
+    record_confidence(report, np.count_nonzero(mask) / max(1, mask.size))
+    return mask.astype(np.uint8)
+
+def parse_steps(value):
+    """Parse the --steps JSON array and check each operation."""
+    try:
+        steps = json.loads(value)
+    except json.JSONDecodeError as e:
+        raise argparse.ArgumentTypeError(f"steps must be a JSON array: {e}")
+    if not isinstance(steps, list):
+        raise argparse.ArgumentTypeError("steps must be a JSON array")
+    for step in steps:
+        if not isinstance(step, dict) or step.get('op') not in ('remove', 'despeckle'):
+            raise argparse.ArgumentTypeError(f"unsupported step: {step}")
+    return steps
+
+POST_FILTERS = ('despeckle', 'denoise', 'normalize_contrast')
+
+def parse_post_filters(value):
+    """Parse the --post-filters JSON array and check each filter."""
+    try:
+        filters = json.loads(value)
+    except json.JSONDecodeError as e:
+        raise argparse.ArgumentTypeError(f"post filters must be a JSON array: {e}")
+    if not isinstance(filters, list):
+        raise argparse.ArgumentTypeError("post filters must be a JSON array")
+    for post_filter in filters:
+        if not isinstance(post_filter, dict) or post_filter.get('op') not in POST_FILTERS:
+            raise argparse.ArgumentTypeError(f"unsupported post filter: {post_filter}")
+    return filters
+
+def apply_post_filters(img, filters):
+    """Clean up a scan after removal: despeckle (median filter), denoise
+    (non-local means) and normalize_contrast (level stretch), in order."""
+    import cv2
+    import numpy as np
+
+    for post_filter in filters or ():
+        op = post_filter['op']
+        if op == 'despeckle':
+            img = cv2.medianBlur(img, int(post_filter.get('size') or 3))
+        elif op == 'denoise':
+            strength = float(post_filter.get('strength') or 10)
+            if img.ndim == 2 or is_cmyk(img):
+                img = cv2.fastNlMeansDenoising(img, None, strength, 7, 21)
+            else:
+                img = cv2.fastNlMeansDenoisingColored(img, None, strength, strength, 7, 21)
+        elif op == 'normalize_contrast':
+            # Levels come from the luminance so colours keep their balance
+            clip = post_filter.get('clip')
+            clip = 1.0 if clip is None else float(clip)
+            cmyk = is_cmyk(img)
+            if cmyk:
+                gray = cv2.cvtColor(cmyk_to_bgr(img), cv2.COLOR_BGR2GRAY)
+                # Ink is stretched as the light it leaves, like RGB
+                img = 255 - img
+            else:
+                gray = img if img.ndim == 2 else cv2.cvtColor(img, cv2.COLOR_BGR2GRAY)
+            low, high = np.percentile(gray, (clip, 100 - clip))
+            if high - low >= 1:
+                scale = 255.0 / (high - low)
+                img = np.clip((img.astype(np.float32) - low) * scale, 0, 255).astype(np.uint8)
+            if cmyk:
+                img = 255 - img
+    return img
+
+def is_cmyk(img):
+    """Whether a decoded image holds C, M, Y and K channels. Images are read
+    as BGR everywhere, so four channels only come from CMYK PDF renders."""
+    return img.ndim == 3 and img.shape[2] == 4
+
+def cmyk_to_bgr(img):
+    """A BGR approximation of a CMYK image, good enough to find marks in but
+    not for output: it ignores how real inks mix."""
+    import numpy as np
+
+    ink = img.astype(np.float32) / 255.0
+    light = (1.0 - ink[:, :, :3]) * (1.0 - ink[:, :, 3:])
+    return np.round(light[:, :, ::-1] * 255.0).astype(np.uint8)
+
+def apply_steps(img, steps, report=None):
+    """Run each step on the in-memory image so it is decoded and encoded once.
+
+    Returns (result, removed) where `removed` is true if any remove step found a watermark.
+    """
+    import cv2
+
+    removed_any = False
+    for step in steps:
+        if step['op'] == 'remove':
+            img, removed = clean_image(
+                img,
+                region=tuple(step.get('region') or DEFAULT_REGION),
+                algorithm=step.get('algorithm') or 'telea',
+                template=step.get('template'),
+                mode=step.get('mode') or 'corner',
+                inpaint_radius=step.get('inpaint_radius', 5),
+                mask_padding=step.get('mask_padding', 3),
+                feather=step.get('feather', 0),
+                report=report,
+            )
+            removed_any = removed_any or removed
+        elif step['op'] == 'despeckle':
+            img = cv2.medianBlur(img, int(step.get('kernel_size') or step.get('size') or 3))
+    return img, removed_any
+
+def solid_fill(img, mask):
+    """Fill `mask` with the background around it instead of inpainting.
+
+    The fill is the median colour of a band just outside the mask, with noise
+    matching the band's spread so paper grain does not turn into a flat patch.
+    Far faster than inpainting, and seamless on plain page corners.
+    """
+    import cv2
+    import numpy as np
+
+    kernel = cv2.getStructuringElement(cv2.MORPH_RECT, (15, 15))
+    band = cv2.subtract(cv2.dilate(mask, kernel, iterations=1), mask)
+    samples = img[band > 0]
+    if len(samples) == 0:
+        return img
+    color = np.median(samples, axis=0)
+    spread = np.minimum(samples.std(axis=0), 12.0)
+
+    # Seeded so the same input always gives the same output
+    rng = np.random.default_rng(0)
+    count = int(np.count_nonzero(mask))
+    fill = color + rng.standard_normal((count, img.shape[2])) * spread
+    result = img.copy()
+    result[mask > 0] = np.clip(fill, 0, 255).astype(img.dtype)
+    return result
+
+def feather_edges(original, cleaned, mask, feather):
+    """Blend `cleaned` into `original` across a soft edge `feather` pixels wide
+    just inside `mask`, so the filled region does not end in a visible seam."""
+    import cv2
+    import numpy as np
+
+    if feather <= 0:
+        return cleaned
+    size = 2 * feather + 1
+    # Outside the mask `cleaned` is the original, so the ramp has to run
+    # inward: full replacement from `feather` pixels in, fading to the edge
+    kernel = cv2.getStructuringElement(cv2.MORPH_ELLIPSE, (size, size))
+    core = cv2.erode((mask > 0).astype(np.uint8), kernel)
+    alpha = cv2.GaussianBlur(core.astype(np.float32), (size, size), 0)
+    alpha = (alpha * (mask > 0))[:, :, np.newaxis]
+    blended = cleaned.astype(np.float32) * alpha + original.astype(np.float32) * (1.0 - alpha)
+    return np.clip(blended, 0, 255).astype(original.dtype)
+
+def clean_image(img, region=DEFAULT_REGION, algorithm='telea', template=None, mode='corner', steps=None,
+                inpaint_radius=5, mask_padding=3, feather=0, report=None, post_filters=None):
+    """Remove the watermark from a decoded image.
+
+    The detected mask is grown by `mask_padding` pixels, filled, and with
+    `feather` blended into the original over that many pixels around its edge.
+    `post_filters` then run on the whole image, watermark or not.
+
+    Returns (result, removed) where `removed` tells whether a watermark was found.
+    When given a dict as `report`, records the detection confidence in it.
+    """
+    if steps:
+        result, removed = apply_steps(img, steps, report)
+    else:
+        mask = detect_mask(img, region, template, mode, mask_padding, report)
+        removed = mask is not None
+        result = fill_mask(img, mask, algorithm, inpaint_radius, feather) if removed else img
+    return apply_post_filters(result, post_filters), removed
+
+def detect_mask(img, region=DEFAULT_REGION, template=None, mode='corner', mask_padding=3, report=None):
+    """The watermark mask grown by `mask_padding` pixels, or None when no
+    watermark is found."""
+    import cv2
+    import numpy as np
+
+    # The mask is found on an RGB view of a CMYK page and filled in CMYK
+    if is_cmyk(img):
+        img = cmyk_to_bgr(img)
+
+    if mode == 'grid':
+        mask = build_grid_mask(img, report)
+    else:
+        mask = build_mask(img, region, template, report)
 
     # Check if watermark was detected
-    if np.sum(mask) > 100:
-        # Expand mask to ensure full coverage
-        kernel_expand = cv2.getStructuringElement(cv2.MORPH_RECT, (7, 7))
+    if np.sum(mask) <= 100:
+        return None
+
+    # Expand mask to ensure full coverage
+    if mask_padding > 0:
+        size = 2 * mask_padding + 1
+        kernel_expand = cv2.getStructuringElement(cv2.MORPH_RECT, (size, size))
         mask = cv2.dilate(mask, kernel_expand, iterations=1)
+    return mask
 
-        # Use OpenCV inpaint to repair
-        result = cv2.inpaint(img, mask, inpaintRadius=5, flags=cv2.INPAINT_TELEA)
+def fill_mask(img, mask, algorithm='telea', inpaint_radius=5, feather=0):
+    """Fill `mask` with `algorithm` and feather the edge of the fill."""
+    import cv2
+    import numpy as np
 
-        cv2.imwrite(output_path, result)
-        return True
+    if algorithm == 'solid_fill':
+        result = solid_fill(img, mask)
     else:
-        # No watermark detected, copy original
-        cv2.imwrite(output_path, img)
+        # Use OpenCV inpaint to repair
+        flags = cv2.INPAINT_NS if algorithm == 'ns' else cv2.INPAINT_TELEA
+        if is_cmyk(img):
+            # inpaint takes at most three channels: CMY and K are filled apart
+            colors = cv2.inpaint(img[:, :, :3], mask, inpaintRadius=inpaint_radius, flags=flags)
+            black = cv2.inpaint(img[:, :, 3], mask, inpaintRadius=inpaint_radius, flags=flags)
+            result = np.dstack((colors, black))
+        else:
+            result = cv2.inpaint(img, mask, inpaintRadius=inpaint_radius, flags=flags)
+    return feather_edges(img, result, mask, feather)
+
+def remove_watermark(image_path, output_path, region=DEFAULT_REGION, algorithm='telea', template=None,
+                     mode='corner', steps=None, inpaint_radius=5, mask_padding=3, feather=0, report=None,
+                     post_filters=None):
+    """Remove watermark from a single image.
+
+    When given a dict as `report`, records the image's status and detection
+    confidence in it.
+    """
+    import cv2
+
+    img = read_image(image_path)
+    if img is None:
+        print(f"  Error: Cannot read image: {image_path}", file=sys.stderr)
+        if report is not None:
+            report.update(status='failed', output_path=None, error='Cannot read image')
         return False
 
+    result, removed = clean_image(img, region, algorithm, template, mode, steps,
+                                  inpaint_radius, mask_padding, feather, report, post_filters)
+
+    # No watermark detected writes the original unchanged
+    cv2.imwrite(output_path, result)
+    if report is not None:
+        report['status'] = 'watermark_removed' if removed else 'no_watermark'
+    return removed
+
+def add_removal_arguments(parser):
+    """Register the removal settings shared by every script that cleans images."""
+    parser.add_argument('--region', type=parse_region, default=DEFAULT_REGION,
+                        help='Watermark region as x,y,w,h fractions (default 0.80,0.92,0.20,0.08)')
+    parser.add_argument('--algorithm', choices=ALGORITHMS, default='telea',
+                        help='Inpainting algorithm, or solid_fill to paint the surrounding background '
+                             'colour over the watermark (default telea)')
+    parser.add_argument('--template', help='Watermark template image for template matching')
+    parser.add_argument('--mode', choices=MODES, default='corner',
+                        help='corner: text inside --region (default); grid: whole-image stock-photo overlay')
+    parser.add_argument('--inpaint-radius', type=int, default=5,
+                        help='Neighbourhood in pixels inpainting draws from (default 5)')
+    parser.add_argument('--mask-padding', type=int, default=3,
+                        help='Pixels the detected mask is grown by before filling (default 3)')
+    parser.add_argument('--feather', type=int, default=0,
+                        help='Width in pixels of the soft edge blending the fill into the image '
+                             '(default 0, a hard edge)')
+    parser.add_argument('--post-filters', type=parse_post_filters,
+                        help='JSON array of clean-up passes run after removal, e.g. '
+                             '[{"op":"despeckle"},{"op":"denoise","strength":10},{"op":"normalize_contrast"}]')
+    parser.add_argument('--steps', type=parse_steps,
+                        help='JSON array of steps, e.g. [{"op":"remove","region":[...]},{"op":"despeckle","kernel_size":3}]; '
+                             'overrides the single-pass settings above')
+
+def removal_options(args):
+    """Keyword arguments for remove_watermark/clean_image from parsed CLI args."""
+    return {
+        'region': args.region,
+        'algorithm': args.algorithm,
+        'template': args.template,
+        'mode': args.mode,
+        'steps': args.steps,
+        'inpaint_radius': args.inpaint_radius,
+        'mask_padding': args.mask_padding,
+        'feather': args.feather,
+        'post_filters': args.post_filters,
+    }
+
+def clean_stdio(args):
+    """Clean the image on stdin without touching the file system."""
+    try:
+        import cv2
+        import numpy as np
+    except ImportError:
+        print("Error: opencv-python not installed. Run: pip install opencv-python-headless numpy", file=sys.stderr)
+        sys.exit(1)
+
+    data = sys.stdin.buffer.read()
+    img = cv2.imdecode(np.frombuffer(data, np.uint8), cv2.IMREAD_COLOR)
+    if img is None:
+        print("Error: Cannot decode the image read from stdin", file=sys.stderr)
+        sys.exit(1)
+
+    result, removed = clean_image(img, **removal_options(args))
+    ok, encoded = cv2.imencode('.png', result)
+    if not ok:
+        print("Error: Cannot encode the cleaned image", file=sys.stderr)
+        sys.exit(1)
+    sys.stdout.buffer.write(encoded.tobytes())
+    sys.stdout.buffer.flush()
+
+    print("  ✓ Watermark removed" if removed else "  ○ No watermark detected", file=sys.stderr)
+    result = {"processed": int(removed), "skipped": int(not removed)}
+    print(f"JSON_RESULT:{json.dumps(result)}", file=sys.stderr)
+
 def main():
     parser = argparse.ArgumentParser(description='Remove watermarks from images')
     parser.add_argument('--image', help='Single image path')
     parser.add_argument('--dir', help='Directory containing images')
     parser.add_argument('--output', help='Output directory (optional)')
+    parser.add_argument('--stdio', action='store_true',
+                        help='Read one image from stdin and write the cleaned PNG to stdout; '
+                             'messages and the JSON result go to stderr')
+    parser.add_argument('--recursive', action='store_true',
+                        help='With --dir, also clean images in subdirectories, '
+                             'mirroring the tree under --output')
+    parser.add_argument('--extensions', type=parse_extensions, default=set(DEFAULT_EXTENSIONS),
+                        help='With --dir, comma-separated extensions to clean '
+                             f'(default {",".join(DEFAULT_EXTENSIONS)})')
+    parser.add_argument('--include', action='append', default=[],
+                        help='With --dir, only clean images whose relative path matches '
+                             'this glob (repeatable)')
+    parser.add_argument('--exclude', action='append', default=[],
+                        help='With --dir, skip images whose relative path matches this '
+                             'glob (repeatable)')
+    add_removal_arguments(parser)
 
     args = parser.parse_args()
 
+    if args.stdio:
+        clean_stdio(args)
+        return
+
     if not args.image and not args.dir:
         print("Error: Either --image or --dir must be provided", file=sys.stderr)
         sys.exit(1)
@@ -82,8 +563,14 @@ def main():
         print("Error: opencv-python not installed. Run: pip install opencv-python-headless numpy", file=sys.stderr)
         sys.exit(1)
 
+    if args.template and not os.path.exists(args.template):
+        print(f"Error: Template not found: {args.template}", file=sys.stderr)
+        sys.exit(1)
+
     processed_count = 0
     skipped_count = 0
+    failed_count = 0
+    files = []
 
     if args.image:
         # Process single image
@@ -94,15 +581,20 @@ def main():
 
         if args.output:
             Path(args.output).mkdir(parents=True, exist_ok=True)
-            output_path = os.path.join(args.output, os.path.basename(image_path))
+            output_path = os.path.join(args.output, output_name(os.path.basename(image_path)))
         else:
             # Overwrite original
-            output_path = image_path
+            output_path = output_name(image_path)
 
         print(f"Processing: {image_path}")
-        if remove_watermark(image_path, output_path):
+        report = {'input_path': image_path, 'output_path': output_path}
+        files.append(report)
+        if remove_watermark(image_path, output_path, report=report, **removal_options(args)):
             print(f"  ✓ Watermark removed: {output_path}")
             processed_count = 1
+        elif report['status'] == 'failed':
+            print(f"  ✗ Failed: {report['error']}")
+            failed_count = 1
         else:
             print(f"  ○ No watermark detected: {output_path}")
             skipped_count = 1
@@ -117,39 +609,45 @@ def main():
         output_dir = args.output if args.output else image_dir
         Path(output_dir).mkdir(parents=True, exist_ok=True)
 
-        # Get all image files
-        image_extensions = {'.png', '.jpg', '.jpeg', '.webp', '.gif'}
-        image_files = sorted([
-            f for f in os.listdir(image_dir)
-            if os.path.isfile(os.path.join(image_dir, f))
-            and Path(f).suffix.lower() in image_extensions
-            and not f.endswith('_processed.png')  # Skip already processed
-        ])
+        image_files = find_images(image_dir, args.extensions, args.recursive,
+                                  output_dir if args.output else None,
+                                  args.include, args.exclude)
 
         print(f"Found {len(image_files)} images in {image_dir}")
 
         for image_file in image_files:
             input_path = os.path.join(image_dir, image_file)
-            output_path = os.path.join(output_dir, image_file)
+            output_path = os.path.join(output_dir, output_name(image_file))
+            Path(output_path).parent.mkdir(parents=True, exist_ok=True)
 
             print(f"Processing: {image_file}")
-            if remove_watermark(input_path, output_path):
+            report = {'input_path': input_path, 'output_path': output_path}
+            files.append(report)
+            if remove_watermark(input_path, output_path, report=report, **removal_options(args)):
                 print(f"  ✓ Watermark removed")
                 processed_count += 1
+            elif report['status'] == 'failed':
+                print(f"  ✗ Failed: {report['error']}")
+                failed_count += 1
             else:
                 print(f"  ○ No watermark detected")
                 skipped_count += 1
 
-    print(f"\nComplete! Processed: {processed_count}, Skipped: {skipped_count}")
+    print(f"\nComplete! Processed: {processed_count}, Skipped: {skipped_count}, Failed: {failed_count}")
 
     # Output JSON for easy parsing
     import json
     result = {
         "processed": processed_count,
         "skipped": skipped_count,
-        "output_dir": args.output or (args.dir if args.dir else os.path.dirname(args.image))
+        "failed": failed_count,
+        "output_dir": args.output or (args.dir if args.dir else os.path.dirname(args.image)),
+        "files": files,
     }
     print(f"JSON_RESULT:{json.dumps(result)}")
+    # A single image that cannot be read fails the run, as in-process
+    if args.image and failed_count:
+        sys.exit(1)
 
 if __name__ == "__main__":
     main()
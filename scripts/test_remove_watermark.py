@@ -0,0 +1,43 @@
+#!/usr/bin/env python3
+"""
+Checks for remove_watermark.py
+Usage: python -m unittest discover -s scripts -p 'test_*.py'
+"""
+
+import unittest
+
+try:
+    import cv2
+    import numpy as np
+except ImportError:
+    cv2 = None
+
+import remove_watermark
+
+
+@unittest.skipIf(cv2 is None, 'needs opencv-python and numpy')
+class FeatherEdgesTest(unittest.TestCase):
+    def setUp(self):
+        self.original = np.full((60, 60, 3), 200, np.uint8)
+        self.cleaned = np.full((60, 60, 3), 100, np.uint8)
+        self.mask = np.zeros((60, 60), np.uint8)
+        self.mask[20:40, 20:40] = 255
+        # Inpainting only changes the masked pixels
+        self.cleaned[self.mask == 0] = self.original[self.mask == 0]
+
+    def test_zero_feather_keeps_the_fill(self):
+        result = remove_watermark.feather_edges(self.original, self.cleaned, self.mask, 0)
+        np.testing.assert_array_equal(result, self.cleaned)
+
+    def test_feather_blends_the_fill_edge(self):
+        result = remove_watermark.feather_edges(self.original, self.cleaned, self.mask, 4)
+        # The border of the fill moves toward the original...
+        self.assertGreater(int(result[22, 30, 0]), 100)
+        self.assertLess(int(result[22, 30, 0]), 200)
+        # ...the middle stays filled and the outside stays untouched
+        self.assertEqual(int(result[30, 30, 0]), 100)
+        np.testing.assert_array_equal(result[self.mask == 0], self.original[self.mask == 0])
+
+
+if __name__ == '__main__':
+    unittest.main()
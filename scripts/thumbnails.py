@@ -0,0 +1,65 @@
+#!/usr/bin/env python3
+"""
+Thumbnails - Small before/after images of one page of a cleaned PDF
+Usage: python thumbnails.py <before_pdf> <after_pdf> <page> <output_dir> [--size <px>]
+                     [--after-page <page>]
+
+--after-page names the page of after_pdf to show when pages were left out of
+it, so `page` of the input landed elsewhere (default: the same number).
+"""
+
+import sys
+import os
+import json
+import argparse
+from pathlib import Path
+
+# Low resolution is enough for an image that is shrunk to a thumbnail
+RENDER_DPI = 72
+
+def render_thumbnail(pdf_path, page, size, output_path):
+    """Render `page` of `pdf_path` and save it no larger than size x size."""
+    from rasterize import render_pages
+
+    images = render_pages(pdf_path, dpi=RENDER_DPI, first_page=page, last_page=page)
+    if not images:
+        raise ValueError(f"Page {page} does not exist in {pdf_path}")
+    image = images[0]
+    image.thumbnail((size, size))
+    image.save(output_path, "PNG")
+
+def main():
+    parser = argparse.ArgumentParser(description='Render before/after thumbnails of one PDF page')
+    parser.add_argument('before_pdf')
+    parser.add_argument('after_pdf')
+    parser.add_argument('page', type=int)
+    parser.add_argument('output_dir')
+    parser.add_argument('--size', type=int, default=256,
+                        help='Longest side of the thumbnails in pixels (default 256)')
+    parser.add_argument('--after-page', type=int,
+                        help='Page of after_pdf to show (default: page)')
+    args = parser.parse_args()
+
+    try:
+        from PIL import Image  # noqa: F401
+    except ImportError as e:
+        print(f"Error: Missing dependency: {e}", file=sys.stderr)
+        print("Run: pip install pdf2image Pillow", file=sys.stderr)
+        sys.exit(1)
+
+    Path(args.output_dir).mkdir(parents=True, exist_ok=True)
+    before_path = os.path.join(args.output_dir, f"page_{args.page:03d}_before.png")
+    after_path = os.path.join(args.output_dir, f"page_{args.page:03d}_after.png")
+    try:
+        render_thumbnail(args.before_pdf, args.page, args.size, before_path)
+        render_thumbnail(args.after_pdf, args.after_page or args.page, args.size, after_path)
+    except Exception as e:
+        print(f"Error rendering thumbnails: {e}", file=sys.stderr)
+        sys.exit(1)
+
+    print(f"Thumbnails of page {args.page}: {before_path}, {after_path}")
+    result = {"page": args.page, "before": before_path, "after": after_path}
+    print(f"JSON_RESULT:{json.dumps(result)}")
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,44 @@
+//! Library API - the core pipeline as typed async functions
+//!
+//! These are the operations behind the MCP tools, for embedding the pipeline
+//! in other Rust programs. They take plain paths and settings and return
+//! typed results, with failures as `anyhow` errors rather than
+//! `CallToolResult`s.
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use watermark_remover_mcp_server::api;
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let settings = api::resolve_settings(api::RemovalSettings::default(), Some("notebooklm")).await?;
+//! let pages = api::process_pdf(Path::new("deck.pdf"), Path::new("deck_pages"), &settings).await?;
+//! let order = api::ImageOrder::Natural;
+//! let resize = api::PageResize::default();
+//! api::images_to_pdf(&pages.output_dir, Path::new("deck_clean.pdf"), Some("page_*.png"), order, resize, false).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub use crate::tools::CleanedImage;
+pub use crate::tools::DirFilter;
+pub use crate::tools::ImageOrder;
+pub use crate::tools::ImagesToPdfOutput;
+pub use crate::tools::PageResize;
+pub use crate::tools::PageResult;
+pub use crate::tools::PageStatus;
+pub use crate::tools::PdfToImagesOutput;
+pub use crate::tools::PostFilter;
+pub use crate::tools::ProcessPdfOutput;
+pub use crate::tools::RemovalSettings;
+pub use crate::tools::RemovalStep;
+pub use crate::tools::RemoveWatermarkOutput;
+pub use crate::tools::StepTimings;
+pub use crate::tools::WatermarkInput;
+pub use crate::tools::append_images_to_pdf;
+pub use crate::tools::images_to_pdf;
+pub use crate::tools::pdf_to_images;
+pub use crate::tools::process_pdf;
+pub use crate::tools::process_pdf_to_pdf;
+pub use crate::tools::remove_watermark;
+pub use crate::tools::remove_watermark_bytes;
+pub use crate::tools::resolve_settings;
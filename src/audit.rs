@@ -0,0 +1,136 @@
+//! Audit log - one JSON line per tool call
+//!
+//! Enabled by `WATERMARK_AUDIT_LOG` or the config file's `audit_log`. Each
+//! line records when a tool ran, with which arguments (inline base64 data is
+//! replaced by its length), how long it took and whether it succeeded, so a
+//! deployment can review which documents an agent read or modified.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tracing::info;
+use tracing::warn;
+
+use crate::config::config;
+use crate::tools::ToolResult;
+
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    timestamp: u64,
+    tool: &'a str,
+    arguments: Value,
+    duration_ms: u128,
+    /// `ok` or `error`
+    outcome: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Value>,
+}
+
+static AUDIT_LOG: OnceLock<Option<Mutex<File>>> = OnceLock::new();
+
+/// Path of the audit log, if auditing is enabled.
+fn audit_log_path() -> Option<PathBuf> {
+    std::env::var_os("WATERMARK_AUDIT_LOG")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config().audit_log.clone())
+}
+
+/// Append one record for a finished tool call. Write failures are logged
+/// and otherwise ignored so auditing never breaks a call.
+pub fn record_tool_call(
+    tool: &str,
+    arguments: Option<&Value>,
+    duration: Duration,
+    outcome: &ToolResult,
+) {
+    let Some(file) = AUDIT_LOG.get_or_init(open_audit_log) else {
+        return;
+    };
+
+    let (outcome, error) = match outcome {
+        Ok(result) if result.is_error == Some(true) => ("error", None),
+        Ok(_) => ("ok", None),
+        Err(e) => (
+            "error",
+            Some(serde_json::json!({
+                "code": e.code(),
+                "kind": e.kind(),
+                "message": e.to_string(),
+            })),
+        ),
+    };
+    let record = AuditRecord {
+        timestamp: unix_now(),
+        tool,
+        arguments: arguments.map(redact_inline_data).unwrap_or(Value::Null),
+        duration_ms: duration.as_millis(),
+        outcome,
+        error,
+    };
+
+    let Ok(mut line) = serde_json::to_vec(&record) else {
+        return;
+    };
+    line.push(b'\n');
+    let mut file = file
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Err(e) = file.write_all(&line) {
+        warn!("Failed to write audit log: {e}");
+    }
+}
+
+fn open_audit_log() -> Option<Mutex<File>> {
+    let path = audit_log_path()?;
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(file) => {
+            info!("Writing audit log to {}", path.display());
+            Some(Mutex::new(file))
+        }
+        Err(e) => {
+            warn!("Audit log disabled: cannot open {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Copy of `arguments` with every `*_base64` string replaced by a short
+/// placeholder.
+fn redact_inline_data(arguments: &Value) -> Value {
+    match arguments {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::String(data) if key.ends_with("_base64") => {
+                            Value::String(format!("<{} bytes of base64>", data.len()))
+                        }
+                        _ => redact_inline_data(value),
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact_inline_data).collect()),
+        _ => arguments.clone(),
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,320 @@
+//! Command-line mode - runs the tool handlers directly, without MCP
+
+use anyhow::Result;
+use clap::Args;
+use clap::Parser;
+use clap::Subcommand;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use serde_json::json;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use watermark_remover_mcp_server::api;
+use watermark_remover_mcp_server::api::PostFilter;
+use watermark_remover_mcp_server::api::RemovalSettings;
+use watermark_remover_mcp_server::api::resolve_settings;
+use watermark_remover_mcp_server::logging::init_logging;
+use watermark_remover_mcp_server::tools::ToolResult;
+use watermark_remover_mcp_server::tools::handle_check_environment;
+use watermark_remover_mcp_server::tools::handle_preview_page;
+
+#[derive(Parser)]
+#[command(version, about = "Watermark Remover: MCP server and command-line tool")]
+pub struct Cli {
+    /// Configuration file (default: ~/.config/watermark-remover/config.toml)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the MCP server over stdio (the default)
+    Serve,
+    /// Remove watermarks from a PDF and write a cleaned PDF
+    Process(ProcessArgs),
+    /// Check whether a page has a watermark and write before/after thumbnails
+    Detect(DetectArgs),
+    /// Check Python, modules, poppler and the scripts directory
+    Doctor,
+}
+
+/// Removal settings shared by `process` and `detect`
+#[derive(Args)]
+pub struct RemovalArgs {
+    /// Named profile, e.g. notebooklm
+    #[arg(long)]
+    profile: Option<String>,
+    /// Render DPI
+    #[arg(long)]
+    dpi: Option<u32>,
+    /// Watermark region as x,y,width,height fractions
+    #[arg(long, value_name = "X,Y,W,H", value_parser = parse_region)]
+    region: Option<[f64; 4]>,
+    /// Watermark position instead of a region: bottom_right, bottom_left,
+    /// top_right, top_left, center, footer or header
+    #[arg(long, conflicts_with = "region")]
+    position: Option<String>,
+    /// Inpainting algorithm (telea, ns or solid_fill)
+    #[arg(long)]
+    algorithm: Option<String>,
+    /// Detection mode (corner or grid)
+    #[arg(long)]
+    mode: Option<String>,
+    /// Watermark template image
+    #[arg(long)]
+    template: Option<String>,
+    /// Neighbourhood in pixels that inpainting draws from
+    #[arg(long)]
+    inpaint_radius: Option<u32>,
+    /// Pixels the detected mask is grown by before filling
+    #[arg(long)]
+    mask_padding: Option<u32>,
+    /// Width in pixels of the soft edge blending the fill into the image
+    #[arg(long)]
+    feather: Option<u32>,
+    /// What to do with blank pages: process, skip or drop
+    #[arg(long)]
+    blank_pages: Option<String>,
+    /// What to do with pages repeating the one before: keep, report or drop
+    #[arg(long)]
+    duplicate_pages: Option<String>,
+    /// Re-render pages with a tiny watermark at up to this DPI for removal
+    #[arg(long)]
+    adaptive_dpi: Option<u32>,
+    /// Clean PDF pages in CMYK and write a CMYK PDF, for print
+    #[arg(long)]
+    cmyk_output: bool,
+    /// Clean-up pass after removal: despeckle, denoise or
+    /// normalize_contrast; repeat to run several in order
+    #[arg(long = "post-filter", value_name = "FILTER", value_parser = parse_post_filter)]
+    post_filters: Vec<PostFilter>,
+}
+
+#[derive(Args)]
+pub struct ProcessArgs {
+    /// Input PDF, EPUB or DjVu file
+    input: PathBuf,
+    /// Output PDF (default: <input>_nowatermark.pdf)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Replace the output PDF if it already exists
+    #[arg(long)]
+    overwrite: bool,
+    /// Write <input>_nowatermark_2.pdf (or _3, ...) if the default output exists
+    #[arg(long, conflicts_with_all = ["output", "overwrite"])]
+    auto_rename: bool,
+    /// Keep the cleaned page images in this directory
+    #[arg(long, value_name = "DIR")]
+    pages_dir: Option<PathBuf>,
+    /// Write a byte-identical PDF for the same input: fixed dates, no random
+    /// document ID, pages in order
+    #[arg(long)]
+    deterministic: bool,
+    #[command(flatten)]
+    removal: RemovalArgs,
+}
+
+#[derive(Args)]
+pub struct DetectArgs {
+    /// Input PDF, EPUB or DjVu file
+    input: PathBuf,
+    /// 1-based page number
+    #[arg(long, default_value_t = 1)]
+    page: u32,
+    /// Directory for the before/after thumbnails
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+    #[command(flatten)]
+    removal: RemovalArgs,
+}
+
+fn parse_region(value: &str) -> std::result::Result<[f64; 4], String> {
+    let parts: Vec<f64> = value
+        .split(',')
+        .map(|p| p.trim().parse::<f64>().map_err(|e| e.to_string()))
+        .collect::<std::result::Result<_, _>>()?;
+    parts
+        .try_into()
+        .map_err(|_| "expected four comma-separated numbers".to_string())
+}
+
+fn parse_post_filter(value: &str) -> std::result::Result<PostFilter, String> {
+    serde_json::from_value(json!({ "op": value }))
+        .map_err(|_| "expected despeckle, denoise or normalize_contrast".to_string())
+}
+
+impl RemovalArgs {
+    fn settings(&self) -> RemovalSettings {
+        RemovalSettings {
+            region: self.region,
+            position: self.position.clone(),
+            size: None,
+            algorithm: self.algorithm.clone(),
+            template: self.template.clone(),
+            dpi: self.dpi,
+            mode: self.mode.clone(),
+            inpaint_radius: self.inpaint_radius,
+            mask_padding: self.mask_padding,
+            feather: self.feather,
+            blank_pages: self.blank_pages.clone(),
+            duplicate_pages: self.duplicate_pages.clone(),
+            adaptive_dpi: self.adaptive_dpi,
+            cmyk_output: self.cmyk_output.then_some(true),
+            post_filters: (!self.post_filters.is_empty()).then(|| self.post_filters.clone()),
+            steps: None,
+        }
+    }
+
+    /// Merge the settings into a tool call's arguments.
+    fn extend(self, args: &mut serde_json::Value) {
+        let settings = json!({
+            "profile": self.profile,
+            "dpi": self.dpi,
+            "region": self.region,
+            "position": self.position,
+            "algorithm": self.algorithm,
+            "mode": self.mode,
+            "template": self.template,
+            "inpaint_radius": self.inpaint_radius,
+            "mask_padding": self.mask_padding,
+            "feather": self.feather,
+            "blank_pages": self.blank_pages,
+            "duplicate_pages": self.duplicate_pages,
+            "adaptive_dpi": self.adaptive_dpi,
+            "cmyk_output": self.cmyk_output.then_some(true),
+            "post_filters": (!self.post_filters.is_empty()).then_some(self.post_filters),
+        });
+        if let (Some(args), Some(settings)) = (args.as_object_mut(), settings.as_object()) {
+            for (key, value) in settings {
+                if !value.is_null() {
+                    args.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Run a CLI subcommand. `Serve` is handled by the caller.
+pub async fn run_command(command: Command) -> Result<ExitCode> {
+    init_logging();
+
+    match command {
+        Command::Serve => unreachable!("serve is handled by run_main"),
+        Command::Process(args) => process(args).await,
+        Command::Detect(args) => {
+            let mut call = json!({
+                "pdf_path": args.input,
+                "page": args.page,
+                "output_dir": args.output_dir,
+            });
+            args.removal.extend(&mut call);
+            report(handle_preview_page(call).await)
+        }
+        Command::Doctor => report(handle_check_environment(json!({})).await),
+    }
+}
+
+async fn process(args: ProcessArgs) -> Result<ExitCode> {
+    let stem = args.input.file_stem().unwrap_or_default().to_string_lossy();
+    let output = match &args.output {
+        Some(output) => output.clone(),
+        None => default_output(
+            args.input.with_file_name(format!("{stem}_nowatermark.pdf")),
+            args.auto_rename,
+        ),
+    };
+    if output.exists() && !args.overwrite {
+        let _ = writeln!(
+            std::io::stderr(),
+            "Error: Output already exists: {} (pass --overwrite to replace it)",
+            output.display()
+        );
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let result = async {
+        let settings =
+            resolve_settings(args.removal.settings(), args.removal.profile.as_deref()).await?;
+        // Without --pages-dir, pages are streamed into the output PDF
+        let Some(pages_dir) = &args.pages_dir else {
+            let pdf = api::process_pdf_to_pdf(&args.input, &output, &settings, args.deterministic)
+                .await?;
+            let _ = writeln!(std::io::stdout(), "{}", pdf.log);
+            return Ok(pdf);
+        };
+        let pages = api::process_pdf(&args.input, pages_dir, &settings).await?;
+        let _ = writeln!(std::io::stdout(), "{}", pages.log);
+        api::images_to_pdf(
+            pages_dir,
+            &output,
+            Some("page_*.png"),
+            api::ImageOrder::Natural,
+            api::PageResize::default(),
+            args.deterministic,
+        )
+        .await
+    }
+    .await;
+
+    match result {
+        Ok(pdf) => {
+            let _ = writeln!(
+                std::io::stdout(),
+                "Wrote {} ({} pages)",
+                pdf.output_path.display(),
+                pdf.page_count
+            );
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(e) => {
+            let _ = writeln!(std::io::stderr(), "Error: {e:#}");
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Write a result's text to stdout, or stderr for errors. Write failures
+/// (e.g. a closed pipe) are ignored.
+/// `path`, or with `auto_rename` its first free `_2`, `_3`, ... variant.
+fn default_output(path: PathBuf, auto_rename: bool) -> PathBuf {
+    if !auto_rename || !path.exists() {
+        return path;
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| path.with_file_name(format!("{stem}_{n}{extension}")))
+        .find(|candidate| !candidate.exists())
+        .expect("unbounded range")
+}
+
+fn print_result(result: &CallToolResult) {
+    for block in &result.content {
+        if let ContentBlock::TextContent(text) = block {
+            let _ = if result.is_error == Some(true) {
+                writeln!(std::io::stderr(), "{}", text.text)
+            } else {
+                writeln!(std::io::stdout(), "{}", text.text)
+            };
+        }
+    }
+}
+
+/// Print a tool result or error and map `is_error` to the exit code.
+fn report(result: ToolResult) -> Result<ExitCode> {
+    let result = result.unwrap_or_else(|e| e.to_call_result());
+    print_result(&result);
+    Ok(if result.is_error == Some(true) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
@@ -0,0 +1,220 @@
+//! Configuration file - server-wide defaults loaded once at startup
+//!
+//! The file is TOML, read from `--config`, `WATERMARK_CONFIG` or
+//! `$XDG_CONFIG_HOME/watermark-remover/config.toml`. Environment variables
+//! still win over the file, and per-call arguments win over both. The
+//! `[defaults]` table can be overridden field by field with
+//! `WATERMARK_DEFAULT_DPI`, `WATERMARK_DEFAULT_REGION` (`x,y,w,h`),
+//! `WATERMARK_DEFAULT_ALGORITHM`, `WATERMARK_DEFAULT_MODE` and
+//! `WATERMARK_DEFAULT_TEMPLATE`.
+//!
+//! ```toml
+//! scripts_dir = "/opt/watermark-remover/scripts"
+//! python = "/opt/venvs/watermark/bin/python"
+//! max_processes = 4
+//! encode_threads = 8
+//! process_timeout_secs = 3600
+//! allowed_dirs = ["/home/me/Documents"]
+//! audit_log = "/var/log/watermark-remover/audit.jsonl"
+//! workspace_dir = "/var/tmp/watermark-remover"
+//! workspace_root = "/home/me/Documents"
+//! temp_ttl_hours = 12
+//! private_workspace = true
+//! page_cache_dir = "/var/cache/watermark-remover/pages"
+//! checkpoint_dir = "/var/lib/watermark-remover/jobs"
+//! resume_jobs = true
+//! max_intermediate_mb = 2048
+//! max_image_megapixels = 50
+//! log_format = "json"
+//! locale = "en"
+//!
+//! [defaults]
+//! dpi = 300
+//! region = [0.8, 0.92, 0.2, 0.08]
+//! algorithm = "ns"
+//!
+//! [[tools]]
+//! name = "remove_stamp"
+//! command = ["{python}", "/opt/site/remove_stamp.py", "{image_path}"]
+//! required = ["image_path"]
+//! properties.image_path = { type = "string" }
+//! ```
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use serde::Deserialize;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::logging::LogFormat;
+use crate::tools::PluginTool;
+use crate::tools::RemovalSettings;
+use crate::tools::builtin_tool_definitions;
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Directory to load the Python scripts from instead of the bundled copies
+    pub scripts_dir: Option<PathBuf>,
+    /// Python interpreter to run the scripts with
+    pub python: Option<PathBuf>,
+    /// Maximum number of concurrent child processes
+    pub max_processes: Option<usize>,
+    /// Threads page images are decoded and re-encoded on when merging
+    pub encode_threads: Option<usize>,
+    /// Seconds before a child process is killed; `0` disables the timeout
+    pub process_timeout_secs: Option<u64>,
+    /// Directories tools may read from and write to; empty allows any path
+    pub allowed_dirs: Vec<PathBuf>,
+    /// JSONL file every tool call is recorded in
+    pub audit_log: Option<PathBuf>,
+    /// Directory intermediate files are created in
+    pub workspace_dir: Option<PathBuf>,
+    /// Directory relative path arguments are resolved against, instead of
+    /// the client's first root
+    pub workspace_root: Option<PathBuf>,
+    /// Hours before intermediate files are deleted; `0` keeps them
+    pub temp_ttl_hours: Option<f64>,
+    /// Create the workspace and its directories owner-only (0700) with
+    /// unguessable names, and point child processes' temp dirs at it
+    pub private_workspace: Option<bool>,
+    /// Directory cleaned pages are cached in; `"off"` disables the cache
+    pub page_cache_dir: Option<PathBuf>,
+    /// Directory background job checkpoints are kept in; `"off"` disables
+    /// them
+    pub checkpoint_dir: Option<PathBuf>,
+    /// Resume jobs interrupted by a crash or restart when the first client
+    /// initializes
+    pub resume_jobs: Option<bool>,
+    /// Megabytes of page images one pipeline run may keep on disk
+    pub max_intermediate_mb: Option<u64>,
+    /// Largest rendered page size, in megapixels
+    pub max_image_megapixels: Option<f64>,
+    /// `text` (the default) or `json` log lines on stderr
+    pub log_format: Option<LogFormat>,
+    /// Language of tool descriptions and error messages, `zh` (the default)
+    /// or `en`; clients can pick their own in `initialize`
+    pub locale: Option<String>,
+    /// Removal settings used when neither the call nor its profile sets them
+    pub defaults: RemovalSettings,
+    /// User-defined tools that run a command
+    pub tools: Vec<PluginTool>,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// The active configuration; empty until [`init_config`] is called.
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Install the configuration. Only the first call has any effect.
+pub fn init_config(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// `$XDG_CONFIG_HOME/watermark-remover`, falling back to `~/.config` and
+/// `APPDATA`.
+pub fn config_dir() -> PathBuf {
+    let config_dir = std::env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| std::env::var("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|_| std::env::var("APPDATA").map(PathBuf::from))
+        .unwrap_or_else(|_| std::env::temp_dir());
+    config_dir.join("watermark-remover")
+}
+
+/// Read the configuration file.
+///
+/// An explicit `path` (or `WATERMARK_CONFIG`) must exist; the default
+/// location is optional and yields an empty configuration when missing.
+pub fn load_config(path: Option<&Path>) -> Result<Config> {
+    let explicit = path
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::var_os("WATERMARK_CONFIG").map(PathBuf::from));
+    let path = match explicit {
+        Some(path) => path,
+        None => {
+            let path = config_dir().join("config.toml");
+            if !path.exists() {
+                return Ok(Config {
+                    defaults: env_defaults()?,
+                    ..Config::default()
+                });
+            }
+            path
+        }
+    };
+
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    let mut config: Config =
+        toml::from_str(&text).with_context(|| format!("Invalid config file {}", path.display()))?;
+    // Kept unresolved so a call's `size` can apply to a default `position`
+    config
+        .defaults
+        .clone()
+        .resolve_position()
+        .and_then(|defaults| defaults.validate())
+        .with_context(|| format!("Invalid [defaults] in {}", path.display()))?;
+    config.defaults = env_defaults()?.or(config.defaults);
+    let builtin = builtin_tool_definitions();
+    for (index, tool) in config.tools.iter().enumerate() {
+        tool.validate(&builtin)
+            .with_context(|| format!("Invalid [[tools]] in {}", path.display()))?;
+        if config.tools[..index]
+            .iter()
+            .any(|other| other.name == tool.name)
+        {
+            bail!("Tool {} is declared twice in {}", tool.name, path.display());
+        }
+    }
+
+    Ok(config)
+}
+
+/// Removal defaults from the `WATERMARK_DEFAULT_*` variables.
+fn env_defaults() -> Result<RemovalSettings> {
+    let var = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let dpi = var("WATERMARK_DEFAULT_DPI")
+        .map(|dpi| {
+            dpi.parse()
+                .ok()
+                .filter(|dpi| (72..=1200).contains(dpi))
+                .with_context(|| {
+                    format!("WATERMARK_DEFAULT_DPI must be a DPI between 72 and 1200, got {dpi}")
+                })
+        })
+        .transpose()?;
+    let region = var("WATERMARK_DEFAULT_REGION")
+        .map(|region| {
+            let values: Vec<f64> = region
+                .split(',')
+                .map(|v| v.trim().parse())
+                .collect::<Result<_, _>>()
+                .unwrap_or_default();
+            <[f64; 4]>::try_from(values).ok().with_context(|| {
+                format!("WATERMARK_DEFAULT_REGION must be x,y,width,height, got {region}")
+            })
+        })
+        .transpose()?;
+    let defaults = RemovalSettings {
+        region,
+        algorithm: var("WATERMARK_DEFAULT_ALGORITHM"),
+        template: var("WATERMARK_DEFAULT_TEMPLATE"),
+        dpi,
+        mode: var("WATERMARK_DEFAULT_MODE"),
+        ..RemovalSettings::default()
+    };
+    defaults
+        .validate()
+        .context("Invalid WATERMARK_DEFAULT_* variable")?;
+    Ok(defaults)
+}
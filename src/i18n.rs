@@ -0,0 +1,863 @@
+//! Localization - English and Chinese tool descriptions and error messages
+//!
+//! Tool definitions are written in Chinese; [`localize_tools`] swaps in the
+//! English text for an English session. A session's locale comes from the
+//! client's `initialize` params (`locale`, or
+//! `capabilities.experimental.locale`), then `WATERMARK_LOCALE`, then the
+//! config file's `locale`, and defaults to Chinese. Tool error messages follow
+//! the session locale; other result text is English in both.
+
+use mcp_types::Tool;
+use serde_json::Value;
+
+use crate::config::config;
+use crate::tools::ToolError;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    Zh,
+    En,
+}
+
+impl Locale {
+    /// Parse a language tag such as `en`, `en-US` or `zh_CN`.
+    pub fn parse(tag: &str) -> Option<Locale> {
+        let language = tag
+            .trim()
+            .split(['-', '_', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "en" => Some(Locale::En),
+            "zh" => Some(Locale::Zh),
+            _ => None,
+        }
+    }
+}
+
+/// Locale for sessions whose client does not ask for one.
+pub fn default_locale() -> Locale {
+    std::env::var("WATERMARK_LOCALE")
+        .ok()
+        .and_then(|tag| Locale::parse(&tag))
+        .or_else(|| config().locale.as_deref().and_then(Locale::parse))
+        .unwrap_or_default()
+}
+
+/// Locale requested in `initialize` params, if any.
+pub fn requested_locale(params: &Value) -> Option<Locale> {
+    params
+        .get("locale")
+        .or_else(|| params.pointer("/capabilities/experimental/locale"))
+        .and_then(Value::as_str)
+        .and_then(Locale::parse)
+}
+
+/// Replace every description in `tools` with its `locale` translation.
+/// Text without a translation is left as written.
+pub fn localize_tools(tools: &mut [Tool], locale: Locale) {
+    if locale == Locale::Zh {
+        return;
+    }
+    for tool in tools {
+        if let Some(description) = &mut tool.description {
+            translate(description);
+        }
+        if let Some(properties) = &mut tool.input_schema.properties {
+            localize_schema(properties);
+        }
+    }
+}
+
+/// Server instructions returned from `initialize`.
+pub fn instructions(locale: Locale) -> &'static str {
+    match locale {
+        Locale::Zh => "Watermark Remover MCP 服务器 - 使用 OpenCV 去除PDF文件和图片中的水印。",
+        Locale::En => {
+            "Watermark Remover MCP Server - Remove watermarks from PDF files and images using OpenCV."
+        }
+    }
+}
+
+/// `error`'s message in `locale`. Messages that carry text from elsewhere
+/// (invalid arguments, script output, internal errors) are not translated.
+pub fn error_message(error: &ToolError, locale: Locale) -> String {
+    if locale == Locale::En {
+        return error.to_string();
+    }
+    match error {
+        ToolError::FileNotFound { what, path } => {
+            format!("{}不存在：{}", input_kind_zh(what), path.display())
+        }
+        ToolError::PathNotAllowed { path } => format!(
+            "路径不在允许的目录中：{}（参见 allowed_dirs / WATERMARK_ALLOWED_DIRS）",
+            path.display()
+        ),
+        ToolError::OutputExists { path } => format!(
+            "输出已存在：{}（传入 overwrite: true 以覆盖）",
+            path.display()
+        ),
+        ToolError::Timeout { script, secs } => format!(
+            "{script} 运行超过 {secs} 秒，已被终止（处理超大输入时可调大 WATERMARK_PROCESS_TIMEOUT_SECS）"
+        ),
+        ToolError::Cancelled { job_id } => format!("任务 {job_id} 已取消"),
+        ToolError::ValidationFailed { argument, reason } => {
+            format!("参数 {argument} 无效：{reason}")
+        }
+        ToolError::QuotaExceeded {
+            quota: "intermediate_disk",
+            limit,
+            ..
+        } => format!(
+            "中间页面图片超出 {} MB 磁盘配额，处理已停止并删除已生成的页面（可调大 WATERMARK_MAX_INTERMEDIATE_MB 或降低DPI）",
+            limit / (1024 * 1024)
+        ),
+        ToolError::QuotaExceeded {
+            limit, requested, ..
+        } => format!(
+            "页面渲染后将达到 {:.1} 百万像素，超过 {:.1} 百万像素的上限（请降低DPI或调大 WATERMARK_MAX_IMAGE_MEGAPIXELS）",
+            *requested as f64 / 1_000_000.0,
+            *limit as f64 / 1_000_000.0
+        ),
+        ToolError::SubprocessFailed { script, stderr } => format!("{script} 执行失败：{stderr}"),
+        ToolError::InvalidArgs(_) | ToolError::BackendMissing { .. } | ToolError::Internal(_) => {
+            error.to_string()
+        }
+    }
+}
+
+fn input_kind_zh(what: &str) -> &str {
+    match what {
+        "PDF file" => "PDF文件",
+        "Directory" => "目录",
+        "Image file" => "图片文件",
+        "Template image" => "模板图片",
+        "ZIP file" | "ZIP archive" => "ZIP文件",
+        "Input" | "Input file" => "输入文件",
+        _ => what,
+    }
+}
+
+fn localize_schema(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(text) if key == "description" => translate(text),
+                    _ => localize_schema(value),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(localize_schema),
+        _ => {}
+    }
+}
+
+fn translate(text: &mut String) {
+    if let Some((_, english)) = ENGLISH.iter().find(|(chinese, _)| chinese == text) {
+        *text = english.to_string();
+    }
+}
+
+/// English text of each Chinese description in the tool definitions
+const ENGLISH: &[(&str, &str)] = &[
+    (
+        "将PDF文件转换为PNG图片。每页转换为一张图片。EPUB和DjVu文档会先转换为PDF。",
+        "Convert a PDF file to PNG images, one image per page. EPUB and DjVu documents are converted to PDF first.",
+    ),
+    (
+        "输出图片的DPI（默认200）",
+        "DPI of the output images (default 200)",
+    ),
+    (
+        "只校验参数并返回执行计划（输入、预计页数/文件数、输出路径及是否已存在），不做任何修改",
+        "Only validate the arguments and return a plan (inputs, expected page/file counts, output paths and whether they exist) without changing anything",
+    ),
+    (
+        "客户端可读取的MCP资源URI（例如其他MCP服务器提供的云盘文档）。服务器通过resources/read向客户端读取，并按文件类型作为pdf_path、image_path或zip_path传入",
+        "URI of an MCP resource the client can read (e.g. a cloud-drive document exposed by another MCP server). The server reads it from the client with resources/read and passes it as pdf_path, image_path or zip_path according to its type",
+    ),
+    (
+        "输出目录路径（可选，默认在服务器工作区中创建临时目录）",
+        "Output directory (optional; defaults to a temporary directory in the server workspace)",
+    ),
+    (
+        "是否允许覆盖已存在的输出文件（默认false，存在时报错并给出冲突路径）",
+        "Allow replacing existing output files (default false; otherwise the call fails and names the conflicting path)",
+    ),
+    (
+        "Base64编码的PDF内容（与pdf_path二选一，适用于无共享文件系统的客户端）",
+        "Base64-encoded PDF content (alternative to pdf_path, for clients without a shared file system)",
+    ),
+    (
+        "PDF文件的绝对路径，也支持EPUB和DjVu文档（与pdf_base64二选一）",
+        "Absolute path of the PDF file; EPUB and DjVu documents are accepted too (alternative to pdf_base64)",
+    ),
+    (
+        "去除图片右下角的水印（如NotebookLM水印）。支持单张图片或整个目录。",
+        "Remove the watermark in the bottom-right corner of images (e.g. the NotebookLM watermark). Accepts a single image or a whole directory.",
+    ),
+    (
+        "修复算法：telea、ns（Navier-Stokes）或 solid_fill（用周围背景色直接填充，速度快，适合纯色页面角落），默认telea",
+        "Inpainting algorithm: telea, ns (Navier-Stokes) or solid_fill (paint the surrounding background colour over the watermark; much faster, suited to plain page corners); default telea",
+    ),
+    (
+        "Base64编码的单张图片内容（与image_path、image_dir三选一）",
+        "Base64-encoded content of a single image (one of image_path, image_dir, image_base64)",
+    ),
+    (
+        "MCP图片内容块（如宿主粘贴的截图），替代image_base64；默认以图片内容返回处理结果",
+        "An MCP image content block (e.g. a screenshot pasted into the host) instead of image_base64; the cleaned image is returned as image content by default",
+    ),
+    (
+        "图片目录路径（与image_path、image_base64三选一）",
+        "Directory of images (one of image_path, image_dir, image_base64)",
+    ),
+    (
+        "单张图片的路径（与image_base64、image_dir三选一）",
+        "Path of a single image (one of image_path, image_dir, image_base64)",
+    ),
+    (
+        "去除模式：corner 处理region内的角落水印（默认）；grid 针对图库照片的半透明居中Logo与重复文字网格，在整张图片上估计水印掩码（忽略region和template）",
+        "Removal mode: corner removes a corner watermark inside region (default); grid targets stock-photo watermarks (a translucent centered logo and a repeated text grid) by estimating the watermark mask over the whole image (ignores region and template)",
+    ),
+    (
+        "输出目录路径（可选，默认覆盖原图或输出到同目录）",
+        "Output directory (optional; by default the input images are overwritten or written next to them)",
+    ),
+    (
+        "命名去水印配置（如 notebooklm），提供region/algorithm/template/dpi的默认值；显式参数优先",
+        "Named removal profile (e.g. notebooklm) supplying defaults for region/algorithm/template/dpi; explicit arguments take precedence",
+    ),
+    (
+        "水印区域 [x, y, 宽, 高]，均为相对图片尺寸的0-1比例（默认 [0.80, 0.92, 0.20, 0.08]，即右下角）",
+        "Watermark region [x, y, width, height] as 0-1 fractions of the image size (default [0.80, 0.92, 0.20, 0.08], the bottom-right corner)",
+    ),
+    (
+        "单张图片模式下，是否在结果中以base64图片内容返回处理后的图片（默认false）",
+        "In single-image mode, also return the cleaned image as base64 image content (default false)",
+    ),
+    (
+        "单张图片通过标准输入/输出传给Python处理，不写临时文件；结果以图片内容返回，仅在指定output_dir时保存为PNG（默认false）",
+        "Stream a single image to Python over stdin/stdout instead of temp files; the result comes back as image content and is saved as PNG only when output_dir is given (default false)",
+    ),
+    (
+        "image_dir模式下，是否同时处理子目录中的图片；输出保持原有目录结构（默认false）",
+        "With image_dir, also process images in subdirectories; outputs keep the directory structure (default false)",
+    ),
+    (
+        "image_dir模式下要处理的图片扩展名（默认png、jpg、jpeg、webp、gif、heic、heif、avif）",
+        "With image_dir, the image extensions to process (default png, jpg, jpeg, webp, gif, heic, heif, avif)",
+    ),
+    (
+        "image_dir模式下只处理相对路径匹配任一glob模式的图片（如 page_*.png、scans/*）",
+        "With image_dir, only process images whose relative path matches one of these globs (e.g. page_*.png, scans/*)",
+    ),
+    (
+        "image_dir模式下跳过相对路径匹配任一glob模式的图片（如 *_thumb.*）",
+        "With image_dir, skip images whose relative path matches one of these globs (e.g. *_thumb.*)",
+    ),
+    (
+        "多步处理流程，在一次调用中对每张图片依次执行（只读写一次图片）。remove步骤未设置的字段沿用上面的参数",
+        "Multi-step pipeline applied to each image in one call (each image is read and written once). Fields a remove step leaves unset fall back to the arguments above",
+    ),
+    (
+        "despeckle的中值滤波核大小（3-15的奇数，默认3）",
+        "Median filter kernel size for despeckle (odd, 3-15, default 3)",
+    ),
+    (
+        "水印模板图片路径（可选），在区域内进行模板匹配以精确定位水印",
+        "Watermark template image (optional); template matching inside the region locates the watermark precisely",
+    ),
+    (
+        "将目录中的图片合并为一个PDF文件。图片默认按文件名自然排序（page_2 在 page_10 之前）。",
+        "Merge the images in a directory into one PDF file. Images are ordered by file name with numbers compared numerically by default (page_2 before page_10).",
+    ),
+    ("包含图片的目录路径", "Directory containing the images"),
+    ("输出PDF文件路径", "Output PDF path"),
+    (
+        "图片文件匹配模式（默认 *_processed.png）",
+        "File name pattern of the images (default *_processed.png)",
+    ),
+    (
+        "页面顺序：natural 按文件名排序且数字按数值比较（默认）；name 按文件名逐字符排序；mtime 按修改时间从旧到新",
+        "Page order: natural sorts by file name comparing numbers numerically (default); name sorts by file name character by character; mtime sorts oldest modification first",
+    ),
+    (
+        "页面图片最大宽度（像素，可选，超出时等比缩小；页面物理尺寸不变）",
+        "Maximum page image width in pixels (optional; wider pages are scaled down proportionally, keeping their physical size)",
+    ),
+    (
+        "页面图片最大高度（像素，可选，超出时等比缩小；页面物理尺寸不变）",
+        "Maximum page image height in pixels (optional; taller pages are scaled down proportionally, keeping their physical size)",
+    ),
+    (
+        "页面图片缩放比例（可选，如0.5；先于max_width/max_height应用）",
+        "Resize factor for page images (optional, e.g. 0.5; applied before max_width/max_height)",
+    ),
+    (
+        "可复现输出：固定PDF日期且不写入随机文档ID，相同输入生成逐字节相同的PDF（默认false）",
+        "Reproducible output: fixed PDF dates and no random document ID, so the same input gives a byte-identical PDF (default false)",
+    ),
+    (
+        "一键处理PDF：转换为图片 → 去除水印 → 合并回PDF。也支持EPUB和DjVu文档（先转换为PDF）。",
+        "Process a PDF in one step: convert to images → remove watermarks → merge back into a PDF. EPUB and DjVu documents are accepted too (converted to PDF first).",
+    ),
+    (
+        "是否作为后台任务运行并立即返回job_id（默认false），适合大文档以避免请求超时",
+        "Run as a background job and return a job_id immediately (default false); recommended for large documents to avoid request timeouts",
+    ),
+    (
+        "后台任务结束（成功、失败或取消）时以POST方式发送JSON摘要（状态、输出、耗时）的http(s)地址，需配合async使用",
+        "http(s) URL that receives a POSTed JSON summary (status, outputs, timings) when the background job succeeds, fails or is cancelled; requires async",
+    ),
+    (
+        "在结果中附带一个示例页处理前后的小缩略图（图片内容），便于直接在对话中检查去除效果；不能与async同用",
+        "Attach small before/after thumbnails (image content) of a sample page to the result, to check the removal right in the chat; not available with async",
+    ),
+    (
+        "是否保留中间文件（页面图片工作目录、由EPUB/DjVu转换的PDF）以便调试；默认在生成PDF后删除",
+        "Keep intermediate files (the page image working directory, PDFs converted from EPUB/DjVu) for debugging; by default they are deleted once the PDF is written",
+    ),
+    (
+        "可复现输出：固定PDF日期、不写入随机文档ID并按页码顺序写入页面，相同输入生成逐字节相同的PDF（默认false；未指定images_output_dir时逐页顺序处理）",
+        "Reproducible output: fixed PDF dates, no random document ID and pages written in page order, so the same input gives a byte-identical PDF (default false; without images_output_dir, pages are processed one at a time)",
+    ),
+    (
+        "处理图片的DPI（默认200）",
+        "DPI used for processing (default 200)",
+    ),
+    (
+        "输出PDF文件路径（可选，默认为 原文件名_nowatermark.pdf）",
+        "Output PDF path (optional; defaults to <name>_nowatermark.pdf)",
+    ),
+    (
+        "保留去水印后页面图片（page_NNN.png）的目录（可选，默认不保留，页面直接写入输出PDF）",
+        "Directory to keep the cleaned page images (page_NNN.png) in (optional; by default they are not kept and pages go straight into the output PDF)",
+    ),
+    (
+        "默认输出的 原文件名_nowatermark.pdf 已存在时，改写为 原文件名_nowatermark_2.pdf 等新名称（默认false），新路径在结果中返回",
+        "If the default output <name>_nowatermark.pdf exists, write <name>_nowatermark_2.pdf etc. instead (default false); the new path is returned in the result",
+    ),
+    (
+        "Base64编码的PDF内容（与pdf_path二选一）",
+        "Base64-encoded PDF content (alternative to pdf_path)",
+    ),
+    (
+        "输入PDF文件路径，也支持EPUB和DjVu文档（与pdf_base64二选一）",
+        "Input PDF path; EPUB and DjVu documents are accepted too (alternative to pdf_base64)",
+    ),
+    (
+        "预览单页去水印效果：以低DPI渲染指定页，去除水印，并返回处理前后的缩略图。适合在处理大文档前验证参数。",
+        "Preview watermark removal on one page: render it at low DPI, remove the watermark and return before/after thumbnails. Useful for checking parameters before processing a large document.",
+    ),
+    (
+        "预览渲染的DPI（默认72）",
+        "DPI of the preview render (default 72)",
+    ),
+    (
+        "缩略图输出目录（可选，默认使用系统临时目录）",
+        "Directory for the thumbnails (optional; defaults to the system temp directory)",
+    ),
+    (
+        "要预览的页码，从1开始（默认1）",
+        "Page to preview, 1-based (default 1)",
+    ),
+    (
+        "命名去水印配置（如 notebooklm），提供region/algorithm/template的默认值（预览不使用配置中的dpi）；显式参数优先",
+        "Named removal profile (e.g. notebooklm) supplying defaults for region/algorithm/template (the preview ignores the profile's dpi); explicit arguments take precedence",
+    ),
+    (
+        "对比原图与处理后的图片，生成高亮变化像素的差异图，并返回变化像素统计，用于检查去水印效果。",
+        "Compare an original and a processed image: writes a diff image highlighting changed pixels and returns changed-pixel statistics, to check the result of watermark removal.",
+    ),
+    (
+        "差异图输出路径（可选，默认写入系统临时目录）",
+        "Output path of the diff image (optional; defaults to the system temp directory)",
+    ),
+    ("原始图片路径", "Path of the original image"),
+    ("处理后的图片路径", "Path of the processed image"),
+    (
+        "判定像素变化的阈值，0-255（默认10）",
+        "Threshold for counting a pixel as changed, 0-255 (default 10)",
+    ),
+    (
+        "在图片或PDF上添加可配置的文字/Logo水印，用于生成测试样本和评估去水印效果。",
+        "Add a configurable text or logo watermark to an image or PDF, for building test samples and evaluating watermark removal.",
+    ),
+    (
+        "PDF输入的渲染DPI（默认200）",
+        "Render DPI for PDF input (default 200)",
+    ),
+    (
+        "图片目录路径（与image_path、pdf_path三选一）",
+        "Directory of images (one of image_path, image_dir, pdf_path)",
+    ),
+    (
+        "单张图片路径（与image_dir、pdf_path三选一）",
+        "Path of a single image (one of image_path, image_dir, pdf_path)",
+    ),
+    (
+        "Logo图片路径（可选，提供时使用Logo代替文字）",
+        "Logo image path (optional; when given, the logo is used instead of text)",
+    ),
+    (
+        "水印不透明度，0-1（默认0.6）",
+        "Watermark opacity, 0-1 (default 0.6)",
+    ),
+    (
+        "输出路径：单张图片/PDF为文件路径，目录模式为输出目录",
+        "Output path: a file path for a single image or PDF, an output directory in directory mode",
+    ),
+    (
+        "PDF文件路径（与image_path、image_dir三选一）",
+        "PDF file path (one of image_path, image_dir, pdf_path)",
+    ),
+    (
+        "水印位置（默认bottom_right）",
+        "Watermark position (default bottom_right)",
+    ),
+    (
+        "文字高度占图片高度的比例（默认0.025）",
+        "Text height as a fraction of the image height (default 0.025)",
+    ),
+    (
+        "水印文字（默认NotebookLM）",
+        "Watermark text (default NotebookLM)",
+    ),
+    (
+        "图片格式转换（PNG/JPEG/WebP/TIFF），支持缩放。支持单张图片或整个目录。",
+        "Convert image formats (PNG/JPEG/WebP/TIFF), optionally resizing. Accepts a single image or a whole directory.",
+    ),
+    ("目标格式", "Target format"),
+    (
+        "图片目录路径（与image_path二选一）",
+        "Directory of images (alternative to image_path)",
+    ),
+    (
+        "单张图片路径（与image_dir二选一）",
+        "Path of a single image (alternative to image_dir)",
+    ),
+    (
+        "最大高度（可选，超出时等比缩小）",
+        "Maximum height (optional; larger images are scaled down proportionally)",
+    ),
+    (
+        "最大宽度（可选，超出时等比缩小）",
+        "Maximum width (optional; larger images are scaled down proportionally)",
+    ),
+    (
+        "输出路径：单张图片为文件路径（或已存在的目录），目录模式为输出目录",
+        "Output path: a file path (or an existing directory) for a single image, an output directory in directory mode",
+    ),
+    (
+        "JPEG/WebP质量，1-100（默认90）",
+        "JPEG/WebP quality, 1-100 (default 90)",
+    ),
+    (
+        "缩放比例（可选，如0.5）",
+        "Scale factor (optional, e.g. 0.5)",
+    ),
+    (
+        "扫描图片校正：自动纠偏（基于Hough直线）、旋转、裁剪。支持单张图片或整个目录。",
+        "Straighten scanned images: automatic deskew (Hough lines), rotation and cropping. Accepts a single image or a whole directory.",
+    ),
+    (
+        "是否自动裁掉浅色页边距（默认false）",
+        "Automatically crop light page margins (default false)",
+    ),
+    (
+        "裁剪矩形（像素，在纠偏和旋转之后应用）",
+        "Crop rectangle in pixels, applied after deskewing and rotation",
+    ),
+    (
+        "是否自动纠正倾斜（默认false）",
+        "Automatically correct skew (default false)",
+    ),
+    (
+        "输出目录路径（可选，默认覆盖原图）",
+        "Output directory (optional; by default the input images are overwritten)",
+    ),
+    ("顺时针旋转角度（度）", "Clockwise rotation in degrees"),
+    (
+        "列出并删除本服务器创建的中间目录（工作区中的页面图片及临时目录，记录于清单文件中）。支持dry_run仅预览。",
+        "List and delete intermediate directories created by this server (page images and temp directories in the workspace, as recorded in the manifest). Supports dry_run to preview only.",
+    ),
+    (
+        "仅列出将被删除的目录，不实际删除（默认false）",
+        "Only list the directories that would be deleted, without deleting them (default false)",
+    ),
+    (
+        "只处理创建时间早于指定小时数的目录（可选）",
+        "Only handle directories created more than this many hours ago (optional)",
+    ),
+    (
+        "查询后台任务的状态与进度（含每页状态）。不提供job_id时列出所有任务。",
+        "Show the status and progress of background jobs (including per-page state). Lists all jobs when no job_id is given.",
+    ),
+    ("任务ID（可选）", "Job ID (optional)"),
+    (
+        "获取已完成后台任务的最终结果。",
+        "Get the final result of a finished background job.",
+    ),
+    ("任务ID", "Job ID"),
+    (
+        "取消正在运行的后台任务并终止其子进程。",
+        "Cancel a running background job and kill its subprocesses.",
+    ),
+    (
+        "紧急停止：取消本会话所有正在运行的后台任务，并终止本会话启动的全部子进程（包括前台调用和文件夹监控的子进程；不影响其他客户端）。返回被取消的任务ID。",
+        "Emergency stop: cancel every running background job of this session and kill all subprocesses it started (including those of foreground calls and folder watches; other clients are not affected). Returns the ids of the cancelled jobs.",
+    ),
+    (
+        "从检查点继续因崩溃、重启或失败而中断的后台process_pdf任务（沿用原任务ID，只处理尚未完成的页面）。不提供job_id时列出可恢复的任务。",
+        "Continue a background process_pdf job interrupted by a crash, restart or failure from its checkpoint (under the original job ID, processing only the pages not yet done). Without job_id, lists the resumable jobs.",
+    ),
+    (
+        "要恢复的任务ID（可选）",
+        "ID of the job to resume (optional)",
+    ),
+    (
+        "删除该任务的检查点而不是恢复（默认false）",
+        "Delete the job's checkpoint instead of resuming it (default false)",
+    ),
+    (
+        "估算处理PDF所需的时间和磁盘空间（基于页数、页面尺寸和DPI），便于在开始前选择合适的DPI。",
+        "Estimate the time and disk space needed to process a PDF (from its page count, page sizes and DPI), to pick a suitable DPI before starting.",
+    ),
+    (
+        "计划使用的DPI（默认200）",
+        "DPI you plan to use (default 200)",
+    ),
+    ("输入PDF文件路径", "Input PDF path"),
+    (
+        "诊断运行环境：检查python3、OpenCV、pdf2image/poppler、Pillow、img2pdf、ffmpeg、磁盘空间及脚本目录，返回结构化报告。",
+        "Diagnose the runtime environment: checks python3, OpenCV, pdf2image/poppler, Pillow, img2pdf, ffmpeg, disk space and the scripts directory, and returns a structured report.",
+    ),
+    (
+        "保存命名去水印配置（区域、算法、模板、DPI、模式、多步流程），之后可在remove_watermark、process_pdf、preview_page中通过profile参数引用。同名配置会被覆盖。",
+        "Save a named removal profile (region, algorithm, template, DPI, mode, multi-step pipeline) to reference later through the profile argument of remove_watermark, process_pdf and preview_page. A profile with the same name is replaced.",
+    ),
+    (
+        "修复算法：telea、ns 或 solid_fill",
+        "Inpainting algorithm: telea, ns or solid_fill",
+    ),
+    (
+        "修复时参考的邻域半径（像素，默认5）",
+        "Neighbourhood radius inpainting draws from, in pixels (default 5)",
+    ),
+    (
+        "水印位置预设，替代region：bottom_right、bottom_left、top_right、top_left、center、footer（页脚整行）或 header（页眉整行）",
+        "Watermark position preset instead of region: bottom_right, bottom_left, top_right, top_left, center, footer (full-width footer band) or header (full-width header band)",
+    ),
+    (
+        "position区域的 [宽, 高]，为页面尺寸的百分比（角落默认 [20, 8]，center默认 [40, 20]，footer/header默认 [100, 8]）",
+        "[width, height] of the position area in percent of the page (default [20, 8] for corners, [40, 20] for center, [100, 8] for footer/header)",
+    ),
+    (
+        "填充前将检测到的水印掩码向外扩展的像素数（默认3）",
+        "Pixels the detected watermark mask is grown by before it is filled (default 3)",
+    ),
+    (
+        "填充区域边缘与原图渐变融合的宽度（像素，默认0即硬边），可减轻纹理背景上的光晕",
+        "Width in pixels of the soft edge blending the filled region into the image (default 0, a hard edge); reduces halos on textured backgrounds",
+    ),
+    (
+        "空白页（几乎没有内容的页面）的处理方式：process 与其他页面一样去水印（默认）；skip 跳过去水印步骤，原样保留；drop 从输出中删除。水印区域内有内容的页面不视为空白页",
+        "What to do with blank pages (pages with next to no ink): process cleans them like any other page (default); skip keeps them as rendered without the removal step; drop leaves them out of the output. A page with marks inside the watermark region is never taken for blank",
+    ),
+    (
+        "与前一页重复的页面（常见于自动导出的幻灯片）：keep 不检测（默认）；report 通过感知哈希检测并在结果中标注 duplicate_of；drop 从输出中删除重复页",
+        "Pages repeating the one before (common in auto-exported slide decks): keep does not look for them (default); report detects them by perceptual hashing and marks them with duplicate_of in the result; drop leaves them out of the output",
+    ),
+    (
+        "自适应DPI：在当前DPI下水印只有几个像素高的页面，以不超过该值的DPI重新渲染后去水印，再缩小回原尺寸，只替换水印区域。其余页面仍按dpi处理（默认不启用）",
+        "Adaptive DPI: pages whose watermark is only a few pixels tall at the run's DPI are re-rendered at up to this DPI for removal and scaled back down, replacing only the watermark area. Other pages stay at dpi (off by default)",
+    ),
+    (
+        "追加模式：只把输出PDF中尚未包含的匹配图片追加到其末尾（PDF不存在时新建），用于逐页流式组装和中断后续合并；已合并的图片名记录在PDF中（默认false）",
+        "Append mode: add only the matching images the output PDF does not hold yet to its end (creating it when missing), for page-at-a-time assembly and resuming interrupted merges; merged image names are recorded in the PDF (default false)",
+    ),
+    (
+        "保留文字层：把原PDF每页的文字以不可见文字复制到去水印后的页面图片上，使输出可搜索、可复制，且与原文完全一致（不使用OCR；仅适用于PDF输入；默认false）",
+        "Keep the text layer: copy each page's text from the source PDF onto the cleaned page images as invisible text, so the output can be searched and copied and matches the source exactly (no OCR; PDF input only; default false)",
+    ),
+    (
+        "去水印后依次对整张图片执行的清理步骤，适用于噪点较多的扫描件：despeckle 中值滤波去除孤立斑点；denoise 非局部均值降噪；normalize_contrast 拉伸色阶使纸张变白、文字变深",
+        "Clean-up passes run in order on the whole image after watermark removal, for noisy scans: despeckle removes isolated specks with a median filter; denoise applies non-local means denoising; normalize_contrast stretches the levels so paper turns white and text dark",
+    ),
+    (
+        "denoise的降噪强度（1-30，默认10，越大越平滑但细节损失越多）",
+        "denoise strength (1-30, default 10; higher smooths more but loses more detail)",
+    ),
+    (
+        "normalize_contrast忽略的最暗和最亮像素百分比（0-10，默认1）",
+        "Percentage of the darkest and lightest pixels normalize_contrast ignores (0-10, default 1)",
+    ),
+    (
+        "用于印刷：所有页面以CMYK渲染（需要Ghostscript），在CMYK下去水印，并以CMYK JPEG写入输出PDF。未设置时，含CMYK内容的页面会经Ghostscript做色彩管理后转为RGB，避免颜色偏移",
+        "For print: render every page in CMYK (needs Ghostscript), remove the watermark in CMYK and write the pages to the output PDF as CMYK JPEGs. Otherwise pages with CMYK content are converted to RGB with Ghostscript's color management so their colors do not shift",
+    ),
+    ("处理PDF时使用的DPI", "DPI used when processing PDFs"),
+    (
+        "在一个样本页（或图片）上分别运行多种去水印算法，按残留水印（模板匹配/区域检测信号）和填充边缘伪影评分排序，并返回各算法的缩略图，用于为大批量处理选择参数。",
+        "Run several removal algorithms on one sample page (or image), rank them by residual watermark (template match / region detection signal) and boundary artifacts at the edge of the fill, and return a thumbnail of each, to help pick settings for a large batch.",
+    ),
+    (
+        "用每种可用的去水印后端（subprocess每图一个进程、batch目录批处理、pipe标准输入输出流式处理、embedded内嵌解释器）处理同一组样本页，报告各后端的吞吐量（每秒图片数、每图耗时）和峰值内存占用（仅Linux），用于为当前硬件选择后端。不提供文档时使用合成的带水印页面。",
+        "Clean the same sample pages with each available removal backend (subprocess: one process per image, batch: one process per directory, pipe: streamed over stdin/stdout, embedded: the in-process interpreter) and report each backend's throughput (images per second, time per image) and peak memory (Linux only), to help pick a backend for this hardware. Without a document, synthetic watermarked pages are used.",
+    ),
+    (
+        "样本PDF文件路径，渲染前samples页（与image_path二选一；都不提供时使用合成页面）",
+        "Sample PDF path; its first `samples` pages are rendered (either this or image_path; with neither, synthetic pages are used)",
+    ),
+    (
+        "样本图片路径，复制samples份（与pdf_path二选一）",
+        "Sample image path, copied `samples` times (either this or pdf_path)",
+    ),
+    (
+        "每个后端处理的样本页数（默认5）",
+        "Number of sample pages each backend cleans (default 5)",
+    ),
+    (
+        "要测试的后端（默认全部；embedded需要以embedded-python特性构建）",
+        "Backends to measure (default all; embedded needs a build with the embedded-python feature)",
+    ),
+    (
+        "样本页的渲染DPI（默认200）",
+        "Render DPI of the sample pages (default 200)",
+    ),
+    (
+        "输入PDF文件路径，也支持EPUB和DjVu文档（与pdf_base64、image_path三选一）",
+        "Input PDF path; EPUB and DjVu documents are accepted too (one of pdf_path, pdf_base64 or image_path)",
+    ),
+    (
+        "Base64编码的PDF内容（与pdf_path、image_path三选一）",
+        "Base64-encoded PDF content (one of pdf_path, pdf_base64 or image_path)",
+    ),
+    (
+        "样本图片路径（与pdf_path、pdf_base64三选一）",
+        "Sample image path (one of pdf_path, pdf_base64 or image_path)",
+    ),
+    (
+        "PDF中用作样本的页码，从1开始（默认1）",
+        "1-based page of the PDF to use as the sample (default 1)",
+    ),
+    (
+        "要比较的算法（默认全部）",
+        "Algorithms to compare (default all)",
+    ),
+    (
+        "PDF样本页的渲染DPI，应与批量处理时一致（默认200）",
+        "Render DPI of the PDF sample page; match the batch's DPI (default 200)",
+    ),
+    (
+        "命名去水印配置（如 notebooklm），提供region/template/dpi等默认值（其中的algorithm不使用）；显式参数优先",
+        "Named removal profile (e.g. notebooklm) supplying defaults such as region/template/dpi (its algorithm is not used); explicit arguments win",
+    ),
+    (
+        "去除模式：corner（角落水印）或 grid（图库照片整图水印）",
+        "Removal mode: corner (corner watermark) or grid (whole-image stock-photo watermark)",
+    ),
+    (
+        "配置名称，仅限字母、数字、-和_",
+        "Profile name; letters, digits, - and _ only",
+    ),
+    (
+        "水印区域 [x, y, 宽, 高]，均为相对图片尺寸的0-1比例",
+        "Watermark region [x, y, width, height] as 0-1 fractions of the image size",
+    ),
+    (
+        "多步处理流程，在一次调用中对每张图片依次执行（只读写一次图片）。remove步骤未设置的字段沿用配置中的其他参数",
+        "Multi-step pipeline applied to each image in one call (each image is read and written once). Fields a remove step leaves unset fall back to the profile's other settings",
+    ),
+    ("水印模板图片路径", "Watermark template image path"),
+    (
+        "列出所有命名去水印配置，包括内置的 notebooklm 配置。",
+        "List all named removal profiles, including the built-in notebooklm profile.",
+    ),
+    (
+        "删除已保存的命名去水印配置。",
+        "Delete a saved named removal profile.",
+    ),
+    ("要删除的配置名称", "Name of the profile to delete"),
+    (
+        "监视目录：新放入的PDF会自动按配置去除水印并合并为新的PDF，每处理完一个文件发送一条 notifications/message 日志通知。返回watch_id，用stop_watch停止。",
+        "Watch a directory: PDFs dropped into it are cleaned with the given settings and merged into a new PDF, and each finished file is reported as a notifications/message log notification. Returns a watch_id; stop it with stop_watch.",
+    ),
+    (
+        "输出的 文件名_nowatermark.pdf 已存在时，改写为 文件名_nowatermark_2.pdf 等新名称（默认false）",
+        "When the output <name>_nowatermark.pdf already exists, write <name>_nowatermark_2.pdf etc. instead (default false)",
+    ),
+    (
+        "要监视的目录（只检查该目录下的PDF，不递归）",
+        "Directory to watch (only PDFs directly in it, not recursive)",
+    ),
+    (
+        "扫描间隔秒数（默认5）",
+        "Scan interval in seconds (default 5)",
+    ),
+    (
+        "处理结果输出目录（可选，默认为 监视目录/cleaned）",
+        "Directory for the results (optional; defaults to <watched dir>/cleaned)",
+    ),
+    (
+        "是否同时处理启动时目录中已有的PDF（默认false）",
+        "Also process PDFs already in the directory when the watch starts (default false)",
+    ),
+    (
+        "命名去水印配置（如 notebooklm）；也可直接传入region/algorithm/template/mode/steps/dpi",
+        "Named removal profile (e.g. notebooklm); region/algorithm/template/mode/steps/dpi can also be passed directly",
+    ),
+    (
+        "停止目录监视并返回处理统计。不传watch_id时列出所有正在运行的监视。",
+        "Stop a directory watch and return its processing counts. Lists the running watches when no watch_id is given.",
+    ),
+    (
+        "start_watch返回的watch_id（可选）",
+        "watch_id returned by start_watch (optional)",
+    ),
+    (
+        "处理ZIP压缩包：解压到临时目录，对其中所有图片和PDF去除水印，按原目录结构输出，可选重新打包为ZIP。",
+        "Process a ZIP archive: extract it to a temp directory, remove watermarks from every image and PDF inside, write the results with the original directory layout, and optionally re-pack them as a ZIP.",
+    ),
+    (
+        "默认输出目录已有文件时，改用 文件名_cleaned_2 等新名称（默认false），新路径在结果中返回",
+        "When the default output directory already has files, use <name>_cleaned_2 etc. instead (default false); the path used is returned in the result",
+    ),
+    (
+        "渲染压缩包内PDF使用的DPI（默认200）",
+        "DPI for rendering PDFs inside the archive (default 200)",
+    ),
+    (
+        "输出目录（可选，默认为 ZIP同目录下的 文件名_cleaned）",
+        "Output directory (optional; defaults to <name>_cleaned next to the ZIP)",
+    ),
+    (
+        "将处理结果重新打包为此ZIP文件（可选）",
+        "Re-pack the results into this ZIP file (optional)",
+    ),
+    (
+        "命名去水印配置（如 notebooklm）；也可直接传入region/algorithm/template/mode/steps",
+        "Named removal profile (e.g. notebooklm); region/algorithm/template/mode/steps can also be passed directly",
+    ),
+    (
+        "Base64编码的ZIP内容（与zip_path二选一）",
+        "Base64-encoded ZIP content (alternative to zip_path)",
+    ),
+    (
+        "输入ZIP文件路径（与zip_base64二选一）",
+        "Input ZIP path (alternative to zip_base64)",
+    ),
+    (
+        "直接提取PDF中内嵌的原始图片（JPEG原样导出，Flate压缩图片导出为PNG），不重新渲染页面，保留扫描件的原始画质。适合在去水印前处理纯图片扫描PDF。",
+        "Extract the original images embedded in a PDF (JPEGs as-is, Flate images as PNG) without re-rendering pages, keeping the full quality of scans. Useful for image-only scanned PDFs before watermark removal.",
+    ),
+    (
+        "默认输出目录已有提取结果时，改用 文件名_images_2 等新名称（默认false），新路径在结果中返回",
+        "When the default output directory already has extracted images, use <name>_images_2 etc. instead (default false); the path used is returned in the result",
+    ),
+    (
+        "图片输出目录（可选，默认在PDF同目录下创建 文件名_images）",
+        "Directory for the images (optional; defaults to <name>_images next to the PDF)",
+    ),
+    (
+        "输入PDF文件路径（与pdf_base64二选一）",
+        "Input PDF path (alternative to pdf_base64)",
+    ),
+    (
+        "用纯色矩形涂黑/涂白图片或PDF页面上的指定区域（不做内容修复），用于去除敏感信息。PDF页面会被栅格化，覆盖区域下的文字不会保留。",
+        "Cover regions of images or PDF pages with solid rectangles (no inpainting) to remove sensitive information. PDF pages are rasterized, so no text under the covered regions is kept.",
+    ),
+    (
+        "填充颜色：black、white 或 #rrggbb（默认black）",
+        "Fill color: black, white or #rrggbb (default black)",
+    ),
+    ("要覆盖的矩形列表", "Rectangles to cover"),
+    (
+        "页码（从1开始；目录模式为排序后的图片序号）。省略时应用于所有页",
+        "Page number (1-based; in directory mode, the index in sorted image order). Applies to every page when omitted",
+    ),
+    (
+        "[x, y, 宽, 高]，均为相对页面尺寸的0-1比例",
+        "[x, y, width, height] as 0-1 fractions of the page size",
+    ),
+    (
+        "服务器运行统计：各工具调用次数、错误数和耗时，已处理页数、写入字节数，以及当前运行的任务和子进程数。",
+        "Server statistics: calls, errors and durations per tool, pages processed, bytes written, and the number of running jobs and subprocesses.",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::builtin_tool_definitions;
+
+    #[test]
+    fn parse_reads_the_language_of_a_tag() {
+        assert_eq!(Locale::parse("en"), Some(Locale::En));
+        assert_eq!(Locale::parse(" en-US "), Some(Locale::En));
+        assert_eq!(Locale::parse("EN_gb"), Some(Locale::En));
+        assert_eq!(Locale::parse("zh_CN.UTF-8"), Some(Locale::Zh));
+        assert_eq!(Locale::parse("zh-Hans"), Some(Locale::Zh));
+        assert_eq!(Locale::parse("fr"), None);
+        assert_eq!(Locale::parse("english"), None);
+        assert_eq!(Locale::parse(""), None);
+    }
+
+    #[test]
+    fn requested_locale_looks_in_both_places() {
+        let params = serde_json::json!({ "locale": "en-US" });
+        assert_eq!(requested_locale(&params), Some(Locale::En));
+        let params = serde_json::json!({ "capabilities": { "experimental": { "locale": "zh" } } });
+        assert_eq!(requested_locale(&params), Some(Locale::Zh));
+        assert_eq!(requested_locale(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn every_tool_description_has_an_english_text() {
+        let mut tools = builtin_tool_definitions();
+        localize_tools(&mut tools, Locale::En);
+        let mut untranslated = Vec::new();
+        for tool in &tools {
+            let mut texts: Vec<&str> = tool.description.as_deref().into_iter().collect();
+            collect_descriptions(tool.input_schema.properties.as_ref(), &mut texts);
+            untranslated.extend(
+                texts
+                    .into_iter()
+                    .filter(|text| text.chars().any(|c| ('\u{4e00}'..='\u{9fff}').contains(&c)))
+                    .map(|text| format!("{}: {text}", tool.name)),
+            );
+        }
+        assert!(untranslated.is_empty(), "{untranslated:#?}");
+    }
+
+    #[test]
+    fn translations_are_listed_once() {
+        let mut seen = std::collections::HashSet::new();
+        for (chinese, _) in ENGLISH {
+            assert!(seen.insert(chinese), "listed twice: {chinese}");
+        }
+    }
+
+    fn collect_descriptions<'a>(value: Option<&'a Value>, texts: &mut Vec<&'a str>) {
+        match value {
+            Some(Value::Object(map)) => {
+                for (key, value) in map {
+                    match value {
+                        Value::String(text) if key == "description" => texts.push(text),
+                        _ => collect_descriptions(Some(value), texts),
+                    }
+                }
+            }
+            Some(Value::Array(items)) => {
+                for item in items {
+                    collect_descriptions(Some(item), texts);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,361 @@
+//! Background job subsystem for long-running tool calls
+//!
+//! A job wraps a tool invocation in a spawned task so the `tools/call` that
+//! started it can return a `job_id` immediately. Clients then poll
+//! `job_status` / `job_result` or abort with `job_cancel`. A job can also
+//! POST a summary to a webhook once it finishes.
+
+use mcp_types::CallToolResult;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tokio::task::AbortHandle;
+use tracing::Instrument;
+use tracing::error_span;
+use tracing::info;
+
+use crate::session::new_session_id;
+use crate::tools::ToolResult;
+use crate::tools::inherit_output;
+use crate::tools::kill_job_process_groups;
+use crate::tools::post_job_webhook;
+use crate::tools::run_as_job;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        self != JobStatus::Running
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct PageState {
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Snapshot of a job as reported by `job_status`.
+#[derive(Clone, Serialize)]
+pub struct JobInfo {
+    pub job_id: String,
+    pub tool: String,
+    pub status: JobStatus,
+    pub created_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<u32>,
+    pub pages_done: usize,
+    pub pages: BTreeMap<u32, PageState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+struct Job {
+    info: JobInfo,
+    result: Option<CallToolResult>,
+    abort: Option<AbortHandle>,
+    /// URL the job's summary is POSTed to once it finishes
+    webhook: Option<String>,
+}
+
+impl Job {
+    /// POST the job's state and the structured content of its result to
+    /// its webhook, if it has one.
+    fn notify_webhook(&self) {
+        let Some(url) = &self.webhook else {
+            return;
+        };
+        let mut summary = serde_json::to_value(&self.info).unwrap_or_default();
+        summary["result"] = self
+            .result
+            .as_ref()
+            .and_then(|result| result.structured_content.clone())
+            .unwrap_or_default();
+        post_job_webhook(url.clone(), summary);
+    }
+}
+
+/// Shared table of jobs. Cloning yields another handle to the same table.
+#[derive(Clone)]
+pub struct JobManager {
+    jobs: Arc<Mutex<HashMap<String, Job>>>,
+    /// Random id of the client session the jobs belong to
+    session_id: Arc<str>,
+}
+
+/// Handle given to a running job so it can report progress.
+#[derive(Clone)]
+pub struct JobProgress {
+    job_id: String,
+    jobs: Arc<Mutex<HashMap<String, Job>>>,
+}
+
+static JOB_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self {
+            jobs: Arc::default(),
+            session_id: new_session_id().into(),
+        }
+    }
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Id of the client session these jobs belong to, recorded as the owner
+    /// of their checkpoints.
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Start `run` in the background and return its job id. `webhook` is
+    /// told when the job finishes.
+    pub fn spawn<F, Fut>(&self, tool: &str, webhook: Option<String>, run: F) -> String
+    where
+        F: FnOnce(JobProgress) -> Fut,
+        Fut: Future<Output = ToolResult> + Send + 'static,
+    {
+        let job_id = format!(
+            "job_{:x}_{}",
+            unix_now(),
+            JOB_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        self.spawn_as(job_id, tool, webhook, run)
+    }
+
+    /// Like [`spawn`](Self::spawn), under an existing `job_id`, e.g. to
+    /// resume a job from its checkpoint. A finished job of that id is
+    /// replaced.
+    pub fn spawn_as<F, Fut>(
+        &self,
+        job_id: String,
+        tool: &str,
+        webhook: Option<String>,
+        run: F,
+    ) -> String
+    where
+        F: FnOnce(JobProgress) -> Fut,
+        Fut: Future<Output = ToolResult> + Send + 'static,
+    {
+        let progress = JobProgress {
+            job_id: job_id.clone(),
+            jobs: self.jobs.clone(),
+        };
+
+        // Insert before spawning so the task can never finish ahead of its entry
+        self.lock().insert(
+            job_id.clone(),
+            Job {
+                info: JobInfo {
+                    job_id: job_id.clone(),
+                    tool: tool.to_string(),
+                    status: JobStatus::Running,
+                    created_at: unix_now(),
+                    finished_at: None,
+                    total_pages: None,
+                    pages_done: 0,
+                    pages: BTreeMap::new(),
+                    message: None,
+                },
+                result: None,
+                abort: None,
+                webhook,
+            },
+        );
+
+        // Keeps streaming script output to the client that started the job
+        let fut = inherit_output(run_as_job(job_id.clone(), run(progress.clone())));
+        // Nested in the span of the request that started the job
+        let span = error_span!("job", job_id = %job_id);
+        let handle = tokio::spawn(
+            async move {
+                let outcome = fut.await;
+                progress.finish(outcome);
+            }
+            .instrument(span),
+        );
+
+        if let Some(job) = self.lock().get_mut(&job_id) {
+            job.abort = Some(handle.abort_handle());
+        }
+
+        info!("Started job {job_id} for {tool}");
+        job_id
+    }
+
+    pub fn status(&self, job_id: &str) -> Option<JobInfo> {
+        self.lock().get(job_id).map(|job| job.info.clone())
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self.lock().values().map(|job| job.info.clone()).collect();
+        jobs.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then(a.job_id.cmp(&b.job_id))
+        });
+        jobs
+    }
+
+    /// Return the job's info together with its final result, if finished.
+    pub fn result(&self, job_id: &str) -> Option<(JobInfo, Option<CallToolResult>)> {
+        self.lock()
+            .get(job_id)
+            .map(|job| (job.info.clone(), job.result.clone()))
+    }
+
+    /// Abort a running job. Returns the job's status after the call, or
+    /// `None` if the id is unknown.
+    ///
+    /// The job's child process groups are killed before its task is
+    /// aborted, while they are still tracked, so the processes its python
+    /// children started die with them. The job is marked cancelled first so
+    /// the failures this causes are not recorded instead.
+    pub fn cancel(&self, job_id: &str) -> Option<JobStatus> {
+        self.cancel_killing(job_id).map(|(status, _)| status)
+    }
+
+    /// Abort every running job, e.g. when the session that owns them ends.
+    /// Returns the ids of the aborted jobs and how many subprocess groups
+    /// were killed with them.
+    pub fn cancel_all(&self) -> (Vec<String>, usize) {
+        let running: Vec<String> = self
+            .lock()
+            .values()
+            .filter(|job| job.info.status == JobStatus::Running)
+            .map(|job| job.info.job_id.clone())
+            .collect();
+        let killed = running
+            .iter()
+            .filter_map(|job_id| self.cancel_killing(job_id))
+            .map(|(_, killed)| killed)
+            .sum();
+        (running, killed)
+    }
+
+    /// [`cancel`](Self::cancel), also returning how many subprocess groups
+    /// were killed.
+    fn cancel_killing(&self, job_id: &str) -> Option<(JobStatus, usize)> {
+        let mut jobs = self.lock();
+        let job = jobs.get_mut(job_id)?;
+        let mut killed = 0;
+        if job.info.status == JobStatus::Running {
+            job.info.status = JobStatus::Cancelled;
+            killed = kill_job_process_groups(job_id);
+            if let Some(abort) = job.abort.take() {
+                abort.abort();
+            }
+            job.info.finished_at = Some(unix_now());
+            info!("Cancelled job {job_id}");
+            job.notify_webhook();
+        }
+        Some((job.info.status, killed))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Job>> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl JobProgress {
+    pub fn job_id(&self) -> &str {
+        &self.job_id
+    }
+
+    pub fn set_total_pages(&self, total: u32) {
+        self.update(|info| info.total_pages = Some(total));
+    }
+
+    pub fn page_finished(&self, page: u32, status: &str, message: Option<String>) {
+        self.update(|info| {
+            info.pages.insert(
+                page,
+                PageState {
+                    status: status.to_string(),
+                    message,
+                },
+            );
+            info.pages_done = info.pages.len();
+        });
+    }
+
+    pub fn set_message(&self, message: impl Into<String>) {
+        let message = message.into();
+        self.update(|info| info.message = Some(message));
+    }
+
+    fn finish(&self, outcome: ToolResult) {
+        let mut jobs = self
+            .jobs
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let Some(job) = jobs.get_mut(&self.job_id) else {
+            return;
+        };
+        if job.info.status == JobStatus::Cancelled {
+            return;
+        }
+
+        job.info.finished_at = Some(unix_now());
+        job.abort = None;
+        match outcome {
+            Ok(result) => {
+                job.info.status = if result.is_error == Some(true) {
+                    JobStatus::Failed
+                } else {
+                    JobStatus::Succeeded
+                };
+                job.result = Some(result);
+            }
+            Err(e) => {
+                job.info.status = JobStatus::Failed;
+                job.info.message = Some(format!("Error: {e}"));
+                job.result = Some(e.to_call_result());
+            }
+        }
+        info!("Job {} finished: {:?}", self.job_id, job.info.status);
+        job.notify_webhook();
+    }
+
+    fn update(&self, f: impl FnOnce(&mut JobInfo)) {
+        let mut jobs = self
+            .jobs
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(job) = jobs.get_mut(&self.job_id)
+            && job.info.status == JobStatus::Running
+        {
+            f(&mut job.info);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -4,53 +4,100 @@
 //! and images using OpenCV and Python scripts.
 
 #![deny(clippy::print_stdout, clippy::print_stderr)]
+// The tool schemas are large `json!` literals
+#![recursion_limit = "256"]
 
 use std::io::Result as IoResult;
 use mcp_types::JSONRPCMessage;
 use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
 use tokio::io::AsyncWriteExt;
 use tokio::io::BufReader;
 use tokio::io::{self};
-use tokio::sync::mpsc;
 use tracing::debug;
 use tracing::error;
 use tracing::info;
-use tracing_subscriber::EnvFilter;
 
+pub mod api;
+pub mod audit;
+pub mod config;
+pub mod i18n;
+pub mod jobs;
+pub mod logging;
 pub mod message_processor;
+pub mod metrics;
+pub mod session;
 pub mod tools;
+pub mod watch;
 
-use crate::message_processor::MessageProcessor;
-use crate::message_processor::OutgoingMessage;
-use crate::message_processor::OutgoingMessageSender;
-
-/// Size of the bounded channels used to communicate between tasks
-const CHANNEL_CAPACITY: usize = 128;
+use crate::session::Session;
 
 pub async fn run_main(
 ) -> IoResult<()> {
-    // Install a simple subscriber so `tracing` output is visible
-    tracing_subscriber::fmt()
-        .with_writer(std::io::stderr)
-        .with_env_filter(EnvFilter::from_default_env())
-        .init();
-
-    // Set up channels
-    let (incoming_tx, mut incoming_rx) = mpsc::channel::<JSONRPCMessage>(CHANNEL_CAPACITY);
-    let (outgoing_tx, mut outgoing_rx) = mpsc::unbounded_channel::<OutgoingMessage>();
+    // Install a subscriber so `tracing` output is visible
+    logging::init_logging();
+
+    // Extract the bundled Python scripts up front so a broken cache dir is
+    // reported at startup rather than on the first tool call
+    if let Err(e) = tools::scripts_dir() {
+        error!("Failed to prepare Python scripts: {e:#}");
+    }
+    tools::python_interpreter();
+    tools::allowed_dirs();
+    metrics::init_metrics();
+    tools::spawn_workspace_gc();
+
+    run_with_transport(io::stdin(), io::stdout()).await
+}
 
-    // Task: read from stdin, push to `incoming_tx`
-    let stdin_reader_handle = tokio::spawn({
+/// Serve the MCP protocol over `reader` and `writer` instead of stdin and
+/// stdout, e.g. one end of a [`tokio::io::duplex`] pipe for integration tests
+/// or for embedding the server in another program. Messages are
+/// newline-delimited JSON-RPC, as on stdio. Returns once `reader` reaches EOF
+/// and every response has been written. The connection is one [`Session`];
+/// transports serving several clients keep theirs in a
+/// [`session::SessionManager`].
+///
+/// Unlike [`run_main`] this does not install a tracing subscriber.
+///
+/// ```no_run
+/// use tokio::io::AsyncBufReadExt;
+/// use tokio::io::AsyncWriteExt;
+/// use tokio::io::BufReader;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let (client, server) = tokio::io::duplex(64 * 1024);
+/// let (server_read, server_write) = tokio::io::split(server);
+/// tokio::spawn(watermark_remover_mcp_server::run_with_transport(server_read, server_write));
+///
+/// let (client_read, mut client_write) = tokio::io::split(client);
+/// client_write
+///     .write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"tools/list\"}\n")
+///     .await?;
+/// let mut lines = BufReader::new(client_read).lines();
+/// let response = lines.next_line().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn run_with_transport<R, W>(reader: R, mut writer: W) -> IoResult<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let session = Session::spawn("default");
+    let outgoing = session.outgoing();
+
+    // Task: read from `reader`, hand messages to the session
+    let reader_handle = tokio::spawn({
         async move {
-            let stdin = io::stdin();
-            let reader = BufReader::new(stdin);
-            let mut lines = reader.lines();
+            let mut lines = BufReader::new(reader).lines();
 
             while let Some(line) = lines.next_line().await.unwrap_or_default() {
                 match serde_json::from_str::<JSONRPCMessage>(&line) {
                     Ok(msg) => {
-                        if incoming_tx.send(msg).await.is_err() {
-                            // Receiver gone – nothing left to do
+                        if !session.send(msg).await {
+                            // Session gone – nothing left to do
                             break;
                         }
                     }
@@ -58,53 +105,43 @@ pub async fn run_main(
                 }
             }
 
-            debug!("stdin reader finished (EOF)");
+            debug!("reader finished (EOF)");
+            session.close();
         }
     });
 
-    // Task: process incoming messages
-    let processor_handle = tokio::spawn({
-        let outgoing_message_sender = OutgoingMessageSender::new(outgoing_tx);
-        let mut processor = MessageProcessor::new(outgoing_message_sender);
-        async move {
-            while let Some(msg) = incoming_rx.recv().await {
-                match msg {
-                    JSONRPCMessage::Request(r) => processor.process_request(r).await,
-                    JSONRPCMessage::Response(r) => processor.process_response(r).await,
-                    JSONRPCMessage::Notification(n) => processor.process_notification(n).await,
-                    JSONRPCMessage::Error(e) => processor.process_error(e),
-                }
-            }
-
-            info!("processor task exited (channel closed)");
-        }
-    });
-
-    // Task: write outgoing messages to stdout
-    let stdout_writer_handle = tokio::spawn(async move {
-        let mut stdout = io::stdout();
-        while let Some(outgoing_message) = outgoing_rx.recv().await {
+    // Task: write outgoing messages to `writer`
+    let writer_handle = tokio::spawn(async move {
+        while let Some(outgoing_message) = outgoing.recv().await {
             let msg: JSONRPCMessage = outgoing_message.into();
             match serde_json::to_string(&msg) {
                 Ok(json) => {
-                    if let Err(e) = stdout.write_all(json.as_bytes()).await {
-                        error!("Failed to write to stdout: {e}");
+                    if let Err(e) = writer.write_all(json.as_bytes()).await {
+                        error!("Failed to write message: {e}");
                         break;
                     }
-                    if let Err(e) = stdout.write_all(b"\n").await {
-                        error!("Failed to write newline to stdout: {e}");
+                    if let Err(e) = writer.write_all(b"\n").await {
+                        error!("Failed to write newline: {e}");
                         break;
                     }
                 }
                 Err(e) => error!("Failed to serialize JSONRPCMessage: {e}"),
             }
+            if outgoing.is_empty()
+                && let Err(e) = writer.flush().await
+            {
+                error!("Failed to flush output: {e}");
+                break;
+            }
         }
 
-        info!("stdout writer exited (channel closed)");
+        // Unblock anything still waiting for room after a write error
+        outgoing.close();
+        info!("writer exited (channel closed)");
     });
 
     // Wait for all tasks to finish
-    let _ = tokio::join!(stdin_reader_handle, processor_handle, stdout_writer_handle);
+    let _ = tokio::join!(reader_handle, writer_handle);
 
     Ok(())
 }
@@ -0,0 +1,46 @@
+//! Logging setup - `tracing` output on stderr, as text or JSON lines
+//!
+//! The filter comes from `RUST_LOG`. `WATERMARK_LOG_FORMAT=json` (or the
+//! config file's `log_format = "json"`) switches to one JSON object per line
+//! carrying the enclosing spans, so the `request` span (JSON-RPC id, method
+//! and tool) and any `job` or `watch` span can be used to correlate the
+//! lines of concurrent calls, including the subprocess output logged under
+//! the `subprocess` target.
+
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::config;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Format from `WATERMARK_LOG_FORMAT`, then the config file. Unknown values
+/// fall back to text.
+pub fn log_format() -> LogFormat {
+    match std::env::var("WATERMARK_LOG_FORMAT") {
+        Ok(format) if format.trim().eq_ignore_ascii_case("json") => LogFormat::Json,
+        Ok(format) if !format.trim().is_empty() => LogFormat::Text,
+        _ => config().log_format.unwrap_or_default(),
+    }
+}
+
+/// Install the global subscriber. Call once, after the config is loaded.
+pub fn init_logging() {
+    let builder = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(EnvFilter::from_default_env());
+    match log_format() {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init(),
+    }
+}
@@ -1,7 +1,26 @@
+mod cli;
+
+use clap::Parser;
+use std::process::ExitCode;
+
+use watermark_remover_mcp_server::config::init_config;
+use watermark_remover_mcp_server::config::load_config;
 use watermark_remover_mcp_server::run_main;
 
+use crate::cli::Cli;
+use crate::cli::Command;
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    run_main().await?;
-    Ok(())
+async fn main() -> anyhow::Result<ExitCode> {
+    let cli = Cli::parse();
+
+    init_config(load_config(cli.config.as_deref())?);
+
+    match cli.command {
+        None | Some(Command::Serve) => {
+            run_main().await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(command) => cli::run_command(command).await,
+    }
 }
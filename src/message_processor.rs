@@ -1,8 +1,6 @@
 //! MCP message processor for watermark remover
 
 use mcp_types::CallToolRequestParams;
-use mcp_types::CallToolResult;
-use mcp_types::ContentBlock;
 use mcp_types::Implementation;
 use mcp_types::InitializeRequestParams;
 use mcp_types::InitializeResult;
@@ -12,42 +10,253 @@ use mcp_types::JSONRPCMessage;
 use mcp_types::JSONRPCNotification;
 use mcp_types::JSONRPCRequest;
 use mcp_types::JSONRPCResponse;
+use mcp_types::ListRootsResult;
 use mcp_types::ListToolsResult;
+use mcp_types::LoggingLevel;
+use mcp_types::LoggingMessageNotification;
+use mcp_types::LoggingMessageNotificationParams;
+use mcp_types::ModelContextProtocolNotification;
+use mcp_types::RequestId;
 use mcp_types::ServerCapabilities;
 use mcp_types::ServerCapabilitiesTools;
-use mcp_types::TextContent;
-use tokio::sync::mpsc;
+use mcp_types::SetLevelRequestParams;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::Notify;
+use tokio::sync::oneshot;
+use tracing::Instrument;
+use tracing::Span;
 use tracing::debug;
 use tracing::error;
+use tracing::error_span;
+use tracing::field;
 use tracing::info;
+use tracing::warn;
 
+use crate::audit::record_tool_call;
+use crate::i18n::Locale;
+use crate::i18n::default_locale;
+use crate::i18n::error_message;
+use crate::i18n::instructions;
+use crate::i18n::localize_tools;
+use crate::i18n::requested_locale;
+use crate::jobs::JobManager;
+use crate::metrics;
+use crate::tools::configured_root;
+use crate::tools::forward_output;
 use crate::tools::get_tool_definitions;
 use crate::tools::handle_tool_call;
+use crate::tools::resume_interrupted_jobs;
+use crate::tools::root_from_uri;
+use crate::watch::WatchManager;
 
 pub enum OutgoingMessage {
+    Request(JSONRPCRequest),
     Response(JSONRPCResponse),
     Error(JSONRPCError),
+    Notification(JSONRPCNotification),
 }
 
 impl From<OutgoingMessage> for JSONRPCMessage {
     fn from(msg: OutgoingMessage) -> Self {
         match msg {
+            OutgoingMessage::Request(r) => JSONRPCMessage::Request(r),
             OutgoingMessage::Response(r) => JSONRPCMessage::Response(r),
             OutgoingMessage::Error(e) => JSONRPCMessage::Error(e),
+            OutgoingMessage::Notification(n) => JSONRPCMessage::Notification(n),
         }
     }
 }
 
+impl OutgoingMessage {
+    /// Notifications may be dropped when the client falls behind; responses
+    /// and errors never are.
+    fn is_droppable(&self) -> bool {
+        matches!(self, OutgoingMessage::Notification(_))
+    }
+}
+
+/// Bounded queue of messages waiting for the transport writer.
+///
+/// Responses wait for room, so a client that reads slowly slows down the
+/// processor instead of growing the queue. Notifications are sent from
+/// background tasks that cannot wait; when the queue is full the oldest
+/// queued notification is dropped to make room.
+pub struct OutgoingQueue {
+    state: Mutex<QueueState>,
+    capacity: usize,
+    /// Wakes the writer when a message is queued or the queue closes
+    queued: Notify,
+    /// Wakes senders waiting for room
+    drained: Notify,
+    /// Least severe `notifications/message` level the client wants, as a
+    /// [`severity`] rank
+    log_level: AtomicU8,
+    /// Requests sent through a [`ClientRequester`] that await an answer
+    pending: Mutex<HashMap<RequestId, oneshot::Sender<ClientAnswer>>>,
+    next_request_id: AtomicI64,
+}
+
+/// The client's answer to a server-initiated request
+type ClientAnswer = Result<serde_json::Value, JSONRPCErrorError>;
+
+/// How long a [`ClientRequester`] waits for the client to answer
+const CLIENT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+struct QueueState {
+    messages: VecDeque<OutgoingMessage>,
+    closed: bool,
+}
+
+impl OutgoingQueue {
+    pub fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(QueueState {
+                messages: VecDeque::with_capacity(capacity),
+                closed: false,
+            }),
+            capacity: capacity.max(1),
+            queued: Notify::new(),
+            drained: Notify::new(),
+            log_level: AtomicU8::new(severity(&LoggingLevel::Debug)),
+            pending: Mutex::new(HashMap::new()),
+            next_request_id: AtomicI64::new(0),
+        })
+    }
+
+    /// Next message to write, or `None` once the queue is closed and empty.
+    pub async fn recv(&self) -> Option<OutgoingMessage> {
+        loop {
+            let queued = self.queued.notified();
+            {
+                let mut state = self.lock();
+                if let Some(message) = state.messages.pop_front() {
+                    self.drained.notify_waiters();
+                    return Some(message);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            queued.await;
+        }
+    }
+
+    /// Queue `message`, waiting while the queue is full.
+    async fn send(&self, message: OutgoingMessage) {
+        loop {
+            let drained = self.drained.notified();
+            {
+                let mut state = self.lock();
+                if state.closed {
+                    return;
+                }
+                if state.messages.len() < self.capacity {
+                    state.messages.push_back(message);
+                    self.queued.notify_one();
+                    return;
+                }
+            }
+            drained.await;
+        }
+    }
+
+    /// Queue a droppable `message` without waiting. A full queue drops its
+    /// oldest droppable message, or `message` itself if it holds none.
+    fn send_lossy(&self, message: OutgoingMessage) {
+        let mut state = self.lock();
+        if state.closed {
+            return;
+        }
+        if state.messages.len() >= self.capacity {
+            metrics::add_notification_dropped();
+            match state
+                .messages
+                .iter()
+                .position(OutgoingMessage::is_droppable)
+            {
+                Some(oldest) => {
+                    state.messages.remove(oldest);
+                }
+                None => return,
+            }
+        }
+        state.messages.push_back(message);
+        self.queued.notify_one();
+    }
+
+    /// Hand `message` to the [`ClientRequester`] waiting for it, if it
+    /// answers one of its requests; otherwise give it back. Transports route
+    /// every incoming message through here, so answers arrive while the
+    /// processor is still busy with the tool call that asked.
+    pub fn route_answer(&self, message: JSONRPCMessage) -> Option<JSONRPCMessage> {
+        let (id, answer) = match message {
+            JSONRPCMessage::Response(response) => (response.id.clone(), Ok(response.result)),
+            JSONRPCMessage::Error(error) => (error.id.clone(), Err(error.error)),
+            other => return Some(other),
+        };
+        let waiter = self
+            .pending
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .remove(&id);
+        match (waiter, answer) {
+            (Some(waiter), answer) => {
+                let _ = waiter.send(answer);
+                None
+            }
+            (None, Ok(result)) => Some(JSONRPCMessage::Response(JSONRPCResponse {
+                jsonrpc: mcp_types::JSONRPC_VERSION.to_string(),
+                id,
+                result,
+            })),
+            (None, Err(error)) => Some(JSONRPCMessage::Error(JSONRPCError {
+                jsonrpc: mcp_types::JSONRPC_VERSION.to_string(),
+                id,
+                error,
+            })),
+        }
+    }
+
+    /// Whether no message is waiting to be written.
+    pub fn is_empty(&self) -> bool {
+        self.lock().messages.is_empty()
+    }
+
+    /// Stop accepting messages. The writer still drains what is queued.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.queued.notify_one();
+        self.drained.notify_waiters();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Sends responses and errors to the client. Dropping it closes the queue.
 pub struct OutgoingMessageSender {
-    tx: mpsc::UnboundedSender<OutgoingMessage>,
+    queue: Arc<OutgoingQueue>,
 }
 
 impl OutgoingMessageSender {
-    pub fn new(tx: mpsc::UnboundedSender<OutgoingMessage>) -> Self {
-        Self { tx }
+    pub fn new(queue: Arc<OutgoingQueue>) -> Self {
+        Self { queue }
     }
 
-    pub fn send_response(&self, id: serde_json::Value, result: serde_json::Value) {
+    pub async fn send_response(&self, id: serde_json::Value, result: serde_json::Value) {
         let request_id = if let Some(s) = id.as_str() {
             mcp_types::RequestId::String(s.to_string())
         } else if let Some(i) = id.as_i64() {
@@ -61,10 +270,22 @@ impl OutgoingMessageSender {
             id: request_id,
             result,
         };
-        let _ = self.tx.send(OutgoingMessage::Response(response));
+        self.queue.send(OutgoingMessage::Response(response)).await;
     }
 
-    pub fn send_error(&self, id: serde_json::Value, code: i64, message: String) {
+    pub async fn send_error(&self, id: serde_json::Value, code: i64, message: String) {
+        self.send_error_with_data(id, code, message, None).await;
+    }
+
+    /// Like [`send_error`](Self::send_error), with a machine-readable `data`
+    /// member.
+    pub async fn send_error_with_data(
+        &self,
+        id: serde_json::Value,
+        code: i64,
+        message: String,
+        data: Option<serde_json::Value>,
+    ) {
         let request_id = if let Some(s) = id.as_str() {
             mcp_types::RequestId::String(s.to_string())
         } else if let Some(i) = id.as_i64() {
@@ -79,27 +300,229 @@ impl OutgoingMessageSender {
             error: JSONRPCErrorError {
                 code,
                 message,
-                data: None,
+                data,
             },
         };
-        let _ = self.tx.send(OutgoingMessage::Error(error));
+        self.queue.send(OutgoingMessage::Error(error)).await;
+    }
+
+    /// Send a server-initiated request; its answer arrives through
+    /// [`MessageProcessor::process_response`].
+    pub async fn send_request(
+        &self,
+        id: mcp_types::RequestId,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) {
+        let request = JSONRPCRequest {
+            jsonrpc: mcp_types::JSONRPC_VERSION.to_string(),
+            id,
+            method: method.to_string(),
+            params,
+        };
+        self.queue.send(OutgoingMessage::Request(request)).await;
+    }
+
+    /// Only send `notifications/message` at `level` or above from now on.
+    pub fn set_log_level(&self, level: LoggingLevel) {
+        self.queue
+            .log_level
+            .store(severity(&level), Ordering::Relaxed);
+    }
+
+    /// Handle for server-initiated requests that wait for the client's
+    /// answer, e.g. from inside a tool call.
+    pub fn client_requester(&self) -> ClientRequester {
+        ClientRequester {
+            queue: Arc::downgrade(&self.queue),
+        }
+    }
+
+    /// Handle for server-initiated notifications from background tasks.
+    pub fn notification_sender(&self) -> NotificationSender {
+        NotificationSender {
+            queue: Arc::downgrade(&self.queue),
+        }
+    }
+}
+
+impl Drop for OutgoingMessageSender {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+/// Sends server-initiated notifications (e.g. from folder watches).
+///
+/// Holds a weak handle so long-lived background tasks do not keep the stdout
+/// writer alive once the client has disconnected. Notifications never block;
+/// see [`OutgoingQueue`] for what happens when the client falls behind.
+#[derive(Clone)]
+pub struct NotificationSender {
+    queue: Weak<OutgoingQueue>,
+}
+
+impl NotificationSender {
+    pub fn send<N: ModelContextProtocolNotification>(&self, params: N::Params) {
+        let Some(queue) = self.queue.upgrade() else {
+            return;
+        };
+        let notification = JSONRPCNotification {
+            jsonrpc: mcp_types::JSONRPC_VERSION.to_string(),
+            method: N::METHOD.to_string(),
+            params: serde_json::to_value(params).ok(),
+        };
+        queue.send_lossy(OutgoingMessage::Notification(notification));
+    }
+
+    /// Send a `notifications/message`, unless the client asked for a higher
+    /// level through `logging/setLevel`.
+    pub fn log(&self, level: LoggingLevel, logger: &str, data: serde_json::Value) {
+        let Some(queue) = self.queue.upgrade() else {
+            return;
+        };
+        if severity(&level) < queue.log_level.load(Ordering::Relaxed) {
+            return;
+        }
+        self.send::<LoggingMessageNotification>(LoggingMessageNotificationParams {
+            data,
+            level,
+            logger: Some(logger.to_string()),
+        });
+    }
+
+    /// Whether both senders reach the same client.
+    pub fn same_client(&self, other: &NotificationSender) -> bool {
+        Weak::ptr_eq(&self.queue, &other.queue)
+    }
+}
+
+/// Sends server-initiated requests and waits for the client's answers.
+///
+/// Answers are picked out of the incoming messages by
+/// [`OutgoingQueue::route_answer`], so unlike `roots/list` they do not wait
+/// for the processor to finish its current request.
+#[derive(Clone)]
+pub struct ClientRequester {
+    queue: Weak<OutgoingQueue>,
+}
+
+impl ClientRequester {
+    /// Send a `method` request to the client and wait for its result. Gives
+    /// up with an error after two minutes or once the client disconnects.
+    pub async fn request(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, String> {
+        let Some(queue) = self.queue.upgrade() else {
+            return Err("client disconnected".to_string());
+        };
+        let n = queue.next_request_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let id = RequestId::String(format!("request-{n}"));
+        let (answer_tx, answer_rx) = oneshot::channel();
+        queue
+            .pending
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .insert(id.clone(), answer_tx);
+        queue
+            .send(OutgoingMessage::Request(JSONRPCRequest {
+                jsonrpc: mcp_types::JSONRPC_VERSION.to_string(),
+                id: id.clone(),
+                method: method.to_string(),
+                params,
+            }))
+            .await;
+        // Only the channel keeps the queue alive while waiting
+        drop(queue);
+
+        let answer = tokio::time::timeout(CLIENT_REQUEST_TIMEOUT, answer_rx).await;
+        match answer {
+            Ok(Ok(Ok(result))) => Ok(result),
+            Ok(Ok(Err(error))) => Err(error.message),
+            Ok(Err(_)) => Err("client disconnected".to_string()),
+            Err(_) => {
+                if let Some(queue) = self.queue.upgrade() {
+                    queue
+                        .pending
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .remove(&id);
+                }
+                Err(format!(
+                    "no answer to {method} within {}s",
+                    CLIENT_REQUEST_TIMEOUT.as_secs()
+                ))
+            }
+        }
+    }
+}
+
+/// Rank of `level` in the syslog order the MCP spec uses, debug lowest.
+fn severity(level: &LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
     }
 }
 
 pub struct MessageProcessor {
     sender: OutgoingMessageSender,
     initialized: bool,
+    /// Language of tool descriptions and error messages
+    locale: Locale,
+    jobs: JobManager,
+    watches: WatchManager,
+    /// Whether the client answers `roots/list`
+    client_has_roots: bool,
+    /// The client's first root, the workspace root unless one is configured
+    client_root: Option<PathBuf>,
+    /// Id of the `roots/list` request awaiting an answer
+    pending_roots: Option<RequestId>,
+    next_request_id: i64,
 }
 
 impl MessageProcessor {
     pub fn new(sender: OutgoingMessageSender) -> Self {
+        let watches = WatchManager::new(sender.notification_sender());
         Self {
             sender,
             initialized: false,
+            locale: default_locale(),
+            jobs: JobManager::new(),
+            watches,
+            client_has_roots: false,
+            client_root: None,
+            pending_roots: None,
+            next_request_id: 0,
         }
     }
 
+    /// Cancel the jobs and stop the watches this client started. Called when
+    /// its session ends.
+    pub fn shutdown(&self) {
+        self.jobs.cancel_all();
+        self.watches.stop_all();
+    }
+
+    /// Handle one request inside a `request` span carrying its id, method
+    /// and (for `tools/call`) tool name, so every log line it causes can be
+    /// traced back to it. The span is at error level so it is kept whatever
+    /// the log filter.
     pub async fn process_request(&mut self, request: JSONRPCRequest) {
+        let id = serde_json::to_value(&request.id).unwrap_or(serde_json::Value::Null);
+        let span = error_span!("request", id = %id, method = %request.method, tool = field::Empty);
+        self.dispatch_request(request).instrument(span).await;
+    }
+
+    async fn dispatch_request(&mut self, request: JSONRPCRequest) {
         debug!("Processing request: {}", request.method);
 
         let id = serde_json::to_value(request.id.clone()).unwrap_or(serde_json::Value::Null);
@@ -115,50 +538,104 @@ impl MessageProcessor {
             "tools/call" => {
                 self.handle_tool_call(id, params).await;
             }
+            "logging/setLevel" => {
+                self.handle_set_level(id, params).await;
+            }
             _ => {
-                self.sender.send_error(
-                    serde_json::to_value(request.id).unwrap_or(serde_json::Value::Null),
-                    -32601,
-                    format!("Method not found: {}", request.method),
-                );
+                self.sender
+                    .send_error(
+                        serde_json::to_value(request.id).unwrap_or(serde_json::Value::Null),
+                        -32601,
+                        format!("Method not found: {}", request.method),
+                    )
+                    .await;
             }
         }
     }
 
     pub async fn process_response(&mut self, response: JSONRPCResponse) {
         debug!("Received response: {:?}", response.id);
+        if self.pending_roots.as_ref() != Some(&response.id) {
+            return;
+        }
+        self.pending_roots = None;
+        match serde_json::from_value::<ListRootsResult>(response.result) {
+            Ok(result) => {
+                self.client_root = result
+                    .roots
+                    .iter()
+                    .find_map(|root| root_from_uri(&root.uri));
+                match &self.client_root {
+                    Some(root) => info!("Workspace root from client: {}", root.display()),
+                    None => info!("Client reported no local roots"),
+                }
+            }
+            Err(e) => warn!("Malformed roots/list result: {e}"),
+        }
     }
 
     pub async fn process_notification(&mut self, notification: JSONRPCNotification) {
         debug!("Received notification: {}", notification.method);
+        match notification.method.as_str() {
+            "notifications/initialized" | "notifications/roots/list_changed" => {
+                self.request_roots().await;
+            }
+            _ => {}
+        }
     }
 
     pub fn process_error(&mut self, error: JSONRPCError) {
+        if self.pending_roots.as_ref() == Some(&error.id) {
+            self.pending_roots = None;
+            warn!("Client failed roots/list: {}", error.error.message);
+            return;
+        }
         error!(
             "Received error: {} - {}",
             error.error.code, error.error.message
         );
     }
 
+    /// Ask the client for its roots, unless it has none to give or a
+    /// workspace root is configured.
+    async fn request_roots(&mut self) {
+        if !self.client_has_roots || configured_root().is_some() {
+            return;
+        }
+        self.next_request_id += 1;
+        let id = RequestId::String(format!("roots-{}", self.next_request_id));
+        self.pending_roots = Some(id.clone());
+        self.sender.send_request(id, "roots/list", None).await;
+    }
+
+    /// Directory relative path arguments are resolved against.
+    fn workspace_root(&self) -> Option<PathBuf> {
+        configured_root().or_else(|| self.client_root.clone())
+    }
+
     async fn handle_initialize(&mut self, id: serde_json::Value, params: serde_json::Value) {
-        let _request: InitializeRequestParams = match serde_json::from_value(params) {
+        if let Some(locale) = requested_locale(&params) {
+            self.locale = locale;
+        }
+        let request: InitializeRequestParams = match serde_json::from_value(params) {
             Ok(r) => r,
             Err(e) => {
                 self.sender
-                    .send_error(id, -32602, format!("Invalid params: {e}"));
+                    .send_error(id, -32602, format!("Invalid params: {e}"))
+                    .await;
                 return;
             }
         };
 
+        self.client_has_roots = request.capabilities.roots.is_some();
+
         let result = InitializeResult {
             protocol_version: mcp_types::MCP_SCHEMA_VERSION.to_string(),
             capabilities: ServerCapabilities {
-                tools: Some(ServerCapabilitiesTools {
-                    list_changed: None,
-                }),
+                tools: Some(ServerCapabilitiesTools { list_changed: None }),
                 prompts: None,
                 resources: None,
-                logging: None,
+                logging: Some(serde_json::json!({})),
                 completions: None,
                 experimental: None,
             },
@@ -168,15 +645,18 @@ impl MessageProcessor {
                 version: "0.1.0".to_string(),
                 user_agent: None,
             },
-            instructions: Some("Watermark Remover MCP Server - Remove watermarks from PDF files and images using OpenCV.".to_string()),
+            instructions: Some(instructions(self.locale).to_string()),
         };
 
         self.initialized = true;
+        resume_interrupted_jobs(&self.jobs);
         match serde_json::to_value(result) {
-            Ok(val) => self.sender.send_response(id, val),
-            Err(e) => self
-                .sender
-                .send_error(id, -32000, format!("Serialization error: {e}")),
+            Ok(val) => self.sender.send_response(id, val).await,
+            Err(e) => {
+                self.sender
+                    .send_error(id, -32000, format!("Serialization error: {e}"))
+                    .await
+            }
         }
         info!("Initialized Watermark Remover MCP server");
     }
@@ -184,28 +664,48 @@ impl MessageProcessor {
     async fn handle_list_tools(&mut self, id: serde_json::Value, _params: serde_json::Value) {
         if !self.initialized {
             self.sender
-                .send_error(id, -32002, "Server not initialized".to_string());
+                .send_error(id, -32002, "Server not initialized".to_string())
+                .await;
             return;
         }
 
-        let tools = get_tool_definitions();
+        let mut tools = get_tool_definitions();
+        localize_tools(&mut tools, self.locale);
         let result = ListToolsResult {
             tools,
             next_cursor: None,
         };
 
         match serde_json::to_value(result) {
-            Ok(val) => self.sender.send_response(id, val),
-            Err(e) => self
-                .sender
-                .send_error(id, -32000, format!("Serialization error: {e}")),
+            Ok(val) => self.sender.send_response(id, val).await,
+            Err(e) => {
+                self.sender
+                    .send_error(id, -32000, format!("Serialization error: {e}"))
+                    .await
+            }
+        }
+    }
+
+    async fn handle_set_level(&mut self, id: serde_json::Value, params: serde_json::Value) {
+        match serde_json::from_value::<SetLevelRequestParams>(params) {
+            Ok(params) => {
+                debug!("Client log level set to {:?}", params.level);
+                self.sender.set_log_level(params.level);
+                self.sender.send_response(id, serde_json::json!({})).await;
+            }
+            Err(e) => {
+                self.sender
+                    .send_error(id, -32602, format!("Invalid params: {e}"))
+                    .await;
+            }
         }
     }
 
     async fn handle_tool_call(&mut self, id: serde_json::Value, params: serde_json::Value) {
         if !self.initialized {
             self.sender
-                .send_error(id, -32002, "Server not initialized".to_string());
+                .send_error(id, -32002, "Server not initialized".to_string())
+                .await;
             return;
         }
 
@@ -213,36 +713,66 @@ impl MessageProcessor {
             Ok(r) => r,
             Err(e) => {
                 self.sender
-                    .send_error(id, -32602, format!("Invalid params: {e}"));
+                    .send_error(id, -32602, format!("Invalid params: {e}"))
+                    .await;
                 return;
             }
         };
 
-        match handle_tool_call(request).await {
+        Span::current().record("tool", request.name.as_str());
+        let tool = request.name.clone();
+        let arguments = request.arguments.clone();
+        let started = Instant::now();
+        let root = self.workspace_root();
+        // Output of the scripts the call runs reaches the client as it arrives
+        let outcome = forward_output(
+            self.sender.notification_sender(),
+            handle_tool_call(
+                request,
+                &self.jobs,
+                &self.watches,
+                root.as_deref(),
+                &self.sender.client_requester(),
+            ),
+        )
+        .await;
+        let elapsed = started.elapsed();
+        record_tool_call(&tool, arguments.as_ref(), elapsed, &outcome);
+        metrics::record_tool_call(
+            &tool,
+            elapsed,
+            outcome
+                .as_ref()
+                .map_or(true, |result| result.is_error == Some(true)),
+        );
+
+        match outcome {
             Ok(result) => match serde_json::to_value(result) {
-                Ok(val) => self.sender.send_response(id, val),
-                Err(e) => self
-                    .sender
-                    .send_error(id, -32000, format!("Serialization error: {e}")),
-            },
-            Err(e) => {
-                let result = CallToolResult {
-                    content: vec![ContentBlock::TextContent(TextContent {
-                        r#type: "text".to_string(),
-                        text: format!("Error: {e}"),
-                        annotations: None,
-                    })],
-                    is_error: Some(true),
-                    structured_content: None,
-                };
-                match serde_json::to_value(result) {
-                    Ok(val) => self.sender.send_response(id, val),
-                    Err(e) => {
-                        self.sender
-                            .send_error(id, -32000, format!("Serialization error: {e}"))
-                    }
+                Ok(val) => self.sender.send_response(id, val).await,
+                Err(e) => {
+                    self.sender
+                        .send_error(id, -32000, format!("Serialization error: {e}"))
+                        .await
                 }
+            },
+            Err(e) if e.is_protocol_error() => {
+                self.sender
+                    .send_error_with_data(
+                        id,
+                        e.code(),
+                        error_message(&e, self.locale),
+                        Some(e.data()),
+                    )
+                    .await
             }
+            Err(e) => match serde_json::to_value(e.to_localized_call_result(self.locale)) {
+                Ok(val) => self.sender.send_response(id, val).await,
+                Err(e) => {
+                    self.sender
+                        .send_error(id, -32000, format!("Serialization error: {e}"))
+                        .await
+                }
+            },
         }
     }
 }
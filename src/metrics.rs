@@ -0,0 +1,105 @@
+//! Server metrics - per-tool call counters and pipeline totals
+//!
+//! Counters live for the lifetime of the process and are reported by the
+//! `server_stats` tool. Tool calls are recorded by the message processor;
+//! pages and bytes are added by the pipeline functions themselves, so work
+//! done by background jobs and watches is counted too.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Call statistics for one tool.
+#[derive(Clone, Default, Serialize)]
+pub struct ToolStats {
+    pub calls: u64,
+    pub errors: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+}
+
+/// Snapshot returned by [`snapshot`].
+#[derive(Serialize)]
+pub struct MetricsSnapshot {
+    pub uptime_secs: u64,
+    pub tool_calls: u64,
+    pub tool_errors: u64,
+    pub pages_processed: u64,
+    pub bytes_written: u64,
+    /// Notifications dropped because the client read too slowly
+    pub notifications_dropped: u64,
+    pub tools: BTreeMap<String, ToolStats>,
+}
+
+static STARTED: LazyLock<Instant> = LazyLock::new(Instant::now);
+static TOOLS: LazyLock<Mutex<BTreeMap<String, ToolStats>>> = LazyLock::new(Mutex::default);
+static PAGES_PROCESSED: AtomicU64 = AtomicU64::new(0);
+static BYTES_WRITTEN: AtomicU64 = AtomicU64::new(0);
+static NOTIFICATIONS_DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// Start the uptime clock. Later calls have no effect.
+pub fn init_metrics() {
+    LazyLock::force(&STARTED);
+}
+
+/// Count one finished tool call.
+pub fn record_tool_call(tool: &str, duration: Duration, is_error: bool) {
+    let ms = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+    let mut tools = TOOLS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let stats = tools.entry(tool.to_string()).or_default();
+    stats.calls += 1;
+    if is_error {
+        stats.errors += 1;
+    }
+    stats.total_ms += ms;
+    stats.max_ms = stats.max_ms.max(ms);
+}
+
+/// Count pages rendered or cleaned by the pipeline.
+pub fn add_pages(pages: usize) {
+    PAGES_PROCESSED.fetch_add(pages as u64, Ordering::Relaxed);
+}
+
+/// Count bytes of output files written by the pipeline.
+pub fn add_bytes_written(bytes: u64) {
+    BYTES_WRITTEN.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// Count the current size of each file in `paths`.
+pub fn add_files_written(paths: &[PathBuf]) {
+    let bytes = paths
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum();
+    add_bytes_written(bytes);
+}
+
+/// Count a notification dropped from the full outgoing queue.
+pub fn add_notification_dropped() {
+    NOTIFICATIONS_DROPPED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn snapshot() -> MetricsSnapshot {
+    let tools = TOOLS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone();
+    MetricsSnapshot {
+        uptime_secs: STARTED.elapsed().as_secs(),
+        tool_calls: tools.values().map(|s| s.calls).sum(),
+        tool_errors: tools.values().map(|s| s.errors).sum(),
+        pages_processed: PAGES_PROCESSED.load(Ordering::Relaxed),
+        bytes_written: BYTES_WRITTEN.load(Ordering::Relaxed),
+        notifications_dropped: NOTIFICATIONS_DROPPED.load(Ordering::Relaxed),
+        tools,
+    }
+}
@@ -0,0 +1,215 @@
+//! Sessions - per-client protocol state for multi-client transports
+//!
+//! Over stdio there is exactly one client. A transport serving several
+//! clients gives each connection, or each `Mcp-Session-Id` for Streamable
+//! HTTP, its own [`Session`]: a [`MessageProcessor`] with its own
+//! `initialized` flag, locale, jobs and watches, fed through its own incoming
+//! channel and answering through its own [`OutgoingQueue`]. Request ids only
+//! have to be unique within a session, and one client cannot see or cancel
+//! another's jobs.
+//!
+//! [`SessionManager`] keys the sessions by id. Closing a session lets the
+//! requests in flight answer, then cancels its running jobs, stops its
+//! watches and closes its outgoing queue.
+
+use mcp_types::JSONRPCMessage;
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io::Read;
+use std::sync::Arc;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+use tracing::Instrument;
+use tracing::error_span;
+use tracing::info;
+
+use crate::message_processor::MessageProcessor;
+use crate::message_processor::OutgoingMessageSender;
+use crate::message_processor::OutgoingQueue;
+
+/// Size of each session's incoming channel and outgoing queue
+const SESSION_CAPACITY: usize = 128;
+
+/// One client's protocol state, served by a spawned processor task.
+pub struct Session {
+    id: String,
+    incoming: Mutex<Option<mpsc::Sender<JSONRPCMessage>>>,
+    outgoing: Arc<OutgoingQueue>,
+}
+
+impl Session {
+    /// Start a session called `id`. Must be called within a tokio runtime.
+    pub fn spawn(id: impl Into<String>) -> Arc<Self> {
+        let id = id.into();
+        let (incoming_tx, mut incoming_rx) = mpsc::channel::<JSONRPCMessage>(SESSION_CAPACITY);
+        let outgoing = OutgoingQueue::new(SESSION_CAPACITY);
+
+        let mut processor = MessageProcessor::new(OutgoingMessageSender::new(outgoing.clone()));
+        // A session outlives any one request, so its span is a root
+        let span = error_span!(parent: None, "session", session_id = %id);
+        tokio::spawn(
+            async move {
+                while let Some(msg) = incoming_rx.recv().await {
+                    match msg {
+                        JSONRPCMessage::Request(r) => processor.process_request(r).await,
+                        JSONRPCMessage::Response(r) => processor.process_response(r).await,
+                        JSONRPCMessage::Notification(n) => processor.process_notification(n).await,
+                        JSONRPCMessage::Error(e) => processor.process_error(e),
+                    }
+                }
+
+                // Dropping the processor closes the outgoing queue
+                processor.shutdown();
+                info!("session closed");
+            }
+            .instrument(span),
+        );
+
+        Arc::new(Self {
+            id,
+            incoming: Mutex::new(Some(incoming_tx)),
+            outgoing,
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Hand a message from the client to the session, waiting while its
+    /// channel is full. Returns `false` once the session is closed.
+    pub async fn send(&self, message: JSONRPCMessage) -> bool {
+        let Some(incoming) = self.lock().clone() else {
+            return false;
+        };
+        // Answers to requests a tool call is waiting on skip the queue
+        let Some(message) = self.outgoing.route_answer(message) else {
+            return true;
+        };
+        incoming.send(message).await.is_ok()
+    }
+
+    /// Messages for the client. The transport's writer drains them with
+    /// [`OutgoingQueue::recv`], which returns `None` once the session has
+    /// closed and everything queued has been taken.
+    pub fn outgoing(&self) -> Arc<OutgoingQueue> {
+        self.outgoing.clone()
+    }
+
+    /// Stop accepting messages. Messages already sent are still processed.
+    pub fn close(&self) {
+        self.lock().take();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<mpsc::Sender<JSONRPCMessage>>> {
+        self.incoming
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Table of live sessions. Cloning yields another handle to the same table.
+#[derive(Clone, Default)]
+pub struct SessionManager {
+    sessions: Arc<Mutex<HashMap<String, Arc<Session>>>>,
+}
+
+impl SessionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a session under a fresh, hard-to-guess id, as handed out in the
+    /// `Mcp-Session-Id` header of an `initialize` response.
+    pub fn create(&self) -> Arc<Session> {
+        let mut sessions = self.lock();
+        let id = loop {
+            let id = new_session_id();
+            if !sessions.contains_key(&id) {
+                break id;
+            }
+        };
+        let session = Session::spawn(id.clone());
+        sessions.insert(id, session.clone());
+        info!("Opened session {}", session.id());
+        session
+    }
+
+    /// The session keyed `id`, started on first use. For transports that
+    /// key sessions by connection rather than by a server-issued id.
+    pub fn get_or_create(&self, id: &str) -> Arc<Session> {
+        self.lock()
+            .entry(id.to_string())
+            .or_insert_with(|| {
+                info!("Opened session {id}");
+                Session::spawn(id)
+            })
+            .clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Session>> {
+        self.lock().get(id).cloned()
+    }
+
+    /// Close and forget the session keyed `id`. Returns `false` if the id is
+    /// unknown.
+    pub fn close(&self, id: &str) -> bool {
+        let Some(session) = self.lock().remove(id) else {
+            return false;
+        };
+        session.close();
+        info!("Closing session {id}");
+        true
+    }
+
+    /// Close every session, e.g. when the transport shuts down.
+    pub fn close_all(&self) {
+        for (_, session) in self.lock().drain() {
+            session.close();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<Session>>> {
+        self.sessions
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// 128 bits from the OS random source as 32 hex digits. Where there is
+/// none, two randomly keyed SipHash instances stand in.
+pub(crate) fn new_session_id() -> String {
+    let mut bytes = [0u8; 16];
+    let from_os = std::fs::File::open("/dev/urandom")
+        .and_then(|mut random| random.read_exact(&mut bytes))
+        .is_ok();
+    if !from_os {
+        let high = RandomState::new().hash_one(std::process::id());
+        let low = RandomState::new().hash_one(std::time::Instant::now());
+        bytes[..8].copy_from_slice(&high.to_le_bytes());
+        bytes[8..].copy_from_slice(&low.to_le_bytes());
+    }
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_ids_are_128_random_bits() {
+        let id = new_session_id();
+        assert_eq!(id.len(), 32);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(id, new_session_id());
+    }
+}
@@ -0,0 +1,102 @@
+//! Add Watermark tool - stamps known watermarks onto images or PDFs
+
+use anyhow::Context;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tracing::info;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::python::python_command;
+use super::script_output::parse_json_result;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+
+#[derive(Deserialize)]
+struct AddWatermarkArgs {
+    image_path: Option<String>,
+    image_dir: Option<String>,
+    pdf_path: Option<String>,
+    output_path: String,
+    text: Option<String>,
+    logo_path: Option<String>,
+    position: Option<String>,
+    opacity: Option<f64>,
+    scale: Option<f64>,
+    dpi: Option<u32>,
+}
+
+pub async fn handle_add_watermark(args: serde_json::Value) -> ToolResult {
+    let args: AddWatermarkArgs = parse_args(args)?;
+
+    let (flag, input) = match (&args.image_path, &args.image_dir, &args.pdf_path) {
+        (Some(path), None, None) => ("--image", path),
+        (None, Some(dir), None) => ("--dir", dir),
+        (None, None, Some(path)) => ("--pdf", path),
+        _ => {
+            return Err(ToolError::invalid_args(
+                "Exactly one of image_path, image_dir or pdf_path must be provided",
+            ));
+        }
+    };
+
+    if !PathBuf::from(input).exists() {
+        return Err(ToolError::not_found("Input", input));
+    }
+
+    let scripts_dir = scripts_dir()?;
+    let script_path = scripts_dir.join("add_watermark.py");
+
+    let mut cmd = python_command();
+    cmd.arg(&script_path)
+        .arg(flag)
+        .arg(input)
+        .arg("--output")
+        .arg(&args.output_path);
+
+    if let Some(text) = &args.text {
+        cmd.arg("--text").arg(text);
+    }
+    if let Some(logo_path) = &args.logo_path {
+        cmd.arg("--logo").arg(logo_path);
+    }
+    if let Some(position) = &args.position {
+        cmd.arg("--position").arg(position);
+    }
+    if let Some(opacity) = args.opacity {
+        cmd.arg("--opacity").arg(opacity.to_string());
+    }
+    if let Some(scale) = args.scale {
+        cmd.arg("--scale").arg(scale.to_string());
+    }
+    if let Some(dpi) = args.dpi {
+        cmd.arg("--dpi").arg(dpi.to_string());
+    }
+
+    info!("Adding watermark: {} -> {}", input, args.output_path);
+
+    let output = run_command(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))
+        .await
+        .context("Failed to execute add_watermark.py")?;
+
+    if !output.status.success() {
+        return Err(ToolError::script_failed("add_watermark.py", &output.stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!("Successfully added watermarks.\n{stdout}"),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: parse_json_result(&stdout),
+    })
+}
@@ -0,0 +1,119 @@
+//! Adjust Image tool - deskew, rotate and crop scanned images
+
+use anyhow::Context;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tracing::info;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::overwrite::check_image_outputs;
+use super::python::python_command;
+use super::script_output::parse_json_result;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+
+#[derive(Deserialize)]
+struct CropRect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Deserialize)]
+struct AdjustImageArgs {
+    image_path: Option<String>,
+    image_dir: Option<String>,
+    output_dir: Option<String>,
+    overwrite: Option<bool>,
+    deskew: Option<bool>,
+    rotate: Option<f64>,
+    crop: Option<CropRect>,
+    auto_crop: Option<bool>,
+}
+
+pub async fn handle_adjust_image(args: serde_json::Value) -> ToolResult {
+    let args: AdjustImageArgs = parse_args(args)?;
+
+    let (flag, input) = match (&args.image_path, &args.image_dir) {
+        (Some(path), None) => ("--image", path),
+        (None, Some(dir)) => ("--dir", dir),
+        _ => {
+            return Err(ToolError::invalid_args(
+                "Exactly one of image_path or image_dir must be provided",
+            ));
+        }
+    };
+
+    if !PathBuf::from(input).exists() {
+        return Err(ToolError::not_found("Input", input));
+    }
+    check_image_outputs(
+        Path::new(input),
+        args.output_dir.as_deref().map(Path::new),
+        args.overwrite.unwrap_or(false),
+    )?;
+
+    let deskew = args.deskew.unwrap_or(false);
+    let auto_crop = args.auto_crop.unwrap_or(false);
+    if !deskew && args.rotate.is_none() && args.crop.is_none() && !auto_crop {
+        return Err(ToolError::invalid_args(
+            "At least one of deskew, rotate, crop or auto_crop must be set",
+        ));
+    }
+
+    let scripts_dir = scripts_dir()?;
+    let script_path = scripts_dir.join("adjust_image.py");
+
+    let mut cmd = python_command();
+    cmd.arg(&script_path).arg(flag).arg(input);
+
+    if let Some(output_dir) = &args.output_dir {
+        tokio::fs::create_dir_all(output_dir).await?;
+        cmd.arg("--output").arg(output_dir);
+    }
+    if deskew {
+        cmd.arg("--deskew");
+    }
+    if let Some(rotate) = args.rotate {
+        cmd.arg("--rotate").arg(rotate.to_string());
+    }
+    if let Some(crop) = &args.crop {
+        cmd.arg("--crop").arg(format!(
+            "{},{},{},{}",
+            crop.x, crop.y, crop.width, crop.height
+        ));
+    }
+    if auto_crop {
+        cmd.arg("--auto-crop");
+    }
+
+    info!("Adjusting images: {}", input);
+
+    let output = run_command(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))
+        .await
+        .context("Failed to execute adjust_image.py")?;
+
+    if !output.status.success() {
+        return Err(ToolError::script_failed("adjust_image.py", &output.stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!("Successfully adjusted images.\n{stdout}"),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: parse_json_result(&stdout),
+    })
+}
@@ -0,0 +1,195 @@
+//! Path allowlist - confines tool arguments to configured base directories
+//!
+//! When `WATERMARK_ALLOWED_DIRS` or the config file's `allowed_dirs` is set,
+//! every path-valued argument of a tool call must resolve (after following
+//! symlinks and `..`) to a location inside one of those directories. With
+//! neither set, paths are not restricted. A configured directory that does
+//! not resolve allows nothing; if none resolves, every path is rejected
+//! rather than the restriction being dropped.
+
+use serde_json::Value;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::error;
+use tracing::info;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::object_storage::is_remote;
+use crate::config::config;
+
+static ALLOWED_DIRS: OnceLock<Option<Vec<PathBuf>>> = OnceLock::new();
+
+/// Canonical allowed base directories, or `None` when paths are not
+/// restricted. Empty when every configured directory failed to resolve,
+/// which rejects every path.
+pub fn allowed_dirs() -> Option<&'static [PathBuf]> {
+    ALLOWED_DIRS
+        .get_or_init(|| {
+            let dirs: Vec<PathBuf> = match std::env::var_os("WATERMARK_ALLOWED_DIRS") {
+                Some(value) if !value.is_empty() => std::env::split_paths(&value).collect(),
+                _ => config().allowed_dirs.clone(),
+            };
+            if dirs.is_empty() {
+                return None;
+            }
+            let dirs = canonical_dirs(dirs);
+            if dirs.is_empty() {
+                error!("No allowed directory could be resolved; every tool path is rejected");
+            } else {
+                info!("Tool paths restricted to {dirs:?}");
+            }
+            Some(dirs)
+        })
+        .as_deref()
+}
+
+/// The configured directories that resolve, canonicalized.
+fn canonical_dirs(dirs: Vec<PathBuf>) -> Vec<PathBuf> {
+    dirs.into_iter()
+        .filter_map(|dir| match dir.canonicalize() {
+            Ok(dir) => Some(dir),
+            Err(e) => {
+                error!(
+                    "Allowed directory {} cannot be resolved, so nothing in it is allowed: {e}",
+                    dir.display()
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+/// Reject the call if any path-valued argument, at any depth, falls outside
+/// the allowed directories.
+pub fn check_arguments(arguments: &Value) -> ToolResult<()> {
+    if allowed_dirs().is_none() {
+        return Ok(());
+    }
+    match arguments {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match value {
+                    // Object storage URIs are staged in the workspace
+                    Value::String(path) if is_path_key(key) && !is_remote(path) => {
+                        check_path(Path::new(path))?
+                    }
+                    _ => check_arguments(value)?,
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                check_arguments(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Check one path, which need not exist yet.
+pub fn check_path(path: &Path) -> ToolResult<()> {
+    check_path_in(allowed_dirs(), path)
+}
+
+fn check_path_in(allowed: Option<&[PathBuf]>, path: &Path) -> ToolResult<()> {
+    let Some(allowed) = allowed else {
+        return Ok(());
+    };
+    let resolved = resolve(path);
+    if allowed.iter().any(|dir| resolved.starts_with(dir)) {
+        Ok(())
+    } else {
+        Err(ToolError::PathNotAllowed {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+/// Argument names that hold a file or directory path, e.g. `pdf_path`,
+/// `output_dir`, `output_zip` or `template`.
+pub(super) fn is_path_key(key: &str) -> bool {
+    key == "dir"
+        || key == "template"
+        || key.ends_with("_path")
+        || key.ends_with("_dir")
+        || key.ends_with("_zip")
+}
+
+/// Canonicalize the longest existing prefix of `path` and append the rest,
+/// so outputs that do not exist yet are checked where they will be created.
+fn resolve(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+
+    let mut existing = absolute.as_path();
+    let mut rest = Vec::new();
+    let base = loop {
+        if let Ok(base) = existing.canonicalize() {
+            break base;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            // A `..` below a missing directory, or a missing root
+            _ => return PathBuf::new(),
+        }
+    };
+
+    let mut resolved = base;
+    resolved.extend(rest.into_iter().rev());
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("allowlist_{name}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn unresolvable_dirs_allow_nothing() {
+        let dirs = canonical_dirs(vec![PathBuf::from("/no/such/allowed/dir")]);
+        assert!(dirs.is_empty());
+        // Configured but empty rejects everything instead of lifting the limit
+        let denied = check_path_in(Some(&dirs), Path::new("/tmp/report.pdf"));
+        assert!(matches!(denied, Err(ToolError::PathNotAllowed { .. })));
+        assert!(check_path_in(None, Path::new("/tmp/report.pdf")).is_ok());
+    }
+
+    #[test]
+    fn paths_must_resolve_inside_an_allowed_dir() {
+        let dir = scratch_dir("inside");
+        let dirs = canonical_dirs(vec![dir.clone(), PathBuf::from("/no/such/allowed/dir")]);
+        assert_eq!(dirs, std::slice::from_ref(&dir));
+        let allowed = Some(dirs.as_slice());
+
+        assert!(check_path_in(allowed, &dir.join("in.pdf")).is_ok());
+        assert!(check_path_in(allowed, &dir.join("new/out/page.png")).is_ok());
+        assert!(check_path_in(allowed, &dir.join("../outside.pdf")).is_err());
+        assert!(check_path_in(allowed, &dir.join("missing/../../outside.pdf")).is_err());
+        assert!(check_path_in(allowed, Path::new("/etc/passwd")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn path_keys() {
+        for key in ["dir", "template", "pdf_path", "output_dir", "output_zip"] {
+            assert!(is_path_key(key), "{key}");
+        }
+        for key in ["pattern", "dpi", "resource_uri", "webhook_url"] {
+            assert!(!is_path_key(key), "{key}");
+        }
+    }
+}
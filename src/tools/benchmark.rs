@@ -0,0 +1,478 @@
+//! Benchmark tool - throughput and memory of each removal backend
+//!
+//! Cleans the same sample pages with every backend this build can run and
+//! reports how fast each was and how much memory it took, so operators can
+//! pick one for their hardware:
+//!
+//! - `subprocess`: one `remove_watermark.py --image` process per image
+//! - `batch`: one `remove_watermark.py --dir` process for all images
+//! - `pipe`: one process per image, streamed over stdin/stdout
+//! - `embedded`: the interpreter linked into the server (`embedded-python`
+//!   feature)
+//!
+//! Samples are rendered from `pdf_path`, copied from `image_path`, or
+//! generated: a synthetic text page with a light-gray watermark in the
+//! default bottom-right region.
+
+use anyhow::Context;
+use anyhow::Result;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+use tracing::info;
+use tracing::warn;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::pdf_to_images::render_pdf_pages;
+use super::profiles::RemovalSettings;
+use super::profiles::resolve_settings;
+use super::remove_watermark::DirFilter;
+use super::remove_watermark::WatermarkInput;
+use super::remove_watermark::remove_watermark_bytes;
+use super::remove_watermark::remove_watermark_subprocess;
+use super::workspace::create_temp_dir;
+use super::workspace::remove_temp_dir;
+
+/// Backends in the order they are run
+const BACKENDS: &[&str] = &["subprocess", "batch", "pipe", "embedded"];
+
+/// Most sample pages one benchmark may clean per backend
+const MAX_SAMPLES: u32 = 50;
+
+/// How often the memory of the server and its children is sampled
+const MEMORY_SAMPLE_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Deserialize)]
+struct BenchmarkArgs {
+    pdf_path: Option<String>,
+    image_path: Option<String>,
+    samples: Option<u32>,
+    backends: Option<Vec<String>>,
+    profile: Option<String>,
+    #[serde(flatten)]
+    settings: RemovalSettings,
+}
+
+/// How one backend did on the samples.
+#[derive(Debug, Serialize)]
+struct BackendResult {
+    backend: String,
+    /// Whether this build can run the backend
+    available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    images: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ms_per_image: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    images_per_second: Option<f64>,
+    /// Peak memory of the server and its child processes during the run,
+    /// above the server's own when the run started; Linux only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peak_memory_bytes: Option<u64>,
+    /// Why the backend was not run or failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl BackendResult {
+    fn unavailable(backend: &str, reason: impl Into<String>) -> Self {
+        Self {
+            backend: backend.to_string(),
+            available: false,
+            images: None,
+            total_ms: None,
+            ms_per_image: None,
+            images_per_second: None,
+            peak_memory_bytes: None,
+            error: Some(reason.into()),
+        }
+    }
+}
+
+pub async fn handle_benchmark(args: serde_json::Value) -> ToolResult {
+    let args: BenchmarkArgs = parse_args(args)?;
+
+    let samples = args.samples.unwrap_or(5);
+    if !(1..=MAX_SAMPLES).contains(&samples) {
+        return Err(ToolError::invalid_args(format!(
+            "samples must be between 1 and {MAX_SAMPLES}"
+        )));
+    }
+    let backends = args
+        .backends
+        .unwrap_or_else(|| BACKENDS.iter().map(|b| b.to_string()).collect());
+    if let Some(unknown) = backends.iter().find(|b| !BACKENDS.contains(&b.as_str())) {
+        return Err(ToolError::invalid_args(format!(
+            "Unsupported backend: {unknown} (expected one of {})",
+            BACKENDS.join(", ")
+        )));
+    }
+    let settings = resolve_settings(args.settings, args.profile.as_deref()).await?;
+    let dpi = settings.dpi.unwrap_or(200);
+
+    let dir = create_temp_dir("benchmark").await?;
+    let result = run_benchmark(
+        &dir,
+        args.pdf_path.as_deref(),
+        args.image_path.as_deref(),
+        samples,
+        dpi,
+        &backends,
+        &settings,
+    )
+    .await;
+    remove_temp_dir(&dir).await;
+    let (source, results) = result?;
+
+    let fastest = results
+        .iter()
+        .filter_map(|r| Some((r.backend.as_str(), r.images_per_second?)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(backend, _)| backend.to_string());
+
+    let mut text = format!("Benchmark of {samples} sample page(s) from {source} at {dpi} DPI:\n");
+    for r in &results {
+        match (r.images_per_second, &r.error) {
+            (Some(rate), _) => text.push_str(&format!(
+                "  {}: {rate} images/s ({} ms per image, peak memory {})\n",
+                r.backend,
+                r.ms_per_image.unwrap_or_default(),
+                r.peak_memory_bytes
+                    .map(|bytes| format!("{:.1} MB", bytes as f64 / 1_000_000.0))
+                    .unwrap_or_else(|| "not measured".to_string())
+            )),
+            (None, Some(error)) => text.push_str(&format!("  {}: {error}\n", r.backend)),
+            (None, None) => {}
+        }
+    }
+    if let Some(backend) = &fastest {
+        text.push_str(&format!("Fastest: {backend}"));
+    }
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text,
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(json!({
+            "source": source,
+            "samples": samples,
+            "dpi": dpi,
+            "fastest": fastest,
+            "results": results,
+        })),
+    })
+}
+
+/// Prepare the samples in `dir` and run each backend on them. Returns a
+/// description of where the samples came from and the results.
+async fn run_benchmark(
+    dir: &Path,
+    pdf_path: Option<&str>,
+    image_path: Option<&str>,
+    samples: u32,
+    dpi: u32,
+    backends: &[String],
+    settings: &RemovalSettings,
+) -> Result<(String, Vec<BackendResult>)> {
+    let samples_dir = dir.join("samples");
+    tokio::fs::create_dir_all(&samples_dir).await?;
+    let (source, images) = match (pdf_path, image_path) {
+        (Some(_), Some(_)) => {
+            return Err(ToolError::invalid_args(
+                "Pass either pdf_path or image_path, not both (or neither for synthetic pages)",
+            )
+            .into());
+        }
+        (Some(pdf), None) => {
+            let pdf = PathBuf::from(pdf);
+            if !pdf.exists() {
+                return Err(ToolError::not_found("PDF file", pdf).into());
+            }
+            let rendered =
+                render_pdf_pages(&pdf, Some(&samples_dir), Some(dpi), Some(samples)).await?;
+            (pdf.display().to_string(), rendered.images)
+        }
+        (None, Some(image)) => {
+            let image = PathBuf::from(image);
+            if !image.exists() {
+                return Err(ToolError::not_found("Image file", image).into());
+            }
+            let extension = image
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_string())
+                .unwrap_or_else(|| "png".to_string());
+            let mut images = Vec::new();
+            for index in 1..=samples {
+                let copy = samples_dir.join(format!("sample_{index:03}.{extension}"));
+                tokio::fs::copy(&image, &copy).await?;
+                images.push(copy);
+            }
+            (image.display().to_string(), images)
+        }
+        (None, None) => {
+            let page = tokio::task::spawn_blocking(move || synthetic_page(dpi))
+                .await
+                .context("Synthetic page task failed")??;
+            let mut images = Vec::new();
+            for index in 1..=samples {
+                let path = samples_dir.join(format!("sample_{index:03}.png"));
+                tokio::fs::write(&path, &page).await?;
+                images.push(path);
+            }
+            ("synthetic pages".to_string(), images)
+        }
+    };
+
+    let mut results = Vec::new();
+    for backend in backends {
+        if backend == "embedded" && !cfg!(feature = "embedded-python") {
+            results.push(BackendResult::unavailable(
+                backend,
+                "built without the embedded-python feature",
+            ));
+            continue;
+        }
+        let output_dir = dir.join(format!("out_{backend}"));
+        tokio::fs::create_dir_all(&output_dir).await?;
+        info!(
+            "Benchmarking the {backend} backend on {} images",
+            images.len()
+        );
+
+        let memory = MemorySampler::start();
+        let started = Instant::now();
+        let run = run_backend(backend, &samples_dir, &images, &output_dir, settings).await;
+        let elapsed = started.elapsed();
+        let peak_memory_bytes = memory.and_then(MemorySampler::finish);
+
+        results.push(match run {
+            Ok(()) => {
+                let seconds = elapsed.as_secs_f64();
+                let count = images.len() as f64;
+                BackendResult {
+                    backend: backend.clone(),
+                    available: true,
+                    images: Some(images.len()),
+                    total_ms: Some(u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)),
+                    // Two decimals are plenty for a rate
+                    ms_per_image: Some((seconds * 1000.0 / count * 100.0).round() / 100.0),
+                    images_per_second: Some((count / seconds * 100.0).round() / 100.0),
+                    peak_memory_bytes,
+                    error: None,
+                }
+            }
+            Err(e) => {
+                warn!("The {backend} backend failed: {e:#}");
+                BackendResult {
+                    error: Some(format!("{e:#}")),
+                    available: true,
+                    ..BackendResult::unavailable(backend, "")
+                }
+            }
+        });
+    }
+    Ok((source, results))
+}
+
+/// Clean every image in `images` (all of `samples_dir`) with `backend`.
+async fn run_backend(
+    backend: &str,
+    samples_dir: &Path,
+    images: &[PathBuf],
+    output_dir: &Path,
+    settings: &RemovalSettings,
+) -> Result<()> {
+    match backend {
+        "subprocess" => {
+            for image in images {
+                let input = WatermarkInput::Image(image.clone());
+                remove_watermark_subprocess(&input, Some(output_dir), settings).await?;
+            }
+        }
+        "batch" => {
+            let input = WatermarkInput::Dir(samples_dir.to_path_buf(), DirFilter::default());
+            remove_watermark_subprocess(&input, Some(output_dir), settings).await?;
+        }
+        "pipe" => {
+            for image in images {
+                let bytes = tokio::fs::read(image).await?;
+                remove_watermark_bytes(bytes, settings).await?;
+            }
+        }
+        #[cfg(feature = "embedded-python")]
+        "embedded" => {
+            for image in images {
+                super::embedded_python::remove_watermark_image(image, Some(output_dir), settings)
+                    .await?;
+            }
+        }
+        _ => unreachable!("backend names are checked up front"),
+    }
+    Ok(())
+}
+
+/// An A4 page at `dpi`, PNG-encoded: lines of dark "words" and a light-gray
+/// watermark in the default bottom-right region, which the corner detector
+/// picks up like the NotebookLM logo.
+fn synthetic_page(dpi: u32) -> Result<Vec<u8>> {
+    let width = (8.27 * f64::from(dpi)) as usize;
+    let height = (11.69 * f64::from(dpi)) as usize;
+    let mut pixels = vec![255u8; width * height * 3];
+    let mut fill = |x0: usize, y0: usize, w: usize, h: usize, value: u8| {
+        for y in y0..(y0 + h).min(height) {
+            let row = y * width * 3;
+            pixels[row + x0 * 3..row + (x0 + w).min(width) * 3].fill(value);
+        }
+    };
+
+    // Body text: word-sized blocks of varying width, seeded for repeatability
+    let margin = width / 10;
+    let line = (dpi / 6).max(4) as usize;
+    let mut seed = 0x2545_f491_u32;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed as usize
+    };
+    let mut y = margin;
+    while y + line < height * 85 / 100 {
+        let mut x = margin;
+        while x < width - margin {
+            let word = line * (2 + next() % 5);
+            fill(x, y, word.min(width - margin - x), line / 2, 30);
+            x += word + line / 2;
+        }
+        y += line;
+    }
+
+    // Watermark: light-gray blocks inside [0.80, 0.92, 0.20, 0.08]
+    let (wx, wy) = (width * 83 / 100, height * 94 / 100);
+    let letter = (width * 12 / 100) / 10;
+    for index in 0..8 {
+        fill(
+            wx + index * letter * 5 / 4,
+            wy,
+            letter,
+            height * 3 / 100,
+            200,
+        );
+    }
+
+    let mut encoded = Vec::new();
+    let mut encoder = png::Encoder::new(&mut encoded, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().context("Error encoding PNG")?;
+    writer
+        .write_image_data(&pixels)
+        .context("Error encoding PNG")?;
+    writer.finish().context("Error encoding PNG")?;
+    Ok(encoded)
+}
+
+/// Samples the memory of the server and its child processes on a thread
+/// until [`finish`](Self::finish)ed.
+struct MemorySampler {
+    baseline: u64,
+    peak: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl MemorySampler {
+    /// `None` where process memory cannot be read.
+    fn start() -> Option<Self> {
+        let baseline = process_tree_rss()?;
+        let peak = Arc::new(AtomicU64::new(baseline));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let peak = peak.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    if let Some(rss) = process_tree_rss() {
+                        peak.fetch_max(rss, Ordering::Relaxed);
+                    }
+                    std::thread::sleep(MEMORY_SAMPLE_INTERVAL);
+                }
+            })
+        };
+        Some(Self {
+            baseline,
+            peak,
+            stop,
+            thread,
+        })
+    }
+
+    /// Peak memory above the baseline.
+    fn finish(self) -> Option<u64> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread.join().ok()?;
+        Some(
+            self.peak
+                .load(Ordering::Relaxed)
+                .saturating_sub(self.baseline),
+        )
+    }
+}
+
+/// Resident memory of this process and all its descendants, in bytes.
+#[cfg(target_os = "linux")]
+fn process_tree_rss() -> Option<u64> {
+    use std::collections::HashMap;
+
+    let field = |pid: &str, file: &str, name: &str| -> Option<u64> {
+        let status = std::fs::read_to_string(format!("/proc/{pid}/{file}")).ok()?;
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(name))?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    };
+
+    let mut children: HashMap<u64, Vec<u64>> = HashMap::new();
+    for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(pid) = name.parse::<u64>() else {
+            continue;
+        };
+        if let Some(parent) = field(&name, "status", "PPid:") {
+            children.entry(parent).or_default().push(pid);
+        }
+    }
+
+    let mut total = 0;
+    let mut pending = vec![u64::from(std::process::id())];
+    while let Some(pid) = pending.pop() {
+        total += field(&pid.to_string(), "status", "VmRSS:").unwrap_or_default() * 1024;
+        pending.extend(children.get(&pid).into_iter().flatten());
+    }
+    Some(total)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_tree_rss() -> Option<u64> {
+    None
+}
@@ -0,0 +1,285 @@
+//! Check Environment tool - diagnoses the runtime dependencies of the pipeline
+
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Serialize;
+use serde_json::json;
+use std::process::Stdio;
+use tokio::process::Command;
+
+use super::error::ToolResult;
+use super::python::python_command;
+use super::python::python_interpreter;
+use super::scripts::bundled_script_names;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+
+/// Python modules needed by the scripts, with the pip package providing each
+const PYTHON_MODULES: &[(&str, &str)] = &[
+    ("cv2", "opencv-python-headless"),
+    ("numpy", "numpy"),
+    ("pdf2image", "pdf2image"),
+    ("img2pdf", "img2pdf"),
+    ("PIL", "Pillow"),
+];
+
+/// Python modules only some inputs need, with the pip package providing each
+const OPTIONAL_PYTHON_MODULES: &[(&str, &str)] = &[
+    // HEIC/HEIF/AVIF decoding
+    ("pillow_heif", "pillow-heif"),
+];
+
+/// Ghostscript's console executable
+const GHOSTSCRIPT: &str = if cfg!(windows) { "gswin64c" } else { "gs" };
+
+/// Warn when less than this much space is free in the temp directory
+const MIN_FREE_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Probe run inside the interpreter: module versions and free disk space
+const PYTHON_PROBE: &str = r#"
+import importlib, json, shutil, sys, tempfile
+mods = {}
+for name in sys.argv[1:]:
+    try:
+        m = importlib.import_module(name)
+        mods[name] = {"ok": True, "version": str(getattr(m, "__version__", "unknown"))}
+    except Exception as e:
+        mods[name] = {"ok": False, "error": str(e)}
+tmp = tempfile.gettempdir()
+print(json.dumps({"modules": mods, "temp_dir": tmp, "free_bytes": shutil.disk_usage(tmp).free}))
+"#;
+
+#[derive(Serialize)]
+struct Check {
+    name: String,
+    ok: bool,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+    /// Optional components do not make the overall report fail
+    required: bool,
+}
+
+pub async fn handle_check_environment(_args: serde_json::Value) -> ToolResult {
+    let mut checks = Vec::new();
+
+    // Python interpreter
+    let interpreter = python_interpreter();
+    let program = interpreter.program.to_string_lossy();
+    let python = run_version(&program, &["--version"]).await;
+    let python_ok = python.is_ok();
+    checks.push(Check {
+        name: "python".to_string(),
+        ok: python_ok,
+        detail: format!(
+            "{} ({program}, from {})",
+            python.unwrap_or_else(|e| e),
+            interpreter.source
+        ),
+        hint: (!python_ok).then(|| {
+            "Install Python 3.10+ and make sure python3 is on PATH, or set WATERMARK_PYTHON"
+                .to_string()
+        }),
+        required: true,
+    });
+
+    // Python modules and disk space
+    if python_ok {
+        let mut cmd = python_command();
+        cmd.arg("-c").arg(PYTHON_PROBE);
+        for (module, _) in PYTHON_MODULES.iter().chain(OPTIONAL_PYTHON_MODULES) {
+            cmd.arg(module);
+        }
+        let probe = run_command(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))
+            .await
+            .ok()
+            .filter(|o| o.status.success())
+            .and_then(|o| serde_json::from_slice::<serde_json::Value>(&o.stdout).ok());
+
+        match probe {
+            Some(probe) => {
+                let modules = PYTHON_MODULES
+                    .iter()
+                    .map(|module| (module, true))
+                    .chain(OPTIONAL_PYTHON_MODULES.iter().map(|module| (module, false)));
+                for ((module, package), required) in modules {
+                    let info = &probe["modules"][module];
+                    let ok = info["ok"].as_bool().unwrap_or(false);
+                    checks.push(Check {
+                        name: format!("python module {module}"),
+                        ok,
+                        detail: if ok {
+                            format!("version {}", info["version"].as_str().unwrap_or("unknown"))
+                        } else {
+                            info["error"]
+                                .as_str()
+                                .unwrap_or("not importable")
+                                .to_string()
+                        },
+                        hint: (!ok).then(|| format!("pip install {package}")),
+                        required,
+                    });
+                }
+
+                let free = probe["free_bytes"].as_u64().unwrap_or(0);
+                let ok = free >= MIN_FREE_BYTES;
+                checks.push(Check {
+                    name: "free disk space".to_string(),
+                    ok,
+                    detail: format!(
+                        "{:.1} GB free in {}",
+                        free as f64 / (1024.0 * 1024.0 * 1024.0),
+                        probe["temp_dir"].as_str().unwrap_or("?")
+                    ),
+                    hint: (!ok).then(|| {
+                        "Free up space; high-DPI page images need several MB each".to_string()
+                    }),
+                    required: false,
+                });
+            }
+            None => checks.push(Check {
+                name: "python modules".to_string(),
+                ok: false,
+                detail: "Failed to run the python dependency probe".to_string(),
+                hint: Some("pip install -r scripts/requirements.txt".to_string()),
+                required: true,
+            }),
+        }
+    }
+
+    // Poppler (used by pdf2image), or Ghostscript when poppler is missing
+    let poppler = run_version("pdftoppm", &["-v"]).await;
+    let poppler_ok = poppler.is_ok();
+    let ghostscript = run_version(GHOSTSCRIPT, &["--version"]).await;
+    let ghostscript_ok = ghostscript.is_ok();
+    checks.push(Check {
+        name: "poppler (pdftoppm)".to_string(),
+        ok: poppler_ok,
+        detail: poppler.unwrap_or_else(|e| e),
+        hint: (!poppler_ok).then(|| {
+            "macOS: brew install poppler; Ubuntu: sudo apt install poppler-utils".to_string()
+        }),
+        required: !ghostscript_ok,
+    });
+    checks.push(Check {
+        name: "ghostscript (gs)".to_string(),
+        ok: ghostscript_ok,
+        detail: ghostscript.unwrap_or_else(|e| e),
+        hint: (!ghostscript_ok).then(|| {
+            "Optional; renders PDFs when poppler is missing (brew install ghostscript; sudo apt install ghostscript)".to_string()
+        }),
+        required: false,
+    });
+
+    // MuPDF renders PDFs, with pdftoppm, when Python is missing
+    let mutool = run_version("mutool", &["-v"]).await;
+    let mutool_ok = mutool.is_ok();
+    checks.push(Check {
+        name: "mupdf (mutool)".to_string(),
+        ok: mutool_ok,
+        detail: mutool.unwrap_or_else(|e| e),
+        hint: (!mutool_ok).then(|| {
+            "Optional; renders PDFs without Python when pdftoppm is missing too".to_string()
+        }),
+        required: false,
+    });
+
+    // ffmpeg is optional
+    let ffmpeg = run_version("ffmpeg", &["-version"]).await;
+    let ffmpeg_ok = ffmpeg.is_ok();
+    checks.push(Check {
+        name: "ffmpeg".to_string(),
+        ok: ffmpeg_ok,
+        detail: ffmpeg.unwrap_or_else(|e| e),
+        hint: (!ffmpeg_ok)
+            .then(|| "Optional; not required by the core PDF/image pipeline".to_string()),
+        required: false,
+    });
+
+    // Scripts directory resolution
+    let scripts_dir = scripts_dir();
+    let (scripts_ok, scripts_detail) = match &scripts_dir {
+        Ok(dir) => {
+            let missing: Vec<&str> = bundled_script_names()
+                .filter(|script| !dir.join(script).exists())
+                .collect();
+            if missing.is_empty() {
+                (true, dir.display().to_string())
+            } else {
+                (
+                    false,
+                    format!("{} (missing: {})", dir.display(), missing.join(", ")),
+                )
+            }
+        }
+        Err(e) => (false, format!("Failed to resolve scripts directory: {e}")),
+    };
+    checks.push(Check {
+        name: "scripts directory".to_string(),
+        ok: scripts_ok,
+        detail: scripts_detail,
+        hint: (!scripts_ok).then(|| {
+            "Unset WATERMARK_SCRIPTS_DIR to use the bundled scripts, or point it at the repository's scripts/ directory".to_string()
+        }),
+        required: true,
+    });
+
+    let healthy = checks.iter().all(|c| c.ok || !c.required);
+    let lines: Vec<String> = checks
+        .iter()
+        .map(|c| {
+            let mark = match (c.ok, c.required) {
+                (true, _) => "✓",
+                (false, true) => "✗",
+                (false, false) => "!",
+            };
+            match &c.hint {
+                Some(hint) => format!("{mark} {}: {} (fix: {hint})", c.name, c.detail),
+                None => format!("{mark} {}: {}", c.name, c.detail),
+            }
+        })
+        .collect();
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!(
+                "Environment {}\n{}",
+                if healthy { "OK" } else { "has problems" },
+                lines.join("\n")
+            ),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(json!({
+            "healthy": healthy,
+            "python": interpreter,
+            "checks": checks,
+        })),
+    })
+}
+
+/// Run `program args...` and return the first line of its version output.
+async fn run_version(program: &str, args: &[&str]) -> std::result::Result<String, String> {
+    let output = run_command(
+        Command::new(program)
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .await
+    .map_err(|e| format!("not found ({e})"))?;
+
+    if !output.status.success() {
+        return Err(format!("exited with {}", output.status));
+    }
+
+    // Some tools (pdftoppm -v, older python) print their version on stderr
+    let text = if output.stdout.is_empty() {
+        String::from_utf8_lossy(&output.stderr).to_string()
+    } else {
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    Ok(text.lines().next().unwrap_or_default().trim().to_string())
+}
@@ -0,0 +1,391 @@
+//! Job checkpoints - resumable state of background `process_pdf` runs
+//!
+//! Every `async: true` `process_pdf` job keeps a small JSON file recording
+//! its input, page and PDF outputs, settings and the pages already written.
+//! The file is updated as each page finishes and deleted when the job
+//! succeeds or is cancelled with `job_cancel`. After a crash or restart,
+//! `resume_job` continues the run under the same job id, rendering only the
+//! pages that are missing from its pages directory, then merges the PDF.
+//! With `resume_jobs` enabled, jobs that were still running when the server
+//! stopped are resumed when the first client initializes. In a private
+//! workspace the checkpoint directory is owner-only.
+//!
+//! Each checkpoint records its owner: the server process and the client
+//! session running the job. A client sees and resumes its own checkpoints
+//! and those whose server process has gone; a job another live process or
+//! session owns is never run twice.
+
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use tracing::info;
+use tracing::warn;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::manifest::unix_now;
+use super::process_pdf::PdfJob;
+use super::scripts::cache_root;
+use super::workspace::create_private_dir;
+use super::workspace::private_workspace;
+use crate::config::config;
+use crate::session::new_session_id;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum CheckpointStatus {
+    /// Running, or interrupted by a crash or shutdown
+    Running,
+    /// The last attempt failed
+    Failed,
+}
+
+/// How long a job whose owner cannot be checked stays claimed after its
+/// last saved page
+#[cfg(not(unix))]
+const OWNER_LEASE_SECS: u64 = 15 * 60;
+
+/// Contents of a checkpoint file.
+#[derive(Clone, Serialize, Deserialize)]
+pub(super) struct CheckpointState {
+    pub job_id: String,
+    pub tool: String,
+    pub status: CheckpointStatus,
+    #[serde(flatten)]
+    pub job: PdfJob,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<u32>,
+    pub pages_done: BTreeSet<u32>,
+    pub created_at: u64,
+    pub updated_at: u64,
+    /// Missing in checkpoints written before owners were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<CheckpointOwner>,
+}
+
+/// Server process and client session running a checkpointed job.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct CheckpointOwner {
+    pub pid: u32,
+    /// Random id of the server process, telling it apart from a later
+    /// process that got the same pid
+    pub instance: String,
+    pub session: String,
+}
+
+impl CheckpointOwner {
+    fn current(session: &str) -> Self {
+        Self {
+            pid: std::process::id(),
+            instance: process_instance().to_string(),
+            session: session.to_string(),
+        }
+    }
+
+    /// Whether the owning server process may still be running the job,
+    /// last saved at `updated_at`.
+    #[cfg_attr(unix, allow(unused_variables))]
+    fn is_alive(&self, updated_at: u64) -> bool {
+        if self.instance == process_instance() {
+            return true;
+        }
+        // An earlier process that had our pid
+        if self.pid == std::process::id() {
+            return false;
+        }
+        #[cfg(unix)]
+        {
+            // SAFETY: signal 0 sends nothing, it only checks the pid exists
+            let found = unsafe { libc::kill(self.pid as libc::pid_t, 0) } == 0;
+            found || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+        }
+        // Without a process check the owner holds a lease it renews with
+        // every saved page
+        #[cfg(not(unix))]
+        {
+            unix_now() < updated_at.saturating_add(OWNER_LEASE_SECS)
+        }
+    }
+}
+
+impl CheckpointState {
+    /// Whether `session` may resume or discard this checkpoint: it owns it,
+    /// or the process that did has gone.
+    pub fn claimable_by(&self, session: &str) -> bool {
+        match &self.owner {
+            Some(owner) if owner.instance == process_instance() => owner.session == session,
+            Some(owner) => !owner.is_alive(self.updated_at),
+            None => true,
+        }
+    }
+}
+
+/// Live checkpoint of a running job, saved on every change.
+pub(super) struct Checkpoint {
+    path: PathBuf,
+    state: Mutex<CheckpointState>,
+}
+
+impl Checkpoint {
+    /// Start checkpointing a new `process_pdf` job, or `None` when
+    /// checkpoints are disabled or cannot be written.
+    pub fn create(job_id: &str, job: &PdfJob, session: &str) -> Option<Self> {
+        let now = unix_now();
+        Self::open(CheckpointState {
+            job_id: job_id.to_string(),
+            tool: "process_pdf".to_string(),
+            status: CheckpointStatus::Running,
+            job: job.clone(),
+            total_pages: None,
+            pages_done: BTreeSet::new(),
+            created_at: now,
+            updated_at: now,
+            owner: Some(CheckpointOwner::current(session)),
+        })
+    }
+
+    /// Continue checkpointing a resumed job, now owned by `session`. Pages
+    /// whose image has gone from the pages directory are forgotten so they
+    /// are rendered again.
+    pub fn resume(mut state: CheckpointState, session: &str) -> Option<Self> {
+        state.status = CheckpointStatus::Running;
+        state.owner = Some(CheckpointOwner::current(session));
+        state
+            .pages_done
+            .retain(|page| state.job.pages_dir.join(page_file(*page)).is_file());
+        Self::open(state)
+    }
+
+    fn open(state: CheckpointState) -> Option<Self> {
+        let dir = checkpoint_dir()?;
+        let checkpoint = Self {
+            path: checkpoint_path(&dir, &state.job_id),
+            state: Mutex::new(state),
+        };
+        let created = if private_workspace() {
+            create_private_dir(&dir)
+        } else {
+            std::fs::create_dir_all(&dir)
+        };
+        if let Err(e) = created.and_then(|()| checkpoint.save()) {
+            warn!("Not checkpointing job: {e}");
+            return None;
+        }
+        Some(checkpoint)
+    }
+
+    pub fn pages_done(&self) -> BTreeSet<u32> {
+        self.lock().pages_done.clone()
+    }
+
+    pub fn total_pages(&self) -> Option<u32> {
+        self.lock().total_pages
+    }
+
+    pub fn set_total_pages(&self, total: u32) {
+        self.update(|state| state.total_pages = Some(total));
+    }
+
+    pub fn page_done(&self, page: u32) {
+        self.update(|state| {
+            state.pages_done.insert(page);
+        });
+    }
+
+    /// The job succeeded; nothing is left to resume.
+    pub fn finish(&self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+
+    /// The job failed; keep the checkpoint for an explicit `resume_job`.
+    pub fn fail(&self) {
+        self.update(|state| state.status = CheckpointStatus::Failed);
+    }
+
+    fn update(&self, f: impl FnOnce(&mut CheckpointState)) {
+        {
+            let mut state = self.lock();
+            f(&mut state);
+            state.updated_at = unix_now();
+        }
+        if let Err(e) = self.save() {
+            warn!("Failed to save checkpoint {}: {e}", self.path.display());
+        }
+    }
+
+    /// Write the state next to the checkpoint and rename it into place, so
+    /// a crash mid-write leaves the previous version intact.
+    fn save(&self) -> std::io::Result<()> {
+        let json = serde_json::to_vec_pretty(&*self.lock()).map_err(std::io::Error::other)?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CheckpointState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// `WATERMARK_CHECKPOINT_DIR`, the config file's `checkpoint_dir`, or
+/// `watermark-remover/jobs` in the user cache dir. `off` disables
+/// checkpoints.
+pub(super) fn checkpoint_dir() -> Option<PathBuf> {
+    let dir = std::env::var_os("WATERMARK_CHECKPOINT_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config().checkpoint_dir.clone())
+        .unwrap_or_else(|| cache_root().join("watermark-remover").join("jobs"));
+    (dir.as_os_str() != "off").then_some(dir)
+}
+
+/// The saved state of `job_id`.
+pub(super) fn load_checkpoint(job_id: &str) -> ToolResult<CheckpointState> {
+    let path = checkpoint_dir()
+        .filter(|_| is_job_id(job_id))
+        .map(|dir| checkpoint_path(&dir, job_id))
+        .filter(|path| path.is_file())
+        .ok_or_else(|| ToolError::invalid_args(format!("No checkpoint for job_id: {job_id}")))?;
+    let json = std::fs::read(&path)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Every readable checkpoint `session` may claim, oldest first.
+pub(super) fn list_checkpoints(session: &str) -> Vec<CheckpointState> {
+    let Some(entries) = checkpoint_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut checkpoints: Vec<CheckpointState> = entries
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let json = std::fs::read(&path).ok()?;
+            serde_json::from_slice(&json)
+                .inspect_err(|e| warn!("Ignoring unreadable checkpoint {}: {e}", path.display()))
+                .ok()
+        })
+        .filter(|checkpoint: &CheckpointState| checkpoint.claimable_by(session))
+        .collect();
+    checkpoints.sort_by(|a, b| {
+        a.created_at
+            .cmp(&b.created_at)
+            .then(a.job_id.cmp(&b.job_id))
+    });
+    checkpoints
+}
+
+/// Forget `job_id`'s checkpoint, e.g. because the job was cancelled.
+pub(super) fn discard_checkpoint(job_id: &str) {
+    if let Some(dir) = checkpoint_dir()
+        && is_job_id(job_id)
+        && std::fs::remove_file(checkpoint_path(&dir, job_id)).is_ok()
+    {
+        info!("Discarded checkpoint of {job_id}");
+    }
+}
+
+/// Whether interrupted jobs should be resumed automatically:
+/// `WATERMARK_RESUME_JOBS`, then the config file's `resume_jobs`.
+pub(super) fn auto_resume_enabled() -> bool {
+    match std::env::var("WATERMARK_RESUME_JOBS") {
+        Ok(value) if !value.trim().is_empty() => !matches!(value.trim(), "0" | "false" | "off"),
+        _ => config().resume_jobs.unwrap_or(false),
+    }
+}
+
+/// Claim the one automatic recovery per server process. Returns `true` for
+/// the first caller only.
+pub(super) fn claim_auto_resume() -> bool {
+    static CLAIMED: AtomicBool = AtomicBool::new(false);
+    !CLAIMED.swap(true, Ordering::SeqCst)
+}
+
+/// Random id of this server process, see [`CheckpointOwner::instance`].
+fn process_instance() -> &'static str {
+    static INSTANCE: OnceLock<String> = OnceLock::new();
+    INSTANCE.get_or_init(new_session_id)
+}
+
+fn checkpoint_path(dir: &Path, job_id: &str) -> PathBuf {
+    dir.join(format!("{job_id}.json"))
+}
+
+/// Job ids are `job_<hex>_<n>`; anything else must not become a path.
+fn is_job_id(job_id: &str) -> bool {
+    !job_id.is_empty()
+        && job_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn page_file(page: u32) -> String {
+    format!("page_{page:03}.png")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::profiles::RemovalSettings;
+
+    fn state(owner: Option<CheckpointOwner>) -> CheckpointState {
+        CheckpointState {
+            job_id: "job_1_0".to_string(),
+            tool: "process_pdf".to_string(),
+            status: CheckpointStatus::Running,
+            job: PdfJob {
+                pdf_path: PathBuf::from("in.pdf"),
+                pages_dir: PathBuf::from("pages"),
+                scratch_pages: false,
+                output_path: PathBuf::from("out.pdf"),
+                settings: RemovalSettings::default(),
+                keep_intermediates: false,
+                deterministic: false,
+                text_layer: false,
+                webhook_url: None,
+            },
+            total_pages: None,
+            pages_done: BTreeSet::new(),
+            created_at: 0,
+            updated_at: unix_now(),
+            owner,
+        }
+    }
+
+    #[test]
+    fn this_process_only_lets_the_owning_session_claim() {
+        let checkpoint = state(Some(CheckpointOwner::current("a")));
+        assert!(checkpoint.claimable_by("a"));
+        assert!(!checkpoint.claimable_by("b"));
+    }
+
+    #[test]
+    fn other_processes_hold_their_checkpoints_while_alive() {
+        let mut owner = CheckpointOwner::current("a");
+        owner.instance = "earlier".to_string();
+        // A previous server process that got this pid has exited
+        assert!(state(Some(owner.clone())).claimable_by("b"));
+
+        #[cfg(unix)]
+        {
+            // pid 1 is always running
+            owner.pid = 1;
+            assert!(!state(Some(owner.clone())).claimable_by("b"));
+            owner.pid = u32::MAX / 2;
+            assert!(state(Some(owner)).claimable_by("b"));
+        }
+    }
+
+    #[test]
+    fn checkpoints_without_an_owner_are_claimable() {
+        assert!(state(None).claimable_by("a"));
+    }
+}
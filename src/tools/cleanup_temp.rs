@@ -0,0 +1,141 @@
+//! Cleanup Temp tool - lists and deletes intermediate directories
+
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use serde_json::json;
+use std::path::Path;
+use std::path::PathBuf;
+use tracing::info;
+use tracing::warn;
+
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::manifest::forget_entries;
+use super::manifest::list_entries;
+use super::manifest::manifest_path;
+use super::manifest::unix_now;
+
+#[derive(Deserialize)]
+struct CleanupTempArgs {
+    dry_run: Option<bool>,
+    older_than_hours: Option<f64>,
+}
+
+pub async fn handle_cleanup_temp(args: serde_json::Value) -> ToolResult {
+    let args: CleanupTempArgs = parse_args(args)?;
+    let dry_run = args.dry_run.unwrap_or(false);
+    let min_age_secs = args
+        .older_than_hours
+        .map(|hours| (hours.max(0.0) * 3600.0) as u64)
+        .unwrap_or(0);
+    let now = unix_now();
+
+    let mut lines = Vec::new();
+    let mut items = Vec::new();
+    let mut forgotten = Vec::new();
+    let mut total_bytes = 0u64;
+
+    for entry in list_entries().await {
+        if !entry.path.exists() {
+            // Already gone; just prune the manifest
+            forgotten.push(entry.path);
+            continue;
+        }
+        if now.saturating_sub(entry.created_at) < min_age_secs {
+            continue;
+        }
+
+        let size = dir_size(&entry.path);
+        total_bytes += size;
+
+        let status = if dry_run {
+            "would_delete"
+        } else {
+            match tokio::fs::remove_dir_all(&entry.path).await {
+                Ok(()) => {
+                    forgotten.push(entry.path.clone());
+                    "deleted"
+                }
+                Err(e) => {
+                    warn!("Failed to delete {}: {e}", entry.path.display());
+                    "failed"
+                }
+            }
+        };
+
+        lines.push(format!(
+            "  [{status}] {} ({:.2} MB, created by {})",
+            entry.path.display(),
+            size as f64 / (1024.0 * 1024.0),
+            entry.created_by
+        ));
+        items.push(json!({
+            "path": entry.path,
+            "created_by": entry.created_by,
+            "created_at": entry.created_at,
+            "size_bytes": size,
+            "status": status,
+        }));
+    }
+
+    forget_entries(&forgotten).await?;
+
+    info!(
+        "cleanup_temp: {} intermediate directories ({} bytes), dry_run={}",
+        items.len(),
+        total_bytes,
+        dry_run
+    );
+
+    let header = if dry_run {
+        format!(
+            "Dry run: {} intermediate directories would be deleted ({:.2} MB).",
+            items.len(),
+            total_bytes as f64 / (1024.0 * 1024.0)
+        )
+    } else {
+        format!(
+            "Cleaned up {} intermediate directories ({:.2} MB).",
+            items.len(),
+            total_bytes as f64 / (1024.0 * 1024.0)
+        )
+    };
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!(
+                "{header}\nManifest: {}\n{}",
+                manifest_path().display(),
+                lines.join("\n")
+            ),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(json!({
+            "dry_run": dry_run,
+            "total_bytes": total_bytes,
+            "directories": items,
+        })),
+    })
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let mut total = 0;
+    let mut pending: Vec<PathBuf> = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(std::result::Result::ok) {
+            match entry.metadata() {
+                Ok(meta) if meta.is_dir() => pending.push(entry.path()),
+                Ok(meta) => total += meta.len(),
+                Err(_) => {}
+            }
+        }
+    }
+    total
+}
@@ -0,0 +1,278 @@
+//! Client resources - `resource_uri` inputs read back from the client
+//!
+//! A tool that takes a `pdf_path`, `image_path` or `zip_path` also accepts a
+//! `resource_uri` naming a resource the client can read, such as a document
+//! exposed by another MCP server it is connected to. The server fetches it
+//! with a `resources/read` request to the client, writes the first content
+//! item into a workspace directory and hands the tool that file under the
+//! input argument matching its type.
+
+use anyhow::Context;
+use mcp_types::ReadResourceResult;
+use mcp_types::ReadResourceResultContents;
+use serde_json::Value;
+use serde_json::json;
+use tracing::info;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::get_tool_definitions;
+use super::inline_input::decode_inline_input;
+use super::roots::percent_decode;
+use super::workspace::create_temp_dir;
+use crate::message_processor::ClientRequester;
+
+/// Input arguments a `resource_uri` can stand in for
+pub(super) const RESOURCE_INPUT_KEYS: &[&str] = &["pdf_path", "image_path", "zip_path"];
+
+/// Extensions (lowercase) passed as `pdf_path`; `ensure_pdf` converts the
+/// documents that are not PDFs
+const DOCUMENT_EXTENSIONS: &[&str] = &["pdf", "epub", "djvu", "djv"];
+
+/// Extensions a URI may give the resource when its MIME type does not;
+/// anything else is sniffed from the content
+const URI_EXTENSIONS: &[&str] = &[
+    "pdf", "epub", "djvu", "djv", "zip", "png", "jpg", "jpeg", "webp", "gif", "tif", "tiff", "bmp",
+    "heic", "heif", "avif",
+];
+
+/// Replace a `resource_uri` argument with the local copy of the resource,
+/// under the tool's matching input argument.
+pub(super) async fn fetch_resource_input(
+    name: &str,
+    arguments: &mut Value,
+    client: &ClientRequester,
+) -> ToolResult<()> {
+    let Some(uri) = arguments
+        .get("resource_uri")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+    else {
+        return Ok(());
+    };
+
+    let properties = get_tool_definitions()
+        .into_iter()
+        .find(|tool| tool.name == name)
+        .and_then(|tool| tool.input_schema.properties)
+        .unwrap_or_default();
+    if !RESOURCE_INPUT_KEYS
+        .iter()
+        .any(|key| properties.get(key).is_some())
+    {
+        return Err(ToolError::invalid_args(format!(
+            "{name} does not read a document, image or archive, so it takes no resource_uri"
+        )));
+    }
+
+    info!("Reading client resource {uri}");
+    let result = client
+        .request("resources/read", Some(json!({ "uri": uri })))
+        .await
+        .map_err(|reason| ToolError::ValidationFailed {
+            argument: "resource_uri".to_string(),
+            reason: format!("The client could not read {uri}: {reason}"),
+        })?;
+    let result: ReadResourceResult =
+        serde_json::from_value(result).context("Malformed resources/read result")?;
+    let Some(contents) = result.contents.into_iter().next() else {
+        return Err(ToolError::not_found("Resource", &uri));
+    };
+    let (bytes, mime_type) = match contents {
+        ReadResourceResultContents::TextResourceContents(text) => {
+            (text.text.into_bytes(), text.mime_type)
+        }
+        ReadResourceResultContents::BlobResourceContents(blob) => (
+            decode_inline_input(&blob.blob).map_err(|e| ToolError::ValidationFailed {
+                argument: "resource_uri".to_string(),
+                reason: format!("{uri}: {e}"),
+            })?,
+            blob.mime_type,
+        ),
+    };
+
+    let (stem, extension) = file_name(&uri, mime_type.as_deref(), &bytes);
+    let key = input_key(extension);
+    if properties.get(key).is_none() {
+        return Err(ToolError::ValidationFailed {
+            argument: "resource_uri".to_string(),
+            reason: format!("{uri} is a .{extension} file, which {name} does not take"),
+        });
+    }
+    if arguments.get(key).is_some() {
+        return Err(ToolError::invalid_args(format!(
+            "Pass either resource_uri or {key}, not both"
+        )));
+    }
+
+    let path = create_temp_dir("resource")
+        .await?
+        .join(format!("{stem}.{extension}"));
+    tokio::fs::write(&path, &bytes).await?;
+    info!("Wrote {} bytes of {uri} to {}", bytes.len(), path.display());
+
+    if let Some(map) = arguments.as_object_mut() {
+        map.remove("resource_uri");
+        map.insert(key.to_string(), json!(path.to_string_lossy()));
+    }
+    Ok(())
+}
+
+/// Stem and extension to save the resource under: the last segment of its
+/// URI, with the extension taken from the MIME type, a known extension in
+/// the URI or the content, in that order. The segment is reduced to a plain
+/// file name after decoding, so encoded separators and `..` cannot lead
+/// out of the directory it is written to.
+fn file_name(uri: &str, mime_type: Option<&str>, bytes: &[u8]) -> (String, &'static str) {
+    let segment = uri
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .and_then(percent_decode)
+        .unwrap_or_default();
+    let name = segment
+        .rsplit(['/', '\\'])
+        .next()
+        .filter(|name| !matches!(*name, "" | "." | ".."))
+        .unwrap_or_default();
+    let (stem, uri_extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (
+            stem,
+            URI_EXTENSIONS
+                .iter()
+                .find(|known| known.eq_ignore_ascii_case(extension))
+                .copied(),
+        ),
+        _ => (name, None),
+    };
+    let stem = if matches!(stem, "" | "." | "..") {
+        "resource"
+    } else {
+        stem
+    };
+
+    let extension = mime_type
+        .and_then(mime_extension)
+        .or(uri_extension)
+        .unwrap_or_else(|| sniff_extension(bytes));
+    (stem.to_string(), extension)
+}
+
+fn mime_extension(mime_type: &str) -> Option<&'static str> {
+    let essence = mime_type.split(';').next().unwrap_or_default().trim();
+    Some(match essence.to_lowercase().as_str() {
+        "application/pdf" => "pdf",
+        "application/epub+zip" => "epub",
+        "image/vnd.djvu" | "image/x-djvu" => "djvu",
+        "application/zip" | "application/x-zip-compressed" => "zip",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        "image/tiff" => "tif",
+        "image/bmp" => "bmp",
+        "image/heic" => "heic",
+        "image/heif" => "heif",
+        "image/avif" => "avif",
+        _ => return None,
+    })
+}
+
+fn sniff_extension(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(b"%PDF") {
+        "pdf"
+    } else if bytes.starts_with(b"PK\x03\x04") {
+        "zip"
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "jpg"
+    } else {
+        "png"
+    }
+}
+
+/// Input argument a file with `extension` is passed as.
+fn input_key(extension: &str) -> &'static str {
+    if DOCUMENT_EXTENSIONS.contains(&extension) {
+        "pdf_path"
+    } else if extension == "zip" {
+        "zip_path"
+    } else {
+        "image_path"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_name_uses_the_last_segment_and_mime_type() {
+        let name = |uri, mime| file_name(uri, mime, b"");
+        assert_eq!(
+            name("drive://docs/Q3%20report.pdf", None),
+            ("Q3 report".to_string(), "pdf")
+        );
+        assert_eq!(
+            name("drive://docs/scan", Some("image/jpeg")),
+            ("scan".to_string(), "jpg")
+        );
+        // The MIME type wins over the URI
+        assert_eq!(
+            name("drive://docs/book.bin", Some("application/epub+zip")),
+            ("book".to_string(), "epub")
+        );
+        assert_eq!(name("urn:isbn:12345", None), ("12345".to_string(), "png"));
+        assert_eq!(name("drive://", None), ("resource".to_string(), "png"));
+    }
+
+    #[test]
+    fn file_name_sniffs_unknown_extensions() {
+        assert_eq!(
+            file_name("drive://docs/report.txt", None, b"%PDF-1.7"),
+            ("report".to_string(), "pdf")
+        );
+        assert_eq!(
+            file_name("drive://docs/archive", Some("text/plain"), b"PK\x03\x04"),
+            ("archive".to_string(), "zip")
+        );
+    }
+
+    #[test]
+    fn file_name_cannot_leave_the_directory() {
+        let (stem, extension) = file_name(
+            "x:///a/..%2F..%2F..%2Froot%2F.ssh%2Fauthorized_keys",
+            Some("application/octet-stream"),
+            b"ssh-ed25519 AAAA",
+        );
+        assert_eq!((stem.as_str(), extension), ("authorized_keys", "png"));
+
+        for uri in [
+            "x:///a/..",
+            "x:///a/%2E%2E",
+            "x:///a/..%2F",
+            "x:///a/..%5C..%5Cb.pdf",
+        ] {
+            let (stem, extension) = file_name(uri, None, b"%PDF");
+            assert!(!stem.contains(['/', '\\']) && stem != "..", "{uri}: {stem}");
+            assert_eq!(extension, "pdf");
+        }
+        let (stem, _) = file_name("x:///a/..%5C..%5Cevil.pdf", None, b"");
+        assert_eq!(stem, "evil");
+    }
+
+    #[test]
+    fn mime_extension_ignores_parameters_and_case() {
+        assert_eq!(mime_extension("application/pdf"), Some("pdf"));
+        assert_eq!(mime_extension("Image/PNG; charset=binary"), Some("png"));
+        assert_eq!(mime_extension("image/x-djvu"), Some("djvu"));
+        assert_eq!(mime_extension("text/html"), None);
+    }
+
+    #[test]
+    fn input_key_follows_the_file_type() {
+        assert_eq!(input_key("pdf"), "pdf_path");
+        assert_eq!(input_key("djv"), "pdf_path");
+        assert_eq!(input_key("zip"), "zip_path");
+        assert_eq!(input_key("heic"), "image_path");
+    }
+}
@@ -0,0 +1,125 @@
+//! CMYK detection - finds the PDF pages that draw in CMYK
+//!
+//! Rasterizers part ways on CMYK: poppler converts it to RGB with a fixed
+//! formula, which shifts print colors, while Ghostscript goes through ICC
+//! profiles. The pages found here are rendered with Ghostscript. A page
+//! counts as CMYK when its content sets a DeviceCMYK color (`k`, `K`, or
+//! `cs`/`CS` naming a CMYK space) or it draws an image in one. DeviceCMYK,
+//! four-component ICCBased spaces and palettes over either are CMYK.
+//!
+//! Form XObjects are not searched, so a page drawing CMYK only inside one
+//! is missed.
+
+use anyhow::Context;
+use anyhow::Result;
+use lopdf::Document;
+use lopdf::Object;
+use lopdf::ObjectId;
+use lopdf::content::Content;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// The 1-based numbers of the pages of `pdf_path` with CMYK content.
+/// Blocking.
+pub(super) fn cmyk_pages(pdf_path: &Path) -> Result<BTreeSet<u32>> {
+    let document = Document::load(pdf_path)
+        .with_context(|| format!("Error reading {}", pdf_path.display()))?;
+    Ok(document
+        .get_pages()
+        .into_iter()
+        .filter(|&(_, page_id)| draws_cmyk(&document, page_id))
+        .map(|(page, _)| page)
+        .collect())
+}
+
+fn draws_cmyk(document: &Document, page_id: ObjectId) -> bool {
+    let images = document.get_page_images(page_id).unwrap_or_default();
+    if images.iter().any(|image| {
+        image
+            .origin_dict
+            .get(b"ColorSpace")
+            .is_ok_and(|space| is_cmyk(document, space))
+    }) {
+        return true;
+    }
+
+    let named = cmyk_names(document, page_id);
+    let Ok(content) = document.get_page_content(page_id) else {
+        return false;
+    };
+    let Ok(content) = Content::decode(&content) else {
+        return false;
+    };
+    content
+        .operations
+        .iter()
+        .any(|op| match op.operator.as_str() {
+            "k" | "K" => true,
+            "cs" | "CS" => op
+                .operands
+                .first()
+                .and_then(|operand| operand.as_name().ok())
+                .is_some_and(|name| name == b"DeviceCMYK" || named.contains(name)),
+            _ => false,
+        })
+}
+
+/// Names the page's `ColorSpace` resources give to CMYK spaces.
+fn cmyk_names(document: &Document, page_id: ObjectId) -> BTreeSet<Vec<u8>> {
+    let mut names = BTreeSet::new();
+    let Ok((own, inherited)) = document.get_page_resources(page_id) else {
+        return names;
+    };
+    let resources = own.into_iter().chain(
+        inherited
+            .iter()
+            .filter_map(|&id| document.get_dictionary(id).ok()),
+    );
+    for resources in resources {
+        let Ok(Object::Dictionary(spaces)) = resources
+            .get(b"ColorSpace")
+            .and_then(|spaces| document.dereference(spaces))
+            .map(|(_, spaces)| spaces)
+        else {
+            continue;
+        };
+        for (name, space) in spaces.iter() {
+            if is_cmyk(document, space) {
+                names.insert(name.clone());
+            }
+        }
+    }
+    names
+}
+
+/// Whether `space` is CMYK or a palette of CMYK colors.
+fn is_cmyk(document: &Document, space: &Object) -> bool {
+    let Ok((_, space)) = document.dereference(space) else {
+        return false;
+    };
+    match space.as_array().map(Vec::as_slice) {
+        Ok([family, base, ..]) if family.as_name().is_ok_and(|name| name == b"Indexed") => document
+            .dereference(base)
+            .is_ok_and(|(_, base)| is_cmyk_base(document, base)),
+        _ => is_cmyk_base(document, space),
+    }
+}
+
+/// Whether `space` is DeviceCMYK or ICC-based with four components.
+fn is_cmyk_base(document: &Document, space: &Object) -> bool {
+    match space {
+        Object::Name(name) => name == b"DeviceCMYK",
+        Object::Array(items) => match items.as_slice() {
+            [family, profile, ..] if family.as_name().is_ok_and(|name| name == b"ICCBased") => {
+                document
+                    .dereference(profile)
+                    .and_then(|(_, profile)| profile.as_stream())
+                    .and_then(|profile| profile.dict.get(b"N"))
+                    .and_then(Object::as_i64)
+                    .is_ok_and(|components| components == 4)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
@@ -0,0 +1,89 @@
+//! Compare Images tool - visual diff between original and processed images
+
+use anyhow::Context;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tracing::info;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::image_content::image_content_from_file;
+use super::python::python_command;
+use super::script_output::parse_json_result;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+use super::workspace::create_temp_dir;
+
+#[derive(Deserialize)]
+struct CompareImagesArgs {
+    original_path: String,
+    processed_path: String,
+    diff_path: Option<String>,
+    threshold: Option<u8>,
+}
+
+pub async fn handle_compare_images(args: serde_json::Value) -> ToolResult {
+    let args: CompareImagesArgs = parse_args(args)?;
+
+    for path in [&args.original_path, &args.processed_path] {
+        if !PathBuf::from(path).exists() {
+            return Err(ToolError::not_found("Image file", path));
+        }
+    }
+
+    let diff_path = match args.diff_path {
+        Some(path) => PathBuf::from(path),
+        None => create_temp_dir("diff").await?.join("diff.png"),
+    };
+    let threshold = args.threshold.unwrap_or(10);
+
+    info!(
+        "Comparing images: {} vs {} -> {}",
+        args.original_path,
+        args.processed_path,
+        diff_path.display()
+    );
+
+    let scripts_dir = scripts_dir()?;
+    let script_path = scripts_dir.join("compare_images.py");
+
+    let output = run_command(
+        python_command()
+            .arg(&script_path)
+            .arg(&args.original_path)
+            .arg(&args.processed_path)
+            .arg(&diff_path)
+            .arg(threshold.to_string())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .await
+    .context("Failed to execute compare_images.py")?;
+
+    if !output.status.success() {
+        return Err(ToolError::script_failed(
+            "compare_images.py",
+            &output.stderr,
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    Ok(CallToolResult {
+        content: vec![
+            ContentBlock::TextContent(TextContent {
+                r#type: "text".to_string(),
+                text: format!("Comparison complete.\n{stdout}"),
+                annotations: None,
+            }),
+            ContentBlock::ImageContent(image_content_from_file(&diff_path).await?),
+        ],
+        is_error: Some(false),
+        structured_content: parse_json_result(&stdout),
+    })
+}
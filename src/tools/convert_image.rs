@@ -0,0 +1,98 @@
+//! Convert Image tool - converts images between formats with optional resize
+
+use anyhow::Context;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tracing::info;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::python::python_command;
+use super::script_output::parse_json_result;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+
+#[derive(Deserialize)]
+struct ConvertImageArgs {
+    image_path: Option<String>,
+    image_dir: Option<String>,
+    output_path: String,
+    format: String,
+    quality: Option<u8>,
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+    scale: Option<f64>,
+}
+
+pub async fn handle_convert_image(args: serde_json::Value) -> ToolResult {
+    let args: ConvertImageArgs = parse_args(args)?;
+
+    let (flag, input) = match (&args.image_path, &args.image_dir) {
+        (Some(path), None) => ("--image", path),
+        (None, Some(dir)) => ("--dir", dir),
+        _ => {
+            return Err(ToolError::invalid_args(
+                "Exactly one of image_path or image_dir must be provided",
+            ));
+        }
+    };
+
+    if !PathBuf::from(input).exists() {
+        return Err(ToolError::not_found("Input", input));
+    }
+
+    let scripts_dir = scripts_dir()?;
+    let script_path = scripts_dir.join("convert_image.py");
+
+    let mut cmd = python_command();
+    cmd.arg(&script_path)
+        .arg(flag)
+        .arg(input)
+        .arg("--output")
+        .arg(&args.output_path)
+        .arg("--format")
+        .arg(args.format.to_lowercase());
+
+    if let Some(quality) = args.quality {
+        cmd.arg("--quality").arg(quality.to_string());
+    }
+    if let Some(max_width) = args.max_width {
+        cmd.arg("--max-width").arg(max_width.to_string());
+    }
+    if let Some(max_height) = args.max_height {
+        cmd.arg("--max-height").arg(max_height.to_string());
+    }
+    if let Some(scale) = args.scale {
+        cmd.arg("--scale").arg(scale.to_string());
+    }
+
+    info!(
+        "Converting images to {}: {} -> {}",
+        args.format, input, args.output_path
+    );
+
+    let output = run_command(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))
+        .await
+        .context("Failed to execute convert_image.py")?;
+
+    if !output.status.success() {
+        return Err(ToolError::script_failed("convert_image.py", &output.stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!("Successfully converted images.\n{stdout}"),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: parse_json_result(&stdout),
+    })
+}
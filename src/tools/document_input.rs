@@ -0,0 +1,70 @@
+//! Document input - converts EPUB and DjVu files to PDF ahead of the pipeline
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tracing::info;
+
+use super::error::ToolError;
+use super::python::python_command;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+use super::workspace::create_temp_dir;
+
+/// Extensions (lowercase) converted by `document_to_pdf.py`
+const DOCUMENT_EXTENSIONS: &[&str] = &["epub", "djvu", "djv"];
+
+/// Whether `path` is an EPUB or DjVu document rather than a PDF.
+pub(super) fn is_document(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| DOCUMENT_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Return `path` itself for PDFs, or convert an EPUB/DjVu document into a PDF
+/// in a fresh temp directory and return that instead.
+///
+/// The converted file keeps the input's stem so derived names such as
+/// `<stem>_pages` stay recognisable.
+pub async fn ensure_pdf(path: &Path) -> Result<PathBuf> {
+    if !is_document(path) {
+        return Ok(path.to_path_buf());
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let pdf_path = create_temp_dir("document")
+        .await?
+        .join(format!("{stem}.pdf"));
+
+    info!(
+        "Converting document to PDF: {} -> {}",
+        path.display(),
+        pdf_path.display()
+    );
+
+    let scripts_dir = scripts_dir()?;
+    let script_path = scripts_dir.join("document_to_pdf.py");
+
+    let output = run_command(
+        python_command()
+            .arg(&script_path)
+            .arg(path)
+            .arg(&pdf_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .await
+    .context("Failed to execute document_to_pdf.py")?;
+
+    if !output.status.success() {
+        bail!(ToolError::script_failed(
+            "document_to_pdf.py",
+            &output.stderr
+        ));
+    }
+
+    Ok(pdf_path)
+}
@@ -0,0 +1,339 @@
+//! Dry runs - describe what a tool call would do without doing it
+//!
+//! Any tool accepts `dry_run: true`. The call is then checked the way the
+//! tool itself would check it (required arguments, input paths, path
+//! allowlist) and answered with a plan: the inputs it would read, with page
+//! and file counts, and the output paths it would write, flagging the ones
+//! that already exist. Nothing is created, converted or deleted, and remote
+//! inputs (`resource_uri`, object storage and WebDAV URIs) are listed
+//! without being fetched.
+
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use std::path::PathBuf;
+
+use super::allowlist::is_path_key;
+use super::client_resources::RESOURCE_INPUT_KEYS;
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::get_tool_definitions;
+use super::object_storage::is_remote;
+use super::pdf_info::read_pdf_info;
+use super::remove_watermark::cleaned_image_path;
+use super::remove_watermark::in_place_argument;
+
+/// Image extensions counted in directory inputs
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "webp", "bmp", "tif", "tiff", "heic", "heif", "avif",
+];
+
+#[derive(Serialize)]
+struct DryRunPlan {
+    dry_run: bool,
+    tool: String,
+    inputs: Vec<PlannedInput>,
+    outputs: Vec<PlannedOutput>,
+    /// PDF pages that would be rendered or processed
+    pages: u32,
+    /// Image files that would be read
+    files: usize,
+    /// Outputs that exist already and would be overwritten or added to
+    existing_outputs: Vec<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct PlannedInput {
+    argument: String,
+    path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pages: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct PlannedOutput {
+    argument: String,
+    path: PathBuf,
+    exists: bool,
+}
+
+/// Whether the arguments ask for a dry run.
+pub(super) fn is_dry_run(arguments: &Value) -> bool {
+    arguments.get("dry_run").and_then(Value::as_bool) == Some(true)
+}
+
+/// Validate a call to `tool` and return its plan instead of running it.
+pub(super) async fn plan_tool_call(tool: &str, arguments: &Value) -> ToolResult {
+    let Some(definition) = get_tool_definitions()
+        .into_iter()
+        .find(|definition| definition.name == tool)
+    else {
+        return Err(ToolError::invalid_args(format!("Unknown tool: {tool}")));
+    };
+    let resource_uri = arguments.get("resource_uri").and_then(Value::as_str);
+    for name in definition.input_schema.required.unwrap_or_default() {
+        // The resource stands in for the input argument it is fetched into
+        if resource_uri.is_some() && RESOURCE_INPUT_KEYS.contains(&name.as_str()) {
+            continue;
+        }
+        if arguments.get(&name).is_none_or(Value::is_null) {
+            return Err(ToolError::invalid_args(format!(
+                "Invalid arguments: missing field `{name}`"
+            )));
+        }
+    }
+
+    let mut paths = Vec::new();
+    if let Some(uri) = resource_uri {
+        paths.push(("resource_uri".to_string(), PathBuf::from(uri)));
+    }
+    collect_paths(arguments, &mut paths);
+
+    let mut plan = DryRunPlan {
+        dry_run: true,
+        tool: tool.to_string(),
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+        pages: 0,
+        files: 0,
+        existing_outputs: Vec::new(),
+    };
+    for (argument, path) in paths {
+        let remote = argument == "resource_uri" || is_remote(&path.to_string_lossy());
+        if is_output_key(&argument) {
+            let exists = !remote && path.exists();
+            if exists && !path.is_dir() {
+                plan.existing_outputs.push(path.clone());
+            }
+            plan.outputs.push(PlannedOutput {
+                argument,
+                path,
+                exists,
+            });
+            continue;
+        }
+
+        let mut input = PlannedInput {
+            argument,
+            path,
+            pages: None,
+            files: None,
+        };
+        // Remote inputs are not fetched, so their size is unknown
+        if remote {
+            plan.inputs.push(input);
+            continue;
+        }
+        if !input.path.exists() {
+            return Err(ToolError::not_found(
+                input_kind(&input.argument),
+                input.path,
+            ));
+        }
+        if input.path.is_dir() {
+            let files = count_images(&input.path);
+            plan.files += files;
+            input.files = Some(files);
+        } else if is_pdf(&input.path) {
+            // Page counts are a prediction; an unreadable PDF is reported
+            // by the real call
+            if let Ok(info) = read_pdf_info(&input.path).await {
+                plan.pages += info.page_count;
+                input.pages = Some(info.page_count);
+            }
+        } else if input.argument != "template" {
+            plan.files += 1;
+        }
+        plan.inputs.push(input);
+    }
+    if let Some((argument, path)) = in_place_output(tool, arguments) {
+        let remote = is_remote(&path.to_string_lossy());
+        let exists = !remote && path.exists();
+        if exists && !path.is_dir() {
+            plan.existing_outputs.push(path.clone());
+        }
+        plan.outputs.push(PlannedOutput {
+            argument,
+            path,
+            exists,
+        });
+    }
+
+    let mut text = format!(
+        "Dry run of {tool}: nothing was changed.\n\nWould read {} input(s)",
+        plan.inputs.len()
+    );
+    if plan.pages > 0 {
+        text.push_str(&format!(", {} PDF page(s)", plan.pages));
+    }
+    if plan.files > 0 {
+        text.push_str(&format!(", {} image file(s)", plan.files));
+    }
+    for output in &plan.outputs {
+        text.push_str(&format!(
+            "\nWould write {}: {}{}",
+            output.argument,
+            output.path.display(),
+            if output.exists { " (exists)" } else { "" }
+        ));
+    }
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text,
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(serde_json::to_value(&plan)?),
+    })
+}
+
+/// Path-valued arguments at any depth, in argument order.
+fn collect_paths(value: &Value, paths: &mut Vec<(String, PathBuf)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match value {
+                    Value::String(path) if is_path_key(key) => {
+                        paths.push((key.clone(), PathBuf::from(path)));
+                    }
+                    _ => collect_paths(value, paths),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_paths(item, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Arguments naming a file or directory the tool writes.
+fn is_output_key(key: &str) -> bool {
+    key.starts_with("output_") || key == "images_output_dir" || key == "diff_path"
+}
+
+/// The input a call overwrites because it names no output:
+/// `remove_watermark` without `output_dir` cleans its image, or the images
+/// of its directory, in place.
+fn in_place_output(tool: &str, arguments: &Value) -> Option<(String, PathBuf)> {
+    let argument = in_place_argument(tool, arguments)?;
+    let path = Path::new(arguments.get(argument)?.as_str()?);
+    let path = if argument == "image_path" {
+        cleaned_image_path(path, None)
+    } else {
+        path.to_path_buf()
+    };
+    Some((argument.to_string(), path))
+}
+
+fn input_kind(argument: &str) -> &'static str {
+    match argument {
+        "pdf_path" => "PDF file",
+        "template" => "Template image",
+        "zip_path" => "ZIP archive",
+        "dir" | "image_dir" => "Directory",
+        _ => "Input file",
+    }
+}
+
+fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+fn count_images(dir: &Path) -> usize {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(std::result::Result::ok)
+                .filter(|entry| {
+                    entry.path().extension().is_some_and(|ext| {
+                        IMAGE_EXTENSIONS
+                            .iter()
+                            .any(|known| ext.eq_ignore_ascii_case(known))
+                    })
+                })
+                .count()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn output_keys_are_the_written_paths() {
+        for key in [
+            "output_path",
+            "output_dir",
+            "output_zip",
+            "images_output_dir",
+            "diff_path",
+        ] {
+            assert!(is_output_key(key), "{key}");
+        }
+        for key in ["pdf_path", "image_path", "image_dir", "dir", "template"] {
+            assert!(!is_output_key(key), "{key}");
+        }
+    }
+
+    #[test]
+    fn collect_paths_finds_nested_path_arguments() {
+        let mut paths = Vec::new();
+        collect_paths(
+            &json!({
+                "pdf_path": "in.pdf",
+                "dpi": 200,
+                "pages": [{ "image_path": "s3://bucket/a.png" }],
+                "output_path": "out.pdf",
+            }),
+            &mut paths,
+        );
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                ("image_path", "s3://bucket/a.png"),
+                ("output_path", "out.pdf"),
+                ("pdf_path", "in.pdf"),
+            ]
+            .map(|(key, path)| (key.to_string(), PathBuf::from(path)))
+        );
+    }
+
+    #[test]
+    fn remove_watermark_without_output_dir_writes_its_input() {
+        assert_eq!(
+            in_place_output("remove_watermark", &json!({ "image_path": "scans/a.heic" })),
+            Some(("image_path".to_string(), PathBuf::from("scans/a.png")))
+        );
+        assert_eq!(
+            in_place_output(
+                "remove_watermark",
+                &json!({ "image_dir": "s3://bucket/scans/" })
+            ),
+            Some(("image_dir".to_string(), PathBuf::from("s3://bucket/scans/")))
+        );
+        for arguments in [
+            json!({ "image_path": "a.png", "output_dir": "out" }),
+            json!({ "image_path": "a.png", "pipe": true }),
+        ] {
+            assert_eq!(in_place_output("remove_watermark", &arguments), None);
+        }
+        assert_eq!(
+            in_place_output("convert_image", &json!({ "image_path": "a.png" })),
+            None
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Embedded Python backend - single-image removal without a subprocess
+//!
+//! Built with the `embedded-python` feature. Single-image `remove_watermark`
+//! calls then run `remove_watermark.py`'s functions in an interpreter linked
+//! into the server instead of starting `python3` for each image, which
+//! dominates the cost of cleaning many small images one call at a time.
+//! Python exceptions come back as typed errors rather than parsed stderr.
+//!
+//! The interpreter is the one PyO3 was built against, not the configured
+//! `python`; set `PYTHONPATH` if OpenCV lives in a virtualenv. Calls are not
+//! bounded by the process timeout, since an in-process call cannot be killed.
+//! `WATERMARK_EMBEDDED_PYTHON=0` falls back to the subprocess.
+
+use anyhow::Context;
+use anyhow::Result;
+use pyo3::exceptions::PyImportError;
+use pyo3::exceptions::PyOSError;
+use pyo3::prelude::*;
+use pyo3::types::PyModule;
+use std::ffi::CString;
+use std::path::Path;
+use tracing::info;
+
+use super::error::ToolError;
+use super::process_pdf::PageStatus;
+use super::profiles::RemovalSettings;
+use super::remove_watermark::ImageResult;
+use super::remove_watermark::RemoveWatermarkOutput;
+use super::remove_watermark::cleaned_image_path;
+use super::scripts::scripts_dir;
+
+/// Glue between the server and `remove_watermark.py`: settings arrive as the
+/// script's own command-line flags so both paths parse them identically.
+const GLUE: &str = r#"
+import argparse
+import sys
+
+def clean(scripts_dir, image_path, output_path, script_args):
+    if scripts_dir not in sys.path:
+        sys.path.insert(0, scripts_dir)
+    import cv2
+    import remove_watermark as rw
+
+    parser = argparse.ArgumentParser(exit_on_error=False)
+    rw.add_removal_arguments(parser)
+    options = rw.removal_options(parser.parse_args(script_args))
+
+    # Shares the script's decoding, so HEIC/HEIF/AVIF work here too
+    img = rw.read_image(image_path)
+    if img is None:
+        raise OSError(f"Cannot read image: {image_path}")
+    report = {}
+    result, removed = rw.clean_image(img, report=report, **options)
+    if not cv2.imwrite(output_path, result):
+        raise OSError(f"Cannot write image: {output_path}")
+    return removed, report.get('confidence')
+"#;
+
+/// Whether single images should be cleaned in-process.
+pub(super) fn enabled() -> bool {
+    std::env::var("WATERMARK_EMBEDDED_PYTHON").map_or(true, |v| v.trim() != "0")
+}
+
+/// Clean `image_path` into `output_dir` (or in place), like
+/// `remove_watermark.py --image`.
+pub(super) async fn remove_watermark_image(
+    image_path: &Path,
+    output_dir: Option<&Path>,
+    settings: &RemovalSettings,
+) -> Result<RemoveWatermarkOutput> {
+    let scripts_dir = scripts_dir()?;
+    let output_path = cleaned_image_path(image_path, output_dir);
+    let output_dir = output_dir
+        .map(Path::to_path_buf)
+        .or_else(|| image_path.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+
+    info!(
+        "Removing watermark from image in-process: {}",
+        image_path.display()
+    );
+    let input = image_path.to_path_buf();
+    let script_args = settings.script_args();
+    let (removed, confidence) = {
+        let output_path = output_path.clone();
+        tokio::task::spawn_blocking(move || {
+            call_clean(&scripts_dir, &input, &output_path, script_args)
+        })
+        .await
+        .context("Embedded Python call panicked")??
+    };
+
+    let log = format!(
+        "Processing: {}\n  {} {}\n",
+        image_path.display(),
+        if removed {
+            "✓ Watermark removed:"
+        } else {
+            "○ No watermark detected:"
+        },
+        output_path.display()
+    );
+    Ok(RemoveWatermarkOutput {
+        processed: usize::from(removed),
+        skipped: usize::from(!removed),
+        failed: 0,
+        output_dir,
+        files: vec![ImageResult {
+            input_path: image_path.to_path_buf(),
+            output_path: Some(output_path),
+            status: if removed {
+                PageStatus::WatermarkRemoved
+            } else {
+                PageStatus::NoWatermark
+            },
+            confidence,
+            error: None,
+        }],
+        log,
+    })
+}
+
+fn call_clean(
+    scripts_dir: &Path,
+    image_path: &Path,
+    output_path: &Path,
+    script_args: Vec<String>,
+) -> Result<(bool, Option<f64>), ToolError> {
+    Python::attach(|py| {
+        let glue = CString::new(GLUE).expect("glue code has no NUL bytes");
+        let module =
+            PyModule::from_code(py, &glue, c"watermark_embedded.py", c"watermark_embedded")?;
+        module
+            .getattr("clean")?
+            .call1((
+                scripts_dir.to_string_lossy().to_string(),
+                image_path.to_string_lossy().to_string(),
+                output_path.to_string_lossy().to_string(),
+                script_args,
+            ))?
+            .extract::<(bool, Option<f64>)>()
+    })
+    .map_err(|e| python_error(&e))
+}
+
+/// Classify a Python exception the way [`ToolError::script_failed`] does for
+/// script stderr.
+fn python_error(error: &PyErr) -> ToolError {
+    Python::attach(|py| {
+        let message = error.to_string();
+        if error.is_instance_of::<PyImportError>(py) {
+            ToolError::BackendMissing {
+                backend: "python-package".to_string(),
+                detail: format!("{message}. Run: pip install opencv-python-headless numpy"),
+            }
+        } else if error.is_instance_of::<PyOSError>(py) {
+            ToolError::Internal(anyhow::anyhow!(message))
+        } else {
+            ToolError::SubprocessFailed {
+                script: "remove_watermark.py".to_string(),
+                stderr: message,
+            }
+        }
+    })
+}
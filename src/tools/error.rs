@@ -0,0 +1,295 @@
+//! Tool errors - typed failure causes with stable codes
+//!
+//! Every tool handler returns [`ToolResult`]. Malformed arguments are a
+//! protocol error and go back as a JSON-RPC error (`-32602`); every other
+//! failure is a tool execution error, reported as an `isError` result whose
+//! `structuredContent.error` carries the same `code`, `kind` and `data`, so
+//! the model still sees the message and agents can branch on the cause.
+
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use serde_json::json;
+use std::path::PathBuf;
+
+use crate::i18n::Locale;
+use crate::i18n::error_message;
+
+pub type ToolResult<T = CallToolResult> = std::result::Result<T, ToolError>;
+
+#[derive(Debug)]
+pub enum ToolError {
+    /// An input file or directory does not exist
+    FileNotFound {
+        /// What was missing, e.g. "PDF file" or "Directory"
+        what: &'static str,
+        path: PathBuf,
+    },
+    /// A path argument lies outside the configured allowed directories
+    PathNotAllowed { path: PathBuf },
+    /// The arguments are missing, contradictory or out of range
+    InvalidArgs(String),
+    /// An argument failed pre-flight validation, e.g. a DPI out of range or
+    /// an input that is not the expected file type
+    ValidationFailed { argument: String, reason: String },
+    /// Python, a Python package or an external program is not installed
+    BackendMissing { backend: String, detail: String },
+    /// A helper script exited with a non-zero status
+    SubprocessFailed { script: String, stderr: String },
+    /// A helper script outlived `WATERMARK_PROCESS_TIMEOUT_SECS` and was killed
+    Timeout { script: String, secs: u64 },
+    /// The job was cancelled before it produced a result
+    Cancelled { job_id: String },
+    /// An output file exists and `overwrite` was not set
+    OutputExists { path: PathBuf },
+    /// The run would exceed a configured disk or image size limit
+    QuotaExceeded {
+        /// `intermediate_disk` (bytes) or `image_pixels` (pixels per page)
+        quota: &'static str,
+        limit: u64,
+        requested: u64,
+    },
+    /// Anything else, e.g. an I/O error writing the output
+    Internal(anyhow::Error),
+}
+
+impl ToolError {
+    pub fn not_found(what: &'static str, path: impl Into<PathBuf>) -> Self {
+        ToolError::FileNotFound {
+            what,
+            path: path.into(),
+        }
+    }
+
+    pub fn invalid_args(message: impl Into<String>) -> Self {
+        ToolError::InvalidArgs(message.into())
+    }
+
+    /// Classify a failed script run from its stderr: the scripts report a
+    /// missing package, poppler or document converter in a recognisable way.
+    pub fn script_failed(script: &str, stderr: &[u8]) -> Self {
+        let stderr = String::from_utf8_lossy(stderr).trim().to_string();
+        let stderr = stderr
+            .strip_prefix("Error: ")
+            .unwrap_or(&stderr)
+            .to_string();
+        let backend = if stderr.contains("poppler") {
+            Some("poppler")
+        } else if stderr.contains("not installed")
+            || stderr.contains("Missing dependency")
+            || stderr.contains("No module named")
+        {
+            Some("python-package")
+        } else if stderr.contains("needed to convert") {
+            Some("document-converter")
+        } else {
+            None
+        };
+        match backend {
+            Some(backend) => ToolError::BackendMissing {
+                backend: backend.to_string(),
+                detail: stderr,
+            },
+            None => ToolError::SubprocessFailed {
+                script: script.to_string(),
+                stderr,
+            },
+        }
+    }
+
+    /// JSON-RPC error code. `-32602` and `-32603` are the standard codes;
+    /// the rest sit in the implementation-defined server error range.
+    pub fn code(&self) -> i64 {
+        match self {
+            ToolError::InvalidArgs(_) => -32602,
+            ToolError::FileNotFound { .. } => -32010,
+            ToolError::BackendMissing { .. } => -32011,
+            ToolError::SubprocessFailed { .. } => -32012,
+            ToolError::Timeout { .. } => -32013,
+            ToolError::Cancelled { .. } => -32014,
+            ToolError::PathNotAllowed { .. } => -32015,
+            ToolError::QuotaExceeded { .. } => -32016,
+            ToolError::OutputExists { .. } => -32017,
+            ToolError::ValidationFailed { .. } => -32018,
+            ToolError::Internal(_) => -32603,
+        }
+    }
+
+    /// Stable machine-readable name of the variant.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ToolError::FileNotFound { .. } => "file_not_found",
+            ToolError::InvalidArgs(_) => "invalid_args",
+            ToolError::BackendMissing { .. } => "backend_missing",
+            ToolError::SubprocessFailed { .. } => "subprocess_failed",
+            ToolError::Timeout { .. } => "timeout",
+            ToolError::Cancelled { .. } => "cancelled",
+            ToolError::PathNotAllowed { .. } => "path_not_allowed",
+            ToolError::QuotaExceeded { .. } => "quota_exceeded",
+            ToolError::OutputExists { .. } => "output_exists",
+            ToolError::ValidationFailed { .. } => "validation_failed",
+            ToolError::Internal(_) => "internal",
+        }
+    }
+
+    /// `data` member of the JSON-RPC error: the kind plus its fields.
+    pub fn data(&self) -> Value {
+        let mut data = match self {
+            ToolError::FileNotFound { path, .. }
+            | ToolError::PathNotAllowed { path }
+            | ToolError::OutputExists { path } => {
+                json!({ "path": path })
+            }
+            ToolError::BackendMissing { backend, detail } => {
+                json!({ "backend": backend, "detail": detail })
+            }
+            ToolError::SubprocessFailed { script, stderr } => {
+                json!({ "script": script, "stderr": stderr })
+            }
+            ToolError::Timeout { script, secs } => json!({ "script": script, "secs": secs }),
+            ToolError::Cancelled { job_id } => json!({ "job_id": job_id }),
+            ToolError::ValidationFailed { argument, reason } => {
+                json!({ "argument": argument, "reason": reason })
+            }
+            ToolError::QuotaExceeded {
+                quota,
+                limit,
+                requested,
+            } => json!({ "quota": quota, "limit": limit, "requested": requested }),
+            ToolError::InvalidArgs(_) | ToolError::Internal(_) => json!({}),
+        };
+        data["kind"] = json!(self.kind());
+        data
+    }
+
+    /// Whether the error belongs in a JSON-RPC error response rather than
+    /// an `isError` tool result.
+    pub fn is_protocol_error(&self) -> bool {
+        matches!(self, ToolError::InvalidArgs(_))
+    }
+
+    /// The `isError` tool result describing this error.
+    pub fn to_call_result(&self) -> CallToolResult {
+        self.to_localized_call_result(Locale::En)
+    }
+
+    /// [`to_call_result`](Self::to_call_result) with the message in `locale`.
+    pub fn to_localized_call_result(&self, locale: Locale) -> CallToolResult {
+        let message = error_message(self, locale);
+        let text = match locale {
+            Locale::Zh => format!("错误：{message}"),
+            Locale::En => format!("Error: {message}"),
+        };
+        CallToolResult {
+            content: vec![ContentBlock::TextContent(TextContent {
+                r#type: "text".to_string(),
+                text,
+                annotations: None,
+            })],
+            is_error: Some(true),
+            structured_content: Some(json!({
+                "error": {
+                    "code": self.code(),
+                    "message": message,
+                    "data": self.data(),
+                }
+            })),
+        }
+    }
+}
+
+impl std::fmt::Display for ToolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolError::FileNotFound { what, path } => {
+                write!(f, "{what} not found: {}", path.display())
+            }
+            ToolError::PathNotAllowed { path } => write!(
+                f,
+                "Path is outside the allowed directories: {} (see allowed_dirs / WATERMARK_ALLOWED_DIRS)",
+                path.display()
+            ),
+            ToolError::InvalidArgs(message) => f.write_str(message),
+            ToolError::ValidationFailed { argument, reason } => {
+                write!(f, "Invalid {argument}: {reason}")
+            }
+            ToolError::OutputExists { path } => write!(
+                f,
+                "Output already exists: {} (pass overwrite: true to replace it)",
+                path.display()
+            ),
+            ToolError::BackendMissing { detail, .. } => f.write_str(detail),
+            ToolError::SubprocessFailed { script, stderr } => {
+                write!(f, "{script} failed: {stderr}")
+            }
+            ToolError::Timeout { script, secs } => write!(
+                f,
+                "{script} timed out after {secs}s and was killed (raise WATERMARK_PROCESS_TIMEOUT_SECS for very large inputs)"
+            ),
+            ToolError::Cancelled { job_id } => write!(f, "Job {job_id} was cancelled"),
+            ToolError::QuotaExceeded {
+                quota: "intermediate_disk",
+                limit,
+                ..
+            } => write!(
+                f,
+                "Intermediate page images exceeded the {} MB disk quota; the run was stopped and its pages deleted (raise WATERMARK_MAX_INTERMEDIATE_MB or use a lower DPI)",
+                limit / (1024 * 1024)
+            ),
+            ToolError::QuotaExceeded {
+                limit, requested, ..
+            } => write!(
+                f,
+                "A page would render at {:.1} megapixels, above the {:.1} megapixel limit (lower the DPI or raise WATERMARK_MAX_IMAGE_MEGAPIXELS)",
+                *requested as f64 / 1_000_000.0,
+                *limit as f64 / 1_000_000.0
+            ),
+            ToolError::Internal(e) => write!(f, "{e:#}"),
+        }
+    }
+}
+
+impl std::error::Error for ToolError {}
+
+/// Recover a `ToolError` raised under `.context(..)` (or inside an
+/// `io::Error`, as [`run_command`](super::subprocess::run_command) does for
+/// timeouts); anything else becomes [`ToolError::Internal`].
+impl From<anyhow::Error> for ToolError {
+    fn from(e: anyhow::Error) -> Self {
+        let e = match e.downcast::<ToolError>() {
+            Ok(tool_error) => return tool_error,
+            Err(e) => e,
+        };
+        if let Some(io) = e.downcast_ref::<std::io::Error>()
+            && io.get_ref().is_some_and(|inner| inner.is::<ToolError>())
+        {
+            let io = e
+                .downcast::<std::io::Error>()
+                .expect("checked by downcast_ref");
+            let inner = io.into_inner().expect("checked by get_ref");
+            return *inner.downcast::<ToolError>().expect("checked by is");
+        }
+        ToolError::Internal(e)
+    }
+}
+
+impl From<std::io::Error> for ToolError {
+    fn from(e: std::io::Error) -> Self {
+        anyhow::Error::from(e).into()
+    }
+}
+
+impl From<serde_json::Error> for ToolError {
+    fn from(e: serde_json::Error) -> Self {
+        ToolError::Internal(e.into())
+    }
+}
+
+/// Deserialize a tool's arguments, reporting a mismatch as
+/// [`ToolError::InvalidArgs`].
+pub fn parse_args<T: DeserializeOwned>(args: Value) -> ToolResult<T> {
+    serde_json::from_value(args)
+        .map_err(|e| ToolError::InvalidArgs(format!("Invalid arguments: {e}")))
+}
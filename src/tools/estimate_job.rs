@@ -0,0 +1,122 @@
+//! Estimate Job tool - predicts processing time and disk usage for a PDF
+
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use serde_json::json;
+use std::path::PathBuf;
+use tracing::info;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::pdf_info::read_pdf_info;
+use crate::config::config;
+
+/// Rasterization throughput of poppler, in megapixels per second
+const RENDER_MPX_PER_SEC: f64 = 20.0;
+/// OpenCV detection + inpainting cost per page, in seconds
+const REMOVAL_SECS_PER_PAGE: f64 = 0.25;
+/// img2pdf assembly throughput, in megabytes per second
+const MERGE_MB_PER_SEC: f64 = 80.0;
+/// Fixed python interpreter and import overhead per script run, in seconds
+const PYTHON_STARTUP_SECS: f64 = 1.0;
+/// Average PNG size per pixel for typical document pages
+const PNG_BYTES_PER_PIXEL: f64 = 0.6;
+/// US Letter in points, used when page sizes cannot be read
+const DEFAULT_PAGE_PT: (f64, f64) = (612.0, 792.0);
+
+#[derive(Deserialize)]
+struct EstimateJobArgs {
+    pdf_path: String,
+    dpi: Option<u32>,
+}
+
+pub async fn handle_estimate_job(args: serde_json::Value) -> ToolResult {
+    let args: EstimateJobArgs = parse_args(args)?;
+
+    let pdf_path = PathBuf::from(&args.pdf_path);
+    if !pdf_path.exists() {
+        return Err(ToolError::not_found("PDF file", args.pdf_path));
+    }
+
+    let dpi = args.dpi.or(config().defaults.dpi).unwrap_or(200);
+
+    info!("Estimating job for {} at {} DPI", args.pdf_path, dpi);
+
+    let pdf_info = read_pdf_info(&pdf_path).await?;
+
+    let scale = f64::from(dpi) / 72.0;
+    let page_pixels: Vec<f64> = if pdf_info.pages.is_empty() {
+        let (w, h) = DEFAULT_PAGE_PT;
+        vec![(w * scale) * (h * scale); pdf_info.page_count as usize]
+    } else {
+        pdf_info
+            .pages
+            .iter()
+            .map(|p| (p.width_pt * scale) * (p.height_pt * scale))
+            .collect()
+    };
+    let total_pixels: f64 = page_pixels.iter().sum();
+    let max_page_pixels = page_pixels.iter().copied().fold(0.0, f64::max);
+    let pages = page_pixels.len() as f64;
+
+    let intermediate_bytes = total_pixels * PNG_BYTES_PER_PIXEL;
+    let output_bytes = intermediate_bytes;
+    let render_secs = total_pixels / 1e6 / RENDER_MPX_PER_SEC;
+    let removal_secs = pages * REMOVAL_SECS_PER_PAGE;
+    let merge_secs = output_bytes / 1e6 / MERGE_MB_PER_SEC;
+    let total_secs = PYTHON_STARTUP_SECS + render_secs + removal_secs + merge_secs;
+
+    let mb = |bytes: f64| bytes / (1024.0 * 1024.0);
+
+    let text = [
+        format!(
+            "Estimate for {} at {dpi} DPI ({} pages, input {:.2} MB):",
+            args.pdf_path,
+            pdf_info.page_count,
+            mb(pdf_info.file_size as f64)
+        ),
+        format!(
+            "  Time: ~{total_secs:.0}s (render {render_secs:.0}s, removal {removal_secs:.0}s, merge {merge_secs:.0}s)"
+        ),
+        format!("  Intermediate images: ~{:.1} MB", mb(intermediate_bytes)),
+        format!("  Output PDF: ~{:.1} MB", mb(output_bytes)),
+        format!(
+            "  Peak disk usage: ~{:.1} MB",
+            mb(intermediate_bytes + output_bytes)
+        ),
+        format!("  Largest page: {:.1} megapixels", max_page_pixels / 1e6),
+        "These are rough estimates; lower the DPI to reduce both time and disk usage.".to_string(),
+    ]
+    .join("\n");
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text,
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(json!({
+            "pdf_path": args.pdf_path,
+            "dpi": dpi,
+            "page_count": pdf_info.page_count,
+            "input_bytes": pdf_info.file_size,
+            "total_megapixels": total_pixels / 1e6,
+            "max_page_megapixels": max_page_pixels / 1e6,
+            "estimated_seconds": {
+                "total": total_secs,
+                "render": render_secs,
+                "removal": removal_secs,
+                "merge": merge_secs,
+            },
+            "estimated_bytes": {
+                "intermediate": intermediate_bytes as u64,
+                "output": output_bytes as u64,
+                "peak": (intermediate_bytes + output_bytes) as u64,
+            },
+        })),
+    })
+}
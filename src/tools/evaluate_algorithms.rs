@@ -0,0 +1,178 @@
+//! Evaluate Algorithms tool - rank the removal algorithms on one sample page
+//!
+//! Runs every requested algorithm on the same page with the same detected
+//! mask and scores the results, so the settings for a large batch can be
+//! picked from one page instead of a trial run over the whole document.
+
+use anyhow::Context;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tracing::info;
+
+use super::document_input::ensure_pdf;
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::image_content::image_content_from_file;
+use super::inline_input::write_inline_input;
+use super::overwrite::check_output_dir;
+use super::profiles::ALGORITHMS;
+use super::profiles::RemovalSettings;
+use super::profiles::resolve_settings;
+use super::python::python_command;
+use super::script_output::parse_json_result;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+use super::workspace::create_temp_dir;
+
+#[derive(Deserialize)]
+struct EvaluateAlgorithmsArgs {
+    pdf_path: Option<String>,
+    pdf_base64: Option<String>,
+    image_path: Option<String>,
+    page: Option<u32>,
+    algorithms: Option<Vec<String>>,
+    output_dir: Option<String>,
+    overwrite: Option<bool>,
+    profile: Option<String>,
+    #[serde(flatten)]
+    settings: RemovalSettings,
+}
+
+pub async fn handle_evaluate_algorithms(args: serde_json::Value) -> ToolResult {
+    let args: EvaluateAlgorithmsArgs = parse_args(args)?;
+
+    let input = match (&args.pdf_path, &args.pdf_base64, &args.image_path) {
+        (Some(path), None, None) => PathBuf::from(path),
+        (None, Some(data), None) => write_inline_input(data, "document", "pdf").await?,
+        (None, None, Some(path)) => PathBuf::from(path),
+        (None, None, None) => {
+            return Err(ToolError::invalid_args(
+                "One of pdf_path, pdf_base64 or image_path must be provided",
+            ));
+        }
+        _ => {
+            return Err(ToolError::invalid_args(
+                "Pass only one of pdf_path, pdf_base64 or image_path",
+            ));
+        }
+    };
+    if !input.exists() {
+        return Err(ToolError::not_found("Input file", input));
+    }
+    // EPUB and DjVu inputs are converted up front and then handled as PDFs
+    let input = if args.image_path.is_some() {
+        input
+    } else {
+        ensure_pdf(&input).await?
+    };
+
+    let algorithms = args
+        .algorithms
+        .unwrap_or_else(|| ALGORITHMS.iter().map(|a| a.to_string()).collect());
+    if algorithms.is_empty() {
+        return Err(ToolError::invalid_args("algorithms must not be empty"));
+    }
+    if let Some(unknown) = algorithms
+        .iter()
+        .find(|a| !ALGORITHMS.contains(&a.as_str()))
+    {
+        return Err(ToolError::invalid_args(format!(
+            "Unsupported algorithm: {unknown} (expected one of {})",
+            ALGORITHMS.join(", ")
+        )));
+    }
+
+    let mut settings = resolve_settings(args.settings, args.profile.as_deref()).await?;
+    if settings.steps.is_some() {
+        return Err(ToolError::invalid_args(
+            "evaluate_algorithms compares single removal passes; leave out steps",
+        ));
+    }
+    // Each candidate sets the algorithm in turn
+    settings.algorithm = None;
+    let dpi = settings.dpi.unwrap_or(200);
+    let page = args.page.unwrap_or(1).max(1);
+
+    let output_dir = match args.output_dir {
+        Some(dir) => {
+            let dir = PathBuf::from(dir);
+            let names: Vec<String> = std::iter::once("before")
+                .chain(algorithms.iter().map(String::as_str))
+                .map(|name| format!("{name}.png"))
+                .collect();
+            check_output_dir(&dir, args.overwrite.unwrap_or(false), |name| {
+                names.iter().any(|n| n == name)
+            })?;
+            tokio::fs::create_dir_all(&dir).await?;
+            dir
+        }
+        None => create_temp_dir("evaluate").await?,
+    };
+
+    info!(
+        "Evaluating {} on {}",
+        algorithms.join(", "),
+        input.display()
+    );
+
+    let output = run_command(
+        python_command()
+            .arg(scripts_dir()?.join("evaluate_algorithms.py"))
+            .arg(&input)
+            .arg(&output_dir)
+            .arg(format!("--page={page}"))
+            .arg(format!("--dpi={dpi}"))
+            .arg(format!("--algorithms={}", algorithms.join(",")))
+            .args(settings.script_args())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .await
+    .context("Failed to execute evaluate_algorithms.py")?;
+
+    if !output.status.success() {
+        return Err(ToolError::script_failed(
+            "evaluate_algorithms.py",
+            &output.stderr,
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let result = parse_json_result(&stdout).context("evaluate_algorithms.py printed no result")?;
+    let ranked: Vec<&str> = result["results"]
+        .as_array()
+        .map(|results| {
+            results
+                .iter()
+                .filter_map(|r| r["algorithm"].as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut content = vec![ContentBlock::TextContent(TextContent {
+        r#type: "text".to_string(),
+        text: format!(
+            "Best algorithm: {}\n{stdout}\nThumbnails: before, then {}",
+            result["best"].as_str().unwrap_or_default(),
+            ranked.join(", ")
+        ),
+        annotations: None,
+    })];
+    for name in std::iter::once("before").chain(ranked.iter().copied()) {
+        let thumbnail = output_dir.join(format!("{name}.png"));
+        content.push(ContentBlock::ImageContent(
+            image_content_from_file(&thumbnail).await?,
+        ));
+    }
+
+    Ok(CallToolResult {
+        content,
+        is_error: Some(false),
+        structured_content: Some(result),
+    })
+}
@@ -0,0 +1,254 @@
+//! Extract PDF Images tool - pulls embedded image streams out of a PDF
+
+use anyhow::Context;
+use anyhow::Result;
+use lopdf::Document;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use tracing::info;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::inline_input::write_inline_input;
+use super::manifest::record_intermediate_dir;
+use super::overwrite::check_output_dir;
+use super::overwrite::default_output;
+
+#[derive(Deserialize)]
+struct ExtractPdfImagesArgs {
+    pdf_path: Option<String>,
+    pdf_base64: Option<String>,
+    output_dir: Option<String>,
+    overwrite: Option<bool>,
+    auto_rename: Option<bool>,
+}
+
+/// One image XObject found on a page.
+#[derive(Serialize)]
+struct ExtractedImage {
+    page: u32,
+    width: i64,
+    height: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    /// Set when the same stream was already written for an earlier page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    same_as: Option<PathBuf>,
+    /// Why the image was not written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped: Option<String>,
+}
+
+pub async fn handle_extract_pdf_images(args: serde_json::Value) -> ToolResult {
+    let args: ExtractPdfImagesArgs = parse_args(args)?;
+
+    let pdf_path = match (&args.pdf_path, &args.pdf_base64) {
+        (Some(path), _) => PathBuf::from(path),
+        (None, Some(data)) => write_inline_input(data, "document", "pdf").await?,
+        (None, None) => {
+            return Err(ToolError::invalid_args(
+                "Either pdf_path or pdf_base64 must be provided",
+            ));
+        }
+    };
+    if !pdf_path.exists() {
+        return Err(ToolError::not_found("PDF file", pdf_path));
+    }
+
+    let overwrite = args.overwrite.unwrap_or(false);
+    let would_write = |name: &str| name.starts_with("page_") && name.contains("_img_");
+    let (output_dir, renamed_from) = match args.output_dir {
+        Some(dir) => (PathBuf::from(dir), None),
+        None => {
+            let stem = pdf_path.file_stem().unwrap_or_default().to_string_lossy();
+            let dir = pdf_path
+                .parent()
+                .unwrap_or(&pdf_path)
+                .join(format!("{stem}_images"));
+            let (dir, renamed_from) = default_output(
+                dir,
+                !overwrite && args.auto_rename.unwrap_or(false),
+                |dir| check_output_dir(dir, false, would_write).is_err(),
+            );
+            record_intermediate_dir(&dir, "extract_pdf_images").await;
+            (dir, renamed_from)
+        }
+    };
+    check_output_dir(&output_dir, overwrite, would_write)?;
+    tokio::fs::create_dir_all(&output_dir).await?;
+
+    info!(
+        "Extracting embedded images: {} -> {}",
+        pdf_path.display(),
+        output_dir.display()
+    );
+
+    // lopdf is synchronous and large scans can hold hundreds of MB of streams
+    let images = {
+        let pdf_path = pdf_path.clone();
+        let output_dir = output_dir.clone();
+        tokio::task::spawn_blocking(move || extract_images(&pdf_path, &output_dir))
+            .await
+            .context("PDF reader task failed")?
+    };
+    let images = images.context("Error reading PDF")?;
+
+    let written = images.iter().filter(|i| i.path.is_some()).count();
+    let lines: Vec<String> = images
+        .iter()
+        .map(|i| match (&i.path, &i.same_as, &i.skipped) {
+            (Some(path), _, _) => format!(
+                "  page {}: {}x{} -> {}",
+                i.page,
+                i.width,
+                i.height,
+                path.display()
+            ),
+            (None, Some(same), _) => format!("  page {}: same image as {}", i.page, same.display()),
+            (None, None, Some(reason)) => format!("  page {}: skipped ({reason})", i.page),
+            (None, None, None) => format!("  page {}: skipped", i.page),
+        })
+        .collect();
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!(
+                "Extracted {written} of {} embedded images to {}\n{}",
+                images.len(),
+                output_dir.display(),
+                lines.join("\n")
+            ),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(json!({
+            "output_dir": output_dir,
+            "renamed_from": renamed_from,
+            "images": images,
+        })),
+    })
+}
+
+fn extract_images(pdf_path: &Path, output_dir: &Path) -> Result<Vec<ExtractedImage>> {
+    let doc = Document::load(pdf_path).context("Failed to parse PDF")?;
+    if doc.is_encrypted() {
+        anyhow::bail!("PDF is encrypted");
+    }
+
+    let mut images = Vec::new();
+    let mut written: HashMap<lopdf::ObjectId, PathBuf> = HashMap::new();
+
+    for (page, page_id) in doc.get_pages() {
+        // Pages without an XObject resource dictionary simply have no images
+        let page_images = doc.get_page_images(page_id).unwrap_or_default();
+
+        for (index, image) in page_images.iter().enumerate() {
+            let mut entry = ExtractedImage {
+                page,
+                width: image.width,
+                height: image.height,
+                path: None,
+                same_as: None,
+                skipped: None,
+            };
+
+            if let Some(path) = written.get(&image.id) {
+                entry.same_as = Some(path.clone());
+                images.push(entry);
+                continue;
+            }
+
+            let stem = output_dir.join(format!("page_{page:03}_img_{:02}", index + 1));
+            let filters = image.filters.clone().unwrap_or_default();
+            let result = match filters.as_slice() {
+                // Already a complete image file: copy the bytes untouched
+                [f] if f == "DCTDecode" => write_raw(&stem, "jpg", image.content),
+                [f] if f == "JPXDecode" => write_raw(&stem, "jp2", image.content),
+                [] | [_] if filters.iter().all(|f| f == "FlateDecode") => {
+                    write_png(&doc, image, &stem)
+                }
+                _ => Err(format!("unsupported filter {}", filters.join("+"))),
+            };
+
+            match result {
+                Ok(path) => {
+                    written.insert(image.id, path.clone());
+                    entry.path = Some(path);
+                }
+                Err(reason) => entry.skipped = Some(reason),
+            }
+            images.push(entry);
+        }
+    }
+
+    Ok(images)
+}
+
+fn write_raw(stem: &Path, extension: &str, bytes: &[u8]) -> std::result::Result<PathBuf, String> {
+    let path = stem.with_extension(extension);
+    std::fs::write(&path, bytes).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Re-encode an uncompressed or Flate-compressed pixel stream as PNG.
+///
+/// Only 8-bit gray and RGB data is supported; the channel count is taken
+/// from the decoded length so `ICCBased` profiles with 1 or 3 components work.
+fn write_png(
+    doc: &Document,
+    image: &lopdf::xobject::PdfImage,
+    stem: &Path,
+) -> std::result::Result<PathBuf, String> {
+    if image.bits_per_component != Some(8) {
+        return Err(format!(
+            "unsupported bits per component {:?}",
+            image.bits_per_component
+        ));
+    }
+    if image.color_space.as_deref() == Some("Indexed") {
+        return Err("unsupported color space Indexed".to_string());
+    }
+
+    let stream = doc
+        .get_object(image.id)
+        .and_then(|o| o.as_stream())
+        .map_err(|e| e.to_string())?;
+    let pixels = if stream.dict.get(b"Filter").is_ok() {
+        stream.decompressed_content().map_err(|e| e.to_string())?
+    } else {
+        stream.content.clone()
+    };
+
+    let (width, height) = (image.width as usize, image.height as usize);
+    let color = match pixels.len().checked_div(width * height) {
+        Some(1) => png::ColorType::Grayscale,
+        Some(3) => png::ColorType::Rgb,
+        Some(4) => return Err("unsupported color space DeviceCMYK".to_string()),
+        _ => return Err("unexpected pixel data length".to_string()),
+    };
+    let channels = if color == png::ColorType::Rgb { 3 } else { 1 };
+
+    let path = stem.with_extension("png");
+    let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(
+        std::io::BufWriter::new(file),
+        image.width as u32,
+        image.height as u32,
+    );
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer
+        .write_image_data(&pixels[..width * height * channels])
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
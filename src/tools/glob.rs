@@ -0,0 +1,105 @@
+//! Glob patterns for selecting files, matching Python's `fnmatch.fnmatchcase`
+//!
+//! `*` matches any run of characters (including `/`), `?` any one character
+//! and `[...]` / `[!...]` a character (not) in the set, with `a-z` ranges.
+//! Matching is case-sensitive. The scripts use `fnmatchcase` on the same
+//! patterns, so the server's overwrite checks select the files they do.
+
+/// Whether `text` matches `pattern` as a whole.
+pub(super) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it is trying
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(p + 1),
+            Some('[') => match_class(&pattern, p, text[t]),
+            Some(c) if *c == text[t] => Some(p + 1),
+            _ => None,
+        };
+        match (step, backtrack) {
+            (Some(next), _) => {
+                p = next;
+                t += 1;
+            }
+            (None, Some((star, start))) => {
+                p = star;
+                t = start + 1;
+                backtrack = Some((star, start + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Match `c` against the class opening at `pattern[open]`, returning the
+/// position after the class. An unclosed `[` is a literal, as in `fnmatch`.
+fn match_class(pattern: &[char], open: usize, c: char) -> Option<usize> {
+    let mut i = open + 1;
+    let negated = matches!(pattern.get(i), Some('!'));
+    if negated {
+        i += 1;
+    }
+    // A `]` right after the opening is part of the set
+    let first = i;
+    let Some(close) = (first + 1..pattern.len()).find(|&j| pattern[j] == ']') else {
+        return (c == '[').then_some(open + 1);
+    };
+
+    let set = &pattern[first..close];
+    let mut found = false;
+    let mut k = 0;
+    while k < set.len() {
+        if k + 2 < set.len() && set[k + 1] == '-' {
+            found |= set[k] <= c && c <= set[k + 2];
+            k += 3;
+        } else {
+            found |= set[k] == c;
+            k += 1;
+        }
+    }
+    (found != negated).then_some(close + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stars_and_question_marks() {
+        assert!(glob_match("page_*.png", "page_001.png"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*.png", "scans/a.png"));
+        assert!(glob_match("scans/*", "scans/sub/a.png"));
+        assert!(glob_match("*a*b*", "xxaybzb"));
+        assert!(glob_match("page_???.png", "page_012.png"));
+        assert!(!glob_match("page_???.png", "page_12.png"));
+        assert!(!glob_match("page_*.png", "page_001.jpg"));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn character_classes() {
+        assert!(glob_match("page_[0-4]*", "page_3.png"));
+        assert!(!glob_match("page_[0-4]*", "page_7.png"));
+        assert!(glob_match("page_[!0-4]*", "page_7.png"));
+        assert!(glob_match("[]]", "]"));
+        assert!(glob_match("[!]]", "a"));
+        // An unclosed `[` is a literal
+        assert!(glob_match("a[b", "a[b"));
+        assert!(!glob_match("a[b", "ab"));
+    }
+
+    #[test]
+    fn matching_is_case_sensitive() {
+        assert!(!glob_match("*.PNG", "page.png"));
+    }
+}
@@ -0,0 +1,52 @@
+//! Image content helpers - embeds image files as MCP ImageContent blocks
+
+use anyhow::Context;
+use anyhow::Result;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use mcp_types::ImageContent;
+use std::path::Path;
+
+/// Read an image file and wrap it as a base64 `ImageContent` block.
+pub async fn image_content_from_file(path: &Path) -> Result<ImageContent> {
+    let bytes = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read image: {}", path.display()))?;
+
+    Ok(ImageContent {
+        r#type: "image".to_string(),
+        data: STANDARD.encode(bytes),
+        mime_type: image_mime_type(path).to_string(),
+        annotations: None,
+    })
+}
+
+/// Wrap in-memory PNG data as a base64 `ImageContent` block.
+pub fn image_content_from_png(bytes: &[u8]) -> ImageContent {
+    ImageContent {
+        r#type: "image".to_string(),
+        data: STANDARD.encode(bytes),
+        mime_type: "image/png".to_string(),
+        annotations: None,
+    }
+}
+
+/// Map an image file extension to its MIME type, defaulting to PNG.
+pub fn image_mime_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "webp" => "image/webp",
+        "gif" => "image/gif",
+        "tif" | "tiff" => "image/tiff",
+        "bmp" => "image/bmp",
+        "heic" => "image/heic",
+        "heif" => "image/heif",
+        "avif" => "image/avif",
+        _ => "image/png",
+    }
+}
@@ -2,106 +2,594 @@
 
 use anyhow::Context;
 use anyhow::Result;
+use anyhow::bail;
+use lopdf::Dictionary;
+use lopdf::Document;
+use lopdf::Object;
+use lopdf::ObjectId;
 use mcp_types::CallToolResult;
 use mcp_types::ContentBlock;
 use mcp_types::TextContent;
 use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
 use std::path::PathBuf;
 use std::process::Stdio;
-use tokio::process::Command;
 use tracing::info;
+use tracing::warn;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::native_fallback;
+use super::process_pdf::PageResult;
+use super::process_pdf::StepTimings;
+use super::python::python_command;
+use super::script_output::parse_json_result;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+use super::workspace::create_temp_dir;
+use super::workspace::remove_temp_dir;
+use crate::metrics;
 
 #[derive(Deserialize)]
 struct ImagesToPdfArgs {
     image_dir: String,
     output_path: String,
     pattern: Option<String>,
+    #[serde(default)]
+    sort: ImageOrder,
+    #[serde(flatten)]
+    resize: PageResize,
+    #[serde(default)]
+    deterministic: bool,
+    #[serde(default)]
+    append: bool,
 }
 
-pub async fn handle_images_to_pdf(args: serde_json::Value) -> Result<CallToolResult> {
-    let args: ImagesToPdfArgs = serde_json::from_value(args)?;
-
-    let image_dir = PathBuf::from(&args.image_dir);
-    if !image_dir.exists() || !image_dir.is_dir() {
-        return Ok(CallToolResult {
-            content: vec![ContentBlock::TextContent(TextContent {
-                r#type: "text".to_string(),
-                text: format!("Error: Directory not found: {}", args.image_dir),
-                annotations: None,
-            })],
-            is_error: Some(true),
-            structured_content: None,
-        });
+/// Page order of [`images_to_pdf`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageOrder {
+    /// Plain file name order: `page_10.png` before `page_2.png`
+    Name,
+    /// File name order with digit runs compared as numbers
+    #[default]
+    Natural,
+    /// Oldest modification time first
+    Mtime,
+}
+
+impl ImageOrder {
+    fn as_str(self) -> &'static str {
+        match self {
+            ImageOrder::Name => "name",
+            ImageOrder::Natural => "natural",
+            ImageOrder::Mtime => "mtime",
+        }
     }
+}
 
-    let pattern = args.pattern.unwrap_or_else(|| "*.png".to_string());
+/// Resizing applied to each image as [`images_to_pdf`] merges it. The page
+/// keeps its physical size; only its pixel count changes.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct PageResize {
+    /// Downscale pages wider than this many pixels
+    pub max_width: Option<u32>,
+    /// Downscale pages taller than this many pixels
+    pub max_height: Option<u32>,
+    /// Resize every page by this factor, e.g. `0.5`, before the limits
+    /// above are applied
+    pub scale: Option<f64>,
+}
+
+impl PageResize {
+    fn script_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(max_width) = self.max_width {
+            args.push(format!("--max-width={max_width}"));
+        }
+        if let Some(max_height) = self.max_height {
+            args.push(format!("--max-height={max_height}"));
+        }
+        if let Some(scale) = self.scale {
+            args.push(format!("--scale={scale}"));
+        }
+        args
+    }
+}
+
+/// Result of [`images_to_pdf`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImagesToPdfOutput {
+    pub output_path: PathBuf,
+    pub page_count: usize,
+    pub size_bytes: u64,
+    /// Per-page results when the pages were rendered from a document by
+    /// `process_pdf`; empty for plain image merges
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pages: Vec<PageResult>,
+    /// Step timings of the `process_pdf` run that rendered the pages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<StepTimings>,
+    /// Pages [`append_images_to_pdf`] added to an existing PDF
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appended: Option<usize>,
+    /// Human-readable script output
+    #[serde(skip)]
+    pub log: String,
+}
+
+/// Merge the images in `image_dir` matching `pattern` (default `*.png`) into
+/// one PDF at `output_path`, one page per image in `order`, resized as
+/// `resize` says. With `deterministic`, the PDF's dates are fixed and it
+/// gets no random document ID, so the same images always give the same
+/// bytes.
+pub async fn images_to_pdf(
+    image_dir: &Path,
+    output_path: &Path,
+    pattern: Option<&str>,
+    order: ImageOrder,
+    resize: PageResize,
+    deterministic: bool,
+) -> Result<ImagesToPdfOutput> {
+    if !image_dir.is_dir() {
+        bail!(ToolError::not_found("Directory", image_dir));
+    }
+
+    let pattern = pattern.unwrap_or("*.png");
+    check_pattern(pattern)?;
 
     info!(
         "Merging images to PDF: {} -> {}",
-        args.image_dir, args.output_path
+        image_dir.display(),
+        output_path.display()
     );
 
-    let scripts_dir = get_scripts_dir()?;
+    let scripts_dir = scripts_dir()?;
     let script_path = scripts_dir.join("images_to_pdf.py");
 
-    let output = Command::new("python3")
-        .arg(&script_path)
-        .arg(&args.image_dir)
-        .arg(&args.output_path)
-        .arg(&pattern)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await
-        .context("Failed to execute images_to_pdf.py")?;
+    let mut cmd = python_command();
+    cmd.arg(&script_path)
+        .arg(image_dir)
+        .arg(output_path)
+        .arg(pattern)
+        .arg("--sort")
+        .arg(order.as_str())
+        .args(resize.script_args());
+    if deterministic {
+        cmd.arg("--deterministic");
+    }
+    let output = match run_command(cmd.stdout(Stdio::piped()).stderr(Stdio::piped())).await {
+        Err(e) if native_fallback::python_missing(&e) => {
+            warn!("{e}; merging without Python");
+            let result =
+                native_fallback::merge(image_dir, output_path, pattern, order, resize).await?;
+            metrics::add_bytes_written(result.size_bytes);
+            return Ok(result);
+        }
+        output => output.context("Failed to execute images_to_pdf.py")?,
+    };
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Ok(CallToolResult {
-            content: vec![ContentBlock::TextContent(TextContent {
-                r#type: "text".to_string(),
-                text: format!("Error running images_to_pdf.py: {stderr}"),
-                annotations: None,
-            })],
-            is_error: Some(true),
-            structured_content: None,
+        bail!(ToolError::script_failed("images_to_pdf.py", &output.stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let mut result: ImagesToPdfOutput = parse_json_result(&stdout)
+        .and_then(|json| serde_json::from_value(json).ok())
+        .context("images_to_pdf.py did not report a result")?;
+    result.log = stdout;
+    metrics::add_bytes_written(result.size_bytes);
+    Ok(result)
+}
+
+/// Add the images in `image_dir` matching `pattern` that `output_path` does
+/// not hold yet to the end of it, creating it when missing. Merged image
+/// names are recorded in the PDF, so calling this as pages are produced
+/// assembles the document a page at a time, and an interrupted merge picks
+/// up where it stopped. A PDF without that record is taken to hold the first
+/// images in `order`, one per page. New images always go after the existing
+/// pages, whatever their place in `order`. The new pages are merged exactly as
+/// [`images_to_pdf`] would, then added to the existing document, which is
+/// replaced in one rename.
+pub async fn append_images_to_pdf(
+    image_dir: &Path,
+    output_path: &Path,
+    pattern: Option<&str>,
+    order: ImageOrder,
+    resize: PageResize,
+    deterministic: bool,
+) -> Result<ImagesToPdfOutput> {
+    if !image_dir.is_dir() {
+        bail!(ToolError::not_found("Directory", image_dir));
+    }
+    let pattern = pattern.unwrap_or("*.png");
+    check_pattern(pattern)?;
+    let images = native_fallback::matching_images(image_dir, pattern, order).await?;
+
+    let existing = if output_path.is_file() {
+        let path = output_path.to_path_buf();
+        Some(tokio::task::spawn_blocking(move || merged_images(&path)).await??)
+    } else {
+        None
+    };
+    let (merged, new): (Vec<PathBuf>, Vec<PathBuf>) = match &existing {
+        Some((_, Some(names))) => {
+            let names: HashSet<&String> = names.iter().collect();
+            let (merged, new): (Vec<PathBuf>, Vec<PathBuf>) = images
+                .into_iter()
+                .partition(|path| file_name(path).is_some_and(|name| names.contains(&name)));
+            if merged.len() < names.len() {
+                warn!(
+                    "{} holds {} images no longer in {}",
+                    output_path.display(),
+                    names.len() - merged.len(),
+                    image_dir.display()
+                );
+            }
+            (merged, new)
+        }
+        Some((page_count, None)) => {
+            let mut images = images;
+            let new = images.split_off((*page_count).min(images.len()));
+            (images, new)
+        }
+        None => (Vec::new(), images),
+    };
+
+    if new.is_empty() {
+        let page_count = existing.map_or(0, |(page_count, _)| page_count);
+        return Ok(ImagesToPdfOutput {
+            output_path: output_path.to_path_buf(),
+            page_count,
+            size_bytes: tokio::fs::metadata(output_path).await?.len(),
+            pages: Vec::new(),
+            timings: None,
+            appended: Some(0),
+            log: format!("No new images to append to {}\n", output_path.display()),
         });
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    info!(
+        "Appending {} images to {}",
+        new.len(),
+        output_path.display()
+    );
+    // The new images are merged on their own from a staging directory,
+    // named so that name order is the order they were picked in
+    let staging = create_temp_dir("append").await?;
+    let result = async {
+        let pages_dir = staging.join("pages");
+        tokio::fs::create_dir_all(&pages_dir).await?;
+        for (index, image) in new.iter().enumerate() {
+            let name = file_name(image).unwrap_or_default();
+            let staged = pages_dir.join(format!("{index:06}_{name}"));
+            if tokio::fs::hard_link(image, &staged).await.is_err() {
+                tokio::fs::copy(image, &staged).await?;
+            }
+        }
+        let new_pdf = staging.join("new.pdf");
+        images_to_pdf(
+            &pages_dir,
+            &new_pdf,
+            Some("*"),
+            ImageOrder::Name,
+            resize,
+            deterministic,
+        )
+        .await?;
+
+        let listed: Vec<String> = new
+            .iter()
+            .filter_map(|path| file_name(path))
+            .map(|name| format!("  - {name}"))
+            .collect();
+        let names: Vec<String> = merged
+            .iter()
+            .chain(&new)
+            .filter_map(|path| file_name(path))
+            .collect();
+        let output = output_path.to_path_buf();
+        let base = existing.is_some().then(|| output.clone());
+        let page_count = tokio::task::spawn_blocking(move || {
+            append_pdf(base.as_deref(), &new_pdf, &names, &output)
+        })
+        .await??;
+        let size_bytes = tokio::fs::metadata(output_path).await?.len();
+        metrics::add_bytes_written(size_bytes);
+        anyhow::Ok(ImagesToPdfOutput {
+            output_path: output_path.to_path_buf(),
+            page_count,
+            size_bytes,
+            pages: Vec::new(),
+            timings: None,
+            appended: Some(new.len()),
+            log: format!(
+                "Appended {} images to {} ({page_count} pages)\n{}\n",
+                new.len(),
+                output_path.display(),
+                listed.join("\n")
+            ),
+        })
+    }
+    .await;
+    remove_temp_dir(&staging).await;
+    result
+}
+
+/// Reject a `pattern` reaching outside the image directory. Patterns match
+/// file names only, as the built-in merger reads them, so the script's glob
+/// cannot be pointed at other directories past the path allowlist.
+fn check_pattern(pattern: &str) -> Result<()> {
+    if pattern.contains(['/', '\\']) || pattern.contains("..") {
+        bail!(ToolError::invalid_args(format!(
+            "pattern must match file names in image_dir, not paths: {pattern}"
+        )));
+    }
+    Ok(())
+}
+
+/// Info dictionary key listing the images merged into a PDF by
+/// [`append_images_to_pdf`]
+const MERGED_IMAGES_KEY: &[u8] = b"MergedImages";
+
+fn file_name(path: &Path) -> Option<String> {
+    Some(path.file_name()?.to_string_lossy().to_string())
+}
+
+/// Page count of the PDF at `path` and the image names recorded in it, if
+/// it was written by [`append_images_to_pdf`].
+fn merged_images(path: &Path) -> Result<(usize, Option<Vec<String>>)> {
+    let document = Document::load(path)
+        .with_context(|| format!("Cannot append to {}: not a readable PDF", path.display()))?;
+    let names = info_dictionary(&document)
+        .and_then(|info| info.get(MERGED_IMAGES_KEY).ok()?.as_array().ok())
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| name.as_str().ok())
+                .map(|name| String::from_utf8_lossy(name).to_string())
+                .collect()
+        });
+    Ok((document.get_pages().len(), names))
+}
+
+fn info_dictionary(document: &Document) -> Option<&Dictionary> {
+    match document.trailer.get(b"Info").ok()? {
+        Object::Reference(id) => document.get_dictionary(*id).ok(),
+        Object::Dictionary(info) => Some(info),
+        _ => None,
+    }
+}
+
+/// Write `output`: the pages of `base` (when given) followed by those of
+/// `new_pdf`, recording `names` as the merged images. Returns the page count.
+fn append_pdf(
+    base: Option<&Path>,
+    new_pdf: &Path,
+    names: &[String],
+    output: &Path,
+) -> Result<usize> {
+    let mut new = Document::load(new_pdf)?;
+    let mut document = match base {
+        Some(base) => {
+            let mut document = Document::load(base)?;
+            new.renumber_objects_with(document.max_id + 1);
+            document.max_id = new.max_id;
+            let pages_id = document.catalog()?.get(b"Pages")?.as_reference()?;
+            let new_pages: Vec<ObjectId> = new.get_pages().into_values().collect();
+            document.objects.extend(std::mem::take(&mut new.objects));
+            for page in &new_pages {
+                document
+                    .get_dictionary_mut(*page)?
+                    .set("Parent", Object::Reference(pages_id));
+            }
+            let tree = document.get_dictionary_mut(pages_id)?;
+            let count = tree.get(b"Count")?.as_i64()?;
+            tree.get_mut(b"Kids")?
+                .as_array_mut()?
+                .extend(new_pages.iter().map(|&id| Object::Reference(id)));
+            tree.set("Count", count + new_pages.len() as i64);
+            // The new document's catalog, page tree and info are now orphans
+            document.prune_objects();
+            document
+        }
+        None => new,
+    };
+
+    let names = Object::Array(
+        names
+            .iter()
+            .map(|name| Object::string_literal(name.as_str()))
+            .collect(),
+    );
+    match document.trailer.get_mut(b"Info") {
+        Ok(Object::Dictionary(info)) => info.set(MERGED_IMAGES_KEY, names),
+        Ok(Object::Reference(id)) => {
+            let id = *id;
+            document
+                .get_dictionary_mut(id)?
+                .set(MERGED_IMAGES_KEY, names);
+        }
+        _ => {
+            let info = Dictionary::from_iter([(MERGED_IMAGES_KEY, names)]);
+            let id = document.add_object(info);
+            document.trailer.set("Info", Object::Reference(id));
+        }
+    }
+
+    let file_name = output.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = output.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+    document
+        .save(&tmp)
+        .and_then(|_| std::fs::rename(&tmp, output))
+        .with_context(|| format!("Error writing {}", output.display()))
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&tmp);
+        })?;
+    Ok(document.get_pages().len())
+}
+
+pub async fn handle_images_to_pdf(args: serde_json::Value) -> ToolResult {
+    let args: ImagesToPdfArgs = parse_args(args)?;
+
+    let (image_dir, output_path) = (Path::new(&args.image_dir), Path::new(&args.output_path));
+    let pattern = args.pattern.as_deref();
+    let result = if args.append {
+        append_images_to_pdf(
+            image_dir,
+            output_path,
+            pattern,
+            args.sort,
+            args.resize,
+            args.deterministic,
+        )
+        .await?
+    } else {
+        images_to_pdf(
+            image_dir,
+            output_path,
+            pattern,
+            args.sort,
+            args.resize,
+            args.deterministic,
+        )
+        .await?
+    };
 
     Ok(CallToolResult {
         content: vec![ContentBlock::TextContent(TextContent {
             r#type: "text".to_string(),
-            text: format!("Successfully created PDF: {}\n{}", args.output_path, stdout),
+            text: format!(
+                "Successfully created PDF: {}\n{}",
+                result.output_path.display(),
+                result.log
+            ),
             annotations: None,
         })],
         is_error: Some(false),
-        structured_content: None,
+        structured_content: Some(serde_json::to_value(&result)?),
     })
 }
 
-fn get_scripts_dir() -> Result<PathBuf> {
-    if let Ok(exe_path) = std::env::current_exe()
-        && let Some(parent) = exe_path.parent()
-    {
-        let possible_paths = vec![
-            parent.join("../../../watermark-remover-mcp-server/scripts"),
-            parent.join("../../watermark-remover-mcp-server/scripts"),
-            parent.join("scripts"),
-        ];
-
-        for path in possible_paths {
-            if path.exists() {
-                return Ok(path.canonicalize()?);
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    /// Save a PDF with one page per width at `path`.
+    fn pdf(path: &Path, widths: &[i64]) {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let kids: Vec<Object> = widths
+            .iter()
+            .map(|&width| {
+                document
+                    .add_object(dictionary! {
+                        "Type" => "Page",
+                        "Parent" => pages_id,
+                        "MediaBox" => vec![0.into(), 0.into(), width.into(), 100.into()],
+                    })
+                    .into()
+            })
+            .collect();
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => widths.len() as i64,
+            }),
+        );
+        let catalog = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        document.trailer.set("Root", catalog);
+        document.save(path).unwrap();
+    }
+
+    fn page_widths(path: &Path) -> Vec<i64> {
+        let document = Document::load(path).unwrap();
+        document
+            .get_pages()
+            .into_values()
+            .map(|id| {
+                let page = document.get_dictionary(id).unwrap();
+                page.get(b"MediaBox").unwrap().as_array().unwrap()[2]
+                    .as_i64()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn patterns_stay_in_the_image_dir() {
+        for pattern in ["*.png", "page_*.jpg", "scan?.[pj]*"] {
+            assert!(check_pattern(pattern).is_ok(), "{pattern}");
+        }
+        for pattern in [
+            "../../secret/*.png",
+            "..",
+            "sub/*.png",
+            "..\\*.png",
+            "/etc/*",
+        ] {
+            assert!(check_pattern(pattern).is_err(), "{pattern}");
         }
     }
 
-    if let Ok(scripts_dir) = std::env::var("WATERMARK_SCRIPTS_DIR") {
-        return Ok(PathBuf::from(scripts_dir));
+    #[test]
+    fn append_pdf_adds_pages_after_the_existing_ones() {
+        let dir = std::env::temp_dir().join(format!("append_pdf_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (base, new, output) = (
+            dir.join("base.pdf"),
+            dir.join("new.pdf"),
+            dir.join("out.pdf"),
+        );
+        pdf(&base, &[101, 102]);
+        pdf(&new, &[103, 104, 105]);
+
+        let names = ["a.png", "b.png"].map(String::from);
+        assert_eq!(append_pdf(Some(&base), &new, &names, &output).unwrap(), 5);
+        assert_eq!(page_widths(&output), [101, 102, 103, 104, 105]);
+
+        // The appended pages hang off the base's page tree
+        let document = Document::load(&output).unwrap();
+        let pages_id = document
+            .catalog()
+            .unwrap()
+            .get(b"Pages")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let tree = document.get_dictionary(pages_id).unwrap();
+        assert_eq!(tree.get(b"Count").unwrap().as_i64().unwrap(), 5);
+        for id in document.get_pages().into_values() {
+            let parent = document.get_dictionary(id).unwrap().get(b"Parent").unwrap();
+            assert_eq!(parent.as_reference().unwrap(), pages_id);
+        }
+
+        let (count, merged) = merged_images(&output).unwrap();
+        assert_eq!(count, 5);
+        assert_eq!(merged.unwrap(), names);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
-    let cwd = std::env::current_dir()?;
-    Ok(cwd.join("scripts"))
+    #[test]
+    fn append_pdf_without_a_base_records_the_names() {
+        let dir = std::env::temp_dir().join(format!("append_pdf_new_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (new, output) = (dir.join("new.pdf"), dir.join("out.pdf"));
+        pdf(&new, &[110]);
+
+        assert_eq!(merged_images(&new).unwrap(), (1, None));
+        let names = [String::from("page_1.png")];
+        assert_eq!(append_pdf(None, &new, &names, &output).unwrap(), 1);
+        assert_eq!(merged_images(&output).unwrap(), (1, Some(names.to_vec())));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
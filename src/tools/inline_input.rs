@@ -0,0 +1,71 @@
+//! Inline input support - materializes base64 document bodies as temp files
+
+use anyhow::Context;
+use anyhow::Result;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::path::PathBuf;
+use tracing::info;
+
+use super::preflight::heif_format;
+use super::workspace::create_temp_dir;
+
+/// Decode base64 `data` (optionally prefixed with a `data:...;base64,` header)
+/// and write it to a fresh file in the system temp directory.
+///
+/// Returns the path of the written file, named `<stem>.<extension>` inside a
+/// unique per-call directory so that derived outputs (e.g. `<stem>_pages`)
+/// stay grouped together.
+pub async fn write_inline_input(data: &str, stem: &str, extension: &str) -> Result<PathBuf> {
+    let bytes = decode_inline_input(data)?;
+
+    let dir = create_temp_dir("inline").await?;
+    let path = dir.join(format!("{stem}.{extension}"));
+    tokio::fs::write(&path, &bytes).await?;
+
+    info!(
+        "Wrote {} bytes of inline content to {}",
+        bytes.len(),
+        path.display()
+    );
+    Ok(path)
+}
+
+/// Decode base64 `data`, optionally prefixed with a `data:...;base64,`
+/// header, without writing it anywhere.
+pub fn decode_inline_input(data: &str) -> Result<Vec<u8>> {
+    let payload = strip_data_url(data);
+    let cleaned: String = payload.chars().filter(|c| !c.is_whitespace()).collect();
+    STANDARD
+        .decode(cleaned.as_bytes())
+        .context("Invalid base64 content")
+}
+
+/// Guess a file extension for inline image data from its magic bytes
+/// (after base64 decoding), defaulting to `png`.
+pub fn sniff_image_extension(data: &str) -> &'static str {
+    let payload = strip_data_url(data);
+    let prefix: String = payload
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .take(16)
+        .collect();
+    let bytes = STANDARD.decode(prefix.as_bytes()).unwrap_or_default();
+
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "jpg"
+    } else if bytes.starts_with(b"RIFF") && bytes.len() >= 12 && &bytes[8..12] == b"WEBP" {
+        "webp"
+    } else if bytes.starts_with(b"GIF8") {
+        "gif"
+    } else {
+        heif_format(&bytes).unwrap_or("png")
+    }
+}
+
+fn strip_data_url(data: &str) -> &str {
+    match data.split_once(";base64,") {
+        Some((header, body)) if header.starts_with("data:") => body,
+        _ => data,
+    }
+}
@@ -0,0 +1,303 @@
+//! Job tools - status polling, result retrieval, cancellation and resuming
+
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+use tracing::warn;
+
+use super::checkpoint::CheckpointStatus;
+use super::checkpoint::auto_resume_enabled;
+use super::checkpoint::claim_auto_resume;
+use super::checkpoint::discard_checkpoint;
+use super::checkpoint::list_checkpoints;
+use super::checkpoint::load_checkpoint;
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::process_pdf::resume_process_pdf;
+use super::subprocess::kill_client_process_groups;
+use crate::jobs::JobManager;
+use crate::jobs::JobStatus;
+
+#[derive(Deserialize)]
+struct JobStatusArgs {
+    job_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JobIdArgs {
+    job_id: String,
+}
+
+#[derive(Deserialize)]
+struct ResumeJobArgs {
+    job_id: Option<String>,
+    discard: Option<bool>,
+}
+
+pub async fn handle_job_status(args: serde_json::Value, jobs: &JobManager) -> ToolResult {
+    let args: JobStatusArgs = parse_args(args)?;
+
+    let Some(job_id) = args.job_id else {
+        let all = jobs.list();
+        let lines: Vec<String> = all
+            .iter()
+            .map(|job| {
+                format!(
+                    "  {} [{}] {:?} ({}/{} pages)",
+                    job.job_id,
+                    job.tool,
+                    job.status,
+                    job.pages_done,
+                    job.total_pages
+                        .map(|t| t.to_string())
+                        .unwrap_or_else(|| "?".to_string())
+                )
+            })
+            .collect();
+        return Ok(CallToolResult {
+            content: vec![ContentBlock::TextContent(TextContent {
+                r#type: "text".to_string(),
+                text: format!("{} jobs\n{}", all.len(), lines.join("\n")),
+                annotations: None,
+            })],
+            is_error: Some(false),
+            structured_content: Some(json!({ "jobs": all })),
+        });
+    };
+
+    let Some(info) = jobs.status(&job_id) else {
+        return Err(unknown_job(&job_id));
+    };
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!(
+                "Job {} ({}): {:?}\nPages: {}/{}\n{}",
+                info.job_id,
+                info.tool,
+                info.status,
+                info.pages_done,
+                info.total_pages
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| "?".to_string()),
+                info.message.clone().unwrap_or_default()
+            ),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(serde_json::to_value(&info)?),
+    })
+}
+
+pub async fn handle_job_result(args: serde_json::Value, jobs: &JobManager) -> ToolResult {
+    let args: JobIdArgs = parse_args(args)?;
+
+    let Some((info, result)) = jobs.result(&args.job_id) else {
+        return Err(unknown_job(&args.job_id));
+    };
+
+    if let Some(result) = result {
+        return Ok(result);
+    }
+
+    if info.status == JobStatus::Cancelled {
+        return Err(ToolError::Cancelled {
+            job_id: info.job_id,
+        });
+    }
+
+    let text = format!(
+        "Job {} is still running ({} pages done). Poll job_status for progress.",
+        info.job_id, info.pages_done
+    );
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text,
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(serde_json::to_value(&info)?),
+    })
+}
+
+pub async fn handle_job_cancel(args: serde_json::Value, jobs: &JobManager) -> ToolResult {
+    let args: JobIdArgs = parse_args(args)?;
+
+    let Some(status) = jobs.cancel(&args.job_id) else {
+        return Err(unknown_job(&args.job_id));
+    };
+    // A cancelled job is not resumed
+    discard_checkpoint(&args.job_id);
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!("Job {}: {:?}", args.job_id, status),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(json!({ "job_id": args.job_id, "status": status })),
+    })
+}
+
+/// Cancel every running job of the session and kill every helper process the
+/// session has started, including those of foreground calls and watches.
+/// Each job's processes are killed before its task is aborted, and the
+/// remaining ones after.
+pub async fn handle_abort_all(_args: serde_json::Value, jobs: &JobManager) -> ToolResult {
+    let (aborted, killed_with_jobs) = jobs.cancel_all();
+    for job_id in &aborted {
+        discard_checkpoint(job_id);
+    }
+    let killed = killed_with_jobs + kill_client_process_groups();
+    warn!(
+        "Aborted {} jobs and killed {killed} subprocesses",
+        aborted.len()
+    );
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: if aborted.is_empty() {
+                format!("No running jobs\nKilled subprocesses: {killed}")
+            } else {
+                format!(
+                    "Aborted {} jobs: {}\nKilled subprocesses: {killed}",
+                    aborted.len(),
+                    aborted.join(", ")
+                )
+            },
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(json!({
+            "aborted_jobs": aborted,
+            "killed_subprocesses": killed,
+        })),
+    })
+}
+
+pub async fn handle_resume_job(args: serde_json::Value, jobs: &JobManager) -> ToolResult {
+    let args: ResumeJobArgs = parse_args(args)?;
+
+    let Some(job_id) = args.job_id else {
+        let checkpoints = list_checkpoints(jobs.session_id());
+        let lines: Vec<String> = checkpoints
+            .iter()
+            .map(|checkpoint| {
+                format!(
+                    "  {} [{}] {:?}: {} ({}/{} pages)",
+                    checkpoint.job_id,
+                    checkpoint.tool,
+                    checkpoint.status,
+                    checkpoint.job.pdf_path.display(),
+                    checkpoint.pages_done.len(),
+                    checkpoint
+                        .total_pages
+                        .map(|t| t.to_string())
+                        .unwrap_or_else(|| "?".to_string())
+                )
+            })
+            .collect();
+        return Ok(CallToolResult {
+            content: vec![ContentBlock::TextContent(TextContent {
+                r#type: "text".to_string(),
+                text: format!("{} resumable jobs\n{}", checkpoints.len(), lines.join("\n")),
+                annotations: None,
+            })],
+            is_error: Some(false),
+            structured_content: Some(json!({ "checkpoints": checkpoints })),
+        });
+    };
+
+    if jobs
+        .status(&job_id)
+        .is_some_and(|info| !info.status.is_finished())
+    {
+        return Err(ToolError::invalid_args(format!(
+            "Job {job_id} is still running"
+        )));
+    }
+    let checkpoint = load_checkpoint(&job_id)?;
+    if !checkpoint.claimable_by(jobs.session_id()) {
+        return Err(ToolError::invalid_args(format!(
+            "Job {job_id} belongs to another session or server process that is still running"
+        )));
+    }
+
+    if args.discard.unwrap_or(false) {
+        discard_checkpoint(&job_id);
+        return Ok(CallToolResult {
+            content: vec![ContentBlock::TextContent(TextContent {
+                r#type: "text".to_string(),
+                text: format!("Discarded the checkpoint of job {job_id}."),
+                annotations: None,
+            })],
+            is_error: Some(false),
+            structured_content: Some(json!({ "job_id": job_id, "discarded": true })),
+        });
+    }
+
+    if !checkpoint.job.pdf_path.exists() {
+        return Err(ToolError::not_found("PDF file", checkpoint.job.pdf_path));
+    }
+    let pages_done = checkpoint.pages_done.len();
+    let total_pages = checkpoint.total_pages;
+    resume_process_pdf(checkpoint, jobs);
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!(
+                "Resumed job {job_id} ({pages_done}/{} pages done before).\nPoll job_status / job_result with this job_id, or stop it with job_cancel.",
+                total_pages
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| "?".to_string())
+            ),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(json!({
+            "job_id": job_id,
+            "pages_done": pages_done,
+            "total_pages": total_pages,
+        })),
+    })
+}
+
+/// Resume the jobs a previous server process left running, if enabled.
+/// Only the first call per process does anything, so the interrupted jobs
+/// are adopted by the first client session. Returns the resumed job ids.
+pub fn resume_interrupted_jobs(jobs: &JobManager) -> Vec<String> {
+    if !auto_resume_enabled() || !claim_auto_resume() {
+        return Vec::new();
+    }
+    let mut resumed = Vec::new();
+    for checkpoint in list_checkpoints(jobs.session_id()) {
+        if checkpoint.status != CheckpointStatus::Running {
+            continue;
+        }
+        if !checkpoint.job.pdf_path.exists() {
+            warn!(
+                "Not resuming {}: {} is gone",
+                checkpoint.job_id,
+                checkpoint.job.pdf_path.display()
+            );
+            continue;
+        }
+        info!("Resuming interrupted job {}", checkpoint.job_id);
+        resumed.push(resume_process_pdf(checkpoint, jobs));
+    }
+    resumed
+}
+
+fn unknown_job(job_id: &str) -> ToolError {
+    ToolError::invalid_args(format!("Unknown job_id: {job_id}"))
+}
@@ -0,0 +1,103 @@
+//! Intermediate directory manifest - remembers scratch dirs this server created
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Serializes read-modify-write cycles on the manifest file within this process
+static MANIFEST_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: PathBuf,
+    pub created_by: String,
+    pub created_at: u64,
+}
+
+/// Location of the manifest file: `WATERMARK_MANIFEST_PATH`, or
+/// `$XDG_CACHE_HOME/watermark-remover/manifest.json` (falling back to
+/// `~/.cache` and finally the system temp dir).
+pub fn manifest_path() -> PathBuf {
+    if let Ok(path) = std::env::var("WATERMARK_MANIFEST_PATH") {
+        return PathBuf::from(path);
+    }
+
+    let cache_dir = std::env::var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| std::env::var("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|_| std::env::var("LOCALAPPDATA").map(PathBuf::from))
+        .unwrap_or_else(|_| std::env::temp_dir());
+    cache_dir.join("watermark-remover").join("manifest.json")
+}
+
+/// Record an intermediate directory so `cleanup_temp` can find it later.
+///
+/// Failures are logged rather than propagated: losing a manifest entry must
+/// never fail the tool call that produced the directory.
+pub async fn record_intermediate_dir(path: &Path, created_by: &str) {
+    if let Err(e) = try_record(path, created_by).await {
+        warn!("Failed to record {} in manifest: {e}", path.display());
+    }
+}
+
+async fn try_record(path: &Path, created_by: &str) -> Result<()> {
+    let _guard = MANIFEST_LOCK.lock().await;
+
+    let path = std::path::absolute(path)?;
+    let mut entries = read_entries().await;
+    if entries.iter().any(|e| e.path == path) {
+        return Ok(());
+    }
+
+    entries.push(ManifestEntry {
+        path,
+        created_by: created_by.to_string(),
+        created_at: unix_now(),
+    });
+    write_entries(&entries).await
+}
+
+/// Return all recorded entries.
+pub async fn list_entries() -> Vec<ManifestEntry> {
+    let _guard = MANIFEST_LOCK.lock().await;
+    read_entries().await
+}
+
+/// Drop the given paths from the manifest.
+pub async fn forget_entries(paths: &[PathBuf]) -> Result<()> {
+    let _guard = MANIFEST_LOCK.lock().await;
+
+    let mut entries = read_entries().await;
+    entries.retain(|e| !paths.contains(&e.path));
+    write_entries(&entries).await
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+async fn read_entries() -> Vec<ManifestEntry> {
+    match tokio::fs::read(manifest_path()).await {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+async fn write_entries(entries: &[ManifestEntry]) -> Result<()> {
+    let path = manifest_path();
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(&path, serde_json::to_vec_pretty(entries)?).await?;
+    Ok(())
+}
@@ -1,49 +1,256 @@
 //! Tool implementations for Watermark Remover
 
+mod add_watermark;
+mod adjust_image;
+mod allowlist;
+mod benchmark;
+mod check_environment;
+mod checkpoint;
+mod cleanup_temp;
+mod client_resources;
+mod color_space;
+mod compare_images;
+mod convert_image;
+mod document_input;
+mod dry_run;
+#[cfg(feature = "embedded-python")]
+mod embedded_python;
+mod error;
+mod estimate_job;
+mod evaluate_algorithms;
+mod extract_pdf_images;
+mod glob;
+mod image_content;
 mod images_to_pdf;
+mod inline_input;
+mod job_tools;
+mod manifest;
+mod native_fallback;
+mod object_storage;
+mod overwrite;
+mod page_cache;
+mod pdf_info;
 mod pdf_to_images;
+mod pdf_writer;
+mod plugins;
+mod preflight;
+mod preview_page;
 mod process_pdf;
+mod process_zip;
+mod profile_tools;
+mod profiles;
+mod python;
+mod quotas;
+mod redact_regions;
 mod remove_watermark;
+mod resource_links;
+mod roots;
+mod script_output;
+mod scripts;
+mod server_stats;
+mod subprocess;
+mod text_layer;
+mod thumbnails;
+mod verify_output;
+mod watch_tools;
+#[cfg(feature = "webdav")]
+mod webdav;
+mod webhook;
+mod workspace;
 
-use anyhow::Result;
 use mcp_types::CallToolRequestParams;
-use mcp_types::CallToolResult;
 use mcp_types::Tool;
+use mcp_types::ToolAnnotations;
 use mcp_types::ToolInputSchema;
 use serde_json::json;
+use std::path::Path;
+use std::time::SystemTime;
 
+use crate::jobs::JobManager;
+use crate::message_processor::ClientRequester;
+use crate::watch::WatchManager;
+
+pub use add_watermark::handle_add_watermark;
+pub use adjust_image::handle_adjust_image;
+pub use allowlist::allowed_dirs;
+pub use benchmark::handle_benchmark;
+pub use check_environment::handle_check_environment;
+pub use cleanup_temp::handle_cleanup_temp;
+pub use compare_images::handle_compare_images;
+pub use convert_image::handle_convert_image;
+pub use error::ToolError;
+pub use error::ToolResult;
+pub use estimate_job::handle_estimate_job;
+pub use evaluate_algorithms::handle_evaluate_algorithms;
+pub use extract_pdf_images::handle_extract_pdf_images;
+pub use images_to_pdf::ImageOrder;
+pub use images_to_pdf::ImagesToPdfOutput;
+pub use images_to_pdf::PageResize;
+pub use images_to_pdf::append_images_to_pdf;
 pub use images_to_pdf::handle_images_to_pdf;
+pub use images_to_pdf::images_to_pdf;
+pub use job_tools::handle_abort_all;
+pub use job_tools::handle_job_cancel;
+pub use job_tools::handle_job_result;
+pub use job_tools::handle_job_status;
+pub use job_tools::handle_resume_job;
+pub use job_tools::resume_interrupted_jobs;
+pub use pdf_to_images::PdfToImagesOutput;
 pub use pdf_to_images::handle_pdf_to_images;
+pub use pdf_to_images::pdf_to_images;
+pub use plugins::PluginTool;
+pub use preview_page::handle_preview_page;
+pub use process_pdf::PageResult;
+pub use process_pdf::PageStatus;
+pub use process_pdf::ProcessPdfOutput;
+pub use process_pdf::StepTimings;
 pub use process_pdf::handle_process_pdf;
+pub use process_pdf::process_pdf;
+pub use process_pdf::process_pdf_to_pdf;
+pub use process_zip::handle_process_zip;
+pub use profile_tools::handle_delete_profile;
+pub use profile_tools::handle_list_profiles;
+pub use profile_tools::handle_save_profile;
+pub use profiles::PostFilter;
+pub use profiles::RemovalSettings;
+pub use profiles::RemovalStep;
+pub use profiles::resolve_settings;
+pub use python::python_interpreter;
+pub use redact_regions::handle_redact_regions;
+pub use remove_watermark::CleanedImage;
+pub use remove_watermark::DirFilter;
+pub use remove_watermark::ImageResult;
+pub use remove_watermark::RemoveWatermarkOutput;
+pub use remove_watermark::WatermarkInput;
 pub use remove_watermark::handle_remove_watermark;
+pub use remove_watermark::remove_watermark;
+pub use remove_watermark::remove_watermark_bytes;
+pub use roots::configured_root;
+pub use roots::root_from_uri;
+pub use scripts::scripts_dir;
+pub use server_stats::handle_server_stats;
+pub use subprocess::forward_output;
+pub use subprocess::inherit_output;
+pub use subprocess::kill_job_process_groups;
+pub use subprocess::run_as_job;
+pub use watch_tools::handle_start_watch;
+pub use watch_tools::handle_stop_watch;
+pub use webhook::post_job_webhook;
+pub use workspace::spawn_workspace_gc;
 
-/// Get tool definitions for MCP
+/// Get tool definitions for MCP: the built-in tools followed by the plugin
+/// tools from the config file
 pub fn get_tool_definitions() -> Vec<Tool> {
+    let mut tools = builtin_tool_definitions();
+    tools.extend(plugins::plugin_definitions());
+
+    // Every tool takes `dry_run`, handled in `handle_tool_call`, every tool
+    // that writes files takes `overwrite`, and every tool that reads a
+    // document, image or archive takes `resource_uri` in its place
+    for tool in &mut tools {
+        let properties = tool
+            .input_schema
+            .properties
+            .get_or_insert_with(|| json!({}));
+        let writes_files = properties.as_object().is_some_and(|properties| {
+            properties
+                .keys()
+                .any(|key| key.starts_with("output_") || key == "diff_path")
+        });
+        if writes_files {
+            properties["overwrite"] = json!({
+                "type": "boolean",
+                "default": false,
+                "description": "是否允许覆盖已存在的输出文件（默认false，存在时报错并给出冲突路径）"
+            });
+        }
+        let reads_input = properties.as_object().is_some_and(|properties| {
+            client_resources::RESOURCE_INPUT_KEYS
+                .iter()
+                .any(|key| properties.contains_key(*key))
+        });
+        if reads_input {
+            properties["resource_uri"] = json!({
+                "type": "string",
+                "description": "客户端可读取的MCP资源URI（例如其他MCP服务器提供的云盘文档）。服务器通过resources/read向客户端读取，并按文件类型作为pdf_path、image_path或zip_path传入"
+            });
+            if let Some(required) = &mut tool.input_schema.required {
+                required
+                    .retain(|key| !client_resources::RESOURCE_INPUT_KEYS.contains(&key.as_str()));
+            }
+        }
+        properties["dry_run"] = json!({
+            "type": "boolean",
+            "default": false,
+            "description": "只校验参数并返回执行计划（输入、预计页数/文件数、输出路径及是否已存在），不做任何修改"
+        });
+    }
+    tools
+}
+
+/// What a tool does to the files and state around it
+enum Effect {
+    /// Only reads
+    ReadOnly,
+    /// Creates files or state but never replaces existing ones unless
+    /// `overwrite` is set
+    Additive { idempotent: bool },
+    /// May replace or delete existing files or state
+    Destructive { idempotent: bool },
+}
+
+/// Safety hints hosts use to decide when to ask the user before a call.
+/// Built-in tools only touch the local machine.
+fn annotations(effect: Effect) -> Option<ToolAnnotations> {
+    let (read_only, destructive, idempotent) = match effect {
+        Effect::ReadOnly => (true, false, true),
+        Effect::Additive { idempotent } => (false, false, idempotent),
+        Effect::Destructive { idempotent } => (false, true, idempotent),
+    };
+    Some(ToolAnnotations {
+        read_only_hint: Some(read_only),
+        destructive_hint: Some(destructive),
+        idempotent_hint: Some(idempotent),
+        open_world_hint: Some(false),
+        title: None,
+    })
+}
+
+/// Definitions of the tools built into the server.
+pub fn builtin_tool_definitions() -> Vec<Tool> {
     vec![
         Tool {
             name: "pdf_to_images".to_string(),
             title: None,
-            description: Some("将PDF文件转换为PNG图片。每页转换为一张图片。".to_string()),
-            annotations: None,
+            description: Some(
+                "将PDF文件转换为PNG图片。每页转换为一张图片。EPUB和DjVu文档会先转换为PDF。".to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
             output_schema: None,
             input_schema: ToolInputSchema {
                 r#type: "object".to_string(),
                 properties: Some(json!({
                     "pdf_path": {
                         "type": "string",
-                        "description": "PDF文件的绝对路径"
+                        "description": "PDF文件的绝对路径，也支持EPUB和DjVu文档（与pdf_base64二选一）"
+                    },
+                    "pdf_base64": {
+                        "type": "string",
+                        "description": "Base64编码的PDF内容（与pdf_path二选一，适用于无共享文件系统的客户端）"
                     },
                     "output_dir": {
                         "type": "string",
-                        "description": "输出目录路径（可选，默认在PDF同目录下创建临时目录）"
+                        "description": "输出目录路径（可选，默认在服务器工作区中创建临时目录）"
                     },
                     "dpi": {
                         "type": "integer",
+                        "minimum": 72,
+                        "maximum": 1200,
                         "default": 200,
                         "description": "输出图片的DPI（默认200）"
                     }
                 })),
-                required: Some(vec!["pdf_path".to_string()]),
+                required: Some(vec![]),
             },
         },
         Tool {
@@ -52,22 +259,162 @@ pub fn get_tool_definitions() -> Vec<Tool> {
             description: Some(
                 "去除图片右下角的水印（如NotebookLM水印）。支持单张图片或整个目录。".to_string(),
             ),
-            annotations: None,
+            annotations: annotations(Effect::Destructive { idempotent: false }),
             output_schema: None,
             input_schema: ToolInputSchema {
                 r#type: "object".to_string(),
                 properties: Some(json!({
                     "image_path": {
                         "type": "string",
-                        "description": "单张图片的路径（与image_dir二选一）"
+                        "description": "单张图片的路径（与image_base64、image_dir三选一）"
+                    },
+                    "image_base64": {
+                        "type": "string",
+                        "description": "Base64编码的单张图片内容（与image_path、image_dir三选一）"
+                    },
+                    "image": {
+                        "type": "object",
+                        "description": "MCP图片内容块（如宿主粘贴的截图），替代image_base64；默认以图片内容返回处理结果",
+                        "properties": {
+                            "type": { "type": "string", "const": "image" },
+                            "data": { "type": "string" },
+                            "mimeType": { "type": "string" }
+                        },
+                        "required": ["type", "data", "mimeType"]
                     },
                     "image_dir": {
                         "type": "string",
-                        "description": "图片目录路径（与image_path二选一）"
+                        "description": "图片目录路径（与image_path、image_base64三选一）"
+                    },
+                    "recursive": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "image_dir模式下，是否同时处理子目录中的图片；输出保持原有目录结构（默认false）"
+                    },
+                    "extensions": {
+                        "type": "array",
+                        "items": {
+                            "type": "string",
+                            "enum": ["png", "jpg", "jpeg", "webp", "gif", "bmp", "tif", "tiff", "heic", "heif", "avif"]
+                        },
+                        "description": "image_dir模式下要处理的图片扩展名（默认png、jpg、jpeg、webp、gif、heic、heif、avif）"
+                    },
+                    "include": {
+                        "type": "array",
+                        "items": {"type": "string"},
+                        "description": "image_dir模式下只处理相对路径匹配任一glob模式的图片（如 page_*.png、scans/*）"
+                    },
+                    "exclude": {
+                        "type": "array",
+                        "items": {"type": "string"},
+                        "description": "image_dir模式下跳过相对路径匹配任一glob模式的图片（如 *_thumb.*）"
                     },
                     "output_dir": {
                         "type": "string",
                         "description": "输出目录路径（可选，默认覆盖原图或输出到同目录）"
+                    },
+                    "return_image": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "单张图片模式下，是否在结果中以base64图片内容返回处理后的图片（默认false）"
+                    },
+                    "pipe": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "单张图片通过标准输入/输出传给Python处理，不写临时文件；结果以图片内容返回，仅在指定output_dir时保存为PNG（默认false）"
+                    },
+                    "profile": {
+                        "type": "string",
+                        "description": "命名去水印配置（如 notebooklm），提供region/algorithm/template/dpi的默认值；显式参数优先"
+                    },
+                    "region": {
+                        "type": "array",
+                        "items": { "type": "number" },
+                        "minItems": 4,
+                        "maxItems": 4,
+                        "description": "水印区域 [x, y, 宽, 高]，均为相对图片尺寸的0-1比例（默认 [0.80, 0.92, 0.20, 0.08]，即右下角）"
+                    },
+                    "position": {
+                        "type": "string",
+                        "enum": ["bottom_right", "bottom_left", "top_right", "top_left", "center", "footer", "header"],
+                        "description": "水印位置预设，替代region：bottom_right、bottom_left、top_right、top_left、center、footer（页脚整行）或 header（页眉整行）"
+                    },
+                    "size": {
+                        "type": "array",
+                        "items": { "type": "number" },
+                        "minItems": 2,
+                        "maxItems": 2,
+                        "description": "position区域的 [宽, 高]，为页面尺寸的百分比（角落默认 [20, 8]，center默认 [40, 20]，footer/header默认 [100, 8]）"
+                    },
+                    "algorithm": {
+                        "type": "string",
+                        "enum": ["telea", "ns", "solid_fill"],
+                        "description": "修复算法：telea、ns（Navier-Stokes）或 solid_fill（用周围背景色直接填充，速度快，适合纯色页面角落），默认telea"
+                    },
+                    "template": {
+                        "type": "string",
+                        "description": "水印模板图片路径（可选），在区域内进行模板匹配以精确定位水印"
+                    },
+                    "mode": {
+                        "type": "string",
+                        "enum": ["corner", "grid"],
+                        "description": "去除模式：corner 处理region内的角落水印（默认）；grid 针对图库照片的半透明居中Logo与重复文字网格，在整张图片上估计水印掩码（忽略region和template）"
+                    },
+                    "inpaint_radius": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": 50,
+                        "default": 5,
+                        "description": "修复时参考的邻域半径（像素，默认5）"
+                    },
+                    "mask_padding": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 50,
+                        "default": 3,
+                        "description": "填充前将检测到的水印掩码向外扩展的像素数（默认3）"
+                    },
+                    "feather": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 50,
+                        "default": 0,
+                        "description": "填充区域边缘与原图渐变融合的宽度（像素，默认0即硬边），可减轻纹理背景上的光晕"
+                    },
+                    "post_filters": {
+                        "type": "array",
+                        "description": "去水印后依次对整张图片执行的清理步骤，适用于噪点较多的扫描件：despeckle 中值滤波去除孤立斑点；denoise 非局部均值降噪；normalize_contrast 拉伸色阶使纸张变白、文字变深",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "op": { "type": "string", "enum": ["despeckle", "denoise", "normalize_contrast"] },
+                                "size": { "type": "integer", "description": "despeckle的中值滤波核大小（3-15的奇数，默认3）" },
+                                "strength": { "type": "integer", "minimum": 1, "maximum": 30, "description": "denoise的降噪强度（1-30，默认10，越大越平滑但细节损失越多）" },
+                                "clip": { "type": "number", "minimum": 0, "maximum": 10, "description": "normalize_contrast忽略的最暗和最亮像素百分比（0-10，默认1）" }
+                            },
+                            "required": ["op"]
+                        }
+                    },
+                    "steps": {
+                        "type": "array",
+                        "description": "多步处理流程，在一次调用中对每张图片依次执行（只读写一次图片）。remove步骤未设置的字段沿用上面的参数",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "op": { "type": "string", "enum": ["remove", "despeckle"] },
+                                "region": { "type": "array", "items": { "type": "number" }, "minItems": 4, "maxItems": 4 },
+                                "position": { "type": "string", "enum": ["bottom_right", "bottom_left", "top_right", "top_left", "center", "footer", "header"] },
+                                "size": { "type": "array", "items": { "type": "number" }, "minItems": 2, "maxItems": 2 },
+                                "algorithm": { "type": "string", "enum": ["telea", "ns", "solid_fill"] },
+                                "template": { "type": "string" },
+                                "mode": { "type": "string", "enum": ["corner", "grid"] },
+                                "inpaint_radius": { "type": "integer", "minimum": 1, "maximum": 50 },
+                                "mask_padding": { "type": "integer", "minimum": 0, "maximum": 50 },
+                                "feather": { "type": "integer", "minimum": 0, "maximum": 50 },
+                                "kernel_size": { "type": "integer", "description": "despeckle的中值滤波核大小（3-15的奇数，默认3）" }
+                            },
+                            "required": ["op"]
+                        }
                     }
                 })),
                 required: Some(vec![]),
@@ -76,8 +423,11 @@ pub fn get_tool_definitions() -> Vec<Tool> {
         Tool {
             name: "images_to_pdf".to_string(),
             title: None,
-            description: Some("将目录中的图片合并为一个PDF文件。图片按文件名排序。".to_string()),
-            annotations: None,
+            description: Some(
+                "将目录中的图片合并为一个PDF文件。图片默认按文件名自然排序（page_2 在 page_10 之前）。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
             output_schema: None,
             input_schema: ToolInputSchema {
                 r#type: "object".to_string(),
@@ -94,6 +444,37 @@ pub fn get_tool_definitions() -> Vec<Tool> {
                         "type": "string",
                         "default": "*_processed.png",
                         "description": "图片文件匹配模式（默认 *_processed.png）"
+                    },
+                    "sort": {
+                        "type": "string",
+                        "enum": ["name", "natural", "mtime"],
+                        "default": "natural",
+                        "description": "页面顺序：natural 按文件名排序且数字按数值比较（默认）；name 按文件名逐字符排序；mtime 按修改时间从旧到新"
+                    },
+                    "max_width": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "页面图片最大宽度（像素，可选，超出时等比缩小；页面物理尺寸不变）"
+                    },
+                    "max_height": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "页面图片最大高度（像素，可选，超出时等比缩小；页面物理尺寸不变）"
+                    },
+                    "scale": {
+                        "type": "number",
+                        "exclusiveMinimum": 0,
+                        "description": "页面图片缩放比例（可选，如0.5；先于max_width/max_height应用）"
+                    },
+                    "deterministic": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "可复现输出：固定PDF日期且不写入随机文档ID，相同输入生成逐字节相同的PDF（默认false）"
+                    },
+                    "append": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "追加模式：只把输出PDF中尚未包含的匹配图片追加到其末尾（PDF不存在时新建），用于逐页流式组装和中断后续合并；已合并的图片名记录在PDF中（默认false）"
                     }
                 })),
                 required: Some(vec!["image_dir".to_string(), "output_path".to_string()]),
@@ -102,43 +483,1340 @@ pub fn get_tool_definitions() -> Vec<Tool> {
         Tool {
             name: "process_pdf".to_string(),
             title: None,
-            description: Some("一键处理PDF：转换为图片 → 去除水印 → 合并回PDF。".to_string()),
-            annotations: None,
+            description: Some(
+                "一键处理PDF：转换为图片 → 去除水印 → 合并回PDF。也支持EPUB和DjVu文档（先转换为PDF）。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
             output_schema: None,
             input_schema: ToolInputSchema {
                 r#type: "object".to_string(),
                 properties: Some(json!({
                     "pdf_path": {
                         "type": "string",
-                        "description": "输入PDF文件路径"
+                        "description": "输入PDF文件路径，也支持EPUB和DjVu文档（与pdf_base64二选一）"
+                    },
+                    "pdf_base64": {
+                        "type": "string",
+                        "description": "Base64编码的PDF内容（与pdf_path二选一）"
                     },
                     "output_path": {
                         "type": "string",
                         "description": "输出PDF文件路径（可选，默认为 原文件名_nowatermark.pdf）"
                     },
+                    "images_output_dir": {
+                        "type": "string",
+                        "description": "保留去水印后页面图片（page_NNN.png）的目录（可选，默认不保留，页面直接写入输出PDF）"
+                    },
+                    "auto_rename": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "默认输出的 原文件名_nowatermark.pdf 已存在时，改写为 原文件名_nowatermark_2.pdf 等新名称（默认false），新路径在结果中返回"
+                    },
                     "dpi": {
                         "type": "integer",
+                        "minimum": 72,
+                        "maximum": 1200,
                         "default": 200,
                         "description": "处理图片的DPI（默认200）"
+                    },
+                    "async": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "是否作为后台任务运行并立即返回job_id（默认false），适合大文档以避免请求超时"
+                    },
+                    "webhook_url": {
+                        "type": "string",
+                        "description": "后台任务结束（成功、失败或取消）时以POST方式发送JSON摘要（状态、输出、耗时）的http(s)地址，需配合async使用"
+                    },
+                    "thumbnails": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "在结果中附带一个示例页处理前后的小缩略图（图片内容），便于直接在对话中检查去除效果；不能与async同用"
+                    },
+                    "keep_intermediates": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "是否保留中间文件（页面图片工作目录、由EPUB/DjVu转换的PDF）以便调试；默认在生成PDF后删除"
+                    },
+                    "deterministic": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "可复现输出：固定PDF日期、不写入随机文档ID并按页码顺序写入页面，相同输入生成逐字节相同的PDF（默认false；未指定images_output_dir时逐页顺序处理）"
+                    },
+                    "text_layer": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "保留文字层：把原PDF每页的文字以不可见文字复制到去水印后的页面图片上，使输出可搜索、可复制，且与原文完全一致（不使用OCR；仅适用于PDF输入；默认false）"
+                    },
+                    "profile": {
+                        "type": "string",
+                        "description": "命名去水印配置（如 notebooklm），提供region/algorithm/template/dpi的默认值；显式参数优先"
+                    },
+                    "region": {
+                        "type": "array",
+                        "items": { "type": "number" },
+                        "minItems": 4,
+                        "maxItems": 4,
+                        "description": "水印区域 [x, y, 宽, 高]，均为相对图片尺寸的0-1比例（默认 [0.80, 0.92, 0.20, 0.08]，即右下角）"
+                    },
+                    "position": {
+                        "type": "string",
+                        "enum": ["bottom_right", "bottom_left", "top_right", "top_left", "center", "footer", "header"],
+                        "description": "水印位置预设，替代region：bottom_right、bottom_left、top_right、top_left、center、footer（页脚整行）或 header（页眉整行）"
+                    },
+                    "size": {
+                        "type": "array",
+                        "items": { "type": "number" },
+                        "minItems": 2,
+                        "maxItems": 2,
+                        "description": "position区域的 [宽, 高]，为页面尺寸的百分比（角落默认 [20, 8]，center默认 [40, 20]，footer/header默认 [100, 8]）"
+                    },
+                    "algorithm": {
+                        "type": "string",
+                        "enum": ["telea", "ns", "solid_fill"],
+                        "description": "修复算法：telea、ns（Navier-Stokes）或 solid_fill（用周围背景色直接填充，速度快，适合纯色页面角落），默认telea"
+                    },
+                    "template": {
+                        "type": "string",
+                        "description": "水印模板图片路径（可选），在区域内进行模板匹配以精确定位水印"
+                    },
+                    "mode": {
+                        "type": "string",
+                        "enum": ["corner", "grid"],
+                        "description": "去除模式：corner 处理region内的角落水印（默认）；grid 针对图库照片的半透明居中Logo与重复文字网格，在整张图片上估计水印掩码（忽略region和template）"
+                    },
+                    "inpaint_radius": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": 50,
+                        "default": 5,
+                        "description": "修复时参考的邻域半径（像素，默认5）"
+                    },
+                    "mask_padding": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 50,
+                        "default": 3,
+                        "description": "填充前将检测到的水印掩码向外扩展的像素数（默认3）"
+                    },
+                    "feather": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 50,
+                        "default": 0,
+                        "description": "填充区域边缘与原图渐变融合的宽度（像素，默认0即硬边），可减轻纹理背景上的光晕"
+                    },
+                    "post_filters": {
+                        "type": "array",
+                        "description": "去水印后依次对整张图片执行的清理步骤，适用于噪点较多的扫描件：despeckle 中值滤波去除孤立斑点；denoise 非局部均值降噪；normalize_contrast 拉伸色阶使纸张变白、文字变深",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "op": { "type": "string", "enum": ["despeckle", "denoise", "normalize_contrast"] },
+                                "size": { "type": "integer", "description": "despeckle的中值滤波核大小（3-15的奇数，默认3）" },
+                                "strength": { "type": "integer", "minimum": 1, "maximum": 30, "description": "denoise的降噪强度（1-30，默认10，越大越平滑但细节损失越多）" },
+                                "clip": { "type": "number", "minimum": 0, "maximum": 10, "description": "normalize_contrast忽略的最暗和最亮像素百分比（0-10，默认1）" }
+                            },
+                            "required": ["op"]
+                        }
+                    },
+                    "blank_pages": {
+                        "type": "string",
+                        "enum": ["process", "skip", "drop"],
+                        "default": "process",
+                        "description": "空白页（几乎没有内容的页面）的处理方式：process 与其他页面一样去水印（默认）；skip 跳过去水印步骤，原样保留；drop 从输出中删除。水印区域内有内容的页面不视为空白页"
+                    },
+                    "duplicate_pages": {
+                        "type": "string",
+                        "enum": ["keep", "report", "drop"],
+                        "default": "keep",
+                        "description": "与前一页重复的页面（常见于自动导出的幻灯片）：keep 不检测（默认）；report 通过感知哈希检测并在结果中标注 duplicate_of；drop 从输出中删除重复页"
+                    },
+                    "adaptive_dpi": {
+                        "type": "integer",
+                        "minimum": 72,
+                        "maximum": 1200,
+                        "description": "自适应DPI：在当前DPI下水印只有几个像素高的页面，以不超过该值的DPI重新渲染后去水印，再缩小回原尺寸，只替换水印区域。其余页面仍按dpi处理（默认不启用）"
+                    },
+                    "cmyk_output": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "用于印刷：所有页面以CMYK渲染（需要Ghostscript），在CMYK下去水印，并以CMYK JPEG写入输出PDF。未设置时，含CMYK内容的页面会经Ghostscript做色彩管理后转为RGB，避免颜色偏移"
+                    },
+                    "steps": {
+                        "type": "array",
+                        "description": "多步处理流程，在一次调用中对每张图片依次执行（只读写一次图片）。remove步骤未设置的字段沿用上面的参数",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "op": { "type": "string", "enum": ["remove", "despeckle"] },
+                                "region": { "type": "array", "items": { "type": "number" }, "minItems": 4, "maxItems": 4 },
+                                "position": { "type": "string", "enum": ["bottom_right", "bottom_left", "top_right", "top_left", "center", "footer", "header"] },
+                                "size": { "type": "array", "items": { "type": "number" }, "minItems": 2, "maxItems": 2 },
+                                "algorithm": { "type": "string", "enum": ["telea", "ns", "solid_fill"] },
+                                "template": { "type": "string" },
+                                "mode": { "type": "string", "enum": ["corner", "grid"] },
+                                "inpaint_radius": { "type": "integer", "minimum": 1, "maximum": 50 },
+                                "mask_padding": { "type": "integer", "minimum": 0, "maximum": 50 },
+                                "feather": { "type": "integer", "minimum": 0, "maximum": 50 },
+                                "kernel_size": { "type": "integer", "description": "despeckle的中值滤波核大小（3-15的奇数，默认3）" }
+                            },
+                            "required": ["op"]
+                        }
                     }
                 })),
-                required: Some(vec!["pdf_path".to_string()]),
+                required: Some(vec![]),
             },
         },
-    ]
-}
-
-/// Handle tool call requests
-pub async fn handle_tool_call(request: CallToolRequestParams) -> Result<CallToolResult> {
-    let arguments = request
-        .arguments
-        .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
-
-    match request.name.as_str() {
-        "pdf_to_images" => handle_pdf_to_images(arguments).await,
-        "remove_watermark" => handle_remove_watermark(arguments).await,
-        "images_to_pdf" => handle_images_to_pdf(arguments).await,
-        "process_pdf" => handle_process_pdf(arguments).await,
-        _ => Err(anyhow::anyhow!("Unknown tool: {}", request.name)),
+        Tool {
+            name: "preview_page".to_string(),
+            title: None,
+            description: Some(
+                "预览单页去水印效果：以低DPI渲染指定页，去除水印，并返回处理前后的缩略图。适合在处理大文档前验证参数。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "pdf_path": {
+                        "type": "string",
+                        "description": "输入PDF文件路径，也支持EPUB和DjVu文档（与pdf_base64二选一）"
+                    },
+                    "pdf_base64": {
+                        "type": "string",
+                        "description": "Base64编码的PDF内容（与pdf_path二选一）"
+                    },
+                    "page": {
+                        "type": "integer",
+                        "default": 1,
+                        "description": "要预览的页码，从1开始（默认1）"
+                    },
+                    "dpi": {
+                        "type": "integer",
+                        "minimum": 72,
+                        "maximum": 1200,
+                        "default": 72,
+                        "description": "预览渲染的DPI（默认72）"
+                    },
+                    "output_dir": {
+                        "type": "string",
+                        "description": "缩略图输出目录（可选，默认使用系统临时目录）"
+                    },
+                    "profile": {
+                        "type": "string",
+                        "description": "命名去水印配置（如 notebooklm），提供region/algorithm/template的默认值（预览不使用配置中的dpi）；显式参数优先"
+                    },
+                    "region": {
+                        "type": "array",
+                        "items": { "type": "number" },
+                        "minItems": 4,
+                        "maxItems": 4,
+                        "description": "水印区域 [x, y, 宽, 高]，均为相对图片尺寸的0-1比例（默认 [0.80, 0.92, 0.20, 0.08]，即右下角）"
+                    },
+                    "position": {
+                        "type": "string",
+                        "enum": ["bottom_right", "bottom_left", "top_right", "top_left", "center", "footer", "header"],
+                        "description": "水印位置预设，替代region：bottom_right、bottom_left、top_right、top_left、center、footer（页脚整行）或 header（页眉整行）"
+                    },
+                    "size": {
+                        "type": "array",
+                        "items": { "type": "number" },
+                        "minItems": 2,
+                        "maxItems": 2,
+                        "description": "position区域的 [宽, 高]，为页面尺寸的百分比（角落默认 [20, 8]，center默认 [40, 20]，footer/header默认 [100, 8]）"
+                    },
+                    "algorithm": {
+                        "type": "string",
+                        "enum": ["telea", "ns", "solid_fill"],
+                        "description": "修复算法：telea、ns（Navier-Stokes）或 solid_fill（用周围背景色直接填充，速度快，适合纯色页面角落），默认telea"
+                    },
+                    "template": {
+                        "type": "string",
+                        "description": "水印模板图片路径（可选），在区域内进行模板匹配以精确定位水印"
+                    },
+                    "mode": {
+                        "type": "string",
+                        "enum": ["corner", "grid"],
+                        "description": "去除模式：corner 处理region内的角落水印（默认）；grid 针对图库照片的半透明居中Logo与重复文字网格，在整张图片上估计水印掩码（忽略region和template）"
+                    },
+                    "inpaint_radius": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": 50,
+                        "default": 5,
+                        "description": "修复时参考的邻域半径（像素，默认5）"
+                    },
+                    "mask_padding": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 50,
+                        "default": 3,
+                        "description": "填充前将检测到的水印掩码向外扩展的像素数（默认3）"
+                    },
+                    "feather": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 50,
+                        "default": 0,
+                        "description": "填充区域边缘与原图渐变融合的宽度（像素，默认0即硬边），可减轻纹理背景上的光晕"
+                    },
+                    "steps": {
+                        "type": "array",
+                        "description": "多步处理流程，在一次调用中对每张图片依次执行（只读写一次图片）。remove步骤未设置的字段沿用上面的参数",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "op": { "type": "string", "enum": ["remove", "despeckle"] },
+                                "region": { "type": "array", "items": { "type": "number" }, "minItems": 4, "maxItems": 4 },
+                                "position": { "type": "string", "enum": ["bottom_right", "bottom_left", "top_right", "top_left", "center", "footer", "header"] },
+                                "size": { "type": "array", "items": { "type": "number" }, "minItems": 2, "maxItems": 2 },
+                                "algorithm": { "type": "string", "enum": ["telea", "ns", "solid_fill"] },
+                                "template": { "type": "string" },
+                                "mode": { "type": "string", "enum": ["corner", "grid"] },
+                                "inpaint_radius": { "type": "integer", "minimum": 1, "maximum": 50 },
+                                "mask_padding": { "type": "integer", "minimum": 0, "maximum": 50 },
+                                "feather": { "type": "integer", "minimum": 0, "maximum": 50 },
+                                "kernel_size": { "type": "integer", "description": "despeckle的中值滤波核大小（3-15的奇数，默认3）" }
+                            },
+                            "required": ["op"]
+                        }
+                    }
+                })),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "evaluate_algorithms".to_string(),
+            title: None,
+            description: Some(
+                "在一个样本页（或图片）上分别运行多种去水印算法，按残留水印（模板匹配/区域检测信号）和填充边缘伪影评分排序，并返回各算法的缩略图，用于为大批量处理选择参数。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "pdf_path": {
+                        "type": "string",
+                        "description": "输入PDF文件路径，也支持EPUB和DjVu文档（与pdf_base64、image_path三选一）"
+                    },
+                    "pdf_base64": {
+                        "type": "string",
+                        "description": "Base64编码的PDF内容（与pdf_path、image_path三选一）"
+                    },
+                    "image_path": {
+                        "type": "string",
+                        "description": "样本图片路径（与pdf_path、pdf_base64三选一）"
+                    },
+                    "page": {
+                        "type": "integer",
+                        "default": 1,
+                        "description": "PDF中用作样本的页码，从1开始（默认1）"
+                    },
+                    "algorithms": {
+                        "type": "array",
+                        "items": { "type": "string", "enum": ["telea", "ns", "solid_fill"] },
+                        "description": "要比较的算法（默认全部）"
+                    },
+                    "dpi": {
+                        "type": "integer",
+                        "minimum": 72,
+                        "maximum": 1200,
+                        "default": 200,
+                        "description": "PDF样本页的渲染DPI，应与批量处理时一致（默认200）"
+                    },
+                    "output_dir": {
+                        "type": "string",
+                        "description": "缩略图输出目录（可选，默认使用系统临时目录）"
+                    },
+                    "profile": {
+                        "type": "string",
+                        "description": "命名去水印配置（如 notebooklm），提供region/template/dpi等默认值（其中的algorithm不使用）；显式参数优先"
+                    },
+                    "region": {
+                        "type": "array",
+                        "items": { "type": "number" },
+                        "minItems": 4,
+                        "maxItems": 4,
+                        "description": "水印区域 [x, y, 宽, 高]，均为相对图片尺寸的0-1比例（默认 [0.80, 0.92, 0.20, 0.08]，即右下角）"
+                    },
+                    "position": {
+                        "type": "string",
+                        "enum": ["bottom_right", "bottom_left", "top_right", "top_left", "center", "footer", "header"],
+                        "description": "水印位置预设，替代region：bottom_right、bottom_left、top_right、top_left、center、footer（页脚整行）或 header（页眉整行）"
+                    },
+                    "size": {
+                        "type": "array",
+                        "items": { "type": "number" },
+                        "minItems": 2,
+                        "maxItems": 2,
+                        "description": "position区域的 [宽, 高]，为页面尺寸的百分比（角落默认 [20, 8]，center默认 [40, 20]，footer/header默认 [100, 8]）"
+                    },
+                    "template": {
+                        "type": "string",
+                        "description": "水印模板图片路径（可选），在区域内进行模板匹配以精确定位水印"
+                    },
+                    "mode": {
+                        "type": "string",
+                        "enum": ["corner", "grid"],
+                        "description": "去除模式：corner 处理region内的角落水印（默认）；grid 针对图库照片的半透明居中Logo与重复文字网格，在整张图片上估计水印掩码（忽略region和template）"
+                    },
+                    "inpaint_radius": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": 50,
+                        "default": 5,
+                        "description": "修复时参考的邻域半径（像素，默认5）"
+                    },
+                    "mask_padding": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 50,
+                        "default": 3,
+                        "description": "填充前将检测到的水印掩码向外扩展的像素数（默认3）"
+                    },
+                    "feather": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 50,
+                        "default": 0,
+                        "description": "填充区域边缘与原图渐变融合的宽度（像素，默认0即硬边），可减轻纹理背景上的光晕"
+                    }
+                })),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "benchmark".to_string(),
+            title: None,
+            description: Some(
+                "用每种可用的去水印后端（subprocess每图一个进程、batch目录批处理、pipe标准输入输出流式处理、embedded内嵌解释器）处理同一组样本页，报告各后端的吞吐量（每秒图片数、每图耗时）和峰值内存占用（仅Linux），用于为当前硬件选择后端。不提供文档时使用合成的带水印页面。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "pdf_path": {
+                        "type": "string",
+                        "description": "样本PDF文件路径，渲染前samples页（与image_path二选一；都不提供时使用合成页面）"
+                    },
+                    "image_path": {
+                        "type": "string",
+                        "description": "样本图片路径，复制samples份（与pdf_path二选一）"
+                    },
+                    "samples": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": 50,
+                        "default": 5,
+                        "description": "每个后端处理的样本页数（默认5）"
+                    },
+                    "backends": {
+                        "type": "array",
+                        "items": { "type": "string", "enum": ["subprocess", "batch", "pipe", "embedded"] },
+                        "description": "要测试的后端（默认全部；embedded需要以embedded-python特性构建）"
+                    },
+                    "dpi": {
+                        "type": "integer",
+                        "minimum": 72,
+                        "maximum": 1200,
+                        "default": 200,
+                        "description": "样本页的渲染DPI（默认200）"
+                    },
+                    "profile": {
+                        "type": "string",
+                        "description": "命名去水印配置（如 notebooklm），提供region/algorithm/template/dpi的默认值；显式参数优先"
+                    },
+                    "region": {
+                        "type": "array",
+                        "items": { "type": "number" },
+                        "minItems": 4,
+                        "maxItems": 4,
+                        "description": "水印区域 [x, y, 宽, 高]，均为相对图片尺寸的0-1比例（默认 [0.80, 0.92, 0.20, 0.08]，即右下角）"
+                    },
+                    "algorithm": {
+                        "type": "string",
+                        "enum": ["telea", "ns", "solid_fill"],
+                        "default": "telea",
+                        "description": "修复算法：telea、ns（Navier-Stokes）或 solid_fill（用周围背景色直接填充，速度快，适合纯色页面角落），默认telea"
+                    },
+                    "mode": {
+                        "type": "string",
+                        "enum": ["corner", "grid"],
+                        "description": "去除模式：corner 处理region内的角落水印（默认）；grid 针对图库照片的半透明居中Logo与重复文字网格，在整张图片上估计水印掩码（忽略region和template）"
+                    }
+                })),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "compare_images".to_string(),
+            title: None,
+            description: Some(
+                "对比原图与处理后的图片，生成高亮变化像素的差异图，并返回变化像素统计，用于检查去水印效果。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "original_path": {
+                        "type": "string",
+                        "description": "原始图片路径"
+                    },
+                    "processed_path": {
+                        "type": "string",
+                        "description": "处理后的图片路径"
+                    },
+                    "diff_path": {
+                        "type": "string",
+                        "description": "差异图输出路径（可选，默认写入系统临时目录）"
+                    },
+                    "threshold": {
+                        "type": "integer",
+                        "default": 10,
+                        "description": "判定像素变化的阈值，0-255（默认10）"
+                    }
+                })),
+                required: Some(vec![
+                    "original_path".to_string(),
+                    "processed_path".to_string(),
+                ]),
+            },
+        },
+        Tool {
+            name: "add_watermark".to_string(),
+            title: None,
+            description: Some(
+                "在图片或PDF上添加可配置的文字/Logo水印，用于生成测试样本和评估去水印效果。".to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "image_path": {
+                        "type": "string",
+                        "description": "单张图片路径（与image_dir、pdf_path三选一）"
+                    },
+                    "image_dir": {
+                        "type": "string",
+                        "description": "图片目录路径（与image_path、pdf_path三选一）"
+                    },
+                    "pdf_path": {
+                        "type": "string",
+                        "description": "PDF文件路径（与image_path、image_dir三选一）"
+                    },
+                    "output_path": {
+                        "type": "string",
+                        "description": "输出路径：单张图片/PDF为文件路径，目录模式为输出目录"
+                    },
+                    "text": {
+                        "type": "string",
+                        "default": "NotebookLM",
+                        "description": "水印文字（默认NotebookLM）"
+                    },
+                    "logo_path": {
+                        "type": "string",
+                        "description": "Logo图片路径（可选，提供时使用Logo代替文字）"
+                    },
+                    "position": {
+                        "type": "string",
+                        "enum": ["bottom_right", "bottom_left", "top_right", "top_left", "center"],
+                        "default": "bottom_right",
+                        "description": "水印位置（默认bottom_right）"
+                    },
+                    "opacity": {
+                        "type": "number",
+                        "default": 0.6,
+                        "description": "水印不透明度，0-1（默认0.6）"
+                    },
+                    "scale": {
+                        "type": "number",
+                        "default": 0.025,
+                        "description": "文字高度占图片高度的比例（默认0.025）"
+                    },
+                    "dpi": {
+                        "type": "integer",
+                        "minimum": 72,
+                        "maximum": 1200,
+                        "default": 200,
+                        "description": "PDF输入的渲染DPI（默认200）"
+                    }
+                })),
+                required: Some(vec!["output_path".to_string()]),
+            },
+        },
+        Tool {
+            name: "convert_image".to_string(),
+            title: None,
+            description: Some(
+                "图片格式转换（PNG/JPEG/WebP/TIFF），支持缩放。支持单张图片或整个目录。".to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "image_path": {
+                        "type": "string",
+                        "description": "单张图片路径（与image_dir二选一）"
+                    },
+                    "image_dir": {
+                        "type": "string",
+                        "description": "图片目录路径（与image_path二选一）"
+                    },
+                    "output_path": {
+                        "type": "string",
+                        "description": "输出路径：单张图片为文件路径（或已存在的目录），目录模式为输出目录"
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["png", "jpeg", "webp", "tiff"],
+                        "description": "目标格式"
+                    },
+                    "quality": {
+                        "type": "integer",
+                        "default": 90,
+                        "description": "JPEG/WebP质量，1-100（默认90）"
+                    },
+                    "max_width": {
+                        "type": "integer",
+                        "description": "最大宽度（可选，超出时等比缩小）"
+                    },
+                    "max_height": {
+                        "type": "integer",
+                        "description": "最大高度（可选，超出时等比缩小）"
+                    },
+                    "scale": {
+                        "type": "number",
+                        "description": "缩放比例（可选，如0.5）"
+                    }
+                })),
+                required: Some(vec!["output_path".to_string(), "format".to_string()]),
+            },
+        },
+        Tool {
+            name: "adjust_image".to_string(),
+            title: None,
+            description: Some(
+                "扫描图片校正：自动纠偏（基于Hough直线）、旋转、裁剪。支持单张图片或整个目录。".to_string(),
+            ),
+            annotations: annotations(Effect::Destructive { idempotent: false }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "image_path": {
+                        "type": "string",
+                        "description": "单张图片路径（与image_dir二选一）"
+                    },
+                    "image_dir": {
+                        "type": "string",
+                        "description": "图片目录路径（与image_path二选一）"
+                    },
+                    "output_dir": {
+                        "type": "string",
+                        "description": "输出目录路径（可选，默认覆盖原图）"
+                    },
+                    "deskew": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "是否自动纠正倾斜（默认false）"
+                    },
+                    "rotate": {
+                        "type": "number",
+                        "description": "顺时针旋转角度（度）"
+                    },
+                    "crop": {
+                        "type": "object",
+                        "properties": {
+                            "x": { "type": "integer" },
+                            "y": { "type": "integer" },
+                            "width": { "type": "integer" },
+                            "height": { "type": "integer" }
+                        },
+                        "required": ["x", "y", "width", "height"],
+                        "description": "裁剪矩形（像素，在纠偏和旋转之后应用）"
+                    },
+                    "auto_crop": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "是否自动裁掉浅色页边距（默认false）"
+                    }
+                })),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "cleanup_temp".to_string(),
+            title: None,
+            description: Some(
+                "列出并删除本服务器创建的中间目录（工作区中的页面图片及临时目录，记录于清单文件中）。支持dry_run仅预览。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Destructive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "dry_run": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "仅列出将被删除的目录，不实际删除（默认false）"
+                    },
+                    "older_than_hours": {
+                        "type": "number",
+                        "description": "只处理创建时间早于指定小时数的目录（可选）"
+                    }
+                })),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "job_status".to_string(),
+            title: None,
+            description: Some(
+                "查询后台任务的状态与进度（含每页状态）。不提供job_id时列出所有任务。".to_string(),
+            ),
+            annotations: annotations(Effect::ReadOnly),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "job_id": {
+                        "type": "string",
+                        "description": "任务ID（可选）"
+                    }
+                })),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "job_result".to_string(),
+            title: None,
+            description: Some("获取已完成后台任务的最终结果。".to_string()),
+            annotations: annotations(Effect::ReadOnly),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "job_id": {
+                        "type": "string",
+                        "description": "任务ID"
+                    }
+                })),
+                required: Some(vec!["job_id".to_string()]),
+            },
+        },
+        Tool {
+            name: "job_cancel".to_string(),
+            title: None,
+            description: Some("取消正在运行的后台任务并终止其子进程。".to_string()),
+            annotations: annotations(Effect::Destructive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "job_id": {
+                        "type": "string",
+                        "description": "任务ID"
+                    }
+                })),
+                required: Some(vec!["job_id".to_string()]),
+            },
+        },
+        Tool {
+            name: "abort_all".to_string(),
+            title: None,
+            description: Some(
+                "紧急停止：取消本会话所有正在运行的后台任务，并终止本会话启动的全部子进程（包括前台调用和文件夹监控的子进程；不影响其他客户端）。返回被取消的任务ID。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Destructive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({})),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "resume_job".to_string(),
+            title: None,
+            description: Some(
+                "从检查点继续因崩溃、重启或失败而中断的后台process_pdf任务（沿用原任务ID，只处理尚未完成的页面）。不提供job_id时列出可恢复的任务。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: false }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "job_id": {
+                        "type": "string",
+                        "description": "要恢复的任务ID（可选）"
+                    },
+                    "discard": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "删除该任务的检查点而不是恢复（默认false）"
+                    }
+                })),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "estimate_job".to_string(),
+            title: None,
+            description: Some(
+                "估算处理PDF所需的时间和磁盘空间（基于页数、页面尺寸和DPI），便于在开始前选择合适的DPI。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::ReadOnly),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "pdf_path": {
+                        "type": "string",
+                        "description": "输入PDF文件路径"
+                    },
+                    "dpi": {
+                        "type": "integer",
+                        "minimum": 72,
+                        "maximum": 1200,
+                        "default": 200,
+                        "description": "计划使用的DPI（默认200）"
+                    }
+                })),
+                required: Some(vec!["pdf_path".to_string()]),
+            },
+        },
+        Tool {
+            name: "check_environment".to_string(),
+            title: None,
+            description: Some(
+                "诊断运行环境：检查python3、OpenCV、pdf2image/poppler、Pillow、img2pdf、ffmpeg、磁盘空间及脚本目录，返回结构化报告。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::ReadOnly),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({})),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "save_profile".to_string(),
+            title: None,
+            description: Some(
+                "保存命名去水印配置（区域、算法、模板、DPI、模式、多步流程），之后可在remove_watermark、process_pdf、preview_page中通过profile参数引用。同名配置会被覆盖。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Destructive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "name": {
+                        "type": "string",
+                        "description": "配置名称，仅限字母、数字、-和_"
+                    },
+                    "region": {
+                        "type": "array",
+                        "items": { "type": "number" },
+                        "minItems": 4,
+                        "maxItems": 4,
+                        "description": "水印区域 [x, y, 宽, 高]，均为相对图片尺寸的0-1比例"
+                    },
+                    "position": {
+                        "type": "string",
+                        "enum": ["bottom_right", "bottom_left", "top_right", "top_left", "center", "footer", "header"],
+                        "description": "水印位置预设，替代region：bottom_right、bottom_left、top_right、top_left、center、footer（页脚整行）或 header（页眉整行）"
+                    },
+                    "size": {
+                        "type": "array",
+                        "items": { "type": "number" },
+                        "minItems": 2,
+                        "maxItems": 2,
+                        "description": "position区域的 [宽, 高]，为页面尺寸的百分比（角落默认 [20, 8]，center默认 [40, 20]，footer/header默认 [100, 8]）"
+                    },
+                    "algorithm": {
+                        "type": "string",
+                        "enum": ["telea", "ns", "solid_fill"],
+                        "description": "修复算法：telea、ns 或 solid_fill"
+                    },
+                    "template": {
+                        "type": "string",
+                        "description": "水印模板图片路径"
+                    },
+                    "dpi": {
+                        "type": "integer",
+                        "minimum": 72,
+                        "maximum": 1200,
+                        "description": "处理PDF时使用的DPI"
+                    },
+                    "mode": {
+                        "type": "string",
+                        "enum": ["corner", "grid"],
+                        "description": "去除模式：corner（角落水印）或 grid（图库照片整图水印）"
+                    },
+                    "inpaint_radius": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": 50,
+                        "default": 5,
+                        "description": "修复时参考的邻域半径（像素，默认5）"
+                    },
+                    "mask_padding": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 50,
+                        "default": 3,
+                        "description": "填充前将检测到的水印掩码向外扩展的像素数（默认3）"
+                    },
+                    "feather": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 50,
+                        "default": 0,
+                        "description": "填充区域边缘与原图渐变融合的宽度（像素，默认0即硬边），可减轻纹理背景上的光晕"
+                    },
+                    "post_filters": {
+                        "type": "array",
+                        "description": "去水印后依次对整张图片执行的清理步骤，适用于噪点较多的扫描件：despeckle 中值滤波去除孤立斑点；denoise 非局部均值降噪；normalize_contrast 拉伸色阶使纸张变白、文字变深",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "op": { "type": "string", "enum": ["despeckle", "denoise", "normalize_contrast"] },
+                                "size": { "type": "integer", "description": "despeckle的中值滤波核大小（3-15的奇数，默认3）" },
+                                "strength": { "type": "integer", "minimum": 1, "maximum": 30, "description": "denoise的降噪强度（1-30，默认10，越大越平滑但细节损失越多）" },
+                                "clip": { "type": "number", "minimum": 0, "maximum": 10, "description": "normalize_contrast忽略的最暗和最亮像素百分比（0-10，默认1）" }
+                            },
+                            "required": ["op"]
+                        }
+                    },
+                    "blank_pages": {
+                        "type": "string",
+                        "enum": ["process", "skip", "drop"],
+                        "default": "process",
+                        "description": "空白页（几乎没有内容的页面）的处理方式：process 与其他页面一样去水印（默认）；skip 跳过去水印步骤，原样保留；drop 从输出中删除。水印区域内有内容的页面不视为空白页"
+                    },
+                    "duplicate_pages": {
+                        "type": "string",
+                        "enum": ["keep", "report", "drop"],
+                        "default": "keep",
+                        "description": "与前一页重复的页面（常见于自动导出的幻灯片）：keep 不检测（默认）；report 通过感知哈希检测并在结果中标注 duplicate_of；drop 从输出中删除重复页"
+                    },
+                    "adaptive_dpi": {
+                        "type": "integer",
+                        "minimum": 72,
+                        "maximum": 1200,
+                        "description": "自适应DPI：在当前DPI下水印只有几个像素高的页面，以不超过该值的DPI重新渲染后去水印，再缩小回原尺寸，只替换水印区域。其余页面仍按dpi处理（默认不启用）"
+                    },
+                    "cmyk_output": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "用于印刷：所有页面以CMYK渲染（需要Ghostscript），在CMYK下去水印，并以CMYK JPEG写入输出PDF。未设置时，含CMYK内容的页面会经Ghostscript做色彩管理后转为RGB，避免颜色偏移"
+                    },
+                    "steps": {
+                        "type": "array",
+                        "description": "多步处理流程，在一次调用中对每张图片依次执行（只读写一次图片）。remove步骤未设置的字段沿用配置中的其他参数",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "op": { "type": "string", "enum": ["remove", "despeckle"] },
+                                "region": { "type": "array", "items": { "type": "number" }, "minItems": 4, "maxItems": 4 },
+                                "position": { "type": "string", "enum": ["bottom_right", "bottom_left", "top_right", "top_left", "center", "footer", "header"] },
+                                "size": { "type": "array", "items": { "type": "number" }, "minItems": 2, "maxItems": 2 },
+                                "algorithm": { "type": "string", "enum": ["telea", "ns", "solid_fill"] },
+                                "template": { "type": "string" },
+                                "mode": { "type": "string", "enum": ["corner", "grid"] },
+                                "inpaint_radius": { "type": "integer", "minimum": 1, "maximum": 50 },
+                                "mask_padding": { "type": "integer", "minimum": 0, "maximum": 50 },
+                                "feather": { "type": "integer", "minimum": 0, "maximum": 50 },
+                                "kernel_size": { "type": "integer", "description": "despeckle的中值滤波核大小（3-15的奇数，默认3）" }
+                            },
+                            "required": ["op"]
+                        }
+                    }
+                })),
+                required: Some(vec!["name".to_string()]),
+            },
+        },
+        Tool {
+            name: "list_profiles".to_string(),
+            title: None,
+            description: Some("列出所有命名去水印配置，包括内置的 notebooklm 配置。".to_string()),
+            annotations: annotations(Effect::ReadOnly),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({})),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "delete_profile".to_string(),
+            title: None,
+            description: Some("删除已保存的命名去水印配置。".to_string()),
+            annotations: annotations(Effect::Destructive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "name": {
+                        "type": "string",
+                        "description": "要删除的配置名称"
+                    }
+                })),
+                required: Some(vec!["name".to_string()]),
+            },
+        },
+        Tool {
+            name: "start_watch".to_string(),
+            title: None,
+            description: Some(
+                "监视目录：新放入的PDF会自动按配置去除水印并合并为新的PDF，每处理完一个文件发送一条 notifications/message 日志通知。返回watch_id，用stop_watch停止。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: false }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "dir": {
+                        "type": "string",
+                        "description": "要监视的目录（只检查该目录下的PDF，不递归）"
+                    },
+                    "output_dir": {
+                        "type": "string",
+                        "description": "处理结果输出目录（可选，默认为 监视目录/cleaned）"
+                    },
+                    "auto_rename": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "输出的 文件名_nowatermark.pdf 已存在时，改写为 文件名_nowatermark_2.pdf 等新名称（默认false）"
+                    },
+                    "profile": {
+                        "type": "string",
+                        "description": "命名去水印配置（如 notebooklm）；也可直接传入region/algorithm/template/mode/steps/dpi"
+                    },
+                    "interval_secs": {
+                        "type": "integer",
+                        "default": 5,
+                        "description": "扫描间隔秒数（默认5）"
+                    },
+                    "process_existing": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "是否同时处理启动时目录中已有的PDF（默认false）"
+                    }
+                })),
+                required: Some(vec!["dir".to_string()]),
+            },
+        },
+        Tool {
+            name: "stop_watch".to_string(),
+            title: None,
+            description: Some(
+                "停止目录监视并返回处理统计。不传watch_id时列出所有正在运行的监视。".to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "watch_id": {
+                        "type": "string",
+                        "description": "start_watch返回的watch_id（可选）"
+                    }
+                })),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "process_zip".to_string(),
+            title: None,
+            description: Some(
+                "处理ZIP压缩包：解压到临时目录，对其中所有图片和PDF去除水印，按原目录结构输出，可选重新打包为ZIP。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "zip_path": {
+                        "type": "string",
+                        "description": "输入ZIP文件路径（与zip_base64二选一）"
+                    },
+                    "zip_base64": {
+                        "type": "string",
+                        "description": "Base64编码的ZIP内容（与zip_path二选一）"
+                    },
+                    "output_dir": {
+                        "type": "string",
+                        "description": "输出目录（可选，默认为 ZIP同目录下的 文件名_cleaned）"
+                    },
+                    "auto_rename": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "默认输出目录已有文件时，改用 文件名_cleaned_2 等新名称（默认false），新路径在结果中返回"
+                    },
+                    "output_zip": {
+                        "type": "string",
+                        "description": "将处理结果重新打包为此ZIP文件（可选）"
+                    },
+                    "dpi": {
+                        "type": "integer",
+                        "minimum": 72,
+                        "maximum": 1200,
+                        "default": 200,
+                        "description": "渲染压缩包内PDF使用的DPI（默认200）"
+                    },
+                    "profile": {
+                        "type": "string",
+                        "description": "命名去水印配置（如 notebooklm）；也可直接传入region/algorithm/template/mode/steps"
+                    }
+                })),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "extract_pdf_images".to_string(),
+            title: None,
+            description: Some(
+                "直接提取PDF中内嵌的原始图片（JPEG原样导出，Flate压缩图片导出为PNG），不重新渲染页面，保留扫描件的原始画质。适合在去水印前处理纯图片扫描PDF。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "pdf_path": {
+                        "type": "string",
+                        "description": "输入PDF文件路径（与pdf_base64二选一）"
+                    },
+                    "pdf_base64": {
+                        "type": "string",
+                        "description": "Base64编码的PDF内容（与pdf_path二选一）"
+                    },
+                    "output_dir": {
+                        "type": "string",
+                        "description": "图片输出目录（可选，默认在PDF同目录下创建 文件名_images）"
+                    },
+                    "auto_rename": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "默认输出目录已有提取结果时，改用 文件名_images_2 等新名称（默认false），新路径在结果中返回"
+                    }
+                })),
+                required: Some(vec![]),
+            },
+        },
+        Tool {
+            name: "redact_regions".to_string(),
+            title: None,
+            description: Some(
+                "用纯色矩形涂黑/涂白图片或PDF页面上的指定区域（不做内容修复），用于去除敏感信息。PDF页面会被栅格化，覆盖区域下的文字不会保留。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::Additive { idempotent: true }),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({
+                    "image_path": {
+                        "type": "string",
+                        "description": "单张图片路径（与image_dir、pdf_path三选一）"
+                    },
+                    "image_dir": {
+                        "type": "string",
+                        "description": "图片目录路径（与image_path、pdf_path三选一）"
+                    },
+                    "pdf_path": {
+                        "type": "string",
+                        "description": "PDF文件路径（与image_path、image_dir三选一）"
+                    },
+                    "output_path": {
+                        "type": "string",
+                        "description": "输出路径：单张图片/PDF为文件路径，目录模式为输出目录"
+                    },
+                    "regions": {
+                        "type": "array",
+                        "description": "要覆盖的矩形列表",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "region": {
+                                    "type": "array",
+                                    "items": { "type": "number" },
+                                    "minItems": 4,
+                                    "maxItems": 4,
+                                    "description": "[x, y, 宽, 高]，均为相对页面尺寸的0-1比例"
+                                },
+                                "page": {
+                                    "type": "integer",
+                                    "description": "页码（从1开始；目录模式为排序后的图片序号）。省略时应用于所有页"
+                                }
+                            },
+                            "required": ["region"]
+                        }
+                    },
+                    "color": {
+                        "type": "string",
+                        "default": "black",
+                        "description": "填充颜色：black、white 或 #rrggbb（默认black）"
+                    },
+                    "dpi": {
+                        "type": "integer",
+                        "minimum": 72,
+                        "maximum": 1200,
+                        "default": 200,
+                        "description": "PDF输入的渲染DPI（默认200）"
+                    }
+                })),
+                required: Some(vec!["output_path".to_string(), "regions".to_string()]),
+            },
+        },
+        Tool {
+            name: "server_stats".to_string(),
+            title: None,
+            description: Some(
+                "服务器运行统计：各工具调用次数、错误数和耗时，已处理页数、写入字节数，以及当前运行的任务和子进程数。"
+                    .to_string(),
+            ),
+            annotations: annotations(Effect::ReadOnly),
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(json!({})),
+                required: Some(vec![]),
+            },
+        },
+    ]
+}
+
+/// Handle tool call requests. Relative path arguments are resolved against
+/// `root`, the session's workspace root, when there is one, and
+/// `resource_uri` inputs are read through `client`.
+pub async fn handle_tool_call(
+    request: CallToolRequestParams,
+    jobs: &JobManager,
+    watches: &WatchManager,
+    root: Option<&Path>,
+    client: &ClientRequester,
+) -> ToolResult {
+    let mut arguments = request
+        .arguments
+        .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
+
+    let resolved = root
+        .map(|root| roots::resolve_relative_paths(&mut arguments, root))
+        .unwrap_or_default();
+    allowlist::check_arguments(&arguments)?;
+    // A dry run plans against the URIs themselves and transfers nothing
+    let staged = if dry_run::is_dry_run(&arguments) {
+        None
+    } else {
+        client_resources::fetch_resource_input(&request.name, &mut arguments, client).await?;
+        object_storage::stage_remote_paths(&request.name, &mut arguments).await?
+    };
+    let started = SystemTime::now();
+    let result = run_tool_call(&request.name, arguments, jobs, watches).await;
+    let mut result = match staged {
+        Some(staged) => staged.finish(result).await?,
+        None => result?,
+    };
+    resource_links::add_resource_links(&mut result, started);
+    if !resolved.is_empty() {
+        roots::echo_resolved_paths(&mut result, resolved);
+    }
+    Ok(result)
+}
+
+async fn run_tool_call(
+    name: &str,
+    arguments: serde_json::Value,
+    jobs: &JobManager,
+    watches: &WatchManager,
+) -> ToolResult {
+    if plugins::find_plugin(name).is_none() {
+        preflight::check_arguments(name, &arguments).await?;
+    }
+    if dry_run::is_dry_run(&arguments) {
+        return dry_run::plan_tool_call(name, &arguments).await;
+    }
+    overwrite::check_arguments(&arguments)?;
+
+    match name {
+        "pdf_to_images" => handle_pdf_to_images(arguments).await,
+        "remove_watermark" => handle_remove_watermark(arguments).await,
+        "images_to_pdf" => handle_images_to_pdf(arguments).await,
+        "process_pdf" => handle_process_pdf(arguments, jobs).await,
+        "preview_page" => handle_preview_page(arguments).await,
+        "evaluate_algorithms" => handle_evaluate_algorithms(arguments).await,
+        "benchmark" => handle_benchmark(arguments).await,
+        "compare_images" => handle_compare_images(arguments).await,
+        "add_watermark" => handle_add_watermark(arguments).await,
+        "convert_image" => handle_convert_image(arguments).await,
+        "adjust_image" => handle_adjust_image(arguments).await,
+        "cleanup_temp" => handle_cleanup_temp(arguments).await,
+        "job_status" => handle_job_status(arguments, jobs).await,
+        "job_result" => handle_job_result(arguments, jobs).await,
+        "job_cancel" => handle_job_cancel(arguments, jobs).await,
+        "abort_all" => handle_abort_all(arguments, jobs).await,
+        "resume_job" => handle_resume_job(arguments, jobs).await,
+        "estimate_job" => handle_estimate_job(arguments).await,
+        "check_environment" => handle_check_environment(arguments).await,
+        "save_profile" => handle_save_profile(arguments).await,
+        "list_profiles" => handle_list_profiles(arguments).await,
+        "delete_profile" => handle_delete_profile(arguments).await,
+        "start_watch" => handle_start_watch(arguments, watches).await,
+        "stop_watch" => handle_stop_watch(arguments, watches).await,
+        "process_zip" => handle_process_zip(arguments).await,
+        "extract_pdf_images" => handle_extract_pdf_images(arguments).await,
+        "redact_regions" => handle_redact_regions(arguments).await,
+        "server_stats" => handle_server_stats(arguments, jobs).await,
+        name => match plugins::find_plugin(name) {
+            Some(plugin) => plugins::handle_plugin_tool(plugin, arguments).await,
+            None => Err(ToolError::invalid_args(format!("Unknown tool: {name}"))),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_schemas_keep_position_size_and_kernel_size_apart() {
+        let mut checked = 0;
+        for tool in builtin_tool_definitions() {
+            let Some(step) = tool
+                .input_schema
+                .properties
+                .as_ref()
+                .and_then(|properties| properties.pointer("/steps/items/properties"))
+            else {
+                continue;
+            };
+            assert_eq!(step["size"]["type"], "array", "{}", tool.name);
+            assert_eq!(step["size"]["minItems"], 2, "{}", tool.name);
+            assert_eq!(step["kernel_size"]["type"], "integer", "{}", tool.name);
+            checked += 1;
+        }
+        assert_eq!(checked, 4);
     }
 }
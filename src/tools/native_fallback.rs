@@ -0,0 +1,327 @@
+//! Python-free fallbacks for the convert and merge steps
+//!
+//! When no Python interpreter can be started, `pdf_to_images` renders pages
+//! by calling `pdftoppm` (poppler) or `mutool draw` (MuPDF) directly, and
+//! `images_to_pdf` merges PNG pages with the built-in [`PdfPageWriter`].
+//! Watermark removal itself still needs Python.
+
+use anyhow::Result;
+use anyhow::bail;
+use rayon::prelude::*;
+use std::cmp::Ordering;
+use std::path::Path;
+use std::path::PathBuf;
+use tokio::process::Command;
+use tracing::info;
+
+use super::error::ToolError;
+use super::glob::glob_match;
+use super::images_to_pdf::ImageOrder;
+use super::images_to_pdf::ImagesToPdfOutput;
+use super::images_to_pdf::PageResize;
+use super::pdf_to_images::PdfToImagesOutput;
+use super::pdf_writer::PageImage;
+use super::pdf_writer::PdfPageWriter;
+use super::pdf_writer::encode_pool;
+use super::pdf_writer::prepare_page;
+use super::subprocess::run_command;
+
+/// Page size of merged images, the same as img2pdf's for images without
+/// DPI metadata
+const MERGE_DPI: u32 = 96;
+
+/// Whether `e`, from running a Python command, means the interpreter itself
+/// could not be started.
+pub(super) fn python_missing(e: &std::io::Error) -> bool {
+    e.kind() == std::io::ErrorKind::NotFound
+}
+
+/// Render the pages of `pdf_path` (up to `last_page`) to
+/// `output_dir/page_NNN.png` with `pdftoppm`, or `mutool draw` when poppler
+/// is not installed either.
+pub(super) async fn rasterize(
+    pdf_path: &Path,
+    output_dir: &Path,
+    dpi: u32,
+    last_page: Option<u32>,
+) -> Result<PdfToImagesOutput> {
+    // pdftoppm names pages `page-1.png` or `page-01.png` depending on the
+    // page count, so it renders into a scratch directory first
+    let scratch = output_dir.join(".pdftoppm");
+    tokio::fs::create_dir_all(&scratch).await?;
+    let mut pdftoppm = Command::new("pdftoppm");
+    pdftoppm.arg("-png").arg("-r").arg(dpi.to_string());
+    if let Some(last_page) = last_page {
+        pdftoppm.arg("-l").arg(last_page.to_string());
+    }
+    pdftoppm.arg(pdf_path).arg(scratch.join("page"));
+
+    let (program, output) = match run_command(&mut pdftoppm).await {
+        Ok(output) => ("pdftoppm", output),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let _ = tokio::fs::remove_dir_all(&scratch).await;
+            let mut mutool = Command::new("mutool");
+            mutool
+                .arg("draw")
+                .arg("-q")
+                .arg("-r")
+                .arg(dpi.to_string())
+                .arg("-o")
+                .arg(output_dir.join("page_%03d.png"))
+                .arg(pdf_path);
+            if let Some(last_page) = last_page {
+                mutool.arg(format!("1-{last_page}"));
+            }
+            match run_command(&mut mutool).await {
+                Ok(output) => ("mutool", output),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    bail!(ToolError::BackendMissing {
+                        backend: "poppler".to_string(),
+                        detail: "Python is not available and neither pdftoppm (poppler) nor \
+                                 mutool (MuPDF) is installed to render the PDF"
+                            .to_string(),
+                    });
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if !output.status.success() {
+        let _ = tokio::fs::remove_dir_all(&scratch).await;
+        bail!(ToolError::script_failed(program, &output.stderr));
+    }
+
+    let images = if program == "pdftoppm" {
+        let mut rendered = Vec::new();
+        let mut entries = tokio::fs::read_dir(&scratch).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let page = path
+                .file_stem()
+                .and_then(|stem| stem.to_str()?.rsplit('-').next()?.parse::<u32>().ok());
+            if let Some(page) = page {
+                rendered.push((page, path));
+            }
+        }
+        rendered.sort();
+        let mut images = Vec::new();
+        for (page, path) in rendered {
+            let image = output_dir.join(format!("page_{page:03}.png"));
+            tokio::fs::rename(&path, &image).await?;
+            images.push(image);
+        }
+        tokio::fs::remove_dir_all(&scratch).await?;
+        images
+    } else {
+        let mut images = Vec::new();
+        let mut entries = tokio::fs::read_dir(output_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if glob_match("page_[0-9][0-9][0-9]*.png", &name) {
+                images.push(entry.path());
+            }
+        }
+        images.sort_by(|a, b| natural_cmp(a, b));
+        images
+    };
+
+    info!("Rendered {} pages with {program}", images.len());
+    Ok(PdfToImagesOutput {
+        output_dir: output_dir.to_path_buf(),
+        page_count: images.len(),
+        log: format!(
+            "Python is not available; rendered {} pages with {program} at {dpi} DPI\n",
+            images.len()
+        ),
+        images,
+    })
+}
+
+/// Merge the PNG images in `image_dir` matching `pattern` into `output_path`
+/// with the built-in PDF writer. Other formats and resizing need Python.
+pub(super) async fn merge(
+    image_dir: &Path,
+    output_path: &Path,
+    pattern: &str,
+    order: ImageOrder,
+    resize: PageResize,
+) -> Result<ImagesToPdfOutput> {
+    if resize.max_width.is_some() || resize.max_height.is_some() || resize.scale.is_some() {
+        bail!(python_required("resizing pages while merging"));
+    }
+
+    let images = matching_images(image_dir, pattern, order).await?;
+    if let Some(other) = images.iter().find(|path| {
+        !path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+    }) {
+        bail!(python_required(&format!(
+            "merging {} (only PNG images are merged without it)",
+            other.display()
+        )));
+    }
+
+    info!(
+        "Python is not available; merging {} PNG images with the built-in writer",
+        images.len()
+    );
+    let output = output_path.to_path_buf();
+    let pages = images.clone();
+    let size_bytes = tokio::task::spawn_blocking(move || -> Result<u64> {
+        let mut writer = PdfPageWriter::create(&output, MERGE_DPI, true)?;
+        // Pages are read and re-encoded in parallel a batch at a time, so
+        // only a few decoded pages are held at once, and written in order
+        let pool = encode_pool();
+        let mut page = 0;
+        for batch in pages.chunks(pool.current_num_threads() * 2) {
+            let prepared: Vec<Result<PageImage>> =
+                pool.install(|| batch.par_iter().map(|path| prepare_page(path)).collect());
+            for image in prepared {
+                page += 1;
+                writer.add_page(page, image?)?;
+            }
+        }
+        writer.finish()
+    })
+    .await??;
+
+    let names: Vec<String> = images
+        .iter()
+        .filter_map(|path| path.file_name())
+        .map(|name| format!("  - {}", name.to_string_lossy()))
+        .collect();
+    Ok(ImagesToPdfOutput {
+        output_path: output_path.to_path_buf(),
+        page_count: images.len(),
+        size_bytes,
+        pages: Vec::new(),
+        timings: None,
+        appended: None,
+        log: format!(
+            "Python is not available; merged {} PNG images with the built-in PDF writer\n{}\n",
+            images.len(),
+            names.join("\n")
+        ),
+    })
+}
+
+/// The files in `image_dir` matching `pattern`, in `order`. Fails when there
+/// are none.
+pub(super) async fn matching_images(
+    image_dir: &Path,
+    pattern: &str,
+    order: ImageOrder,
+) -> Result<Vec<PathBuf>> {
+    let mut images = Vec::new();
+    let mut entries = tokio::fs::read_dir(image_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type().await?.is_file() && glob_match(pattern, &name) {
+            images.push(entry.path());
+        }
+    }
+    if images.is_empty() {
+        bail!(ToolError::invalid_args(format!(
+            "No images matching {pattern} found in {}",
+            image_dir.display()
+        )));
+    }
+    match order {
+        ImageOrder::Name => images.sort(),
+        ImageOrder::Natural => images.sort_by(|a, b| natural_cmp(a, b).then_with(|| a.cmp(b))),
+        ImageOrder::Mtime => {
+            let mut timed = Vec::new();
+            for path in images {
+                let modified = tokio::fs::metadata(&path).await?.modified()?;
+                timed.push((modified, path));
+            }
+            timed.sort_by(|(a, a_path), (b, b_path)| {
+                a.cmp(b).then_with(|| natural_cmp(a_path, b_path))
+            });
+            images = timed.into_iter().map(|(_, path)| path).collect();
+        }
+    }
+    Ok(images)
+}
+
+fn python_required(what: &str) -> ToolError {
+    ToolError::BackendMissing {
+        backend: "python".to_string(),
+        detail: format!("Python is not available, and it is needed for {what}"),
+    }
+}
+
+/// Compare file names with digit runs compared as numbers, so `page_2.png`
+/// comes before `page_10.png`; the same order as `images_to_pdf.py`'s
+/// `natural_key`.
+fn natural_cmp(a: &Path, b: &Path) -> Ordering {
+    let key = |path: &Path| -> Vec<(u8, u128, String)> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut parts = Vec::new();
+        let mut chars = name.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            let digit = c.is_ascii_digit();
+            let mut run = String::new();
+            while let Some(&c) = chars.peek()
+                && c.is_ascii_digit() == digit
+            {
+                run.push(c);
+                chars.next();
+            }
+            parts.push(if digit {
+                (0, run.parse().unwrap_or(u128::MAX), String::new())
+            } else {
+                (1, 0, run.to_lowercase())
+            });
+        }
+        parts
+    };
+    key(a).cmp(&key(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(names: &[&str]) -> Vec<String> {
+        let mut paths: Vec<PathBuf> = names.iter().map(PathBuf::from).collect();
+        paths.sort_by(|a, b| natural_cmp(a, b));
+        paths
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn natural_cmp_orders_numbers_by_value() {
+        assert_eq!(
+            sorted(&["page_10.png", "page_2.png", "page_1.png", "page_100.png"]),
+            ["page_1.png", "page_2.png", "page_10.png", "page_100.png"]
+        );
+        assert_eq!(
+            sorted(&["ch2_p10.png", "ch10_p1.png", "ch2_p9.png"]),
+            ["ch2_p9.png", "ch2_p10.png", "ch10_p1.png"]
+        );
+    }
+
+    #[test]
+    fn natural_cmp_ignores_case_and_directories() {
+        assert_eq!(
+            sorted(&["b.png", "A.png", "c.png"]),
+            ["A.png", "b.png", "c.png"]
+        );
+        assert_eq!(
+            natural_cmp(Path::new("z/page_2.png"), Path::new("a/page_10.png")),
+            Ordering::Less
+        );
+        assert_eq!(
+            natural_cmp(Path::new("page_02.png"), Path::new("page_2.png")),
+            Ordering::Equal
+        );
+    }
+}
@@ -0,0 +1,398 @@
+//! Object storage - `s3://` and `gs://` URIs as tool inputs and outputs
+//!
+//! Remote inputs are downloaded into a workspace directory before the call
+//! runs, and remote outputs are written there first and uploaded once the
+//! call has succeeded. Transfers go through the `aws` and `gcloud storage`
+//! CLIs, so credentials come from their usual environment variables and
+//! config files. A URI ending in `/` (or passed as a `*_dir` argument) is a
+//! prefix and is copied recursively. An input the call cleans in place is
+//! uploaded back like an output. With the `webdav` feature, WebDAV URIs are
+//! staged the same way (see [`webdav`](super::webdav)).
+
+use anyhow::Context;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use serde_json::Value;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::process::Command;
+use tracing::info;
+
+use super::allowlist::is_path_key;
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::overwrite::overwrite_allowed;
+use super::remove_watermark::cleaned_name;
+use super::remove_watermark::in_place_argument;
+use super::subprocess::run_command;
+#[cfg(feature = "webdav")]
+use super::webdav;
+#[cfg(feature = "webdav")]
+use super::webdav::is_webdav;
+use super::workspace::create_temp_dir;
+use super::workspace::remove_temp_dir;
+
+/// Whether `path` is an object storage or WebDAV URI rather than a local
+/// path.
+pub(super) fn is_remote(path: &str) -> bool {
+    path.starts_with("s3://") || path.starts_with("gs://") || is_webdav(path)
+}
+
+#[cfg(not(feature = "webdav"))]
+fn is_webdav(_path: &str) -> bool {
+    false
+}
+
+/// Local stand-ins for the remote arguments of one call
+pub(super) struct StagedCall {
+    dir: PathBuf,
+    /// Local output and the URI it is uploaded to
+    uploads: Vec<(PathBuf, String)>,
+    /// Local path and the URI it stands for, to report URIs in the result
+    replaced: Vec<(String, String)>,
+    /// Input argument the call writes over, uploaded back after the call
+    in_place: Option<&'static str>,
+}
+
+/// Replace every remote path argument with a local path in a fresh
+/// workspace directory, downloading the inputs. Returns `None` when the
+/// call names no remote paths.
+pub(super) async fn stage_remote_paths(
+    name: &str,
+    arguments: &mut Value,
+) -> ToolResult<Option<StagedCall>> {
+    let mut remote = Vec::new();
+    collect_remote(arguments, &mut remote);
+    if remote.is_empty() {
+        return Ok(None);
+    }
+    // The staging directory is gone by the time a job or watch would use it
+    if name == "start_watch" || arguments.get("async").and_then(Value::as_bool) == Some(true) {
+        return Err(ToolError::invalid_args(
+            "Remote URIs cannot be used with async or start_watch",
+        ));
+    }
+
+    let overwrite = overwrite_allowed(arguments);
+    let dir = create_temp_dir("remote").await?;
+    let mut staged = StagedCall {
+        dir,
+        uploads: Vec::new(),
+        replaced: Vec::new(),
+        in_place: in_place_argument(name, arguments),
+    };
+    let result = stage_value(arguments, &mut staged, overwrite).await;
+    if let Err(e) = result {
+        remove_temp_dir(&staged.dir).await;
+        return Err(e);
+    }
+    Ok(Some(staged))
+}
+
+impl StagedCall {
+    /// Upload the outputs of a successful call, point the result at the
+    /// URIs instead of the local copies, and delete the staging directory.
+    pub(super) async fn finish(self, result: ToolResult) -> ToolResult {
+        let result = match result {
+            Ok(mut result) if result.is_error != Some(true) => match self.upload_outputs().await {
+                Ok(()) => {
+                    self.report_uris(&mut result);
+                    Ok(result)
+                }
+                Err(e) => Err(e),
+            },
+            other => other,
+        };
+        remove_temp_dir(&self.dir).await;
+        result
+    }
+
+    async fn upload_outputs(&self) -> ToolResult<()> {
+        for (local, uri) in &self.uploads {
+            // Outputs the call did not write, e.g. in a dry run, are skipped
+            if !local.exists() {
+                continue;
+            }
+            info!("Uploading {} to {uri}", local.display());
+            transfer(&local.to_string_lossy(), uri, local.is_dir()).await?;
+        }
+        Ok(())
+    }
+
+    fn report_uris(&self, result: &mut CallToolResult) {
+        if let Some(structured) = &mut result.structured_content {
+            replace_prefixes(structured, &self.replaced);
+        }
+        for block in &mut result.content {
+            if let ContentBlock::TextContent(text) = block {
+                for (local, uri) in &self.replaced {
+                    text.text = text.text.replace(local.as_str(), uri.trim_end_matches('/'));
+                }
+            }
+        }
+    }
+}
+
+fn collect_remote<'a>(value: &'a Value, remote: &mut Vec<&'a str>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match value.as_str() {
+                    Some(path) if is_path_key(key) && is_remote(path) => remote.push(path),
+                    _ => collect_remote(value, remote),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_remote(item, remote);
+            }
+        }
+        _ => {}
+    }
+}
+
+async fn stage_value(
+    value: &mut Value,
+    staged: &mut StagedCall,
+    overwrite: bool,
+) -> ToolResult<()> {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(uri) if is_path_key(key) && is_remote(uri) => {
+                        *uri = Box::pin(stage_uri(key, uri, staged, overwrite)).await?;
+                    }
+                    _ => Box::pin(stage_value(value, staged, overwrite)).await?,
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                Box::pin(stage_value(item, staged, overwrite)).await?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Local path standing in for `uri`, the argument `key`.
+async fn stage_uri(
+    key: &str,
+    uri: &str,
+    staged: &mut StagedCall,
+    overwrite: bool,
+) -> ToolResult<String> {
+    let is_prefix = key == "dir" || key.ends_with("_dir") || uri.ends_with('/');
+    let is_output =
+        key.starts_with("output_") || key.ends_with("_output_dir") || key == "diff_path";
+    // Fixed width, so no slot path is a prefix of another
+    let slot = staged.dir.join(format!("{:03}", staged.replaced.len()));
+    let local = if is_prefix {
+        slot.clone()
+    } else {
+        let name = uri.rsplit('/').next().unwrap_or_default();
+        slot.join(if name.is_empty() { "object" } else { name })
+    };
+    tokio::fs::create_dir_all(&slot).await?;
+
+    if is_output {
+        if !overwrite && !is_prefix && object_exists(uri).await? {
+            return Err(ToolError::OutputExists {
+                path: PathBuf::from(uri),
+            });
+        }
+        staged.uploads.push((local.clone(), uri.to_string()));
+    } else {
+        info!("Downloading {uri} to {}", local.display());
+        transfer(uri, &local.to_string_lossy(), is_prefix).await?;
+        if !local.exists() {
+            return Err(ToolError::not_found("Object", uri));
+        }
+        if staged.in_place == Some(key) {
+            let (cleaned, target) = in_place_upload(key, &local, uri);
+            if cleaned != local {
+                let path = cleaned.to_string_lossy().to_string();
+                staged.replaced.push((path, target.clone()));
+            }
+            staged.uploads.push((cleaned, target));
+        }
+    }
+
+    let local = local.to_string_lossy().to_string();
+    staged.replaced.push((local.clone(), uri.to_string()));
+    Ok(local)
+}
+
+/// Local result and URI to upload for an input the call cleans in place:
+/// the directory itself, or the cleaned image, which is a `.png` for
+/// HEIC/HEIF/AVIF inputs.
+fn in_place_upload(key: &str, local: &Path, uri: &str) -> (PathBuf, String) {
+    if key == "image_path" {
+        let uri = cleaned_name(Path::new(uri)).to_string_lossy().to_string();
+        (cleaned_name(local), uri)
+    } else {
+        (local.to_path_buf(), uri.to_string())
+    }
+}
+
+/// Copy `from` to `to`, one of which is a URI; prefixes are synced
+/// recursively.
+async fn transfer(from: &str, to: &str, recursive: bool) -> ToolResult<()> {
+    let uri = if is_remote(from) { from } else { to };
+    #[cfg(feature = "webdav")]
+    if is_webdav(uri) {
+        return webdav::transfer(from, to, recursive).await;
+    }
+    let mut cmd = cli(uri);
+    match (uri.starts_with("s3://"), recursive) {
+        (true, true) => cmd.args(["s3", "sync", from, to]),
+        (true, false) => cmd.args(["s3", "cp", from, to]),
+        (false, true) => cmd.args(["storage", "rsync", "--recursive", from, to]),
+        (false, false) => cmd.args(["storage", "cp", from, to]),
+    };
+    let program = cli_name(uri);
+    let output = run_command(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))
+        .await
+        .with_context(|| format!("Failed to execute {program}"))?;
+    if !output.status.success() {
+        return Err(ToolError::SubprocessFailed {
+            script: program.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Whether an object already exists at `uri`.
+async fn object_exists(uri: &str) -> ToolResult<bool> {
+    #[cfg(feature = "webdav")]
+    if is_webdav(uri) {
+        return webdav::exists(uri).await;
+    }
+    let mut cmd = cli(uri);
+    if uri.starts_with("s3://") {
+        cmd.args(["s3", "ls", uri]);
+    } else {
+        cmd.args(["storage", "ls", uri]);
+    }
+    let output = run_command(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))
+        .await
+        .with_context(|| format!("Failed to execute {}", cli_name(uri)))?;
+    Ok(output.status.success() && !output.stdout.is_empty())
+}
+
+fn cli(uri: &str) -> Command {
+    Command::new(cli_name(uri))
+}
+
+fn cli_name(uri: &str) -> &'static str {
+    if uri.starts_with("s3://") {
+        "aws"
+    } else {
+        "gcloud"
+    }
+}
+
+/// Rewrite strings in `value` that start with a staged local path to start
+/// with its URI instead.
+fn replace_prefixes(value: &mut Value, replaced: &[(String, String)]) {
+    match value {
+        Value::String(text) => {
+            for (local, uri) in replaced {
+                let Some(rest) = text.strip_prefix(local.as_str()) else {
+                    continue;
+                };
+                if rest.is_empty() || rest.starts_with(std::path::MAIN_SEPARATOR) {
+                    let rest = rest.trim_start_matches(std::path::MAIN_SEPARATOR);
+                    *text = if rest.is_empty() {
+                        uri.clone()
+                    } else {
+                        format!("{}/{rest}", uri.trim_end_matches('/'))
+                    };
+                    break;
+                }
+            }
+        }
+        Value::Object(map) => {
+            for value in map.values_mut() {
+                replace_prefixes(value, replaced);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                replace_prefixes(item, replaced);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn replace_prefixes_reports_uris() {
+        let sep = std::path::MAIN_SEPARATOR;
+        let local = format!("{sep}ws{sep}remote{sep}000");
+        let replaced = vec![(local.clone(), "s3://scans/clean/".to_string())];
+        let mut value = json!({
+            "output_dir": local,
+            "files": [format!("{local}{sep}a.png"), format!("{local}0{sep}b.png")],
+            "count": 2,
+        });
+        replace_prefixes(&mut value, &replaced);
+        assert_eq!(
+            value,
+            json!({
+                "output_dir": "s3://scans/clean/",
+                // A sibling that merely shares the prefix is left alone
+                "files": ["s3://scans/clean/a.png", format!("{local}0{sep}b.png")],
+                "count": 2,
+            })
+        );
+    }
+
+    #[test]
+    fn in_place_inputs_are_uploaded_back() {
+        let local = Path::new("/ws/remote/000");
+        assert_eq!(
+            in_place_upload("image_dir", local, "s3://scans/inbox/"),
+            (local.to_path_buf(), "s3://scans/inbox/".to_string())
+        );
+        assert_eq!(
+            in_place_upload("image_path", &local.join("a.jpg"), "gs://scans/a.jpg"),
+            (local.join("a.jpg"), "gs://scans/a.jpg".to_string())
+        );
+        assert_eq!(
+            in_place_upload("image_path", &local.join("a.heic"), "gs://scans/a.heic"),
+            (local.join("a.png"), "gs://scans/a.png".to_string())
+        );
+    }
+
+    #[test]
+    fn only_remove_watermark_without_output_dir_is_in_place() {
+        let call = |arguments| in_place_argument("remove_watermark", &arguments);
+        assert_eq!(
+            call(json!({ "image_dir": "s3://scans/" })),
+            Some("image_dir")
+        );
+        assert_eq!(
+            call(json!({ "image_path": "s3://scans/a.png" })),
+            Some("image_path")
+        );
+        assert_eq!(
+            call(json!({ "image_dir": "s3://a/", "output_dir": "s3://b/" })),
+            None
+        );
+        assert_eq!(
+            in_place_argument("process_zip", &json!({ "zip_path": "s3://a.zip" })),
+            None
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Overwrite protection - tools never replace existing files unless asked
+//!
+//! A tool call that would write over an existing file fails with
+//! [`ToolError::OutputExists`] naming that file, unless it passes
+//! `overwrite: true`. File-valued outputs (`output_path`, `output_zip`,
+//! `diff_path`) are checked for every tool before it runs; tools that write
+//! into a directory check the file names they would create. An `output_path`
+//! passed with `append: true` is added to rather than replaced, so it is not
+//! checked.
+//!
+//! Where a tool picks the output location itself (`*_cleaned`, `*_images`,
+//! `*_nowatermark.pdf`), `auto_rename: true` moves a clashing default to the
+//! first free `_2`, `_3`, ... name instead of failing.
+
+use serde_json::Value;
+use std::path::Path;
+use std::path::PathBuf;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+
+/// Arguments holding a single output file
+const OUTPUT_FILE_KEYS: &[&str] = &["output_path", "output_zip", "diff_path"];
+
+/// Whether the call passed `overwrite: true`.
+pub(super) fn overwrite_allowed(arguments: &Value) -> bool {
+    arguments.get("overwrite").and_then(Value::as_bool) == Some(true)
+}
+
+/// Reject the call if a file-valued output argument names an existing file.
+pub(super) fn check_arguments(arguments: &Value) -> ToolResult<()> {
+    let overwrite = overwrite_allowed(arguments);
+    let appending = arguments.get("append").and_then(Value::as_bool) == Some(true);
+    for key in OUTPUT_FILE_KEYS {
+        if appending && *key == "output_path" {
+            continue;
+        }
+        if let Some(path) = arguments.get(*key).and_then(Value::as_str) {
+            check_output_file(Path::new(path), overwrite)?;
+        }
+    }
+    Ok(())
+}
+
+/// Fail if `path` is an existing file and `overwrite` is not set.
+/// Directories are not files to overwrite and pass.
+pub(super) fn check_output_file(path: &Path, overwrite: bool) -> ToolResult<()> {
+    if !overwrite && path.is_file() {
+        return Err(ToolError::OutputExists {
+            path: path.to_path_buf(),
+        });
+    }
+    Ok(())
+}
+
+/// Fail if `dir` already holds a file the tool would create, i.e. one whose
+/// name satisfies `would_write`.
+pub(super) fn check_output_dir(
+    dir: &Path,
+    overwrite: bool,
+    would_write: impl Fn(&str) -> bool,
+) -> ToolResult<()> {
+    if overwrite {
+        return Ok(());
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(());
+    };
+    let mut existing: Vec<_> = entries
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(&would_write)
+        })
+        .collect();
+    existing.sort();
+    match existing.into_iter().next() {
+        Some(path) => Err(ToolError::OutputExists { path }),
+        None => Ok(()),
+    }
+}
+
+/// Check the outputs of a tool that writes each input image under the same
+/// name, either into `output_dir` or, without one, over the input itself.
+pub(super) fn check_image_outputs(
+    input: &Path,
+    output_dir: Option<&Path>,
+    overwrite: bool,
+) -> ToolResult<()> {
+    if overwrite {
+        return Ok(());
+    }
+    if input.is_dir() {
+        let names: Vec<_> = std::fs::read_dir(input)
+            .map(|entries| {
+                entries
+                    .filter_map(std::result::Result::ok)
+                    .filter(|entry| entry.path().is_file())
+                    .map(|entry| entry.file_name())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let dir = output_dir.unwrap_or(input);
+        return check_output_dir(dir, overwrite, |name| {
+            names.iter().any(|input_name| input_name == name)
+        });
+    }
+    match (output_dir, input.file_name()) {
+        (Some(dir), Some(name)) => check_output_file(&dir.join(name), overwrite),
+        _ => check_output_file(input, overwrite),
+    }
+}
+
+/// Whether `name` is a rendered page image, `page_NNN.png` or, for CMYK
+/// output, `page_NNN.jpg`.
+pub(super) fn is_page_image(name: &str) -> bool {
+    name.strip_prefix("page_")
+        .and_then(|rest| {
+            rest.strip_suffix(".png")
+                .or_else(|| rest.strip_suffix(".jpg"))
+        })
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Resolve a tool's default output `path`. With `auto_rename`, a path that
+/// `conflicts` is replaced by the first free `name_2.ext`, `name_3.ext`, ...
+/// variant, returned along with the original so the result can report it.
+pub(super) fn default_output(
+    path: PathBuf,
+    auto_rename: bool,
+    conflicts: impl Fn(&Path) -> bool,
+) -> (PathBuf, Option<PathBuf>) {
+    if !auto_rename || !conflicts(&path) {
+        return (path, None);
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let renamed = (2..)
+        .map(|n| path.with_file_name(format!("{stem}_{n}{extension}")))
+        .find(|candidate| !candidate.exists())
+        .expect("unbounded range");
+    (renamed, Some(path))
+}
@@ -0,0 +1,42 @@
+//! Page cache - cleaned pages reused across `process_pdf` runs
+//!
+//! `process_pdf_to_images.py` stores every cleaned page under a hash of the
+//! PDF bytes, page number, DPI and removal settings, so re-running a job or
+//! iterating on one setting only renders and cleans the pages that changed.
+//! Pages unused for longer than the temp TTL are deleted with the workspace.
+//! In a private workspace the cache directory is owner-only as well.
+
+use std::path::PathBuf;
+use tracing::warn;
+
+use super::scripts::cache_root;
+use super::workspace::create_private_dir;
+use super::workspace::private_workspace;
+use crate::config::config;
+
+/// `WATERMARK_PAGE_CACHE_DIR`, the config file's `page_cache_dir`, or
+/// `watermark-remover/pages` in the user cache dir. `off` disables caching.
+pub fn page_cache_dir() -> Option<PathBuf> {
+    let dir = std::env::var_os("WATERMARK_PAGE_CACHE_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config().page_cache_dir.clone())
+        .unwrap_or_else(|| cache_root().join("watermark-remover").join("pages"));
+    (dir.as_os_str() != "off").then_some(dir)
+}
+
+/// [`page_cache_dir`] for a run to write to. In a private workspace it is
+/// created owner-only first, and pages are not cached when that fails.
+pub(super) fn writable_page_cache_dir() -> Option<PathBuf> {
+    let dir = page_cache_dir()?;
+    if private_workspace()
+        && let Err(e) = create_private_dir(&dir)
+    {
+        warn!(
+            "Not caching pages: {} cannot be made private: {e}",
+            dir.display()
+        );
+        return None;
+    }
+    Some(dir)
+}
@@ -0,0 +1,100 @@
+//! PDF metadata - page count, file size and page sizes via `pdf_info.py`,
+//! or read in-process with lopdf where starting Python is not wanted
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use serde::Deserialize;
+use std::path::Path;
+use std::process::Stdio;
+
+use super::error::ToolError;
+use super::python::python_command;
+use super::script_output::parse_json_result;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+
+#[derive(Deserialize)]
+pub(super) struct PdfInfo {
+    pub page_count: u32,
+    pub file_size: u64,
+    pub pages: Vec<PageSize>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct PageSize {
+    pub width_pt: f64,
+    pub height_pt: f64,
+}
+
+/// Run `pdf_info.py` on `pdf_path`.
+pub(super) async fn read_pdf_info(pdf_path: &Path) -> Result<PdfInfo> {
+    let script_path = scripts_dir()?.join("pdf_info.py");
+
+    let output = run_command(
+        python_command()
+            .arg(&script_path)
+            .arg(pdf_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .await
+    .context("Failed to execute pdf_info.py")?;
+
+    if !output.status.success() {
+        bail!(ToolError::script_failed("pdf_info.py", &output.stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_json_result(&stdout)
+        .context("pdf_info.py printed no JSON_RESULT")
+        .and_then(|v| serde_json::from_value(v).context("Malformed pdf_info.py result"))
+}
+
+/// Read page sizes with lopdf instead of `pdf_info.py`, from each page's
+/// `MediaBox` (inherited from the page tree if need be, US Letter if
+/// missing). Blocking; the whole file is parsed.
+pub(super) fn read_pdf_info_native(pdf_path: &Path) -> Result<PdfInfo> {
+    let document = lopdf::Document::load(pdf_path)
+        .with_context(|| format!("Error reading {}", pdf_path.display()))?;
+    let pages: Vec<PageSize> = document
+        .get_pages()
+        .into_values()
+        .map(|page_id| {
+            let (width_pt, height_pt) = media_box(&document, page_id).unwrap_or((612.0, 792.0));
+            PageSize {
+                width_pt,
+                height_pt,
+            }
+        })
+        .collect();
+    Ok(PdfInfo {
+        page_count: u32::try_from(pages.len()).unwrap_or(u32::MAX),
+        file_size: std::fs::metadata(pdf_path)?.len(),
+        pages,
+    })
+}
+
+/// Width and height in points of a page's `MediaBox`.
+fn media_box(document: &lopdf::Document, page_id: lopdf::ObjectId) -> Option<(f64, f64)> {
+    let mut node = document.get_dictionary(page_id).ok()?;
+    // Guards against a cyclic page tree
+    for _ in 0..32 {
+        if let Ok(media_box) = node.get_deref(b"MediaBox", document) {
+            let values: Vec<f64> = media_box
+                .as_array()
+                .ok()?
+                .iter()
+                .filter_map(|value| document.dereference(value).ok()?.1.as_float().ok())
+                .map(f64::from)
+                .collect();
+            let [x0, y0, x1, y1] = values[..] else {
+                return None;
+            };
+            return Some(((x1 - x0).abs(), (y1 - y0).abs()));
+        }
+        let parent = node.get(b"Parent").ok()?.as_reference().ok()?;
+        node = document.get_dictionary(parent).ok()?;
+    }
+    None
+}
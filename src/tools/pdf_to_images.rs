@@ -2,132 +2,169 @@
 
 use anyhow::Context;
 use anyhow::Result;
+use anyhow::bail;
 use mcp_types::CallToolResult;
 use mcp_types::ContentBlock;
 use mcp_types::TextContent;
 use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
 use std::path::PathBuf;
 use std::process::Stdio;
-use tokio::process::Command;
 use tracing::info;
+use tracing::warn;
+
+use super::document_input::ensure_pdf;
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::inline_input::write_inline_input;
+use super::native_fallback;
+use super::overwrite::check_output_dir;
+use super::overwrite::is_page_image;
+use super::python::python_command;
+use super::quotas::check_render_size;
+use super::script_output::parse_json_result;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+use super::workspace::create_temp_dir;
+use crate::config::config;
+use crate::metrics;
 
 #[derive(Deserialize)]
 struct PdfToImagesArgs {
-    pdf_path: String,
+    pdf_path: Option<String>,
+    pdf_base64: Option<String>,
     output_dir: Option<String>,
+    overwrite: Option<bool>,
     dpi: Option<u32>,
 }
 
-pub async fn handle_pdf_to_images(args: serde_json::Value) -> Result<CallToolResult> {
-    let args: PdfToImagesArgs = serde_json::from_value(args)?;
+/// Result of [`pdf_to_images`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PdfToImagesOutput {
+    pub output_dir: PathBuf,
+    pub page_count: usize,
+    /// One `page_NNN.png` per page, in page order
+    pub images: Vec<PathBuf>,
+    /// Human-readable script output
+    #[serde(skip)]
+    pub log: String,
+}
+
+/// Render every page of a PDF (or EPUB/DjVu document) to PNG.
+///
+/// Without `output_dir` the images go to a new directory in the server workspace.
+/// `dpi` defaults to the config file's default, then 200.
+pub async fn pdf_to_images(
+    pdf_path: &Path,
+    output_dir: Option<&Path>,
+    dpi: Option<u32>,
+) -> Result<PdfToImagesOutput> {
+    render_pdf_pages(pdf_path, output_dir, dpi, None).await
+}
 
-    let pdf_path = PathBuf::from(&args.pdf_path);
+/// [`pdf_to_images`] stopping after `last_page`, when given.
+pub(super) async fn render_pdf_pages(
+    pdf_path: &Path,
+    output_dir: Option<&Path>,
+    dpi: Option<u32>,
+    last_page: Option<u32>,
+) -> Result<PdfToImagesOutput> {
     if !pdf_path.exists() {
-        return Ok(CallToolResult {
-            content: vec![ContentBlock::TextContent(TextContent {
-                r#type: "text".to_string(),
-                text: format!("Error: PDF file not found: {}", args.pdf_path),
-                annotations: None,
-            })],
-            is_error: Some(true),
-            structured_content: None,
-        });
+        bail!(ToolError::not_found("PDF file", pdf_path));
     }
 
-    let dpi = args.dpi.unwrap_or(200);
+    let dpi = dpi.or(config().defaults.dpi).unwrap_or(200);
 
     // Determine output directory
-    let output_dir = if let Some(dir) = args.output_dir {
-        PathBuf::from(dir)
-    } else {
-        let stem = pdf_path.file_stem().unwrap_or_default().to_string_lossy();
-        pdf_path
-            .parent()
-            .unwrap_or(&pdf_path)
-            .join(format!("{stem}_pages"))
+    let output_dir = match output_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => create_temp_dir("pages").await?,
     };
 
     // Create output directory
     tokio::fs::create_dir_all(&output_dir).await?;
 
+    // EPUB and DjVu inputs are converted up front and then handled as PDFs
+    let pdf_path = ensure_pdf(pdf_path).await?;
+    check_render_size(&pdf_path, dpi).await?;
+
     info!(
         "Converting PDF to images: {} -> {:?}",
-        args.pdf_path, output_dir
+        pdf_path.display(),
+        output_dir
     );
 
-    // Get the scripts directory (relative to the binary)
-    let scripts_dir = get_scripts_dir()?;
+    let scripts_dir = scripts_dir()?;
     let script_path = scripts_dir.join("pdf_to_images.py");
 
     // Run Python script
-    let output = Command::new("python3")
-        .arg(&script_path)
-        .arg(&args.pdf_path)
+    let mut cmd = python_command();
+    cmd.arg(&script_path)
+        .arg(&pdf_path)
         .arg(output_dir.to_string_lossy().to_string())
         .arg(dpi.to_string())
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await
-        .context("Failed to execute pdf_to_images.py")?;
+        .stderr(Stdio::piped());
+    if let Some(last_page) = last_page {
+        cmd.arg(last_page.to_string());
+    }
+    let output = match run_command(&mut cmd).await {
+        Err(e) if native_fallback::python_missing(&e) => {
+            warn!("{e}; rendering without Python");
+            let result = native_fallback::rasterize(&pdf_path, &output_dir, dpi, last_page).await?;
+            metrics::add_pages(result.page_count);
+            metrics::add_files_written(&result.images);
+            return Ok(result);
+        }
+        output => output.context("Failed to execute pdf_to_images.py")?,
+    };
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Ok(CallToolResult {
-            content: vec![ContentBlock::TextContent(TextContent {
-                r#type: "text".to_string(),
-                text: format!("Error running pdf_to_images.py: {stderr}"),
-                annotations: None,
-            })],
-            is_error: Some(true),
-            structured_content: None,
-        });
+        bail!(ToolError::script_failed("pdf_to_images.py", &output.stderr));
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let mut result: PdfToImagesOutput = parse_json_result(&stdout)
+        .and_then(|json| serde_json::from_value(json).ok())
+        .context("pdf_to_images.py did not report a result")?;
+    result.log = stdout;
+    metrics::add_pages(result.page_count);
+    metrics::add_files_written(&result.images);
+    Ok(result)
+}
+
+pub async fn handle_pdf_to_images(args: serde_json::Value) -> ToolResult {
+    let args: PdfToImagesArgs = parse_args(args)?;
+
+    let pdf_path = match (&args.pdf_path, &args.pdf_base64) {
+        (Some(path), _) => PathBuf::from(path),
+        (None, Some(data)) => write_inline_input(data, "document", "pdf").await?,
+        (None, None) => {
+            return Err(ToolError::invalid_args(
+                "Either pdf_path or pdf_base64 must be provided",
+            ));
+        }
+    };
+
+    let output_dir = args.output_dir.map(PathBuf::from);
+    if let Some(dir) = &output_dir {
+        check_output_dir(dir, args.overwrite.unwrap_or(false), is_page_image)?;
+    }
+    let result = pdf_to_images(&pdf_path, output_dir.as_deref(), args.dpi).await?;
 
     Ok(CallToolResult {
         content: vec![ContentBlock::TextContent(TextContent {
             r#type: "text".to_string(),
             text: format!(
                 "Successfully converted PDF to images.\nOutput directory: {}\n{}",
-                output_dir.display(),
-                stdout
+                result.output_dir.display(),
+                result.log
             ),
             annotations: None,
         })],
         is_error: Some(false),
-        structured_content: None,
+        structured_content: Some(serde_json::to_value(&result)?),
     })
 }
-
-fn get_scripts_dir() -> Result<PathBuf> {
-    // Try to find scripts directory relative to the executable
-    if let Ok(exe_path) = std::env::current_exe() {
-        // In development: executable is in target/debug or target/release
-        // Scripts are in watermark-remover-mcp-server/scripts
-        if let Some(parent) = exe_path.parent() {
-            // Check if we're in target directory
-            let possible_paths = vec![
-                parent.join("../../../watermark-remover-mcp-server/scripts"),
-                parent.join("../../watermark-remover-mcp-server/scripts"),
-                parent.join("scripts"),
-            ];
-
-            for path in possible_paths {
-                if path.exists() {
-                    return Ok(path.canonicalize()?);
-                }
-            }
-        }
-    }
-
-    // Fallback: check environment variable
-    if let Ok(scripts_dir) = std::env::var("WATERMARK_SCRIPTS_DIR") {
-        return Ok(PathBuf::from(scripts_dir));
-    }
-
-    // Last resort: current directory
-    let cwd = std::env::current_dir()?;
-    Ok(cwd.join("scripts"))
-}
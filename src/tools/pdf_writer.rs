@@ -0,0 +1,569 @@
+//! Streaming PDF writer - appends page images to a PDF as they are produced
+//!
+//! Each page is written to the file as soon as it is added and only its
+//! object offsets are kept, so memory and disk use do not grow with the page
+//! count beyond the output itself. PNG image data is embedded as-is (PDF's
+//! Flate filter with PNG predictors reads the same stream); images with an
+//! alpha channel, a palette or 16-bit samples are re-encoded first. JPEG
+//! pages, which is how CMYK pages are written, are embedded as-is too.
+//!
+//! Pages go to a temporary file next to the output, which replaces the
+//! output in one rename once the PDF is finished. A failed or abandoned run
+//! leaves any earlier file at the output path as it was.
+//!
+//! The output carries no timestamps or document ID, so the same pages added
+//! in the same order always give the same bytes.
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::config::config;
+
+/// Object numbers reserved for the catalog and the page tree, which are
+/// written last.
+const CATALOG_ID: u32 = 1;
+const PAGES_ID: u32 = 2;
+
+pub(super) struct PdfPageWriter {
+    path: PathBuf,
+    /// File being written, renamed to `path` by [`finish`](Self::finish)
+    tmp: PathBuf,
+    out: BufWriter<File>,
+    position: u64,
+    /// Byte offset of every object written so far, indexed by number - 1
+    offsets: Vec<u64>,
+    /// Page object number by page number
+    pages: BTreeMap<u32, u32>,
+    dpi: u32,
+    in_page_order: bool,
+    finished: bool,
+}
+
+static ENCODE_POOL: OnceLock<rayon::ThreadPool> = OnceLock::new();
+
+/// Threads pages are prepared on, from `WATERMARK_ENCODE_THREADS` or the
+/// config file's `encode_threads`, defaulting to the number of CPUs.
+pub(super) fn encode_pool() -> &'static rayon::ThreadPool {
+    ENCODE_POOL.get_or_init(|| {
+        let threads = std::env::var("WATERMARK_ENCODE_THREADS")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .or(config().encode_threads)
+            .unwrap_or(0);
+        // 0 lets rayon use one thread per CPU
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|index| format!("encode-{index}"))
+            .build()
+            .expect("failed to start the page encoding threads")
+    })
+}
+
+/// A page image ready to be embedded, from [`prepare_page`].
+pub(super) struct PageImage {
+    width: u32,
+    height: u32,
+    /// 1 for grayscale, 3 for RGB, 4 for CMYK
+    colors: u8,
+    /// Concatenated IDAT data, or the whole JPEG file
+    data: Vec<u8>,
+    encoding: Encoding,
+}
+
+enum Encoding {
+    Png,
+    /// `inverted` for Adobe CMYK JPEGs, which store the ink values
+    /// complemented
+    Jpeg {
+        inverted: bool,
+    },
+}
+
+impl PdfPageWriter {
+    /// Start a PDF that [`finish`](Self::finish) writes to `path`, and
+    /// write its header. Pages are sized so images rendered at `dpi` keep
+    /// their physical size. With `in_page_order`, the producer must add
+    /// pages in page order so the file is reproducible.
+    pub(super) fn create(path: &Path, dpi: u32, in_page_order: bool) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).context("Error creating output directory")?;
+        }
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let tmp = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+        let file =
+            File::create(&tmp).with_context(|| format!("Error creating {}", tmp.display()))?;
+        let mut writer = PdfPageWriter {
+            path: path.to_path_buf(),
+            tmp,
+            out: BufWriter::new(file),
+            position: 0,
+            // Placeholders for the catalog and page tree
+            offsets: vec![0, 0],
+            pages: BTreeMap::new(),
+            dpi: dpi.max(1),
+            in_page_order,
+            finished: false,
+        };
+        writer.write(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n")?;
+        Ok(writer)
+    }
+
+    /// Whether pages must be added in page order.
+    pub(super) fn in_page_order(&self) -> bool {
+        self.in_page_order
+    }
+
+    /// Number of pages added so far.
+    pub(super) fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Append `image`, from [`prepare_page`], as page `page` (1-based).
+    /// Pages may be added in any order; the page tree lists them by page
+    /// number.
+    pub(super) fn add_page(&mut self, page: u32, image: PageImage) -> Result<()> {
+        let scale = 72.0 / f64::from(self.dpi);
+        let width_pt = f64::from(image.width) * scale;
+        let height_pt = f64::from(image.height) * scale;
+        let color_space = match image.colors {
+            1 => "/DeviceGray",
+            4 => "/DeviceCMYK",
+            _ => "/DeviceRGB",
+        };
+        let filter = match image.encoding {
+            Encoding::Png => format!(
+                "/Filter /FlateDecode \
+                 /DecodeParms << /Predictor 15 /Colors {} /BitsPerComponent 8 /Columns {} >>",
+                image.colors, image.width
+            ),
+            Encoding::Jpeg { inverted: true } => {
+                "/Filter /DCTDecode /Decode [1 0 1 0 1 0 1 0]".to_string()
+            }
+            Encoding::Jpeg { inverted: false } => "/Filter /DCTDecode".to_string(),
+        };
+
+        let image_id = self.write_stream(
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {color_space} \
+                 /BitsPerComponent 8 {filter}",
+                image.width, image.height
+            ),
+            &image.data,
+        )?;
+        let content = format!("q {width_pt:.2} 0 0 {height_pt:.2} 0 0 cm /Im0 Do Q");
+        let content_id = self.write_stream("", content.as_bytes())?;
+        let page_id = self.write_object(&format!(
+            "<< /Type /Page /Parent {PAGES_ID} 0 R /MediaBox [0 0 {width_pt:.2} {height_pt:.2}] \
+             /Resources << /XObject << /Im0 {image_id} 0 R >> >> /Contents {content_id} 0 R >>"
+        ))?;
+        self.pages.insert(page, page_id);
+        Ok(())
+    }
+
+    /// Write the page tree, catalog and cross-reference table, and move the
+    /// file to the output path. Returns the size of the finished file.
+    pub(super) fn finish(mut self) -> Result<u64> {
+        if self.pages.is_empty() {
+            bail!("No pages were added to {}", self.path.display());
+        }
+        let kids: Vec<String> = self.pages.values().map(|id| format!("{id} 0 R")).collect();
+        let pages = format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            kids.len()
+        );
+        self.write_reserved(PAGES_ID, &pages)?;
+        self.write_reserved(
+            CATALOG_ID,
+            &format!("<< /Type /Catalog /Pages {PAGES_ID} 0 R >>"),
+        )?;
+
+        let xref_offset = self.position;
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            xref.push_str(&format!("{offset:010} 00000 n \n"));
+        }
+        xref.push_str(&format!(
+            "trailer\n<< /Size {} /Root {CATALOG_ID} 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+            self.offsets.len() + 1
+        ));
+        self.write(xref.as_bytes())?;
+        self.out
+            .flush()
+            .and_then(|_| std::fs::rename(&self.tmp, &self.path))
+            .with_context(|| format!("Error writing {}", self.path.display()))?;
+        self.finished = true;
+        Ok(self.position)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.out
+            .write_all(bytes)
+            .with_context(|| format!("Error writing {}", self.path.display()))?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn write_object(&mut self, body: &str) -> Result<u32> {
+        self.offsets.push(self.position);
+        let id = self.offsets.len() as u32;
+        self.write(format!("{id} 0 obj\n{body}\nendobj\n").as_bytes())?;
+        Ok(id)
+    }
+
+    fn write_reserved(&mut self, id: u32, body: &str) -> Result<()> {
+        self.offsets[id as usize - 1] = self.position;
+        self.write(format!("{id} 0 obj\n{body}\nendobj\n").as_bytes())
+    }
+
+    fn write_stream(&mut self, dict: &str, data: &[u8]) -> Result<u32> {
+        self.offsets.push(self.position);
+        let id = self.offsets.len() as u32;
+        self.write(
+            format!("{id} 0 obj\n<< {dict} /Length {} >>\nstream\n", data.len()).as_bytes(),
+        )?;
+        self.write(data)?;
+        self.write(b"\nendstream\nendobj\n")?;
+        Ok(id)
+    }
+}
+
+impl Drop for PdfPageWriter {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = std::fs::remove_file(&self.tmp);
+        }
+    }
+}
+
+/// Read the PNG or JPEG at `image_path` and get it ready to embed,
+/// re-encoding a PNG when it has an alpha channel, a palette or 16-bit
+/// samples. Needs no writer, so pages can be prepared in parallel.
+pub(super) fn prepare_page(image_path: &Path) -> Result<PageImage> {
+    let bytes = std::fs::read(image_path)
+        .with_context(|| format!("Error reading {}", image_path.display()))?;
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        return parse_jpeg(bytes);
+    }
+    match parse_png(&bytes)? {
+        Some(image) => Ok(image),
+        None => {
+            parse_png(&reencode_png(&bytes)?)?.context("Re-encoded PNG is still not embeddable")
+        }
+    }
+}
+
+/// Split a PNG into its header fields and IDAT data. Returns `None` when the
+/// image cannot be embedded directly: anything but 8-bit, non-interlaced
+/// grayscale or RGB.
+fn parse_png(bytes: &[u8]) -> Result<Option<PageImage>> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    let Some(mut rest) = bytes.strip_prefix(SIGNATURE) else {
+        bail!("Not a PNG image");
+    };
+
+    let mut header = None;
+    let mut data = Vec::new();
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes(rest[..4].try_into()?) as usize;
+        let kind = &rest[4..8];
+        let Some(chunk) = rest.get(8..8 + length) else {
+            bail!("Truncated PNG image");
+        };
+        match kind {
+            b"IHDR" if length >= 13 => header = Some(chunk.to_vec()),
+            b"IDAT" => data.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        rest = &rest[(12 + length).min(rest.len())..];
+    }
+
+    let header = header.context("PNG image has no IHDR chunk")?;
+    let width = u32::from_be_bytes(header[0..4].try_into()?);
+    let height = u32::from_be_bytes(header[4..8].try_into()?);
+    let (bit_depth, color_type, interlace) = (header[8], header[9], header[12]);
+    let colors = match color_type {
+        0 => 1,
+        2 => 3,
+        _ => return Ok(None),
+    };
+    if bit_depth != 8 || interlace != 0 {
+        return Ok(None);
+    }
+    Ok(Some(PageImage {
+        width,
+        height,
+        colors,
+        data,
+        encoding: Encoding::Png,
+    }))
+}
+
+/// Read the size and components of a JPEG from its frame header, which
+/// PDF's DCT filter decodes as it is. Only 8-bit grayscale, RGB (YCbCr) and
+/// CMYK JPEGs can be embedded.
+fn parse_jpeg(bytes: Vec<u8>) -> Result<PageImage> {
+    let mut adobe = false;
+    let mut rest = &bytes[2..];
+    loop {
+        // Markers may be padded with any number of 0xFF bytes
+        while rest.len() >= 2 && rest[0] == 0xFF && rest[1] == 0xFF {
+            rest = &rest[1..];
+        }
+        let [0xFF, marker, high, low, ..] = *rest else {
+            bail!("JPEG image has no frame header");
+        };
+        let length = usize::from(u16::from_be_bytes([high, low]));
+        let Some(segment) = rest.get(4..2 + length).filter(|_| length >= 2) else {
+            bail!("Truncated JPEG image");
+        };
+        match marker {
+            // APP14, written by Adobe for CMYK JPEGs
+            0xEE if segment.starts_with(b"Adobe") => adobe = true,
+            // Start of frame; DHT (0xC4), JPG (0xC8) and DAC (0xCC) share the range
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                let [precision, h1, h0, w1, w0, colors, ..] = *segment else {
+                    bail!("Truncated JPEG frame header");
+                };
+                if precision != 8 || !matches!(colors, 1 | 3 | 4) {
+                    bail!("Cannot embed a {precision}-bit JPEG with {colors} components");
+                }
+                return Ok(PageImage {
+                    width: u32::from(u16::from_be_bytes([w1, w0])),
+                    height: u32::from(u16::from_be_bytes([h1, h0])),
+                    colors,
+                    encoding: Encoding::Jpeg {
+                        inverted: adobe && colors == 4,
+                    },
+                    data: bytes,
+                });
+            }
+            // Start of scan: the frame header should have come before
+            0xDA => bail!("JPEG image has no frame header"),
+            _ => {}
+        }
+        rest = &rest[2 + length..];
+    }
+}
+
+/// Decode a PNG and encode it again as 8-bit grayscale or RGB without
+/// alpha, compositing any transparency onto white.
+fn reencode_png(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().context("Error decoding PNG")?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let frame = reader
+        .next_frame(&mut pixels)
+        .context("Error decoding PNG")?;
+    pixels.truncate(frame.buffer_size());
+
+    let (color, channels, keep) = match frame.color_type {
+        png::ColorType::Grayscale => (png::ColorType::Grayscale, 1, 1),
+        png::ColorType::GrayscaleAlpha => (png::ColorType::Grayscale, 2, 1),
+        png::ColorType::Rgb => (png::ColorType::Rgb, 3, 3),
+        png::ColorType::Rgba => (png::ColorType::Rgb, 4, 3),
+        png::ColorType::Indexed => bail!("Unexpanded palette PNG"),
+    };
+    let pixels: Vec<u8> = if channels == keep {
+        pixels
+    } else {
+        // Transparent areas become paper white rather than whatever the
+        // color channels hold under them
+        pixels
+            .chunks_exact(channels)
+            .flat_map(|pixel| {
+                let alpha = u32::from(pixel[keep]);
+                pixel[..keep]
+                    .iter()
+                    .map(move |&value| over_white(value, alpha))
+            })
+            .collect()
+    };
+
+    let mut encoded = Vec::new();
+    let mut encoder = png::Encoder::new(&mut encoded, frame.width, frame.height);
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .context("Error encoding PNG")?;
+    Ok(encoded)
+}
+
+/// `value` at opacity `alpha` (0-255) composited onto white.
+fn over_white(value: u8, alpha: u32) -> u8 {
+    ((u32::from(value) * alpha + 255 * (255 - alpha) + 127) / 255) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::Document;
+    use lopdf::Object;
+
+    fn png(color: png::ColorType, width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        let mut encoder = png::Encoder::new(&mut encoded, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(pixels))
+            .unwrap();
+        encoded
+    }
+
+    /// SOI, an optional Adobe APP14 segment and a baseline frame header.
+    fn jpeg(width: u16, height: u16, colors: u8, adobe: bool) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8];
+        if adobe {
+            bytes.extend_from_slice(&[0xFF, 0xEE, 0x00, 0x0E]);
+            bytes.extend_from_slice(b"Adobe\x00\x64\x00\x00\x00\x00\x02");
+        }
+        bytes.extend_from_slice(&[0xFF, 0xC0, 0x00, 8 + 3 * colors, 8]);
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.push(colors);
+        for id in 1..=colors {
+            bytes.extend_from_slice(&[id, 0x11, 0]);
+        }
+        bytes.extend_from_slice(&[0xFF, 0xD9]);
+        bytes
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pdf_writer_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parse_png_keeps_rgb_and_gray_as_is() {
+        let image = parse_png(&png(png::ColorType::Rgb, 3, 2, &[0; 18]))
+            .unwrap()
+            .unwrap();
+        assert_eq!((image.width, image.height, image.colors), (3, 2, 3));
+        assert!(matches!(image.encoding, Encoding::Png));
+        assert!(!image.data.is_empty());
+
+        let image = parse_png(&png(png::ColorType::Grayscale, 5, 4, &[0; 20]))
+            .unwrap()
+            .unwrap();
+        assert_eq!((image.width, image.height, image.colors), (5, 4, 1));
+    }
+
+    #[test]
+    fn parse_png_leaves_alpha_for_reencoding() {
+        let rgba = png(png::ColorType::Rgba, 1, 1, &[0, 0, 0, 0]);
+        assert!(parse_png(&rgba).unwrap().is_none());
+        assert!(parse_png(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn reencode_png_puts_transparency_on_white() {
+        // Transparent black, opaque red and half-transparent black
+        let rgba = png(
+            png::ColorType::Rgba,
+            3,
+            1,
+            &[0, 0, 0, 0, 255, 0, 0, 255, 0, 0, 0, 128],
+        );
+        let mut decoder = png::Decoder::new(std::io::Cursor::new(reencode_png(&rgba).unwrap()));
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(frame.color_type, png::ColorType::Rgb);
+        assert_eq!(
+            &pixels[..frame.buffer_size()],
+            &[255, 255, 255, 255, 0, 0, 127, 127, 127]
+        );
+    }
+
+    #[test]
+    fn parse_jpeg_reads_the_frame_header() {
+        let image = parse_jpeg(jpeg(640, 480, 3, false)).unwrap();
+        assert_eq!((image.width, image.height, image.colors), (640, 480, 3));
+        assert!(matches!(image.encoding, Encoding::Jpeg { inverted: false }));
+
+        let image = parse_jpeg(jpeg(10, 20, 4, true)).unwrap();
+        assert_eq!((image.width, image.height, image.colors), (10, 20, 4));
+        assert!(matches!(image.encoding, Encoding::Jpeg { inverted: true }));
+
+        // Only CMYK Adobe JPEGs are stored inverted
+        let image = parse_jpeg(jpeg(10, 20, 3, true)).unwrap();
+        assert!(matches!(image.encoding, Encoding::Jpeg { inverted: false }));
+    }
+
+    #[test]
+    fn parse_jpeg_rejects_missing_frames() {
+        assert!(parse_jpeg(vec![0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x02]).is_err());
+        assert!(parse_jpeg(vec![0xFF, 0xD8, 0xFF, 0xC0, 0x00, 0x40]).is_err());
+        assert!(parse_jpeg(jpeg(10, 10, 2, false)).is_err());
+    }
+
+    #[test]
+    fn writer_lists_pages_in_page_order() {
+        let dir = scratch_dir("order");
+        let output = dir.join("out.pdf");
+        let mut writer = PdfPageWriter::create(&output, 144, false).unwrap();
+        let page = || parse_png(&png(png::ColorType::Rgb, 288, 144, &[200; 288 * 144 * 3]));
+        writer.add_page(2, page().unwrap().unwrap()).unwrap();
+        writer
+            .add_page(1, parse_jpeg(jpeg(144, 144, 4, true)).unwrap())
+            .unwrap();
+        assert!(!output.exists());
+        let size = writer.finish().unwrap();
+        assert_eq!(size, std::fs::metadata(&output).unwrap().len());
+
+        let document = Document::load(&output).unwrap();
+        let pages = document.get_pages();
+        assert_eq!(pages.len(), 2);
+        let media_box = |page: u32| {
+            let page = document.get_dictionary(pages[&page]).unwrap();
+            page.get(b"MediaBox").unwrap().as_array().unwrap().clone()
+        };
+        // 144 DPI pixels are half a point each
+        assert_eq!(media_box(1)[2], Object::Real(72.0));
+        assert_eq!(media_box(2)[2], Object::Real(144.0));
+
+        let images = document.get_page_images(pages[&1]).unwrap();
+        assert_eq!(
+            images[0].color_space.as_deref(),
+            Some("DeviceCMYK"),
+            "page 1 is the CMYK JPEG"
+        );
+        assert_eq!(
+            images[0].origin_dict.get(b"Filter").unwrap(),
+            &Object::Name(b"DCTDecode".to_vec())
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn abandoned_writer_keeps_the_previous_output() {
+        let dir = scratch_dir("abandoned");
+        let output = dir.join("out.pdf");
+        std::fs::write(&output, b"previous").unwrap();
+        let mut writer = PdfPageWriter::create(&output, 72, true).unwrap();
+        writer
+            .add_page(1, parse_jpeg(jpeg(10, 10, 3, false)).unwrap())
+            .unwrap();
+        drop(writer);
+        assert_eq!(std::fs::read(&output).unwrap(), b"previous");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+//! Plugin tools - user-defined tools declared in the config file
+//!
+//! Each `[[tools]]` entry names a tool, describes its arguments with JSON
+//! schema `properties` / `required`, and gives the command to run:
+//!
+//! ```toml
+//! [[tools]]
+//! name = "remove_stamp"
+//! description = "Remove the red approval stamp from an invoice scan"
+//! command = ["{python}", "/opt/site/remove_stamp.py", "{image_path}", "--out={output_path}"]
+//! required = ["image_path"]
+//!
+//! [tools.properties.image_path]
+//! type = "string"
+//! description = "Scanned invoice image"
+//!
+//! [tools.properties.output_path]
+//! type = "string"
+//! ```
+//!
+//! `{name}` in a command element is replaced by that argument, `{python}` by
+//! the configured interpreter and `{scripts_dir}` by the bundled scripts
+//! directory. An element that refers to an argument the call left out is
+//! dropped. Plugins run like the built-in scripts: in a subprocess slot,
+//! under the process timeout, with the path allowlist and overwrite checks
+//! applied to their arguments.
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use mcp_types::Tool;
+use mcp_types::ToolInputSchema;
+use serde::Deserialize;
+use serde_json::Map;
+use serde_json::Value;
+use std::process::Stdio;
+use tokio::process::Command;
+use tracing::info;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::python::python_interpreter;
+use super::script_output::parse_json_result;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+use crate::config::config;
+
+/// A tool declared in the config file's `[[tools]]`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// JSON schema of each argument
+    #[serde(default)]
+    pub properties: Map<String, Value>,
+    #[serde(default)]
+    pub required: Vec<String>,
+    /// Program and arguments, with `{placeholders}`
+    pub command: Vec<String>,
+}
+
+impl PluginTool {
+    /// Check the declaration: a usable name that does not shadow a built-in
+    /// tool, a command, and required arguments that are declared.
+    pub fn validate(&self, builtin: &[Tool]) -> Result<()> {
+        if self.name.is_empty()
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            bail!(
+                "Invalid tool name {:?}: use letters, digits, - and _",
+                self.name
+            );
+        }
+        if builtin.iter().any(|tool| tool.name == self.name) {
+            bail!("Tool {} is already a built-in tool", self.name);
+        }
+        if self.command.is_empty() {
+            bail!("Tool {} has an empty command", self.name);
+        }
+        if let Some(name) = self
+            .required
+            .iter()
+            .find(|name| !self.properties.contains_key(*name))
+        {
+            bail!("Tool {} requires undeclared argument {name}", self.name);
+        }
+        Ok(())
+    }
+
+    fn definition(&self) -> Tool {
+        Tool {
+            name: self.name.clone(),
+            title: None,
+            description: self.description.clone(),
+            annotations: None,
+            output_schema: None,
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Some(Value::Object(self.properties.clone())),
+                required: Some(self.required.clone()),
+            },
+        }
+    }
+
+    /// The command for one call with `arguments`.
+    fn command(&self, arguments: &Map<String, Value>) -> ToolResult<Command> {
+        let mut parts = Vec::with_capacity(self.command.len());
+        for template in &self.command {
+            if let Some(part) = expand(template, arguments)? {
+                parts.push(part);
+            }
+        }
+        let (program, args) = parts
+            .split_first()
+            .ok_or_else(|| ToolError::invalid_args(format!("{}: empty command", self.name)))?;
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        Ok(cmd)
+    }
+}
+
+/// Definitions of the configured plugin tools.
+pub(super) fn plugin_definitions() -> Vec<Tool> {
+    config().tools.iter().map(PluginTool::definition).collect()
+}
+
+/// The configured plugin called `name`, if any.
+pub(super) fn find_plugin(name: &str) -> Option<&'static PluginTool> {
+    config().tools.iter().find(|tool| tool.name == name)
+}
+
+pub(super) async fn handle_plugin_tool(plugin: &PluginTool, args: Value) -> ToolResult {
+    let arguments = match args {
+        Value::Object(map) => map,
+        Value::Null => Map::new(),
+        _ => return Err(ToolError::invalid_args("Arguments must be an object")),
+    };
+    if let Some(name) = plugin
+        .required
+        .iter()
+        .find(|name| arguments.get(*name).is_none_or(Value::is_null))
+    {
+        return Err(ToolError::invalid_args(format!(
+            "Invalid arguments: missing field `{name}`"
+        )));
+    }
+
+    let mut cmd = plugin.command(&arguments)?;
+    info!("Running plugin tool {}", plugin.name);
+
+    let output = run_command(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))
+        .await
+        .with_context(|| format!("Failed to execute plugin tool {}", plugin.name))?;
+
+    if !output.status.success() {
+        return Err(ToolError::script_failed(&plugin.name, &output.stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let text = match stdout.trim() {
+        "" => format!("{} finished.", plugin.name),
+        text => text.to_string(),
+    };
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text,
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: parse_json_result(&stdout),
+    })
+}
+
+/// Fill in the placeholders of one command element, or `None` when it
+/// refers to an argument that was not given.
+fn expand(template: &str, arguments: &Map<String, Value>) -> ToolResult<Option<String>> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        let name = &rest[start + 1..end];
+        match name {
+            "python" => expanded.push_str(&python_interpreter().program.to_string_lossy()),
+            "scripts_dir" => expanded.push_str(&scripts_dir()?.to_string_lossy()),
+            _ => match arguments.get(name) {
+                None | Some(Value::Null) => return Ok(None),
+                Some(Value::String(value)) => expanded.push_str(value),
+                Some(value @ (Value::Number(_) | Value::Bool(_))) => {
+                    expanded.push_str(&value.to_string())
+                }
+                Some(value) => expanded.push_str(&serde_json::to_string(value)?),
+            },
+        }
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(Some(expanded))
+}
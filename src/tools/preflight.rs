@@ -0,0 +1,272 @@
+//! Pre-flight validation - cheap argument checks before any subprocess runs
+//!
+//! Built-in tool calls are checked before they are dispatched, so a bad
+//! argument fails in milliseconds with [`ToolError::ValidationFailed`] naming
+//! it, instead of after Python has started and printed a traceback:
+//!
+//! - `dpi` must lie within [`MIN_DPI`]–[`MAX_DPI`], and sizes and factors
+//!   such as `scale` and `max_width` must be positive
+//! - output directories (`output_dir`, `images_output_dir`, and the parent of
+//!   `output_path`, `output_zip` and `diff_path`) must be writable, or
+//!   creatable inside a writable directory
+//! - an existing `pdf_path` must be a PDF (or an EPUB/DjVu document) and an
+//!   existing image argument an image, judged by their first bytes
+//! - with a pixel limit configured, the pages of `pdf_path` are measured
+//!   in-process and checked against it at the DPI the tool will render at
+//!
+//! Missing inputs are left for the tool to report as `file_not_found`.
+
+use serde_json::Value;
+use std::io::Read;
+use std::path::Path;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::pdf_info::read_pdf_info_native;
+use super::profiles::resolve_settings;
+use super::quotas::check_page_pixels;
+use super::quotas::max_image_pixels;
+use crate::config::config;
+
+/// Lowest accepted `dpi`
+pub(super) const MIN_DPI: u64 = 72;
+/// Highest accepted `dpi`
+pub(super) const MAX_DPI: u64 = 1200;
+
+/// Numeric arguments that must be greater than zero
+const POSITIVE_KEYS: &[&str] = &["scale", "max_width", "max_height"];
+/// Arguments naming a directory the tool writes into
+const OUTPUT_DIR_KEYS: &[&str] = &["output_dir", "images_output_dir"];
+/// Arguments naming a file the tool writes
+const OUTPUT_FILE_KEYS: &[&str] = &["output_path", "output_zip", "diff_path"];
+/// Arguments naming an input image
+const IMAGE_KEYS: &[&str] = &[
+    "image_path",
+    "original_path",
+    "processed_path",
+    "logo_path",
+    "template",
+];
+/// Tools that render every page of `pdf_path`
+const RENDERING_TOOLS: &[&str] = &["pdf_to_images", "process_pdf"];
+
+/// Validate the arguments of a call to the built-in tool `tool`.
+pub(super) async fn check_arguments(tool: &str, arguments: &Value) -> ToolResult<()> {
+    let dpi = check_dpi(arguments)?;
+    for key in POSITIVE_KEYS {
+        check_positive(key, arguments)?;
+    }
+
+    for key in OUTPUT_DIR_KEYS {
+        if let Some(dir) = arguments.get(*key).and_then(Value::as_str) {
+            check_writable(key, Path::new(dir))?;
+        }
+    }
+    for key in OUTPUT_FILE_KEYS {
+        if let Some(path) = arguments.get(*key).and_then(Value::as_str) {
+            let parent = Path::new(path)
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            check_writable(key, parent)?;
+        }
+    }
+
+    if let Some(path) = arguments.get("pdf_path").and_then(Value::as_str) {
+        let path = Path::new(path);
+        check_pdf(path)?;
+        // Resolving the DPI may read a profile, so only with a limit to check
+        if RENDERING_TOOLS.contains(&tool) && max_image_pixels().is_some() {
+            check_page_size(path, rendering_dpi(tool, arguments, dpi).await).await?;
+        }
+    }
+    for key in IMAGE_KEYS {
+        if let Some(path) = arguments.get(*key).and_then(Value::as_str) {
+            check_image(key, Path::new(path))?;
+        }
+    }
+    Ok(())
+}
+
+/// DPI `tool` renders at, layered the way the tool layers it: the `dpi`
+/// argument, then for `process_pdf` its profile, then the configured default
+/// (`WATERMARK_DEFAULT_DPI` or the config file), then 200.
+async fn rendering_dpi(tool: &str, arguments: &Value, explicit: Option<u32>) -> u32 {
+    let layered = if tool == "process_pdf" {
+        let profile = arguments.get("profile").and_then(Value::as_str);
+        let settings = serde_json::from_value(arguments.clone()).unwrap_or_default();
+        // Bad settings are reported by the tool itself
+        resolve_settings(settings, profile)
+            .await
+            .ok()
+            .and_then(|settings| settings.dpi)
+    } else {
+        None
+    };
+    explicit
+        .or(layered)
+        .or(config().defaults.dpi)
+        .unwrap_or(200)
+}
+
+fn invalid(argument: &str, reason: impl Into<String>) -> ToolError {
+    ToolError::ValidationFailed {
+        argument: argument.to_string(),
+        reason: reason.into(),
+    }
+}
+
+fn check_dpi(arguments: &Value) -> ToolResult<Option<u32>> {
+    let Some(dpi) = arguments.get("dpi").filter(|dpi| !dpi.is_null()) else {
+        return Ok(None);
+    };
+    match dpi.as_u64() {
+        Some(dpi @ MIN_DPI..=MAX_DPI) => Ok(u32::try_from(dpi).ok()),
+        _ => Err(invalid(
+            "dpi",
+            format!("must be an integer from {MIN_DPI} to {MAX_DPI}, got {dpi}"),
+        )),
+    }
+}
+
+fn check_positive(argument: &str, arguments: &Value) -> ToolResult<()> {
+    match arguments.get(argument) {
+        None | Some(Value::Null) => Ok(()),
+        Some(value) if value.as_f64().is_some_and(|number| number > 0.0) => Ok(()),
+        Some(value) => Err(invalid(
+            argument,
+            format!("must be greater than 0, got {value}"),
+        )),
+    }
+}
+
+/// `dir` must be a writable directory, or not exist yet below one.
+fn check_writable(argument: &str, dir: &Path) -> ToolResult<()> {
+    let Some(existing) = dir.ancestors().find(|ancestor| ancestor.exists()) else {
+        return Ok(());
+    };
+    if !existing.is_dir() {
+        return Err(invalid(
+            argument,
+            format!("{} is not a directory", existing.display()),
+        ));
+    }
+    if !is_writable(existing) {
+        return Err(invalid(
+            argument,
+            format!("{} is not writable", existing.display()),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_writable(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `path` is a valid NUL-terminated string for the whole call
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_writable(dir: &Path) -> bool {
+    std::fs::metadata(dir).is_ok_and(|meta| !meta.permissions().readonly())
+}
+
+/// The first bytes of `path`, or `None` if it is not a readable file.
+fn read_head(path: &Path) -> Option<Vec<u8>> {
+    if !path.is_file() {
+        return None;
+    }
+    let mut head = Vec::with_capacity(1024);
+    std::fs::File::open(path)
+        .and_then(|file| file.take(1024).read_to_end(&mut head))
+        .ok()?;
+    Some(head)
+}
+
+fn check_pdf(path: &Path) -> ToolResult<()> {
+    // EPUB and DjVu documents are converted to PDF by the tool
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    if matches!(extension.as_deref(), Some("epub" | "djvu" | "djv")) {
+        return Ok(());
+    }
+    let Some(head) = read_head(path) else {
+        return Ok(());
+    };
+    // Readers accept the header anywhere in the first kilobyte
+    if !head.windows(5).any(|window| window == b"%PDF-") {
+        return Err(invalid(
+            "pdf_path",
+            format!("{} is not a PDF file", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+fn check_image(argument: &str, path: &Path) -> ToolResult<()> {
+    let Some(head) = read_head(path) else {
+        return Ok(());
+    };
+    if image_format(&head).is_none() {
+        return Err(invalid(
+            argument,
+            format!("{} is not a supported image", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Format of an encoded image, from its magic bytes.
+pub(super) fn image_format(head: &[u8]) -> Option<&'static str> {
+    if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("jpeg")
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP") {
+        Some("webp")
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        Some("gif")
+    } else if head.starts_with(b"BM") {
+        Some("bmp")
+    } else if head.starts_with(b"II*\0") || head.starts_with(b"MM\0*") {
+        Some("tiff")
+    } else {
+        heif_format(head)
+    }
+}
+
+/// `heic`, `heif` or `avif` for an ISO-BMFF image, judged by the major brand
+/// of its leading `ftyp` box.
+pub(super) fn heif_format(head: &[u8]) -> Option<&'static str> {
+    if head.get(4..8) != Some(b"ftyp") {
+        return None;
+    }
+    match head.get(8..12)? {
+        b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" => Some("heic"),
+        b"mif1" | b"msf1" => Some("heif"),
+        b"avif" | b"avis" => Some("avif"),
+        _ => None,
+    }
+}
+
+/// Fail early if a page would exceed the pixel limit at `dpi`. Documents
+/// lopdf cannot read are left to the pipeline's own check.
+async fn check_page_size(path: &Path, dpi: u32) -> ToolResult<()> {
+    if max_image_pixels().is_none() || !path.is_file() {
+        return Ok(());
+    }
+    let info = {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || read_pdf_info_native(&path)).await
+    };
+    match info {
+        Ok(Ok(info)) => check_page_pixels(&info, dpi),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,139 @@
+//! Preview Page tool - before/after thumbnails of a single PDF page
+
+use anyhow::Context;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tracing::info;
+
+use super::document_input::ensure_pdf;
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::image_content::image_content_from_file;
+use super::inline_input::write_inline_input;
+use super::overwrite::check_output_dir;
+use super::profiles::RemovalSettings;
+use super::profiles::resolve_settings;
+use super::python::python_command;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+use super::workspace::create_temp_dir;
+
+#[derive(Deserialize)]
+struct PreviewPageArgs {
+    pdf_path: Option<String>,
+    pdf_base64: Option<String>,
+    page: Option<u32>,
+    output_dir: Option<String>,
+    overwrite: Option<bool>,
+    profile: Option<String>,
+    #[serde(flatten)]
+    settings: RemovalSettings,
+}
+
+pub async fn handle_preview_page(args: serde_json::Value) -> ToolResult {
+    let args: PreviewPageArgs = parse_args(args)?;
+
+    let pdf_path = match (&args.pdf_path, &args.pdf_base64) {
+        (Some(path), _) => PathBuf::from(path),
+        (None, Some(data)) => write_inline_input(data, "document", "pdf").await?,
+        (None, None) => {
+            return Err(ToolError::invalid_args(
+                "Either pdf_path or pdf_base64 must be provided",
+            ));
+        }
+    };
+    if !pdf_path.exists() {
+        return Err(ToolError::not_found("PDF file", pdf_path));
+    }
+
+    // Previews stay low-resolution unless a DPI is asked for explicitly,
+    // so a profile's processing DPI is not applied here
+    let dpi = args.settings.dpi.unwrap_or(72);
+    let settings = match resolve_settings(args.settings, args.profile.as_deref()).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            return Err(ToolError::invalid_args(format!("{e}")));
+        }
+    };
+
+    // EPUB and DjVu inputs are converted up front and then handled as PDFs
+    let pdf_path = match ensure_pdf(&pdf_path).await {
+        Ok(path) => path,
+        Err(e) => {
+            return Err(ToolError::invalid_args(format!("{e:#}")));
+        }
+    };
+
+    let page = args.page.unwrap_or(1).max(1);
+
+    let output_dir = match args.output_dir {
+        Some(dir) => {
+            let dir = PathBuf::from(dir);
+            let names = [
+                format!("page_{page:03}_before.png"),
+                format!("page_{page:03}_after.png"),
+            ];
+            check_output_dir(&dir, args.overwrite.unwrap_or(false), |name| {
+                names.iter().any(|n| n == name)
+            })?;
+            tokio::fs::create_dir_all(&dir).await?;
+            dir
+        }
+        None => create_temp_dir("preview").await?,
+    };
+
+    info!(
+        "Previewing page {} of {} at {} DPI",
+        page,
+        pdf_path.display(),
+        dpi
+    );
+
+    let scripts_dir = scripts_dir()?;
+    let script_path = scripts_dir.join("preview_page.py");
+
+    let output = run_command(
+        python_command()
+            .arg(&script_path)
+            .arg(&pdf_path)
+            .arg(&output_dir)
+            .arg(page.to_string())
+            .arg(dpi.to_string())
+            .args(settings.script_args())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .await
+    .context("Failed to execute preview_page.py")?;
+
+    if !output.status.success() {
+        return Err(ToolError::script_failed("preview_page.py", &output.stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let before_path = output_dir.join(format!("page_{page:03}_before.png"));
+    let after_path = output_dir.join(format!("page_{page:03}_after.png"));
+
+    Ok(CallToolResult {
+        content: vec![
+            ContentBlock::TextContent(TextContent {
+                r#type: "text".to_string(),
+                text: format!(
+                    "Preview of page {page} (DPI {dpi}).\nBefore: {}\nAfter: {}\n{stdout}",
+                    before_path.display(),
+                    after_path.display()
+                ),
+                annotations: None,
+            }),
+            ContentBlock::ImageContent(image_content_from_file(&before_path).await?),
+            ContentBlock::ImageContent(image_content_from_file(&after_path).await?),
+        ],
+        is_error: Some(false),
+        structured_content: None,
+    })
+}
@@ -2,145 +2,1560 @@
 
 use anyhow::Context;
 use anyhow::Result;
+use anyhow::bail;
 use mcp_types::CallToolResult;
 use mcp_types::ContentBlock;
 use mcp_types::TextContent;
 use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::path::Path;
 use std::path::PathBuf;
 use std::process::Stdio;
-use tokio::process::Command;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::BufReader;
+use tokio::task::JoinSet;
+use tracing::Instrument;
 use tracing::info;
+use tracing::warn;
+
+use super::checkpoint::Checkpoint;
+use super::checkpoint::CheckpointState;
+use super::color_space::cmyk_pages;
+use super::document_input::ensure_pdf;
+use super::document_input::is_document;
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::images_to_pdf::ImageOrder;
+use super::images_to_pdf::ImagesToPdfOutput;
+use super::images_to_pdf::PageResize;
+use super::images_to_pdf::images_to_pdf;
+use super::inline_input::write_inline_input;
+use super::overwrite::check_output_dir;
+use super::overwrite::check_output_file;
+use super::overwrite::default_output;
+use super::overwrite::is_page_image;
+use super::page_cache::writable_page_cache_dir;
+use super::pdf_info::read_pdf_info;
+use super::pdf_writer::PdfPageWriter;
+use super::pdf_writer::prepare_page;
+use super::profiles::RemovalSettings;
+use super::profiles::resolve_settings;
+use super::python::python_command;
+use super::quotas::DiskBudget;
+use super::quotas::check_page_pixels;
+use super::quotas::max_image_pixels;
+use super::scripts::scripts_dir;
+use super::subprocess::OutputStream;
+use super::subprocess::acquire_process_slot;
+use super::subprocess::inherit_output;
+use super::subprocess::isolate_process_group;
+use super::subprocess::kill_process_group;
+use super::subprocess::log_output_line;
+use super::subprocess::max_processes;
+use super::subprocess::process_timeout;
+use super::subprocess::spawn_error;
+use super::subprocess::timeout_error;
+use super::subprocess::track_process_group;
+use super::text_layer::copy_text_layer;
+use super::thumbnails::page_thumbnails;
+use super::thumbnails::sample_page;
+use super::verify_output::OutputVerification;
+use super::verify_output::verify_pdf;
+use super::webhook::check_webhook_url;
+use super::workspace::create_temp_dir;
+use super::workspace::remove_temp_dir;
+use crate::jobs::JobManager;
+use crate::jobs::JobProgress;
+use crate::metrics;
+
+/// PDF being written by [`process_pdf_to_pdf`], shared by the page workers
+type PageSink = Arc<Mutex<PdfPageWriter>>;
 
 #[derive(Deserialize)]
 struct ProcessPdfArgs {
-    pdf_path: String,
-    images_output_dir: String,
-    dpi: Option<u32>,
+    pdf_path: Option<String>,
+    pdf_base64: Option<String>,
+    output_path: Option<String>,
+    images_output_dir: Option<String>,
+    overwrite: Option<bool>,
+    auto_rename: Option<bool>,
+    #[serde(rename = "async")]
+    run_async: Option<bool>,
+    keep_intermediates: Option<bool>,
+    deterministic: Option<bool>,
+    text_layer: Option<bool>,
+    webhook_url: Option<String>,
+    thumbnails: Option<bool>,
+    profile: Option<String>,
+    #[serde(flatten)]
+    settings: RemovalSettings,
+}
+
+/// One `process_pdf` call: render and clean every page into `pages_dir`,
+/// then merge the pages into `output_path`.
+#[derive(Clone, Serialize, Deserialize)]
+pub(super) struct PdfJob {
+    pub pdf_path: PathBuf,
+    /// Directory the cleaned `page_NNN.png` (`.jpg` with `cmyk_output`)
+    /// images are written to
+    pub pages_dir: PathBuf,
+    /// Whether `pages_dir` is scratch space, deleted once the PDF is written
+    #[serde(default)]
+    pub scratch_pages: bool,
+    pub output_path: PathBuf,
+    pub settings: RemovalSettings,
+    /// Keep intermediate files, such as the PDF converted from an EPUB
+    #[serde(default)]
+    pub keep_intermediates: bool,
+    /// Write a reproducible PDF
+    #[serde(default)]
+    pub deterministic: bool,
+    /// Copy the input's text onto the cleaned pages as invisible text
+    #[serde(default)]
+    pub text_layer: bool,
+    /// URL the job summary is POSTed to when the job finishes
+    #[serde(default)]
+    pub webhook_url: Option<String>,
 }
 
-pub async fn handle_process_pdf(args: serde_json::Value) -> Result<CallToolResult> {
-    let args: ProcessPdfArgs = serde_json::from_value(args)?;
+pub async fn handle_process_pdf(args: serde_json::Value, jobs: &JobManager) -> ToolResult {
+    let args: ProcessPdfArgs = parse_args(args)?;
 
-    let pdf_path = PathBuf::from(&args.pdf_path);
+    let pdf_path = match (&args.pdf_path, &args.pdf_base64) {
+        (Some(path), _) => PathBuf::from(path),
+        (None, Some(data)) => write_inline_input(data, "document", "pdf").await?,
+        (None, None) => {
+            return Err(ToolError::invalid_args(
+                "Either pdf_path or pdf_base64 must be provided",
+            ));
+        }
+    };
     if !pdf_path.exists() {
-        return Ok(CallToolResult {
-            content: vec![ContentBlock::TextContent(TextContent {
-                r#type: "text".to_string(),
-                text: format!("Error: PDF file not found: {}", args.pdf_path),
-                annotations: None,
-            })],
-            is_error: Some(true),
-            structured_content: None,
-        });
+        return Err(ToolError::not_found("PDF file", pdf_path));
     }
 
-    let output_dir = PathBuf::from(&args.images_output_dir);
-    let dpi = args.dpi.unwrap_or(200);
+    let settings = resolve_settings(args.settings, args.profile.as_deref()).await?;
+
+    if let Some(url) = &args.webhook_url {
+        if !args.run_async.unwrap_or(false) {
+            return Err(ToolError::invalid_args("webhook_url requires async: true"));
+        }
+        check_webhook_url(url)?;
+    }
+    let thumbnails = args.thumbnails.unwrap_or(false);
+    if thumbnails && args.run_async.unwrap_or(false) {
+        return Err(ToolError::invalid_args(
+            "thumbnails are only attached to synchronous results; leave out async",
+        ));
+    }
+
+    let overwrite = args.overwrite.unwrap_or(false);
+    let (output_path, renamed_from) = match &args.output_path {
+        Some(path) => (PathBuf::from(path), None),
+        None => {
+            let stem = pdf_path.file_stem().unwrap_or_default().to_string_lossy();
+            default_output(
+                pdf_path.with_file_name(format!("{stem}_nowatermark.pdf")),
+                !overwrite && args.auto_rename.unwrap_or(false),
+                Path::exists,
+            )
+        }
+    };
+    check_output_file(&output_path, overwrite)?;
+
+    let images_output_dir = args.images_output_dir.map(PathBuf::from);
+    if let Some(dir) = &images_output_dir {
+        check_output_dir(dir, overwrite, is_page_image)?;
+        std::fs::create_dir_all(dir).context("Error creating output directory")?;
+    }
+    let keep_intermediates = args.keep_intermediates.unwrap_or(false);
+    let deterministic = args.deterministic.unwrap_or(false);
+    let text_layer = args.text_layer.unwrap_or(false);
+    if text_layer && is_document(&pdf_path) {
+        return Err(ToolError::invalid_args(
+            "text_layer copies text from a PDF input; EPUB and DjVu inputs have none to copy",
+        ));
+    }
 
     info!(
-        "Processing PDF: {} -> images in {}",
-        args.pdf_path,
-        output_dir.display()
+        "Processing PDF: {} -> {}",
+        pdf_path.display(),
+        output_path.display()
     );
 
-    // Create output directory
-    if let Err(e) = std::fs::create_dir_all(&output_dir) {
+    if args.run_async.unwrap_or(false) {
+        // Pages go to disk so an interrupted job can be resumed
+        let (pages_dir, scratch_pages) = match images_output_dir {
+            Some(dir) => (dir, false),
+            None => (create_temp_dir("pages").await?, !keep_intermediates),
+        };
+        let job = PdfJob {
+            pdf_path,
+            pages_dir,
+            scratch_pages,
+            output_path: output_path.clone(),
+            settings,
+            keep_intermediates,
+            deterministic,
+            text_layer,
+            webhook_url: args.webhook_url,
+        };
+        let webhook = job.webhook_url.clone();
+        let session = jobs.session_id().to_string();
+        let job_id = jobs.spawn("process_pdf", webhook, move |progress| async move {
+            let checkpoint = Checkpoint::create(progress.job_id(), &job, &session);
+            run_process_pdf_job(job, progress, checkpoint).await
+        });
+        let mut result = json!({ "job_id": job_id, "output_path": output_path });
+        if let Some(original) = renamed_from {
+            result["renamed_from"] = json!(original);
+        }
         return Ok(CallToolResult {
             content: vec![ContentBlock::TextContent(TextContent {
                 r#type: "text".to_string(),
-                text: format!("Error creating output directory: {e}"),
+                text: format!(
+                    "Started background job {job_id}.\nPoll job_status / job_result with this job_id, or stop it with job_cancel."
+                ),
                 annotations: None,
             })],
-            is_error: Some(true),
-            structured_content: None,
+            is_error: Some(false),
+            structured_content: Some(result),
         });
     }
 
-    let scripts_dir = get_scripts_dir()?;
-    let script_path = scripts_dir.join("process_pdf_to_images.py");
+    // Pages kept for debugging go to the workspace like scratch pages would
+    let images_output_dir = match images_output_dir {
+        None if keep_intermediates => Some(create_temp_dir("pages").await?),
+        dir => dir,
+    };
 
-    let output = Command::new("python3")
-        .arg(&script_path)
-        .arg(&args.pdf_path)
-        .arg(output_dir.to_string_lossy().to_string())
-        .arg(dpi.to_string())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await
-        .context("Failed to execute process_pdf_to_images.py")?;
+    // Without a directory to keep them in, pages are streamed into the
+    // output PDF
+    let result = match &images_output_dir {
+        None => process_pdf_to_pdf(&pdf_path, &output_path, &settings, deterministic).await,
+        Some(dir) => {
+            let pattern = page_pattern(&settings);
+            match run_process_pdf(
+                pdf_path.clone(),
+                dir.clone(),
+                settings,
+                None,
+                keep_intermediates,
+            )
+            .await
+            {
+                Ok(pages) => {
+                    let merge_started = Instant::now();
+                    images_to_pdf(
+                        dir,
+                        &output_path,
+                        Some(&pattern),
+                        ImageOrder::Natural,
+                        PageResize::default(),
+                        deterministic,
+                    )
+                    .await
+                    .map(|pdf| pages.into_pdf_output(pdf, merge_started.elapsed()))
+                }
+                Err(e) => Err(e),
+            }
+        }
+    };
+    let sample = match &result {
+        Ok(output) if thumbnails => sample_page(&output.pages),
+        _ => None,
+    };
+    let text_source = text_layer.then_some(pdf_path.as_path());
+    let mut result = process_pdf_result(
+        result,
+        images_output_dir.as_deref(),
+        renamed_from,
+        text_source,
+    )
+    .await?;
+    if let Some(page) = sample {
+        // Thumbnails are a convenience; the PDF is written either way
+        let thumbnails = match ensure_pdf(&pdf_path).await {
+            Ok(input) => page_thumbnails(&input, &output_path, page).await,
+            Err(e) => Err(e),
+        };
+        match thumbnails {
+            Ok(blocks) => result.content.extend(blocks),
+            Err(e) => warn!("Could not render thumbnails of page {}: {e:#}", page.0),
+        }
+    }
+    Ok(result)
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Ok(CallToolResult {
-            content: vec![ContentBlock::TextContent(TextContent {
-                r#type: "text".to_string(),
-                text: format!("Error running process_pdf_to_images.py: {stderr}"),
-                annotations: None,
-            })],
-            is_error: Some(true),
-            structured_content: None,
-        });
+/// Result of [`process_pdf`].
+#[derive(Clone, Debug, Serialize)]
+pub struct ProcessPdfOutput {
+    pub output_dir: PathBuf,
+    /// Cleaned `page_NNN.png` images, in page order
+    pub images: Vec<PathBuf>,
+    /// What happened to each page, in page order
+    pub pages: Vec<PageResult>,
+    /// Where the time went
+    pub timings: StepTimings,
+    /// Human-readable script output
+    #[serde(skip)]
+    pub log: String,
+}
+
+impl ProcessPdfOutput {
+    /// Combine with the result of merging the pages into `pdf`, which took
+    /// `merge`.
+    fn into_pdf_output(self, pdf: ImagesToPdfOutput, merge: Duration) -> ImagesToPdfOutput {
+        let mut timings = self.timings;
+        timings.add_merge(merge);
+        ImagesToPdfOutput {
+            pages: self.pages,
+            timings: Some(timings),
+            log: self.log,
+            ..pdf
+        }
     }
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+/// How long each step of a `process_pdf` run took.
+///
+/// Conversion and removal are summed over pages, so with several workers
+/// they can add up to more than `total_ms`, the wall-clock time of the run.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StepTimings {
+    /// Rendering pages to images
+    pub conversion_ms: u64,
+    /// Removing watermarks and saving the cleaned pages
+    pub removal_ms: u64,
+    /// Writing the cleaned pages into the output PDF
+    pub merge_ms: u64,
+    pub total_ms: u64,
+    /// Pages rendered and cleaned per second of `total_ms`; resumed, cached
+    /// and failed pages are not counted
+    pub pages_per_second: f64,
+    #[serde(skip)]
+    pages: usize,
+}
 
-    // Count output images
-    let image_count = std::fs::read_dir(&output_dir)
-        .map(|entries| {
-            entries
-                .filter_map(std::result::Result::ok)
-                .filter(|e| {
-                    e.path()
-                        .extension()
-                        .map(|ext| ext == "png")
-                        .unwrap_or(false)
-                })
-                .count()
-        })
-        .unwrap_or(0);
+impl StepTimings {
+    fn add_page(&mut self, conversion_ms: u64, removal_ms: u64) {
+        self.conversion_ms += conversion_ms;
+        self.removal_ms += removal_ms;
+        self.pages += 1;
+    }
+
+    fn add_merge(&mut self, merge: Duration) {
+        self.merge_ms += millis(merge);
+        self.set_total(Duration::from_millis(self.total_ms) + merge);
+    }
+
+    fn set_total(&mut self, total: Duration) {
+        self.total_ms = millis(total);
+        self.pages_per_second = if total.is_zero() {
+            0.0
+        } else {
+            // Two decimals are plenty for a rate
+            (self.pages as f64 / total.as_secs_f64() * 100.0).round() / 100.0
+        };
+    }
+}
+
+/// Outcome of one page of a `process_pdf` run, or of one image of a
+/// `remove_watermark` run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageStatus {
+    WatermarkRemoved,
+    NoWatermark,
+    /// Blank page kept as rendered, without the removal step
+    Blank,
+    /// Blank or duplicate page left out of the output
+    Dropped,
+    /// Finished by an earlier attempt of a resumed job
+    Resumed,
+    /// Could not be rendered or cleaned; the other pages went ahead
+    Failed,
+}
+
+impl PageStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            PageStatus::WatermarkRemoved => "watermark_removed",
+            PageStatus::NoWatermark => "no_watermark",
+            PageStatus::Blank => "blank",
+            PageStatus::Dropped => "dropped",
+            PageStatus::Resumed => "resumed",
+            PageStatus::Failed => "failed",
+        }
+    }
+}
+
+/// One page of a `process_pdf` run, as reported in its structured result.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PageResult {
+    /// 1-based page number
+    pub page: u32,
+    pub status: PageStatus,
+    /// Whether the cleaned page was reused from the page cache
+    #[serde(default)]
+    pub cached: bool,
+    /// The cleaned page image, unless it was streamed into the output PDF
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_path: Option<PathBuf>,
+    /// Time the script spent on the page, rendering included. The first
+    /// page of each script run also carries the interpreter's start-up.
+    pub duration_ms: u64,
+    /// Why a `failed` page failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Earlier page this one repeats, with `duplicate_pages` set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<u32>,
+    /// DPI the watermark was removed at, when `adaptive_dpi` re-rendered
+    /// the page for a watermark too small at the run's DPI
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removal_dpi: Option<u32>,
+    /// Whether the page draws in CMYK, so it was rendered with Ghostscript's
+    /// color management where available
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cmyk: bool,
+}
 
+/// Render a PDF (or EPUB/DjVu document) and remove the watermark from every
+/// page, writing `page_NNN.png` files to `images_output_dir`.
+///
+/// `settings` are used as given; call [`resolve_settings`] first to apply a
+/// profile and the config defaults.
+pub async fn process_pdf(
+    pdf_path: &Path,
+    images_output_dir: &Path,
+    settings: &RemovalSettings,
+) -> Result<ProcessPdfOutput> {
+    if !pdf_path.exists() {
+        bail!(ToolError::not_found("PDF file", pdf_path));
+    }
+    tokio::fs::create_dir_all(images_output_dir)
+        .await
+        .context("Error creating output directory")?;
+    run_process_pdf(
+        pdf_path.to_path_buf(),
+        images_output_dir.to_path_buf(),
+        settings.clone(),
+        None,
+        false,
+    )
+    .await
+}
+
+/// The tool result of a finished run, once the text of `text_source` (with
+/// `text_layer`) has been copied onto its pages and its PDF has been read
+/// back and checked by [`verify_pdf`]. A PDF that fails the check makes the
+/// call an error, with the verification in the structured result.
+async fn process_pdf_result(
+    result: Result<ImagesToPdfOutput>,
+    images_output_dir: Option<&Path>,
+    renamed_from: Option<PathBuf>,
+    text_source: Option<&Path>,
+) -> ToolResult {
+    let mut result = result?;
+    let written: Vec<u32> = result
+        .pages
+        .iter()
+        .filter(|page| !matches!(page.status, PageStatus::Dropped | PageStatus::Failed))
+        .map(|page| page.page)
+        .collect();
+
+    let mut text_pages = None;
+    if let Some(source) = text_source {
+        let (source, output, pages) = (
+            source.to_path_buf(),
+            result.output_path.clone(),
+            written.clone(),
+        );
+        let copied = tokio::task::spawn_blocking(move || copy_text_layer(&source, &output, &pages))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|copied| copied);
+        match copied {
+            Ok(pages) => {
+                text_pages = Some(pages);
+                result.size_bytes = tokio::fs::metadata(&result.output_path).await?.len();
+            }
+            // The cleaned PDF is still good without the text
+            Err(e) => warn!("Could not copy the text layer: {e:#}"),
+        }
+    }
+
+    let verification = verify_pdf(&result.output_path, written.len()).await;
+    if !verification.passed() {
+        warn!(
+            "{} failed verification: {}",
+            result.output_path.display(),
+            verification_summary(&verification)
+        );
+    }
+    let mut text = format!(
+        "Successfully processed PDF and removed watermarks!\n\nOutput PDF: {}\nPages: {}\nSize: {} bytes",
+        result.output_path.display(),
+        result.page_count,
+        result.size_bytes
+    );
+    if let Some(original) = &renamed_from {
+        text.push_str(&format!(
+            "\n{} already existed, so the output was renamed",
+            original.display()
+        ));
+    }
+    if let Some(dir) = images_output_dir {
+        text.push_str(&format!("\nPage images: {}", dir.display()));
+    }
+    if let Some(timings) = &result.timings {
+        text.push_str(&format!(
+            "\nTime: {:.1}s (conversion {:.1}s, removal {:.1}s, merge {:.1}s; {} pages/s)",
+            timings.total_ms as f64 / 1000.0,
+            timings.conversion_ms as f64 / 1000.0,
+            timings.removal_ms as f64 / 1000.0,
+            timings.merge_ms as f64 / 1000.0,
+            timings.pages_per_second
+        ));
+    }
+    let blank = |status| {
+        result
+            .pages
+            .iter()
+            .filter(|page| page.status == status)
+            .count()
+    };
+    let duplicates: Vec<&PageResult> = result
+        .pages
+        .iter()
+        .filter(|page| page.duplicate_of.is_some())
+        .collect();
+    let dropped_duplicates = duplicates
+        .iter()
+        .filter(|page| page.status == PageStatus::Dropped)
+        .count();
+    match (
+        blank(PageStatus::Blank),
+        blank(PageStatus::Dropped) - dropped_duplicates,
+    ) {
+        (0, 0) => {}
+        (kept, 0) => text.push_str(&format!("\nBlank pages kept without cleaning: {kept}")),
+        (0, dropped) => text.push_str(&format!("\nBlank pages dropped: {dropped}")),
+        (kept, dropped) => text.push_str(&format!(
+            "\nBlank pages kept without cleaning: {kept}, dropped: {dropped}"
+        )),
+    }
+    if !duplicates.is_empty() {
+        let listed: Vec<String> = duplicates
+            .iter()
+            .map(|page| {
+                format!(
+                    "{} (of {})",
+                    page.page,
+                    page.duplicate_of.unwrap_or_default()
+                )
+            })
+            .collect();
+        text.push_str(&format!(
+            "\nDuplicate pages{}: {}",
+            if dropped_duplicates > 0 {
+                " dropped"
+            } else {
+                ""
+            },
+            listed.join(", ")
+        ));
+    }
+    let cmyk: Vec<String> = result
+        .pages
+        .iter()
+        .filter(|page| page.cmyk)
+        .map(|page| page.page.to_string())
+        .collect();
+    if !cmyk.is_empty() {
+        text.push_str(&format!("\nPages drawing in CMYK: {}", cmyk.join(", ")));
+    }
+    let adaptive: Vec<String> = result
+        .pages
+        .iter()
+        .filter_map(|page| Some(format!("{} ({} DPI)", page.page, page.removal_dpi?)))
+        .collect();
+    if !adaptive.is_empty() {
+        text.push_str(&format!(
+            "\nRemoved at a higher DPI: {}",
+            adaptive.join(", ")
+        ));
+    }
+    let failed: Vec<&PageResult> = result
+        .pages
+        .iter()
+        .filter(|page| page.status == PageStatus::Failed)
+        .collect();
+    if !failed.is_empty() {
+        text.push_str(&format!(
+            "\n\n{} of {} pages failed and were left out:",
+            failed.len(),
+            result.pages.len()
+        ));
+        for page in failed {
+            text.push_str(&format!(
+                "\n  page {}: {}",
+                page.page,
+                page.error.as_deref().unwrap_or_default()
+            ));
+        }
+    }
+    match text_pages {
+        Some(pages) => text.push_str(&format!("\nText layer copied to {pages} pages")),
+        None if text_source.is_some() => {
+            text.push_str("\nText layer could not be copied; see the server log")
+        }
+        None => {}
+    }
+    if verification.passed() {
+        text.push_str(&format!(
+            "\nVerified: {} pages read back",
+            verification.page_count
+        ));
+    } else {
+        text.push_str(&format!(
+            "\n\nOUTPUT VERIFICATION FAILED: {}",
+            verification_summary(&verification)
+        ));
+    }
+    text.push_str(&format!("\n\n{}", result.log));
+
+    let mut structured = serde_json::to_value(&result)?;
+    structured["verification"] = serde_json::to_value(&verification)?;
+    if text_source.is_some() {
+        structured["text_layer_pages"] = json!(text_pages);
+    }
+    if let Some(dir) = images_output_dir {
+        structured["images_output_dir"] = json!(dir);
+    }
+    if let Some(original) = renamed_from {
+        structured["renamed_from"] = json!(original);
+    }
     Ok(CallToolResult {
         content: vec![ContentBlock::TextContent(TextContent {
             r#type: "text".to_string(),
-            text: format!(
-                "Successfully processed PDF and removed watermarks!\n\nImages output directory: {}\nTotal images: {}\n\n{}",
-                output_dir.display(),
-                image_count,
-                stdout
-            ),
+            text,
             annotations: None,
         })],
-        is_error: Some(false),
-        structured_content: None,
+        is_error: Some(!verification.passed()),
+        structured_content: Some(structured),
+    })
+}
+
+fn verification_summary(verification: &OutputVerification) -> String {
+    let mut problems: Vec<String> = verification.error.iter().cloned().collect();
+    problems.extend(
+        verification
+            .broken_pages
+            .iter()
+            .map(|broken| format!("page {}: {}", broken.page, broken.error)),
+    );
+    problems.join("; ")
+}
+
+/// Render a PDF (or EPUB/DjVu document), remove the watermark from every
+/// page and write the cleaned pages to a new PDF at `output_path`.
+///
+/// Each page is appended to the output and its image deleted as soon as it
+/// is cleaned, so peak disk usage stays at a few pages however long the
+/// document is. `settings` are used as given. With `deterministic`, pages
+/// are rendered one after another and appended in page order, so the same
+/// input always gives a byte-identical PDF. An existing file at
+/// `output_path` is only replaced once the run succeeds.
+pub async fn process_pdf_to_pdf(
+    pdf_path: &Path,
+    output_path: &Path,
+    settings: &RemovalSettings,
+    deterministic: bool,
+) -> Result<ImagesToPdfOutput> {
+    if !pdf_path.exists() {
+        bail!(ToolError::not_found("PDF file", pdf_path));
+    }
+    info!(
+        "Processing PDF: {} -> {}",
+        pdf_path.display(),
+        output_path.display()
+    );
+
+    let pages_dir = create_temp_dir("stream").await?;
+    let writer = PdfPageWriter::create(output_path, settings.dpi.unwrap_or(200), deterministic)?;
+    let sink: PageSink = Arc::new(Mutex::new(writer));
+    let result = run_pipeline(
+        pdf_path.to_path_buf(),
+        pages_dir.clone(),
+        settings.clone(),
+        None,
+        Some(sink.clone()),
+        None,
+        false,
+    )
+    .await;
+    remove_temp_dir(&pages_dir).await;
+
+    let finished = async {
+        let ProcessPdfOutput {
+            pages,
+            mut timings,
+            log,
+            ..
+        } = result?;
+        let writer = Arc::into_inner(sink)
+            .context("Page writer still in use")?
+            .into_inner()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let page_count = writer.page_count();
+        let merge_started = Instant::now();
+        let size_bytes = tokio::task::spawn_blocking(move || writer.finish())
+            .await
+            .context("PDF writer task failed")??;
+        timings.add_merge(merge_started.elapsed());
+        anyhow::Ok((pages, timings, log, page_count, size_bytes))
+    }
+    .await;
+    // On error the writer has dropped its temporary file, and any earlier
+    // output is left in place
+    let (pages, timings, log, page_count, size_bytes) = finished?;
+    metrics::add_pages(page_count);
+    metrics::add_bytes_written(size_bytes);
+
+    Ok(ImagesToPdfOutput {
+        output_path: output_path.to_path_buf(),
+        page_count,
+        size_bytes,
+        pages,
+        timings: Some(timings),
+        appended: None,
+        log,
     })
 }
 
-fn get_scripts_dir() -> Result<PathBuf> {
-    // First check environment variable
-    if let Ok(scripts_dir) = std::env::var("WATERMARK_SCRIPTS_DIR") {
-        let path = PathBuf::from(&scripts_dir);
-        if path.exists() {
-            return Ok(path);
+pub(super) async fn run_process_pdf(
+    pdf_path: PathBuf,
+    output_dir: PathBuf,
+    settings: RemovalSettings,
+    progress: Option<JobProgress>,
+    keep_intermediates: bool,
+) -> Result<ProcessPdfOutput> {
+    run_pipeline(
+        pdf_path,
+        output_dir,
+        settings,
+        progress,
+        None,
+        None,
+        keep_intermediates,
+    )
+    .await
+}
+
+/// Continue the background job saved in `state`, under its original job id,
+/// rendering only the pages missing from its pages directory.
+pub(super) fn resume_process_pdf(state: CheckpointState, jobs: &JobManager) -> String {
+    let session = jobs.session_id().to_string();
+    jobs.spawn_as(
+        state.job_id.clone(),
+        "process_pdf",
+        state.job.webhook_url.clone(),
+        move |progress| async move {
+            let job = state.job.clone();
+            let checkpoint = Checkpoint::resume(state, &session);
+            if let Some(checkpoint) = &checkpoint {
+                if let Some(total) = checkpoint.total_pages() {
+                    progress.set_total_pages(total);
+                }
+                for page in checkpoint.pages_done() {
+                    progress.page_finished(page, PageStatus::Resumed.as_str(), None);
+                }
+            }
+            run_process_pdf_job(job, progress, checkpoint).await
+        },
+    )
+}
+
+/// Body of an `async: true` `process_pdf` job. The checkpoint is deleted
+/// when every page succeeds and kept, marked failed, when the job or any
+/// page does not.
+async fn run_process_pdf_job(
+    job: PdfJob,
+    progress: JobProgress,
+    checkpoint: Option<Checkpoint>,
+) -> ToolResult {
+    let checkpoint = checkpoint.map(Arc::new);
+    let mut result = async {
+        let pages = run_pipeline(
+            job.pdf_path.clone(),
+            job.pages_dir.clone(),
+            job.settings.clone(),
+            Some(progress),
+            None,
+            checkpoint.clone(),
+            job.keep_intermediates,
+        )
+        .await?;
+        let merge_started = Instant::now();
+        let pdf = images_to_pdf(
+            &job.pages_dir,
+            &job.output_path,
+            Some(&page_pattern(&job.settings)),
+            ImageOrder::Natural,
+            PageResize::default(),
+            job.deterministic,
+        )
+        .await?;
+        anyhow::Ok(pages.into_pdf_output(pdf, merge_started.elapsed()))
+    }
+    .await;
+
+    // Failed pages leave the checkpoint behind so resume_job can retry them
+    let complete = result.as_ref().is_ok_and(|pdf| {
+        pdf.pages
+            .iter()
+            .all(|page| page.status != PageStatus::Failed)
+    });
+    match &checkpoint {
+        Some(checkpoint) if !complete => checkpoint.fail(),
+        Some(checkpoint) => checkpoint.finish(),
+        None => {}
+    }
+    // Scratch pages are kept only while a checkpoint may still need them
+    if job.scratch_pages && (complete || checkpoint.is_none()) {
+        remove_temp_dir(&job.pages_dir).await;
+        if let Ok(pdf) = &mut result {
+            for page in &mut pdf.pages {
+                page.output_path = None;
+            }
         }
     }
+    let images_output_dir = (!job.scratch_pages).then_some(job.pages_dir.as_path());
+    let text_source = job.text_layer.then_some(job.pdf_path.as_path());
+    process_pdf_result(result, images_output_dir, None, text_source).await
+}
+
+/// Shared body of [`run_process_pdf`] and [`process_pdf_to_pdf`]. With a
+/// `sink`, every finished page is moved from `output_dir` into the PDF
+/// being written. With a `checkpoint`, finished pages are recorded in it and
+/// pages it already holds are skipped.
+///
+/// EPUB and DjVu inputs are converted here, inside the job when running
+/// asynchronously, and then handled as PDFs. The converted PDF is deleted
+/// afterwards unless `keep_intermediates` is set.
+async fn run_pipeline(
+    document: PathBuf,
+    output_dir: PathBuf,
+    settings: RemovalSettings,
+    progress: Option<JobProgress>,
+    sink: Option<PageSink>,
+    checkpoint: Option<Arc<Checkpoint>>,
+    keep_intermediates: bool,
+) -> Result<ProcessPdfOutput> {
+    let pdf_path = ensure_pdf(&document).await?;
+    let converted_dir = (pdf_path != document)
+        .then(|| pdf_path.parent().map(Path::to_path_buf))
+        .flatten();
 
-    if let Ok(exe_path) = std::env::current_exe()
-        && let Some(parent) = exe_path.parent()
+    let result = render_pages(pdf_path, output_dir, settings, progress, sink, checkpoint).await;
+
+    if let Some(dir) = converted_dir
+        && !keep_intermediates
     {
-        let possible_paths = vec![
-            parent.join("../../../watermark-remover-mcp-server/scripts"),
-            parent.join("../../watermark-remover-mcp-server/scripts"),
-            parent.join("scripts"),
-        ];
-
-        for path in possible_paths {
-            if path.exists() {
-                return Ok(path.canonicalize()?);
+        remove_temp_dir(&dir).await;
+    }
+    result
+}
+
+/// Render and clean the pages of `pdf_path`; see [`run_pipeline`].
+async fn render_pages(
+    pdf_path: PathBuf,
+    output_dir: PathBuf,
+    settings: RemovalSettings,
+    progress: Option<JobProgress>,
+    sink: Option<PageSink>,
+    checkpoint: Option<Arc<Checkpoint>>,
+) -> Result<ProcessPdfOutput> {
+    let started = Instant::now();
+    let dpi = settings.dpi.unwrap_or(200);
+
+    let done = checkpoint
+        .as_ref()
+        .map(|checkpoint| checkpoint.pages_done())
+        .unwrap_or_default();
+    // Parallel ranges finish out of order, which a reproducible PDF
+    // cannot take
+    let in_page_order = sink.as_ref().is_some_and(|sink| {
+        sink.lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .in_page_order()
+    });
+    let workers = if in_page_order { 1 } else { max_processes() };
+    let pdf_info = if workers > 1 || max_image_pixels().is_some() || !done.is_empty() {
+        match read_pdf_info(&pdf_path).await {
+            Ok(info) => Some(info),
+            Err(e) if max_image_pixels().is_some() || !done.is_empty() => return Err(e),
+            Err(e) => {
+                warn!("Could not count pages, processing sequentially: {e:#}");
+                None
             }
         }
+    } else {
+        None
+    };
+    if let Some(info) = &pdf_info {
+        check_page_pixels(info, dpi)?;
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.set_total_pages(info.page_count);
+        }
     }
+    // Also looked for with cmyk_output, which renders every page in CMYK,
+    // so the result still tells which pages drew in CMYK
+    let cmyk = {
+        let path = pdf_path.clone();
+        tokio::task::spawn_blocking(move || cmyk_pages(&path))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|pages| pages)
+            .unwrap_or_else(|e| {
+                warn!("Could not look for CMYK pages, rendering every page as RGB: {e:#}");
+                BTreeSet::new()
+            })
+    };
+    let ranges = match &pdf_info {
+        Some(info) if workers > 1 || !done.is_empty() => {
+            page_ranges(info.page_count, &done, workers)
+        }
+        _ => Vec::new(),
+    };
 
-    let cwd = std::env::current_dir()?;
-    Ok(cwd.join("scripts"))
+    let run = Arc::new(PageRun {
+        pdf_path,
+        output_dir,
+        dpi,
+        settings,
+        cmyk_pages: cmyk,
+        progress,
+        sink,
+        checkpoint,
+        budget: DiskBudget::new(),
+        written: Mutex::default(),
+        pages: Mutex::default(),
+        timings: Mutex::default(),
+    });
+    let log = if ranges.is_empty() && !done.is_empty() {
+        info!("All pages of {} were already done", run.pdf_path.display());
+        Ok(String::new())
+    } else if ranges.len() <= 1 {
+        run_page_range(&run, ranges.first().copied()).await
+    } else {
+        info!(
+            "Processing {} in {} page ranges",
+            run.pdf_path.display(),
+            ranges.len()
+        );
+        let mut workers = JoinSet::new();
+        for (index, range) in ranges.iter().copied().enumerate() {
+            let run = run.clone();
+            workers.spawn(
+                inherit_output(async move { (index, run_page_range(&run, Some(range)).await) })
+                    .in_current_span(),
+            );
+        }
+
+        // Dropping the set on the first failure aborts the other workers,
+        // which kills their scripts
+        let mut logs = vec![String::new(); ranges.len()];
+        let mut failure = None;
+        while let Some(joined) = workers.join_next().await {
+            match joined.context("Page worker panicked") {
+                Ok((index, Ok(log))) => logs[index] = log,
+                Ok((_, Err(e))) | Err(e) => {
+                    failure = Some(e);
+                    break;
+                }
+            }
+        }
+        drop(workers);
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(logs.concat()),
+        }
+    };
+
+    let log = match log {
+        Ok(log) => log,
+        Err(e) => {
+            // Free the disk the quota was protecting
+            if matches!(
+                e.downcast_ref::<ToolError>(),
+                Some(ToolError::QuotaExceeded { .. })
+            ) {
+                let written = std::mem::take(
+                    &mut *run
+                        .written
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner),
+                );
+                for path in written {
+                    let _ = tokio::fs::remove_file(path).await;
+                }
+            }
+            return Err(e);
+        }
+    };
+
+    let mut images: Vec<PathBuf> = std::fs::read_dir(&run.output_dir)
+        .map(|entries| {
+            entries
+                .filter_map(std::result::Result::ok)
+                .map(|e| e.path())
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|ext| ext == page_extension(&run.settings))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    images.sort();
+    metrics::add_pages(images.len());
+    metrics::add_files_written(&images);
+
+    let mut pages = std::mem::take(
+        &mut *run
+            .pages
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner),
+    );
+    pages.extend(done.iter().map(|&page| PageResult {
+        page,
+        status: PageStatus::Resumed,
+        cached: false,
+        output_path: Some(run.output_dir.join(page_image_name(page, &run.settings))),
+        duration_ms: 0,
+        error: None,
+        duplicate_of: None,
+        removal_dpi: None,
+        cmyk: false,
+    }));
+    pages.sort_by_key(|result| result.page);
+    for page in &mut pages {
+        page.cmyk = run.cmyk_pages.contains(&page.page);
+    }
+
+    // A range only knows the page before it, not whether that page repeats
+    // an earlier one, so point runs of duplicates at the page they repeat
+    let duplicate_of: std::collections::HashMap<u32, u32> = pages
+        .iter()
+        .filter_map(|page| Some((page.page, page.duplicate_of?)))
+        .collect();
+    for page in &mut pages {
+        while let Some(original) = page
+            .duplicate_of
+            .and_then(|original| duplicate_of.get(&original))
+        {
+            page.duplicate_of = Some(*original);
+        }
+    }
+
+    // Failed pages are skipped, but a run where every page failed is an error
+    if !pages.is_empty() && pages.iter().all(|page| page.status == PageStatus::Failed) {
+        let reasons: Vec<String> = pages
+            .iter()
+            .map(|page| {
+                format!(
+                    "page {}: {}",
+                    page.page,
+                    page.error.as_deref().unwrap_or_default()
+                )
+            })
+            .collect();
+        bail!(ToolError::SubprocessFailed {
+            script: "process_pdf_to_images.py".to_string(),
+            stderr: format!("every page failed; {}", reasons.join("; ")),
+        });
+    }
+
+    if !pages.is_empty()
+        && pages
+            .iter()
+            .all(|page| matches!(page.status, PageStatus::Dropped | PageStatus::Failed))
+    {
+        bail!(ToolError::invalid_args(format!(
+            "Every page of {} was dropped as blank or duplicate, leaving nothing to write",
+            run.pdf_path.display()
+        )));
+    }
+
+    let mut timings = std::mem::take(
+        &mut *run
+            .timings
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner),
+    );
+    timings.set_total(started.elapsed());
+
+    Ok(ProcessPdfOutput {
+        output_dir: run.output_dir.clone(),
+        images,
+        pages,
+        timings,
+        log,
+    })
+}
+
+/// State shared by the page-range workers of one pipeline run.
+struct PageRun {
+    pdf_path: PathBuf,
+    output_dir: PathBuf,
+    dpi: u32,
+    settings: RemovalSettings,
+    /// Pages drawing in CMYK, rendered with color management
+    cmyk_pages: BTreeSet<u32>,
+    progress: Option<JobProgress>,
+    sink: Option<PageSink>,
+    checkpoint: Option<Arc<Checkpoint>>,
+    budget: DiskBudget,
+    /// Page images written by this run and not yet moved into `sink`
+    written: Mutex<Vec<PathBuf>>,
+    /// Pages finished by this run, in completion order
+    pages: Mutex<Vec<PageResult>>,
+    timings: Mutex<StepTimings>,
+}
+
+impl PageRun {
+    fn record_page(&self, result: PageResult) {
+        self.pages
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push(result);
+    }
+
+    fn timings(&self) -> std::sync::MutexGuard<'_, StepTimings> {
+        self.timings
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Split the `page_count` pages not in `done` into contiguous ranges, two
+/// per worker so a slow range does not leave the other workers idle.
+fn page_ranges(page_count: u32, done: &BTreeSet<u32>, workers: usize) -> Vec<(u32, u32)> {
+    let todo: Vec<u32> = (1..=page_count)
+        .filter(|page| !done.contains(page))
+        .collect();
+    let chunks = (workers * 2).min(todo.len()).max(1);
+    let chunk_size = todo.len().div_ceil(chunks).max(1);
+    let mut ranges = Vec::new();
+    for chunk in todo.chunks(chunk_size) {
+        let mut first = chunk[0];
+        let mut last = first;
+        for &page in &chunk[1..] {
+            if page != last + 1 {
+                ranges.push((first, last));
+                first = page;
+            }
+            last = page;
+        }
+        ranges.push((first, last));
+    }
+    ranges
+}
+
+/// Run `process_pdf_to_images.py` over `range` (first and last page,
+/// inclusive) or the whole document, returning its stdout. Each run holds
+/// its own subprocess slot and timeout.
+async fn run_page_range(run: &PageRun, range: Option<(u32, u32)>) -> Result<String> {
+    let scripts_dir = scripts_dir()?;
+    let script_path = scripts_dir.join("process_pdf_to_images.py");
+
+    // Held until the child exits so it counts against the subprocess limit
+    let _slot = acquire_process_slot().await;
+    let mut cmd = python_command();
+    cmd.arg(&script_path)
+        .arg(&run.pdf_path)
+        .arg(run.output_dir.to_string_lossy().to_string())
+        .arg(run.dpi.to_string())
+        .args(run.settings.script_args())
+        .env("PYTHONUNBUFFERED", "1")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(cache_dir) = writable_page_cache_dir() {
+        cmd.arg("--cache-dir").arg(cache_dir);
+    }
+    if let Some(blank_pages) = &run.settings.blank_pages {
+        cmd.arg(format!("--blank-pages={blank_pages}"));
+    }
+    if let Some(duplicate_pages) = &run.settings.duplicate_pages {
+        cmd.arg(format!("--duplicate-pages={duplicate_pages}"));
+    }
+    if let Some(adaptive_dpi) = run.settings.adaptive_dpi {
+        cmd.arg(format!("--adaptive-dpi={adaptive_dpi}"));
+    }
+    let cmyk: Vec<String> = run
+        .cmyk_pages
+        .iter()
+        .filter(|&&page| range.is_none_or(|(first, last)| (first..=last).contains(&page)))
+        .map(u32::to_string)
+        .collect();
+    if !cmyk.is_empty() {
+        cmd.arg(format!("--cmyk-pages={}", cmyk.join(",")));
+    }
+    if run.settings.cmyk_output == Some(true) {
+        cmd.arg("--cmyk-output");
+    }
+    if let Some((first, last)) = range {
+        cmd.arg("--first-page")
+            .arg(first.to_string())
+            .arg("--last-page")
+            .arg(last.to_string());
+    }
+    isolate_process_group(&mut cmd);
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| spawn_error(&cmd, e))
+        .context("Failed to execute process_pdf_to_images.py")?;
+    let pid = child.id();
+    let _group = track_process_group(pid);
+    // Each page line closes the time spent on that page
+    let mut page_started = Instant::now();
+
+    // Logged as it arrives, so a long render shows up in the request's logs
+    let stderr_pipe = child.stderr.take().context("stderr not captured")?;
+    let stderr_task = tokio::spawn(
+        inherit_output(async move {
+            let mut buf = String::new();
+            let mut lines = BufReader::new(stderr_pipe).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                log_output_line("process_pdf_to_images.py", OutputStream::Stderr, &line);
+                buf.push_str(&line);
+                buf.push('\n');
+            }
+            buf
+        })
+        .in_current_span(),
+    );
+
+    // Read stdout line by line so background jobs can report per-page state
+    let mut stdout = String::new();
+    let mut lines = BufReader::new(child.stdout.take().context("stdout not captured")?).lines();
+    let read = async {
+        while let Some(line) = lines.next_line().await? {
+            log_output_line("process_pdf_to_images.py", OutputStream::Stdout, &line);
+            if let Some(progress) = &run.progress {
+                report_progress(progress, &line);
+            }
+            if let Some(checkpoint) = &run.checkpoint
+                && let Some(total) = total_pages(&line)
+            {
+                checkpoint.set_total_pages(total);
+            }
+            if let Some((conversion_ms, removal_ms)) = page_timing(&line) {
+                run.timings().add_page(conversion_ms, removal_ms);
+            }
+            if let Some(mut result) = finished_page(&line) {
+                result.duration_ms = millis(page_started.elapsed());
+                page_started = Instant::now();
+                if result.status == PageStatus::Failed {
+                    warn!(
+                        "Page {} of {} failed: {}",
+                        result.page,
+                        run.pdf_path.display(),
+                        result.error.as_deref().unwrap_or_default()
+                    );
+                    run.record_page(result);
+                } else if result.status == PageStatus::Dropped {
+                    // No image was written for a dropped page
+                    run.record_page(result);
+                } else {
+                    page_written(run, result)
+                        .await
+                        .map_err(std::io::Error::other)?;
+                }
+            }
+            stdout.push_str(&line);
+            stdout.push('\n');
+        }
+        child.wait().await
+    };
+
+    let status = match process_timeout() {
+        Some(timeout) => match tokio::time::timeout(timeout, read).await {
+            Ok(status) => status.map_err(ToolError::from)?,
+            Err(_) => {
+                kill_process_group(pid);
+                return Err(timeout_error(&cmd, timeout).into());
+            }
+        },
+        None => read.await.map_err(ToolError::from)?,
+    };
+    let stderr = stderr_task.await.unwrap_or_default();
+
+    if !status.success() {
+        bail!(ToolError::script_failed(
+            "process_pdf_to_images.py",
+            stderr.as_bytes()
+        ));
+    }
+    Ok(stdout)
+}
+
+/// Charge a finished page against the disk quota, when streaming move it
+/// into the output PDF, and record its `result`.
+async fn page_written(run: &PageRun, mut result: PageResult) -> ToolResult<()> {
+    let page = result.page;
+    let image_path = run.output_dir.join(page_image_name(page, &run.settings));
+    let size = tokio::fs::metadata(&image_path)
+        .await
+        .map(|meta| meta.len())
+        .unwrap_or_default();
+    run.written
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .push(image_path.clone());
+    run.budget.charge(size)?;
+    if let Some(checkpoint) = &run.checkpoint {
+        checkpoint.page_done(page);
+    }
+
+    if let Some(sink) = &run.sink {
+        let merge_started = Instant::now();
+        append_page(sink, page, image_path.clone()).await?;
+        let merge_ms = millis(merge_started.elapsed());
+        run.timings().merge_ms += merge_ms;
+        run.written
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .retain(|path| *path != image_path);
+        run.budget.release(size);
+    } else {
+        result.output_path = Some(image_path);
+    }
+    run.record_page(result);
+    Ok(())
+}
+
+/// Extension of the cleaned page images. CMYK pages are written as JPEG,
+/// PNG having no CMYK.
+fn page_extension(settings: &RemovalSettings) -> &'static str {
+    if settings.cmyk_output == Some(true) {
+        "jpg"
+    } else {
+        "png"
+    }
+}
+
+fn page_image_name(page: u32, settings: &RemovalSettings) -> String {
+    format!("page_{page:03}.{}", page_extension(settings))
+}
+
+/// Glob matching the cleaned page images, for merging them.
+fn page_pattern(settings: &RemovalSettings) -> String {
+    format!("page_*.{}", page_extension(settings))
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Translate the script's progress lines into job state.
+///
+/// Every run of `process_pdf_to_images.py` prints `  Total pages: N` for
+/// the whole document and then one `  page_NNN.png: ✓ Watermark removed` /
+/// `○ No watermark` / `○ Blank page, not cleaned` / `○ Blank page, dropped` /
+/// `○ Duplicate of page N, dropped` / `✗ Failed: <reason>` line per page.
+/// Kept pages repeating the one before carry `(duplicate of page N)`, and
+/// with `cmyk_output` the pages are named `page_NNN.jpg`.
+fn report_progress(progress: &JobProgress, line: &str) {
+    let line = line.trim();
+    if line.starts_with("Total pages: ") {
+        if let Some(total) = total_pages(line) {
+            progress.set_total_pages(total);
+        }
+        return;
+    }
+
+    if let Some(result) = finished_page(line) {
+        progress.page_finished(result.page, result.status.as_str(), result.error);
+    } else if line.starts_with("Step ") {
+        progress.set_message(line);
+    }
+}
+
+/// Page count of the document, for the script's `Total pages: N` line.
+fn total_pages(line: &str) -> Option<u32> {
+    line.trim()
+        .strip_prefix("Total pages: ")?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Milliseconds spent rendering and cleaning a page, for the script's
+/// `Timing page_NNN: render <ms> ms, clean <ms> ms` line.
+fn page_timing(line: &str) -> Option<(u64, u64)> {
+    let (_, times) = line.trim().strip_prefix("Timing page_")?.split_once(": ")?;
+    let (render, clean) = times.split_once(", ")?;
+    let ms = |part: &str, step: &str| -> Option<u64> {
+        part.strip_prefix(step)?.strip_suffix(" ms")?.parse().ok()
+    };
+    Some((ms(render, "render ")?, ms(clean, "clean ")?))
+}
+
+/// The outcome of a page, for a script line reporting a finished or failed
+/// page. The duration is left for the caller to fill in.
+fn finished_page(line: &str) -> Option<PageResult> {
+    let (name, outcome) = line.trim().split_once(": ")?;
+    let (number, extension) = name.strip_prefix("page_")?.split_once('.')?;
+    if !matches!(extension, "png" | "jpg") {
+        return None;
+    }
+    let page = number.parse().ok()?;
+    let error = outcome
+        .strip_prefix("✗ Failed: ")
+        .map(|reason| reason.trim().to_string());
+    let status = if error.is_some() {
+        PageStatus::Failed
+    } else if outcome.ends_with(", dropped") {
+        PageStatus::Dropped
+    } else if outcome.contains("Blank page") {
+        PageStatus::Blank
+    } else if outcome.contains("Watermark removed") {
+        PageStatus::WatermarkRemoved
+    } else {
+        PageStatus::NoWatermark
+    };
+    let duplicate_of = outcome
+        .split_once("uplicate of page ")
+        .filter(|_| error.is_none())
+        .and_then(|(_, rest)| {
+            let digits = rest.split(|c: char| !c.is_ascii_digit()).next()?;
+            digits.parse().ok()
+        });
+    let removal_dpi = outcome
+        .strip_prefix("✓ Watermark removed at ")
+        .and_then(|rest| rest.split_once(" DPI"))
+        .and_then(|(dpi, _)| dpi.parse().ok());
+    Some(PageResult {
+        page,
+        status,
+        cached: error.is_none() && outcome.ends_with("(cached)"),
+        output_path: None,
+        duration_ms: 0,
+        error,
+        duplicate_of,
+        removal_dpi,
+        cmyk: false,
+    })
+}
+
+/// Append a finished page image to the PDF being written and delete it.
+async fn append_page(sink: &PageSink, page: u32, image_path: PathBuf) -> Result<()> {
+    let sink = sink.clone();
+    tokio::task::spawn_blocking(move || {
+        // Parallel page ranges re-encode their pages concurrently; only the
+        // write itself holds the lock
+        let image = prepare_page(&image_path)?;
+        sink.lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .add_page(page, image)?;
+        std::fs::remove_file(&image_path)
+            .with_context(|| format!("Error deleting {}", image_path.display()))
+    })
+    .await
+    .context("PDF writer task failed")?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_ranges_split_pages_between_workers() {
+        let none = BTreeSet::new();
+        assert_eq!(page_ranges(10, &none, 1), vec![(1, 5), (6, 10)]);
+        assert_eq!(
+            page_ranges(10, &none, 2),
+            vec![(1, 3), (4, 6), (7, 9), (10, 10)]
+        );
+        // Never more ranges than pages
+        assert_eq!(page_ranges(2, &none, 8), vec![(1, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn page_ranges_skip_finished_pages() {
+        let done = BTreeSet::from([2, 3, 7]);
+        assert_eq!(
+            page_ranges(8, &done, 1),
+            vec![(1, 1), (4, 5), (6, 6), (8, 8)]
+        );
+        let all = (1..=4).collect();
+        assert!(page_ranges(4, &all, 2).is_empty());
+    }
+
+    #[test]
+    fn finished_page_reads_each_outcome() {
+        let page = finished_page("  page_007.png: ✓ Watermark removed").unwrap();
+        assert_eq!((page.page, page.status), (7, PageStatus::WatermarkRemoved));
+        assert!(!page.cached);
+
+        let page = finished_page("  page_012.jpg: ○ No watermark (cached)").unwrap();
+        assert_eq!((page.page, page.status), (12, PageStatus::NoWatermark));
+        assert!(page.cached);
+
+        let page = finished_page("  page_003.png: ○ Blank page, not cleaned").unwrap();
+        assert_eq!(page.status, PageStatus::Blank);
+        let page = finished_page("  page_003.png: ○ Blank page, dropped").unwrap();
+        assert_eq!(page.status, PageStatus::Dropped);
+    }
+
+    #[test]
+    fn finished_page_reads_failures_duplicates_and_dpi() {
+        let page = finished_page("  page_004.png: ✗ Failed: cannot render (cached)").unwrap();
+        assert_eq!(page.status, PageStatus::Failed);
+        assert_eq!(page.error.as_deref(), Some("cannot render (cached)"));
+        assert!(!page.cached);
+
+        let page = finished_page("  page_005.png: ○ Duplicate of page 4, dropped").unwrap();
+        assert_eq!(
+            (page.status, page.duplicate_of),
+            (PageStatus::Dropped, Some(4))
+        );
+        let page = finished_page("  page_006.png: ○ No watermark (duplicate of page 12)").unwrap();
+        assert_eq!(
+            (page.status, page.duplicate_of),
+            (PageStatus::NoWatermark, Some(12))
+        );
+
+        let page =
+            finished_page("  page_008.png: ✓ Watermark removed at 400 DPI (cached)").unwrap();
+        assert_eq!(page.removal_dpi, Some(400));
+        assert!(page.cached);
+    }
+
+    #[test]
+    fn finished_page_ignores_other_lines() {
+        assert!(finished_page("  Timing page_001: render 12 ms, clean 3 ms").is_none());
+        assert!(finished_page("  Pages:  12").is_none());
+        assert!(finished_page("  page_001.tiff: ✓ Watermark removed").is_none());
+        assert!(finished_page("Processing: page_001.png").is_none());
+    }
+
+    #[test]
+    fn page_timing_reads_render_and_clean() {
+        assert_eq!(
+            page_timing("  Timing page_001: render 120 ms, clean 45 ms"),
+            Some((120, 45))
+        );
+        assert_eq!(page_timing("  page_001.png: ✓ Watermark removed"), None);
+    }
 }
@@ -0,0 +1,139 @@
+//! Process ZIP tool - clean every image and PDF inside a ZIP archive
+
+use anyhow::Context;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use serde_json::json;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tracing::info;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::inline_input::write_inline_input;
+use super::overwrite::check_output_dir;
+use super::overwrite::default_output;
+use super::profiles::RemovalSettings;
+use super::profiles::resolve_settings;
+use super::python::python_command;
+use super::script_output::parse_json_result;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+use super::workspace::create_temp_dir;
+
+#[derive(Deserialize)]
+struct ProcessZipArgs {
+    zip_path: Option<String>,
+    zip_base64: Option<String>,
+    output_dir: Option<String>,
+    output_zip: Option<String>,
+    overwrite: Option<bool>,
+    auto_rename: Option<bool>,
+    profile: Option<String>,
+    #[serde(flatten)]
+    settings: RemovalSettings,
+}
+
+pub async fn handle_process_zip(args: serde_json::Value) -> ToolResult {
+    let args: ProcessZipArgs = parse_args(args)?;
+
+    let zip_path = match (&args.zip_path, &args.zip_base64) {
+        (Some(path), _) => PathBuf::from(path),
+        (None, Some(data)) => write_inline_input(data, "archive", "zip").await?,
+        (None, None) => {
+            return Err(ToolError::invalid_args(
+                "Either zip_path or zip_base64 must be provided",
+            ));
+        }
+    };
+    if !zip_path.exists() {
+        return Err(ToolError::not_found("ZIP file", zip_path));
+    }
+
+    let settings = match resolve_settings(args.settings, args.profile.as_deref()).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            return Err(ToolError::invalid_args(format!("{e}")));
+        }
+    };
+
+    // Cleaned entries keep their names, so any file already there may clash
+    let overwrite = args.overwrite.unwrap_or(false);
+    let (output_dir, renamed_from) = match args.output_dir {
+        Some(dir) => (PathBuf::from(dir), None),
+        None => {
+            let stem = zip_path.file_stem().unwrap_or_default().to_string_lossy();
+            let dir = zip_path
+                .parent()
+                .unwrap_or(&zip_path)
+                .join(format!("{stem}_cleaned"));
+            default_output(
+                dir,
+                !overwrite && args.auto_rename.unwrap_or(false),
+                |dir| check_output_dir(dir, false, |_| true).is_err(),
+            )
+        }
+    };
+    check_output_dir(&output_dir, overwrite, |_| true)?;
+    tokio::fs::create_dir_all(&output_dir).await?;
+
+    // Extracted entries are scratch data; the cleaned copies go to output_dir
+    let work_dir = create_temp_dir("zip").await?;
+
+    info!(
+        "Processing ZIP: {} -> {}",
+        zip_path.display(),
+        output_dir.display()
+    );
+
+    let scripts_dir = scripts_dir()?;
+    let script_path = scripts_dir.join("process_zip.py");
+
+    let mut cmd = python_command();
+    cmd.arg(&script_path)
+        .arg(&zip_path)
+        .arg(&work_dir)
+        .arg(&output_dir)
+        .args(settings.script_args());
+    if let Some(dpi) = settings.dpi {
+        cmd.arg("--dpi").arg(dpi.to_string());
+    }
+    if let Some(output_zip) = &args.output_zip {
+        cmd.arg("--output-zip").arg(output_zip);
+    }
+
+    let output = run_command(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))
+        .await
+        .context("Failed to execute process_zip.py")?;
+
+    // The extracted copy is never useful once the script has finished
+    let _ = tokio::fs::remove_dir_all(&work_dir).await;
+
+    if !output.status.success() {
+        return Err(ToolError::script_failed("process_zip.py", &output.stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!(
+                "Successfully processed ZIP archive.\nOutput directory: {}\n{}",
+                output_dir.display(),
+                stdout
+            ),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: parse_json_result(&stdout).map(|mut result| {
+            if let Some(original) = renamed_from {
+                result["renamed_from"] = json!(original);
+            }
+            result
+        }),
+    })
+}
@@ -0,0 +1,103 @@
+//! Profile tools - save, list and delete named removal profiles
+
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::profiles::BUILTIN_PROFILE;
+use super::profiles::RemovalSettings;
+use super::profiles::delete_profile;
+use super::profiles::list_profiles;
+use super::profiles::save_profile;
+
+#[derive(Deserialize)]
+struct SaveProfileArgs {
+    name: String,
+    #[serde(flatten)]
+    settings: RemovalSettings,
+}
+
+#[derive(Deserialize)]
+struct ProfileNameArgs {
+    name: String,
+}
+
+pub async fn handle_save_profile(args: serde_json::Value) -> ToolResult {
+    let args: SaveProfileArgs = parse_args(args)?;
+
+    args.settings.clone().resolve_position()?.validate()?;
+
+    let path = save_profile(&args.name, &args.settings).await?;
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!("Saved profile '{}' to {}", args.name, path.display()),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(json!({
+            "name": args.name,
+            "path": path,
+            "settings": args.settings,
+        })),
+    })
+}
+
+pub async fn handle_list_profiles(_args: serde_json::Value) -> ToolResult {
+    let profiles = list_profiles().await?;
+
+    let lines: Vec<String> = profiles
+        .iter()
+        .map(|(name, settings)| {
+            format!(
+                "  {name}: {}",
+                serde_json::to_string(settings).unwrap_or_default()
+            )
+        })
+        .collect();
+    let structured: Vec<serde_json::Value> = profiles
+        .iter()
+        .map(|(name, settings)| json!({ "name": name, "settings": settings }))
+        .collect();
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!("{} profiles\n{}", profiles.len(), lines.join("\n")),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(json!({ "profiles": structured })),
+    })
+}
+
+pub async fn handle_delete_profile(args: serde_json::Value) -> ToolResult {
+    let args: ProfileNameArgs = parse_args(args)?;
+
+    let deleted = delete_profile(&args.name).await?;
+
+    if !deleted {
+        let message = if args.name == BUILTIN_PROFILE {
+            format!("'{BUILTIN_PROFILE}' is built in and cannot be deleted")
+        } else {
+            format!("Unknown profile: {}", args.name)
+        };
+        return Err(ToolError::invalid_args(message));
+    }
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!("Deleted profile '{}'", args.name),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(json!({ "name": args.name, "deleted": true })),
+    })
+}
@@ -0,0 +1,658 @@
+//! Named watermark profiles - reusable removal settings stored on disk
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use super::error::ToolError;
+use crate::config::config;
+use crate::config::config_dir;
+
+/// Name of the profile that is always available, matching the scripts' defaults.
+pub const BUILTIN_PROFILE: &str = "notebooklm";
+
+/// `telea` and `ns` inpaint the mask; `solid_fill` paints the surrounding
+/// background colour over it, far faster and seamless on plain pages.
+pub(super) const ALGORITHMS: &[&str] = &["telea", "ns", "solid_fill"];
+
+/// Anchors accepted as `position`, with the default `[width, height]` in
+/// percent of the page
+const POSITIONS: &[(&str, [f64; 2])] = &[
+    ("bottom_right", [20.0, 8.0]),
+    ("bottom_left", [20.0, 8.0]),
+    ("top_right", [20.0, 8.0]),
+    ("top_left", [20.0, 8.0]),
+    ("center", [40.0, 20.0]),
+    ("footer", [100.0, 8.0]),
+    ("header", [100.0, 8.0]),
+];
+
+/// `corner` cleans light text inside `region`; `grid` estimates a whole-image
+/// mask for stock-photo style overlays and ignores `region`/`template`.
+const MODES: &[&str] = &["corner", "grid"];
+
+/// What `process_pdf` does with pages that are effectively blank: clean them
+/// like any other page, keep them as rendered, or leave them out.
+const BLANK_PAGES: &[&str] = &["process", "skip", "drop"];
+
+/// Whether `process_pdf` looks for pages repeating the one before them, and
+/// whether it leaves them out.
+const DUPLICATE_PAGES: &[&str] = &["keep", "report", "drop"];
+
+/// Slack for `x + width` and `y + height`, so regions computed as
+/// `1 - width` are not rejected over rounding
+const REGION_TOLERANCE: f64 = 1e-9;
+
+/// Removal settings shared by the removal tools. Every field is optional so
+/// explicit call arguments can be layered over a saved profile.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RemovalSettings {
+    /// Watermark region as `[x, y, width, height]` fractions of the image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<[f64; 4]>,
+    /// Where the watermark sits, e.g. `bottom_right` or `footer`; an
+    /// alternative to `region`, turned into one by
+    /// [`resolve_position`](Self::resolve_position)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<String>,
+    /// `[width, height]` of the `position` area in percent of the page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<[f64; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<String>,
+    /// Path to a watermark template image used for template matching
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dpi: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    /// Neighbourhood in pixels that inpainting draws from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inpaint_radius: Option<u32>,
+    /// Pixels the detected mask is grown by before it is filled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask_padding: Option<u32>,
+    /// Width in pixels of the soft edge blending the fill into the image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feather: Option<u32>,
+    /// `process`, `skip` or `drop` blank PDF pages; images are always cleaned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blank_pages: Option<String>,
+    /// `keep`, `report` or `drop` PDF pages identical to the one before
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_pages: Option<String>,
+    /// DPI PDF pages whose watermark is only a few pixels tall are
+    /// re-rendered at for removal; unset leaves every page at `dpi`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adaptive_dpi: Option<u32>,
+    /// Render every PDF page in CMYK, clean it there and write it to the
+    /// output as a CMYK image, for print workflows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cmyk_output: Option<bool>,
+    /// Clean-up passes for noisy scans, applied in order to every image
+    /// after the watermark is removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_filters: Option<Vec<PostFilter>>,
+    /// Operations applied in order to each image in a single script run.
+    /// Unset fields of `remove` steps fall back to the settings above.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steps: Option<Vec<RemovalStep>>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum RemovalStep {
+    /// One watermark removal pass; `dpi` and `steps` are not used here
+    Remove(Box<RemovalSettings>),
+    /// Median filter to clean up leftover specks. The kernel is not called
+    /// `size`, which `remove` steps already use for the `position` area.
+    Despeckle {
+        #[serde(alias = "size", skip_serializing_if = "Option::is_none")]
+        kernel_size: Option<u32>,
+    },
+}
+
+/// A clean-up pass run on each image after removal, aimed at scans.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PostFilter {
+    /// Median filter removing isolated specks of dust and toner
+    Despeckle {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        size: Option<u32>,
+    },
+    /// Non-local means denoising of scanner grain; higher `strength` (1-30)
+    /// smooths more and loses more fine detail
+    Denoise {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        strength: Option<u32>,
+    },
+    /// Stretch the levels so the paper is white and the ink dark, ignoring
+    /// the darkest and lightest `clip` percent of pixels
+    NormalizeContrast {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        clip: Option<f64>,
+    },
+}
+
+impl RemovalSettings {
+    /// Fill every unset field from `defaults`. The watermark's location is
+    /// one setting: a `region` here hides the defaults' `position` and
+    /// `size`, and a `position` hides their `region`.
+    pub fn or(self, defaults: RemovalSettings) -> Self {
+        let (region, position, size) = if self.region.is_some() {
+            (self.region, self.position, self.size)
+        } else if self.position.is_some() {
+            (None, self.position, self.size.or(defaults.size))
+        } else {
+            (
+                defaults.region,
+                defaults.position,
+                self.size.or(defaults.size),
+            )
+        };
+        Self {
+            region,
+            position,
+            size,
+            algorithm: self.algorithm.or(defaults.algorithm),
+            template: self.template.or(defaults.template),
+            dpi: self.dpi.or(defaults.dpi),
+            mode: self.mode.or(defaults.mode),
+            inpaint_radius: self.inpaint_radius.or(defaults.inpaint_radius),
+            mask_padding: self.mask_padding.or(defaults.mask_padding),
+            feather: self.feather.or(defaults.feather),
+            blank_pages: self.blank_pages.or(defaults.blank_pages),
+            duplicate_pages: self.duplicate_pages.or(defaults.duplicate_pages),
+            adaptive_dpi: self.adaptive_dpi.or(defaults.adaptive_dpi),
+            cmyk_output: self.cmyk_output.or(defaults.cmyk_output),
+            post_filters: self.post_filters.or(defaults.post_filters),
+            steps: self.steps.or(defaults.steps),
+        }
+    }
+
+    /// Replace `position` and `size` with the `region` they describe, here
+    /// and in every `remove` step. Done once the settings are layered, so a
+    /// `size` from one layer applies to a `position` from another.
+    pub fn resolve_position(mut self) -> Result<Self> {
+        if let Some(steps) = self.steps.take() {
+            let steps = steps
+                .into_iter()
+                .map(|step| match step {
+                    RemovalStep::Remove(settings) => {
+                        let settings = settings.resolve_position()?;
+                        Ok(RemovalStep::Remove(Box::new(settings)))
+                    }
+                    other => Ok(other),
+                })
+                .collect::<Result<_>>()?;
+            self.steps = Some(steps);
+        }
+
+        let Some(position) = self.position.take() else {
+            if self.size.is_some() {
+                bail!(ToolError::invalid_args(
+                    "size only applies together with position"
+                ));
+            }
+            return Ok(self);
+        };
+        if self.region.is_some() {
+            bail!(ToolError::invalid_args(
+                "Pass either region or position, not both"
+            ));
+        }
+        let Some((_, default_size)) = POSITIONS.iter().find(|(name, _)| *name == position) else {
+            bail!(ToolError::invalid_args(format!(
+                "Unsupported position: {position} (expected one of {})",
+                POSITIONS
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        };
+        let [width, height] = self.size.take().unwrap_or(*default_size);
+        if !(width > 0.0 && width <= 100.0 && height > 0.0 && height <= 100.0) {
+            bail!(ToolError::invalid_args(
+                "size must be [width, height] percentages between 0 and 100",
+            ));
+        }
+        let (w, h) = (width / 100.0, height / 100.0);
+        let (x, y) = match position.as_str() {
+            "bottom_right" => (1.0 - w, 1.0 - h),
+            "bottom_left" => (0.0, 1.0 - h),
+            "top_right" => (1.0 - w, 0.0),
+            "top_left" => (0.0, 0.0),
+            "center" => ((1.0 - w) / 2.0, (1.0 - h) / 2.0),
+            "footer" => ((1.0 - w) / 2.0, 1.0 - h),
+            _ => ((1.0 - w) / 2.0, 0.0),
+        };
+        self.region = Some([x, y, w, h]);
+        Ok(self)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some(&[x, y, width, height]) = self.region.as_ref()
+            && !([x, y, width, height]
+                .iter()
+                .all(|v| (0.0..=1.0).contains(v))
+                && x + width <= 1.0 + REGION_TOLERANCE
+                && y + height <= 1.0 + REGION_TOLERANCE)
+        {
+            bail!(ToolError::invalid_args(
+                "region must be [x, y, width, height] fractions between 0 and 1 that stay inside the image",
+            ));
+        }
+        if let Some(algorithm) = &self.algorithm
+            && !ALGORITHMS.contains(&algorithm.as_str())
+        {
+            bail!(ToolError::invalid_args(format!(
+                "Unsupported algorithm: {algorithm} (expected one of {})",
+                ALGORITHMS.join(", ")
+            )));
+        }
+        if let Some(mode) = &self.mode
+            && !MODES.contains(&mode.as_str())
+        {
+            bail!(ToolError::invalid_args(format!(
+                "Unsupported mode: {mode} (expected one of {})",
+                MODES.join(", ")
+            )));
+        }
+        if let Some(blank_pages) = &self.blank_pages
+            && !BLANK_PAGES.contains(&blank_pages.as_str())
+        {
+            bail!(ToolError::invalid_args(format!(
+                "Unsupported blank_pages: {blank_pages} (expected one of {})",
+                BLANK_PAGES.join(", ")
+            )));
+        }
+        if let Some(duplicate_pages) = &self.duplicate_pages
+            && !DUPLICATE_PAGES.contains(&duplicate_pages.as_str())
+        {
+            bail!(ToolError::invalid_args(format!(
+                "Unsupported duplicate_pages: {duplicate_pages} (expected one of {})",
+                DUPLICATE_PAGES.join(", ")
+            )));
+        }
+        for (name, value, range) in [
+            ("inpaint_radius", self.inpaint_radius, 1..=50),
+            ("mask_padding", self.mask_padding, 0..=50),
+            ("feather", self.feather, 0..=50),
+            ("adaptive_dpi", self.adaptive_dpi, 72..=1200),
+        ] {
+            if let Some(value) = value
+                && !range.contains(&value)
+            {
+                bail!(ToolError::invalid_args(format!(
+                    "{name} must be between {} and {} pixels, got {value}",
+                    range.start(),
+                    range.end()
+                )));
+            }
+        }
+        if let Some(template) = &self.template
+            && !PathBuf::from(template).exists()
+        {
+            bail!(ToolError::not_found("Template image", template));
+        }
+        for step in self.steps.iter().flatten() {
+            match step {
+                RemovalStep::Remove(settings) => {
+                    if settings.steps.is_some() {
+                        bail!(ToolError::invalid_args(
+                            "steps cannot be nested inside a remove step",
+                        ));
+                    }
+                    settings.validate()?;
+                }
+                RemovalStep::Despeckle { kernel_size } => validate_despeckle_size(*kernel_size)?,
+            }
+        }
+        for filter in self.post_filters.iter().flatten() {
+            match filter {
+                PostFilter::Despeckle { size } => validate_despeckle_size(*size)?,
+                PostFilter::Denoise {
+                    strength: Some(strength),
+                } if !(1..=30).contains(strength) => {
+                    bail!(ToolError::invalid_args(
+                        "denoise strength must be between 1 and 30",
+                    ));
+                }
+                PostFilter::NormalizeContrast { clip: Some(clip) }
+                    if !(0.0..=10.0).contains(clip) =>
+                {
+                    bail!(ToolError::invalid_args(
+                        "normalize_contrast clip must be between 0 and 10 percent",
+                    ));
+                }
+                PostFilter::Denoise { .. } | PostFilter::NormalizeContrast { .. } => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Command-line flags understood by the removal scripts. DPI is left out
+    /// because each script takes it positionally, and `blank_pages`,
+    /// `duplicate_pages`, `adaptive_dpi` and `cmyk_output` because only the
+    /// PDF script takes them.
+    pub fn script_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some([x, y, w, h]) = self.region {
+            args.push("--region".to_string());
+            args.push(format!("{x},{y},{w},{h}"));
+        }
+        if let Some(algorithm) = &self.algorithm {
+            args.push("--algorithm".to_string());
+            args.push(algorithm.clone());
+        }
+        if let Some(template) = &self.template {
+            args.push("--template".to_string());
+            args.push(template.clone());
+        }
+        if let Some(mode) = &self.mode {
+            args.push("--mode".to_string());
+            args.push(mode.clone());
+        }
+        if let Some(radius) = self.inpaint_radius {
+            args.push(format!("--inpaint-radius={radius}"));
+        }
+        if let Some(padding) = self.mask_padding {
+            args.push(format!("--mask-padding={padding}"));
+        }
+        if let Some(feather) = self.feather {
+            args.push(format!("--feather={feather}"));
+        }
+        if let Some(post_filters) = &self.post_filters {
+            args.push("--post-filters".to_string());
+            args.push(serde_json::to_string(post_filters).unwrap_or_default());
+        }
+        if let Some(steps) = &self.steps {
+            // Post filters run once after all the steps, not per step
+            let base = RemovalSettings {
+                steps: None,
+                post_filters: None,
+                ..self.clone()
+            };
+            let resolved: Vec<RemovalStep> = steps
+                .iter()
+                .map(|step| match step {
+                    RemovalStep::Remove(settings) => {
+                        RemovalStep::Remove(Box::new((**settings).clone().or(base.clone())))
+                    }
+                    other => other.clone(),
+                })
+                .collect();
+            args.push("--steps".to_string());
+            args.push(serde_json::to_string(&resolved).unwrap_or_default());
+        }
+        args
+    }
+}
+
+/// Directory holding one `<name>.json` file per profile:
+/// `WATERMARK_PROFILES_DIR`, or `profiles` under the config directory.
+pub fn profiles_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var("WATERMARK_PROFILES_DIR") {
+        return PathBuf::from(dir);
+    }
+    config_dir().join("profiles")
+}
+
+fn builtin_profile() -> RemovalSettings {
+    RemovalSettings {
+        region: Some([0.80, 0.92, 0.20, 0.08]),
+        position: None,
+        size: None,
+        algorithm: Some("telea".to_string()),
+        template: None,
+        dpi: Some(200),
+        mode: Some("corner".to_string()),
+        inpaint_radius: Some(5),
+        mask_padding: Some(3),
+        feather: Some(0),
+        blank_pages: Some("process".to_string()),
+        duplicate_pages: Some("keep".to_string()),
+        adaptive_dpi: None,
+        cmyk_output: Some(false),
+        post_filters: None,
+        steps: None,
+    }
+}
+
+fn validate_despeckle_size(size: Option<u32>) -> Result<()> {
+    if let Some(size) = size
+        && (!(3..=15).contains(&size) || size % 2 == 0)
+    {
+        bail!(ToolError::invalid_args(
+            "despeckle size must be an odd number between 3 and 15",
+        ));
+    }
+    Ok(())
+}
+
+fn profile_path(name: &str) -> Result<PathBuf> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!(ToolError::invalid_args(format!(
+            "Invalid profile name: {name:?} (use letters, digits, '-' and '_')"
+        )));
+    }
+    Ok(profiles_dir().join(format!("{name}.json")))
+}
+
+/// Load a profile by name. Saved profiles shadow the built-in one.
+pub async fn load_profile(name: &str) -> Result<Option<RemovalSettings>> {
+    let path = profile_path(name)?;
+    match tokio::fs::read_to_string(&path).await {
+        Ok(text) => {
+            let settings = serde_json::from_str(&text)
+                .with_context(|| format!("Invalid profile file: {}", path.display()))?;
+            Ok(Some(settings))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Ok((name == BUILTIN_PROFILE).then(builtin_profile))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn save_profile(name: &str, settings: &RemovalSettings) -> Result<PathBuf> {
+    let path = profile_path(name)?;
+    tokio::fs::create_dir_all(profiles_dir()).await?;
+    tokio::fs::write(&path, serde_json::to_vec_pretty(settings)?).await?;
+    Ok(path)
+}
+
+/// Delete a saved profile. Returns `false` if no such file existed.
+pub async fn delete_profile(name: &str) -> Result<bool> {
+    let path = profile_path(name)?;
+    match tokio::fs::remove_file(&path).await {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// All profiles sorted by name, including the built-in one unless shadowed.
+/// Unreadable files are skipped.
+pub async fn list_profiles() -> Result<Vec<(String, RemovalSettings)>> {
+    let mut profiles = Vec::new();
+    if let Ok(mut entries) = tokio::fs::read_dir(profiles_dir()).await {
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let Some(name) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+            if let Ok(Some(settings)) = load_profile(&name).await {
+                profiles.push((name, settings));
+            }
+        }
+    }
+
+    if !profiles.iter().any(|(name, _)| name == BUILTIN_PROFILE) {
+        profiles.push((BUILTIN_PROFILE.to_string(), builtin_profile()));
+    }
+    profiles.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(profiles)
+}
+
+/// Settings for a removal call: explicit arguments win over the named
+/// profile, which wins over the config file's `[defaults]`.
+pub async fn resolve_settings(
+    explicit: RemovalSettings,
+    profile: Option<&str>,
+) -> Result<RemovalSettings> {
+    let settings = match profile {
+        Some(name) => {
+            let Some(saved) = load_profile(name).await? else {
+                bail!(ToolError::invalid_args(format!("Unknown profile: {name}")));
+            };
+            explicit.or(saved)
+        }
+        None => explicit,
+    };
+    let settings = settings.or(config().defaults.clone()).resolve_position()?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn settings(json: serde_json::Value) -> RemovalSettings {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn assert_region(settings: &RemovalSettings, expected: [f64; 4]) {
+        let region = settings.region.unwrap();
+        for (actual, expected) in region.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-9,
+                "{region:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn positions_become_regions() {
+        let resolved = settings(json!({ "position": "bottom_right" }))
+            .resolve_position()
+            .unwrap();
+        assert_region(&resolved, [0.8, 0.92, 0.2, 0.08]);
+        assert!(resolved.position.is_none() && resolved.size.is_none());
+
+        let resolved = settings(json!({ "position": "footer", "size": [100, 5] }))
+            .resolve_position()
+            .unwrap();
+        assert_region(&resolved, [0.0, 0.95, 1.0, 0.05]);
+
+        let resolved = settings(json!({ "position": "center" }))
+            .resolve_position()
+            .unwrap();
+        assert_region(&resolved, [0.3, 0.4, 0.4, 0.2]);
+
+        let resolved = settings(json!({ "position": "header", "size": [50, 10] }))
+            .resolve_position()
+            .unwrap();
+        assert_region(&resolved, [0.25, 0.0, 0.5, 0.1]);
+    }
+
+    #[test]
+    fn positions_resolve_inside_remove_steps() {
+        let resolved = settings(json!({
+            "steps": [
+                { "op": "remove", "position": "top_left", "size": [10, 10] },
+                { "op": "despeckle", "kernel_size": 5 }
+            ]
+        }))
+        .resolve_position()
+        .unwrap();
+        let steps = resolved.steps.unwrap();
+        let RemovalStep::Remove(step) = &steps[0] else {
+            panic!("expected a remove step");
+        };
+        assert_region(step, [0.0, 0.0, 0.1, 0.1]);
+        assert!(matches!(
+            steps[1],
+            RemovalStep::Despeckle {
+                kernel_size: Some(5)
+            }
+        ));
+    }
+
+    #[test]
+    fn bad_positions_are_rejected() {
+        let resolve = |json| settings(json).resolve_position();
+        assert!(resolve(json!({ "position": "middle" })).is_err());
+        assert!(resolve(json!({ "size": [10, 10] })).is_err());
+        assert!(resolve(json!({ "position": "footer", "size": [0, 5] })).is_err());
+        assert!(resolve(json!({ "position": "footer", "size": [101, 5] })).is_err());
+        assert!(resolve(json!({ "position": "footer", "region": [0, 0, 1, 1] })).is_err());
+    }
+
+    #[test]
+    fn layers_merge_before_the_position_resolves() {
+        // A call's size resizes a profile's position
+        let merged = settings(json!({ "size": [100, 5] }))
+            .or(settings(json!({ "position": "footer", "algorithm": "ns" })))
+            .resolve_position()
+            .unwrap();
+        assert_region(&merged, [0.0, 0.95, 1.0, 0.05]);
+        assert_eq!(merged.algorithm.as_deref(), Some("ns"));
+
+        // A call's position replaces a profile's region, and the reverse
+        let merged = settings(json!({ "position": "top_left" }))
+            .or(settings(json!({ "region": [0.5, 0.5, 0.1, 0.1] })))
+            .resolve_position()
+            .unwrap();
+        assert_region(&merged, [0.0, 0.0, 0.2, 0.08]);
+        let merged = settings(json!({ "region": [0.5, 0.5, 0.1, 0.1] }))
+            .or(settings(
+                json!({ "position": "top_left", "size": [10, 10] }),
+            ))
+            .resolve_position()
+            .unwrap();
+        assert_region(&merged, [0.5, 0.5, 0.1, 0.1]);
+    }
+
+    #[test]
+    fn regions_must_stay_inside_the_image() {
+        let validate = |json| settings(json).validate();
+        assert!(validate(json!({ "region": [0.8, 0.92, 0.2, 0.08] })).is_ok());
+        assert!(validate(json!({ "region": [0.0, 0.0, 1.0, 1.0] })).is_ok());
+        assert!(validate(json!({ "region": [0.9, 0.0, 0.2, 0.1] })).is_err());
+        assert!(validate(json!({ "region": [0.0, 0.95, 0.1, 0.1] })).is_err());
+        assert!(validate(json!({ "region": [-0.1, 0.0, 0.1, 0.1] })).is_err());
+        // Every position's default region passes
+        for (position, _) in POSITIONS {
+            let resolved = settings(json!({ "position": position }))
+                .resolve_position()
+                .unwrap();
+            assert!(resolved.validate().is_ok(), "{position}");
+        }
+    }
+
+    #[test]
+    fn despeckle_steps_take_an_odd_kernel_size() {
+        let step = |step| settings(json!({ "steps": [step] })).validate();
+        assert!(step(json!({ "op": "despeckle", "kernel_size": 5 })).is_ok());
+        // The name from before `kernel_size` still loads
+        assert!(step(json!({ "op": "despeckle", "size": 5 })).is_ok());
+        assert!(step(json!({ "op": "despeckle", "kernel_size": 4 })).is_err());
+        assert!(step(json!({ "op": "despeckle", "kernel_size": 17 })).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+//! Python interpreter selection - which executable runs the scripts
+//!
+//! Resolved once per process, in order: `WATERMARK_PYTHON`, `python` from
+//! the config file, the active `VIRTUAL_ENV`, a `.venv` in the working
+//! directory or next to the binary, and finally `python3` from `PATH`.
+
+use serde::Serialize;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tokio::process::Command;
+use tracing::info;
+use tracing::warn;
+
+use super::workspace::create_private_dir;
+use super::workspace::private_workspace;
+use super::workspace::workspace_root;
+use crate::config::config;
+
+/// The interpreter every tool runs its script with.
+#[derive(Clone, Debug, Serialize)]
+pub struct PythonInterpreter {
+    pub program: PathBuf,
+    /// How it was chosen, e.g. `WATERMARK_PYTHON` or `.venv next to the binary`
+    pub source: String,
+}
+
+static PYTHON: OnceLock<PythonInterpreter> = OnceLock::new();
+
+pub fn python_interpreter() -> &'static PythonInterpreter {
+    PYTHON.get_or_init(|| {
+        let python = detect_python();
+        info!(
+            "Using Python interpreter {} ({})",
+            python.program.display(),
+            python.source
+        );
+        python
+    })
+}
+
+/// A `Command` for the selected interpreter. In a private workspace the
+/// scripts' temporary files go to the workspace too.
+pub fn python_command() -> Command {
+    let mut cmd = Command::new(&python_interpreter().program);
+    if private_workspace() {
+        let root = workspace_root();
+        if let Err(e) = create_private_dir(&root) {
+            warn!("Failed to create the workspace {}: {e}", root.display());
+        }
+        cmd.env("TMPDIR", &root)
+            .env("TEMP", &root)
+            .env("TMP", &root);
+    }
+    cmd
+}
+
+fn detect_python() -> PythonInterpreter {
+    if let Some(program) = std::env::var_os("WATERMARK_PYTHON").filter(|p| !p.is_empty()) {
+        return PythonInterpreter {
+            program: PathBuf::from(program),
+            source: "WATERMARK_PYTHON".to_string(),
+        };
+    }
+
+    if let Some(program) = &config().python {
+        return PythonInterpreter {
+            program: program.clone(),
+            source: "config file".to_string(),
+        };
+    }
+
+    if let Some(venv) = std::env::var_os("VIRTUAL_ENV").filter(|p| !p.is_empty())
+        && let Some(program) = venv_python(Path::new(&venv))
+    {
+        return PythonInterpreter {
+            program,
+            source: "VIRTUAL_ENV".to_string(),
+        };
+    }
+
+    let mut candidates = Vec::new();
+    if let Ok(cwd) = std::env::current_dir() {
+        candidates.push((cwd.join(".venv"), ".venv in the working directory"));
+    }
+    if let Ok(exe_path) = std::env::current_exe()
+        && let Some(parent) = exe_path.parent()
+    {
+        candidates.push((parent.join(".venv"), ".venv next to the binary"));
+        candidates.push((parent.join("../.venv"), ".venv next to the binary"));
+    }
+    for (venv, source) in candidates {
+        if let Some(program) = venv_python(&venv) {
+            return PythonInterpreter {
+                program,
+                source: source.to_string(),
+            };
+        }
+    }
+
+    PythonInterpreter {
+        program: PathBuf::from("python3"),
+        source: "PATH".to_string(),
+    }
+}
+
+fn venv_python(venv: &Path) -> Option<PathBuf> {
+    let program = if cfg!(windows) {
+        venv.join("Scripts").join("python.exe")
+    } else {
+        venv.join("bin").join("python")
+    };
+    program.is_file().then_some(program)
+}
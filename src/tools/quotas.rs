@@ -0,0 +1,105 @@
+//! Resource quotas - limits on intermediate disk usage and rendered page size
+//!
+//! `WATERMARK_MAX_INTERMEDIATE_MB` (or the config file's
+//! `max_intermediate_mb`) caps the page images one `process_pdf` run keeps on
+//! disk at a time. `WATERMARK_MAX_IMAGE_MEGAPIXELS` (or
+//! `max_image_megapixels`) caps the size a PDF page may be rendered at and is
+//! checked before rendering starts. Neither is limited by default.
+
+use anyhow::Result;
+use std::path::Path;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use super::error::ToolError;
+use super::pdf_info::PdfInfo;
+use super::pdf_info::read_pdf_info;
+use crate::config::config;
+
+/// Byte limit on a run's intermediate page images, if any.
+pub(super) fn max_intermediate_bytes() -> Option<u64> {
+    std::env::var("WATERMARK_MAX_INTERMEDIATE_MB")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .or(config().max_intermediate_mb)
+        .filter(|&mb| mb > 0)
+        .map(|mb: u64| mb.saturating_mul(1024 * 1024))
+}
+
+/// Pixel limit on one rendered page, if any.
+pub(super) fn max_image_pixels() -> Option<u64> {
+    std::env::var("WATERMARK_MAX_IMAGE_MEGAPIXELS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .or(config().max_image_megapixels)
+        .filter(|&mp: &f64| mp > 0.0)
+        .map(|mp| (mp * 1_000_000.0) as u64)
+}
+
+/// Fail if any page of `info` would exceed [`max_image_pixels`] when
+/// rendered at `dpi`.
+pub(super) fn check_page_pixels(info: &PdfInfo, dpi: u32) -> Result<(), ToolError> {
+    let Some(limit) = max_image_pixels() else {
+        return Ok(());
+    };
+    let scale = f64::from(dpi) / 72.0;
+    let largest = info
+        .pages
+        .iter()
+        .map(|page| ((page.width_pt * scale) * (page.height_pt * scale)) as u64)
+        .max()
+        .unwrap_or_default();
+    if largest > limit {
+        return Err(ToolError::QuotaExceeded {
+            quota: "image_pixels",
+            limit,
+            requested: largest,
+        });
+    }
+    Ok(())
+}
+
+/// [`check_page_pixels`] for a PDF on disk. Only reads the PDF when a pixel
+/// limit is configured.
+pub(super) async fn check_render_size(pdf_path: &Path, dpi: u32) -> Result<()> {
+    if max_image_pixels().is_none() {
+        return Ok(());
+    }
+    check_page_pixels(&read_pdf_info(pdf_path).await?, dpi)?;
+    Ok(())
+}
+
+/// Running total of the intermediate bytes one pipeline run holds on disk,
+/// checked against [`max_intermediate_bytes`].
+pub(super) struct DiskBudget {
+    limit: Option<u64>,
+    used: AtomicU64,
+}
+
+impl DiskBudget {
+    pub(super) fn new() -> Self {
+        DiskBudget {
+            limit: max_intermediate_bytes(),
+            used: AtomicU64::new(0),
+        }
+    }
+
+    /// Count `bytes` of new intermediate files, failing once the total
+    /// exceeds the limit.
+    pub(super) fn charge(&self, bytes: u64) -> Result<(), ToolError> {
+        let used = self.used.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        match self.limit {
+            Some(limit) if used > limit => Err(ToolError::QuotaExceeded {
+                quota: "intermediate_disk",
+                limit,
+                requested: used,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Count `bytes` of intermediate files as deleted again.
+    pub(super) fn release(&self, bytes: u64) {
+        self.used.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
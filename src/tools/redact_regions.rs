@@ -0,0 +1,134 @@
+//! Redact Regions tool - fills rectangles with a solid color instead of inpainting
+
+use anyhow::Context;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tracing::info;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::python::python_command;
+use super::script_output::parse_json_result;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+
+#[derive(Deserialize)]
+struct RedactRegionsArgs {
+    image_path: Option<String>,
+    image_dir: Option<String>,
+    pdf_path: Option<String>,
+    output_path: String,
+    regions: Vec<RedactRegion>,
+    color: Option<String>,
+    dpi: Option<u32>,
+}
+
+/// One rectangle to fill, as fractions of the page size.
+#[derive(Deserialize, Serialize)]
+struct RedactRegion {
+    region: [f64; 4],
+    /// 1-based page (or image index in a directory); all pages when omitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
+}
+
+pub async fn handle_redact_regions(args: serde_json::Value) -> ToolResult {
+    let args: RedactRegionsArgs = parse_args(args)?;
+
+    let (flag, input) = match (&args.image_path, &args.image_dir, &args.pdf_path) {
+        (Some(path), None, None) => ("--image", path),
+        (None, Some(dir), None) => ("--dir", dir),
+        (None, None, Some(path)) => ("--pdf", path),
+        _ => {
+            return Err(ToolError::invalid_args(
+                "Exactly one of image_path, image_dir or pdf_path must be provided",
+            ));
+        }
+    };
+
+    if !PathBuf::from(input).exists() {
+        return Err(ToolError::not_found("Input", input));
+    }
+
+    if args.regions.is_empty() {
+        return Err(ToolError::invalid_args(
+            "regions must contain at least one rectangle",
+        ));
+    }
+    for entry in &args.regions {
+        let [x, y, w, h] = entry.region;
+        let in_range = entry.region.iter().all(|v| (0.0..=1.0).contains(v));
+        if !in_range || w <= 0.0 || h <= 0.0 || x + w > 1.0 + 1e-9 || y + h > 1.0 + 1e-9 {
+            return Err(ToolError::invalid_args(format!(
+                "Invalid region {:?}: expected [x, y, width, height] fractions within the page",
+                entry.region
+            )));
+        }
+        if entry.page == Some(0) {
+            return Err(ToolError::invalid_args("page numbers start at 1"));
+        }
+    }
+
+    let color = args.color.as_deref().unwrap_or("black");
+    let is_hex = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !matches!(color, "black" | "white") && !is_hex {
+        return Err(ToolError::invalid_args(format!(
+            "Invalid color '{color}': expected black, white or #rrggbb"
+        )));
+    }
+
+    let scripts_dir = scripts_dir()?;
+    let script_path = scripts_dir.join("redact_regions.py");
+
+    let mut cmd = python_command();
+    cmd.arg(&script_path)
+        .arg(flag)
+        .arg(input)
+        .arg("--output")
+        .arg(&args.output_path)
+        .arg("--regions")
+        .arg(serde_json::to_string(&args.regions)?)
+        .arg("--color")
+        .arg(color);
+    if let Some(dpi) = args.dpi {
+        cmd.arg("--dpi").arg(dpi.to_string());
+    }
+
+    info!(
+        "Redacting {} regions: {} -> {}",
+        args.regions.len(),
+        input,
+        args.output_path
+    );
+
+    let output = run_command(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))
+        .await
+        .context("Failed to execute redact_regions.py")?;
+
+    if !output.status.success() {
+        return Err(ToolError::script_failed(
+            "redact_regions.py",
+            &output.stderr,
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!("Successfully redacted regions.\n{stdout}"),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: parse_json_result(&stdout),
+    })
+}
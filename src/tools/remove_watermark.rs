@@ -2,135 +2,554 @@
 
 use anyhow::Context;
 use anyhow::Result;
+use anyhow::bail;
 use mcp_types::CallToolResult;
 use mcp_types::ContentBlock;
+use mcp_types::ImageContent;
 use mcp_types::TextContent;
 use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use std::path::Path;
 use std::path::PathBuf;
 use std::process::Stdio;
-use tokio::process::Command;
 use tracing::info;
 
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::glob::glob_match;
+use super::image_content::image_content_from_file;
+use super::image_content::image_content_from_png;
+use super::inline_input::decode_inline_input;
+use super::inline_input::sniff_image_extension;
+use super::inline_input::write_inline_input;
+use super::overwrite::check_output_file;
+use super::process_pdf::PageStatus;
+use super::profiles::RemovalSettings;
+use super::profiles::resolve_settings;
+use super::python::python_command;
+use super::script_output::parse_json_result;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+use super::subprocess::run_command_with_stdin;
+use crate::metrics;
+
 #[derive(Deserialize)]
 struct RemoveWatermarkArgs {
     image_path: Option<String>,
+    image_base64: Option<String>,
+    /// An MCP `image` content block, as hosts attach pasted screenshots
+    image: Option<ImageContent>,
     image_dir: Option<String>,
     output_dir: Option<String>,
+    overwrite: Option<bool>,
+    return_image: Option<bool>,
+    /// Stream the image through the script's stdin/stdout
+    pipe: Option<bool>,
+    #[serde(flatten)]
+    filter: DirFilter,
+    profile: Option<String>,
+    #[serde(flatten)]
+    settings: RemovalSettings,
+}
+
+/// What [`remove_watermark`] should clean.
+#[derive(Clone, Debug)]
+pub enum WatermarkInput {
+    Image(PathBuf),
+    /// The images in the directory that `DirFilter` selects
+    Dir(PathBuf, DirFilter),
+}
+
+/// Extensions cleaned in a directory when [`DirFilter::extensions`] is empty
+const DEFAULT_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "gif", "heic", "heif", "avif"];
+
+/// Formats OpenCV cannot write: the script decodes them through pillow-heif
+/// and saves the cleaned image as PNG
+const HEIF_EXTENSIONS: &[&str] = &["heic", "heif", "avif"];
+
+/// Name of the cleaned image for `image`: the same, except `.png` for
+/// HEIC/HEIF/AVIF inputs.
+pub(super) fn cleaned_name(image: &Path) -> PathBuf {
+    let heif = image
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| HEIF_EXTENSIONS.iter().any(|h| h.eq_ignore_ascii_case(ext)));
+    if heif {
+        image.with_extension("png")
+    } else {
+        image.to_path_buf()
+    }
+}
+
+/// Where the cleaned copy of `image_path` is written: in `output_dir` if
+/// given, else next to (or over) the input.
+pub(super) fn cleaned_image_path(image_path: &Path, output_dir: Option<&Path>) -> PathBuf {
+    match (output_dir, image_path.file_name()) {
+        (Some(dir), Some(name)) => dir.join(cleaned_name(Path::new(name))),
+        _ => cleaned_name(image_path),
+    }
+}
+
+/// The input argument a call cleans in place: `image_path` or `image_dir`
+/// of a `remove_watermark` call without `output_dir`. Piped calls return
+/// the image and only write with `output_dir`.
+pub(super) fn in_place_argument(tool: &str, arguments: &serde_json::Value) -> Option<&'static str> {
+    let given = |key: &str| arguments.get(key).and_then(serde_json::Value::as_str);
+    if tool != "remove_watermark"
+        || given("output_dir").is_some()
+        || arguments.get("pipe").and_then(serde_json::Value::as_bool) == Some(true)
+    {
+        return None;
+    }
+    ["image_path", "image_dir"]
+        .into_iter()
+        .find(|key| given(key).is_some())
 }
 
-pub async fn handle_remove_watermark(args: serde_json::Value) -> Result<CallToolResult> {
-    let args: RemoveWatermarkArgs = serde_json::from_value(args)?;
+/// Which images of a [`WatermarkInput::Dir`] are cleaned.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct DirFilter {
+    /// Also clean images in subdirectories. Outputs mirror the directory
+    /// tree under the output directory.
+    #[serde(default)]
+    pub recursive: bool,
+    /// Extensions to clean, e.g. `["png", "tiff"]`, matched case-insensitively.
+    /// Empty means png, jpg, jpeg, webp, gif, heic, heif and avif.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Glob patterns; when given, only images matching one of them are
+    /// cleaned. Patterns are matched against the path relative to the
+    /// directory, e.g. `page_*.png` or `scans/*`.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Glob patterns of images to leave alone, e.g. `*_thumb.*`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl DirFilter {
+    fn script_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.recursive {
+            args.push("--recursive".to_string());
+        }
+        if !self.extensions.is_empty() {
+            args.push(format!("--extensions={}", self.extensions.join(",")));
+        }
+        for pattern in &self.include {
+            args.push(format!("--include={pattern}"));
+        }
+        for pattern in &self.exclude {
+            args.push(format!("--exclude={pattern}"));
+        }
+        args
+    }
 
-    // Validate arguments
-    if args.image_path.is_none() && args.image_dir.is_none() {
-        return Ok(CallToolResult {
-            content: vec![ContentBlock::TextContent(TextContent {
-                r#type: "text".to_string(),
-                text: "Error: Either image_path or image_dir must be provided".to_string(),
-                annotations: None,
-            })],
-            is_error: Some(true),
-            structured_content: None,
-        });
+    fn matches_extension(&self, path: &Path) -> bool {
+        let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+            return false;
+        };
+        let wanted = |candidate: &str| candidate.trim_start_matches('.').eq_ignore_ascii_case(ext);
+        if self.extensions.is_empty() {
+            DEFAULT_EXTENSIONS.iter().any(|candidate| wanted(candidate))
+        } else {
+            self.extensions.iter().any(|candidate| wanted(candidate))
+        }
     }
 
-    let scripts_dir = get_scripts_dir()?;
+    fn matches_patterns(&self, relative: &Path) -> bool {
+        // Patterns use `/` whatever the platform, like the script's
+        let relative = relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        (self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| glob_match(pattern, &relative)))
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| glob_match(pattern, &relative))
+    }
+
+    /// Paths, relative to `dir`, of the images `remove_watermark.py` will
+    /// clean, skipping `output_dir` if it lies inside `dir`.
+    fn find_images(&self, dir: &Path, output_dir: Option<&Path>) -> Vec<PathBuf> {
+        let skip = output_dir.and_then(|dir| dir.canonicalize().ok());
+        let mut images = Vec::new();
+        let mut pending = vec![PathBuf::new()];
+        while let Some(relative) = pending.pop() {
+            let Ok(entries) = std::fs::read_dir(dir.join(&relative)) else {
+                continue;
+            };
+            for entry in entries.filter_map(std::result::Result::ok) {
+                let path = entry.path();
+                let name = entry.file_name();
+                if path.is_dir() {
+                    if self.recursive && path.canonicalize().ok() != skip {
+                        pending.push(relative.join(name));
+                    }
+                } else if path.is_file()
+                    && self.matches_extension(&path)
+                    && !name.to_string_lossy().ends_with("_processed.png")
+                {
+                    let image = relative.join(name);
+                    if self.matches_patterns(&image) {
+                        images.push(image);
+                    }
+                }
+            }
+        }
+        images.sort();
+        images
+    }
+}
+
+/// Result of [`remove_watermark`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoveWatermarkOutput {
+    /// Images where a watermark was found and removed
+    pub processed: usize,
+    /// Images without a detectable watermark
+    pub skipped: usize,
+    /// Images that could not be read
+    #[serde(default)]
+    pub failed: usize,
+    pub output_dir: PathBuf,
+    /// Outcome of each image, in the order they were cleaned
+    #[serde(default)]
+    pub files: Vec<ImageResult>,
+    /// Human-readable script output
+    #[serde(skip)]
+    pub log: String,
+}
+
+/// One image of a [`remove_watermark`] run, as reported in its structured
+/// result.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageResult {
+    pub input_path: PathBuf,
+    /// The cleaned image; `None` for a `failed` image
+    pub output_path: Option<PathBuf>,
+    pub status: PageStatus,
+    /// How sure the detection was, from 0 to 1: the template match score
+    /// with a `template`, otherwise the share of the searched area that
+    /// looked like watermark
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Why a `failed` image failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Remove watermarks from one image or a directory of images.
+///
+/// Cleaned files keep their names and go to `output_dir`, or overwrite the
+/// inputs when it is `None`. `settings` are used as given; call
+/// [`resolve_settings`] first to apply a profile and the config defaults.
+pub async fn remove_watermark(
+    input: &WatermarkInput,
+    output_dir: Option<&Path>,
+    settings: &RemovalSettings,
+) -> Result<RemoveWatermarkOutput> {
+    #[cfg(feature = "embedded-python")]
+    if let WatermarkInput::Image(image_path) = input
+        && image_path.exists()
+        && super::embedded_python::enabled()
+    {
+        if let Some(output_dir) = output_dir {
+            tokio::fs::create_dir_all(output_dir).await?;
+        }
+        let result =
+            super::embedded_python::remove_watermark_image(image_path, output_dir, settings)
+                .await?;
+        metrics::add_pages(1);
+        return Ok(result);
+    }
+    remove_watermark_subprocess(input, output_dir, settings).await
+}
+
+/// [`remove_watermark`] in a `remove_watermark.py` process, even when the
+/// embedded interpreter is available.
+pub(super) async fn remove_watermark_subprocess(
+    input: &WatermarkInput,
+    output_dir: Option<&Path>,
+    settings: &RemovalSettings,
+) -> Result<RemoveWatermarkOutput> {
+    let scripts_dir = scripts_dir()?;
     let script_path = scripts_dir.join("remove_watermark.py");
 
-    let mut cmd = Command::new("python3");
+    let mut cmd = python_command();
     cmd.arg(&script_path);
 
-    if let Some(image_path) = &args.image_path {
-        let path = PathBuf::from(image_path);
-        if !path.exists() {
-            return Ok(CallToolResult {
-                content: vec![ContentBlock::TextContent(TextContent {
-                    r#type: "text".to_string(),
-                    text: format!("Error: Image file not found: {image_path}"),
-                    annotations: None,
-                })],
-                is_error: Some(true),
-                structured_content: None,
-            });
-        }
-        cmd.arg("--image").arg(image_path);
-        info!("Removing watermark from image: {}", image_path);
-    } else if let Some(image_dir) = &args.image_dir {
-        let path = PathBuf::from(image_dir);
-        if !path.exists() || !path.is_dir() {
-            return Ok(CallToolResult {
-                content: vec![ContentBlock::TextContent(TextContent {
-                    r#type: "text".to_string(),
-                    text: format!("Error: Directory not found: {image_dir}"),
-                    annotations: None,
-                })],
-                is_error: Some(true),
-                structured_content: None,
-            });
-        }
-        cmd.arg("--dir").arg(image_dir);
-        info!("Removing watermarks from directory: {}", image_dir);
-    }
-
-    if let Some(output_dir) = &args.output_dir {
+    match input {
+        WatermarkInput::Image(image_path) => {
+            if !image_path.exists() {
+                bail!(ToolError::not_found("Image file", image_path));
+            }
+            cmd.arg("--image").arg(image_path);
+            info!("Removing watermark from image: {}", image_path.display());
+        }
+        WatermarkInput::Dir(image_dir, filter) => {
+            if !image_dir.is_dir() {
+                bail!(ToolError::not_found("Directory", image_dir));
+            }
+            cmd.arg("--dir").arg(image_dir).args(filter.script_args());
+            info!(
+                "Removing watermarks from directory: {}",
+                image_dir.display()
+            );
+        }
+    }
+
+    if let Some(output_dir) = output_dir {
         tokio::fs::create_dir_all(output_dir).await?;
         cmd.arg("--output").arg(output_dir);
     }
+    cmd.args(settings.script_args());
 
-    let output = cmd
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
+    let output = run_command(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))
         .await
         .context("Failed to execute remove_watermark.py")?;
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Ok(CallToolResult {
-            content: vec![ContentBlock::TextContent(TextContent {
-                r#type: "text".to_string(),
-                text: format!("Error running remove_watermark.py: {stderr}"),
-                annotations: None,
-            })],
-            is_error: Some(true),
-            structured_content: None,
-        });
+        bail!(ToolError::script_failed(
+            "remove_watermark.py",
+            &output.stderr
+        ));
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let mut result: RemoveWatermarkOutput = parse_json_result(&stdout)
+        .and_then(|json| serde_json::from_value(json).ok())
+        .context("remove_watermark.py did not report a result")?;
+    result.log = stdout;
+    metrics::add_pages(result.processed + result.skipped);
+    Ok(result)
+}
+
+/// A cleaned image held in memory, from [`remove_watermark_bytes`].
+#[derive(Clone, Debug)]
+pub struct CleanedImage {
+    /// The result, PNG-encoded
+    pub png: Vec<u8>,
+    /// Whether a watermark was found and removed
+    pub removed: bool,
+}
+
+/// Remove the watermark from an encoded image (PNG, JPEG, WebP, ...) in
+/// memory. The data is streamed through the script's stdin and stdout, so
+/// neither the input nor the result is written to disk.
+pub async fn remove_watermark_bytes(
+    image: Vec<u8>,
+    settings: &RemovalSettings,
+) -> Result<CleanedImage> {
+    let scripts_dir = scripts_dir()?;
+    let mut cmd = python_command();
+    cmd.arg(scripts_dir.join("remove_watermark.py"))
+        .arg("--stdio")
+        .args(settings.script_args());
+
+    info!("Removing watermark from {} bytes over stdio", image.len());
+    let output = run_command_with_stdin(&mut cmd, image)
+        .await
+        .context("Failed to execute remove_watermark.py")?;
+
+    if !output.status.success() {
+        bail!(ToolError::script_failed(
+            "remove_watermark.py",
+            &output.stderr
+        ));
+    }
+
+    // Messages and the JSON result go to stderr; stdout is the image
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let result =
+        parse_json_result(&stderr).context("remove_watermark.py did not report a result")?;
+    metrics::add_pages(1);
+    Ok(CleanedImage {
+        png: output.stdout,
+        removed: result["processed"].as_u64().unwrap_or_default() > 0,
+    })
+}
+
+pub async fn handle_remove_watermark(args: serde_json::Value) -> ToolResult {
+    let mut args: RemoveWatermarkArgs = parse_args(args)?;
+
+    if let Some(block) = args.image.take() {
+        if args.image_path.is_some() || args.image_base64.is_some() || args.image_dir.is_some() {
+            return Err(ToolError::invalid_args(
+                "Pass image alone, without image_path, image_base64 or image_dir",
+            ));
+        }
+        if block.r#type != "image" {
+            return Err(ToolError::invalid_args(format!(
+                "image must be an image content block, not {}",
+                block.r#type
+            )));
+        }
+        // A pasted image is answered with the cleaned image
+        args.return_image.get_or_insert(true);
+        args.image_base64 = Some(block.data);
+    }
+
+    if args.pipe.unwrap_or(false) {
+        return handle_piped(args).await;
+    }
+
+    // Materialize inline content so the rest of the pipeline sees a plain file
+    let inline = args.image_path.is_none() && args.image_dir.is_none();
+    if inline && let Some(data) = &args.image_base64 {
+        let path = write_inline_input(data, "image", sniff_image_extension(data)).await?;
+        args.image_path = Some(path.to_string_lossy().to_string());
+    }
+
+    let input = match (&args.image_path, &args.image_dir) {
+        (Some(path), _) => WatermarkInput::Image(PathBuf::from(path)),
+        (None, Some(dir)) => WatermarkInput::Dir(PathBuf::from(dir), args.filter),
+        (None, None) => {
+            return Err(ToolError::invalid_args(
+                "One of image_path, image_base64 or image_dir must be provided",
+            ));
+        }
+    };
+
+    let settings = resolve_settings(args.settings, args.profile.as_deref()).await?;
+
+    let output_dir = args.output_dir.map(PathBuf::from);
+    // Inline images live in the workspace and are cleaned in place freely
+    let overwrite = args.overwrite.unwrap_or(false) || inline;
+    match &input {
+        WatermarkInput::Image(path) => {
+            check_output_file(&cleaned_image_path(path, output_dir.as_deref()), overwrite)?;
+        }
+        WatermarkInput::Dir(dir, filter) => {
+            let target = output_dir.as_deref().unwrap_or(dir);
+            for image in filter.find_images(dir, output_dir.as_deref()) {
+                check_output_file(&target.join(cleaned_name(&image)), overwrite)?;
+            }
+        }
+    }
+    let result = remove_watermark(&input, output_dir.as_deref(), &settings).await?;
+
+    let summary = match &input {
+        WatermarkInput::Image(_) => "Successfully removed watermarks.".to_string(),
+        WatermarkInput::Dir(..) => {
+            let count = |status| {
+                result
+                    .files
+                    .iter()
+                    .filter(|file| file.status == status)
+                    .count()
+            };
+            format!(
+                "Cleaned {} images: {} watermark removed, {} no watermark, {} failed.",
+                result.files.len(),
+                count(PageStatus::WatermarkRemoved),
+                count(PageStatus::NoWatermark),
+                count(PageStatus::Failed)
+            )
+        }
+    };
+    let mut content = vec![ContentBlock::TextContent(TextContent {
+        r#type: "text".to_string(),
+        text: format!("{summary}\n{}", result.log),
+        annotations: None,
+    })];
+
+    // Single-image calls may ask for the cleaned image inline
+    if args.return_image.unwrap_or(false)
+        && let WatermarkInput::Image(image_path) = &input
+    {
+        let result_path = cleaned_image_path(image_path, output_dir.as_deref());
+        content.push(ContentBlock::ImageContent(
+            image_content_from_file(&result_path).await?,
+        ));
+    }
 
     Ok(CallToolResult {
-        content: vec![ContentBlock::TextContent(TextContent {
-            r#type: "text".to_string(),
-            text: format!("Successfully removed watermarks.\n{stdout}"),
-            annotations: None,
-        })],
+        content,
         is_error: Some(false),
-        structured_content: None,
+        structured_content: Some(serde_json::to_value(&result)?),
     })
 }
 
-fn get_scripts_dir() -> Result<PathBuf> {
-    if let Ok(exe_path) = std::env::current_exe()
-        && let Some(parent) = exe_path.parent()
-    {
-        let possible_paths = vec![
-            parent.join("../../../watermark-remover-mcp-server/scripts"),
-            parent.join("../../watermark-remover-mcp-server/scripts"),
-            parent.join("scripts"),
-        ];
-
-        for path in possible_paths {
-            if path.exists() {
-                return Ok(path.canonicalize()?);
+/// `pipe: true`: clean one image without temp files. The result is returned
+/// inline, and written to `output_dir` as `<name>.png` only if one is given.
+async fn handle_piped(args: RemoveWatermarkArgs) -> ToolResult {
+    let (image, name) = match (&args.image_path, &args.image_base64, &args.image_dir) {
+        (Some(path), _, _) => {
+            let path = PathBuf::from(path);
+            if !path.exists() {
+                return Err(ToolError::not_found("Image file", path));
             }
+            let name = path
+                .file_stem()
+                .map_or_else(|| "image".into(), |stem| stem.to_string_lossy().to_string());
+            (tokio::fs::read(&path).await?, name)
+        }
+        (None, Some(data), _) => (decode_inline_input(data)?, "image".to_string()),
+        (None, None, _) => {
+            return Err(ToolError::invalid_args(
+                "pipe works on a single image: provide image_path or image_base64",
+            ));
         }
+    };
+
+    let settings = resolve_settings(args.settings, args.profile.as_deref()).await?;
+
+    let output_path = args
+        .output_dir
+        .as_ref()
+        .map(|dir| Path::new(dir).join(format!("{name}.png")));
+    if let Some(output_path) = &output_path {
+        check_output_file(output_path, args.overwrite.unwrap_or(false))?;
     }
 
-    if let Ok(scripts_dir) = std::env::var("WATERMARK_SCRIPTS_DIR") {
-        return Ok(PathBuf::from(scripts_dir));
+    let cleaned = remove_watermark_bytes(image, &settings).await?;
+
+    if let Some(output_path) = &output_path {
+        if let Some(parent) = output_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(output_path, &cleaned.png).await?;
+        metrics::add_bytes_written(cleaned.png.len() as u64);
     }
 
-    let cwd = std::env::current_dir()?;
-    Ok(cwd.join("scripts"))
+    let mut text = format!(
+        "Successfully removed watermarks.\n{}",
+        if cleaned.removed {
+            "  ✓ Watermark removed"
+        } else {
+            "  ○ No watermark detected"
+        }
+    );
+    if let Some(output_path) = &output_path {
+        text.push_str(&format!("\nSaved to: {}", output_path.display()));
+    }
+    let mut content = vec![ContentBlock::TextContent(TextContent {
+        r#type: "text".to_string(),
+        text,
+        annotations: None,
+    })];
+    if output_path.is_none() || args.return_image.unwrap_or(false) {
+        content.push(ContentBlock::ImageContent(image_content_from_png(
+            &cleaned.png,
+        )));
+    }
+
+    Ok(CallToolResult {
+        content,
+        is_error: Some(false),
+        structured_content: Some(json!({
+            "processed": usize::from(cleaned.removed),
+            "skipped": usize::from(!cleaned.removed),
+            "output_path": output_path,
+        })),
+    })
 }
@@ -0,0 +1,121 @@
+//! Resource links - points the client at the files a tool call produced
+//!
+//! Successful results get a `resource_link` content block (a `file://` URI,
+//! MIME type and size) for every PDF, image or ZIP file they name under an
+//! output key of the structured content, so hosts can offer the files for
+//! download or preview. Output directories are listed too, keeping only the
+//! files written during the call.
+
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::ResourceLink;
+use serde_json::Value;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use super::image_content::image_mime_type;
+use super::roots::file_uri;
+
+/// Structured-content keys naming a produced file
+const OUTPUT_FILE_KEYS: &[&str] = &["output_path", "output_zip", "diff_path"];
+/// Structured-content keys naming a directory files were written into
+const OUTPUT_DIR_KEYS: &[&str] = &["output_dir", "images_output_dir"];
+/// File systems with coarse timestamps may date a new file slightly earlier
+const MTIME_SLACK: Duration = Duration::from_secs(2);
+
+/// Append a resource link for each output of `result`, a call that started
+/// at `started`.
+pub(super) fn add_resource_links(result: &mut CallToolResult, started: SystemTime) {
+    if result.is_error == Some(true) {
+        return;
+    }
+    let Some(structured) = &result.structured_content else {
+        return;
+    };
+    let mut files = Vec::new();
+    collect_outputs(structured, started - MTIME_SLACK, &mut files);
+    files.sort();
+    files.dedup();
+    result.content.extend(
+        files
+            .iter()
+            .filter_map(|path| resource_link(path))
+            .map(ContentBlock::ResourceLink),
+    );
+}
+
+fn collect_outputs(value: &Value, since: SystemTime, files: &mut Vec<PathBuf>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match value.as_str() {
+                    Some(path) if OUTPUT_FILE_KEYS.contains(&key.as_str()) => {
+                        files.push(PathBuf::from(path));
+                    }
+                    Some(dir) if OUTPUT_DIR_KEYS.contains(&key.as_str()) => {
+                        files.extend(written_since(Path::new(dir), since));
+                    }
+                    _ => collect_outputs(value, since, files),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_outputs(item, since, files);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Files in `dir` and its subdirectories modified at or after `since`.
+fn written_since(dir: &Path, since: SystemTime) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(std::result::Result::ok) {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                pending.push(entry.path());
+            } else if meta.modified().is_ok_and(|modified| modified >= since) {
+                files.push(entry.path());
+            }
+        }
+    }
+    files
+}
+
+/// A link to `path` if it is an existing PDF, image or ZIP file.
+fn resource_link(path: &Path) -> Option<ResourceLink> {
+    let mime_type = mime_type(path)?;
+    let meta = std::fs::metadata(path).ok().filter(|meta| meta.is_file())?;
+    let path = std::path::absolute(path).ok()?;
+    Some(ResourceLink {
+        r#type: "resource_link".to_string(),
+        uri: file_uri(&path),
+        name: path.file_name()?.to_string_lossy().to_string(),
+        mime_type: Some(mime_type.to_string()),
+        size: i64::try_from(meta.len()).ok(),
+        title: None,
+        description: None,
+        annotations: None,
+    })
+}
+
+fn mime_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_string_lossy().to_ascii_lowercase();
+    match ext.as_str() {
+        "pdf" => Some("application/pdf"),
+        "zip" => Some("application/zip"),
+        "png" | "jpg" | "jpeg" | "webp" | "gif" | "tif" | "tiff" | "bmp" | "heic" | "heif"
+        | "avif" => Some(image_mime_type(path)),
+        _ => None,
+    }
+}
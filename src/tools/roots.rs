@@ -0,0 +1,123 @@
+//! Workspace root - where relative path arguments are resolved
+//!
+//! Agents often pass paths like `scans/report.pdf`. Such relative path
+//! arguments are joined onto a workspace root before anything else looks at
+//! them: `WATERMARK_WORKSPACE_ROOT`, the config file's `workspace_root`, or,
+//! when neither is set, the first root the client reports through
+//! `roots/list`. Without any root, relative paths keep resolving against the
+//! server's working directory.
+//!
+//! The absolute paths used are echoed in the result's
+//! `structuredContent.resolved_paths`, keyed by argument name.
+
+use mcp_types::CallToolResult;
+use serde_json::Map;
+use serde_json::Value;
+use std::path::Path;
+use std::path::PathBuf;
+
+use super::allowlist::is_path_key;
+use crate::config::config;
+
+/// The configured workspace root, if any.
+pub fn configured_root() -> Option<PathBuf> {
+    std::env::var_os("WATERMARK_WORKSPACE_ROOT")
+        .filter(|root| !root.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config().workspace_root.clone())
+}
+
+/// The local directory of a client root, which must be a `file://` URI.
+pub fn root_from_uri(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    // `file://localhost/x` and `file:///x` name the same path
+    let path = path.strip_prefix("localhost").unwrap_or(path);
+    let path = percent_decode(path)?;
+    let path = PathBuf::from(path);
+    path.is_absolute().then_some(path)
+}
+
+/// The `file://` URI of the absolute path `path`.
+pub(super) fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(char::from(byte));
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    uri
+}
+
+pub(super) fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Join every relative path argument, at any depth, onto `root`. Returns
+/// the resolved top-level arguments.
+pub(super) fn resolve_relative_paths(arguments: &mut Value, root: &Path) -> Map<String, Value> {
+    let mut resolved = Map::new();
+    if let Value::Object(map) = arguments {
+        for (key, value) in map.iter_mut() {
+            if resolve_value(key, value, root) {
+                resolved.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    resolved
+}
+
+/// Resolve `value`, the argument `key`; returns whether it was itself a
+/// path that got resolved.
+fn resolve_value(key: &str, value: &mut Value, root: &Path) -> bool {
+    match value {
+        Value::String(path) if is_path_key(key) => {
+            // URIs and empty strings are not file paths
+            if path.is_empty() || path.contains("://") || Path::new(path.as_str()).is_absolute() {
+                return false;
+            }
+            *path = root.join(path.as_str()).to_string_lossy().to_string();
+            true
+        }
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                resolve_value(key, value, root);
+            }
+            false
+        }
+        Value::Array(items) => {
+            for item in items {
+                resolve_value(key, item, root);
+            }
+            false
+        }
+        _ => false,
+    }
+}
+
+/// Add `resolved` to the result's structured content.
+pub(super) fn echo_resolved_paths(result: &mut CallToolResult, resolved: Map<String, Value>) {
+    match &mut result.structured_content {
+        Some(Value::Object(structured)) => {
+            structured.insert("resolved_paths".to_string(), Value::Object(resolved));
+        }
+        Some(_) => {}
+        None => {
+            result.structured_content = Some(serde_json::json!({ "resolved_paths": resolved }));
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! Helpers for reading the output printed by the python scripts
+
+/// Extract the `JSON_RESULT:{...}` payload that every script prints as its
+/// final machine-readable line.
+pub fn parse_json_result(stdout: &str) -> Option<serde_json::Value> {
+    stdout
+        .lines()
+        .rev()
+        .find_map(|line| line.trim().strip_prefix("JSON_RESULT:"))
+        .and_then(|json| serde_json::from_str(json).ok())
+}
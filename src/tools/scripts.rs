@@ -0,0 +1,178 @@
+//! Bundled Python scripts - embedded at build time and extracted on first use
+//!
+//! The scripts in `scripts/` are compiled into the binary, so an installed
+//! server does not depend on where it was unpacked. They are written to a
+//! cache directory named after the crate version and a hash of their
+//! contents, so upgrades never run stale copies and unchanged installs reuse
+//! the same files.
+
+use anyhow::Context;
+use anyhow::Result;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::info;
+
+use crate::config::config;
+
+/// `(file name, contents)` of every script the tools run
+const BUNDLED_SCRIPTS: &[(&str, &str)] = &[
+    (
+        "add_watermark.py",
+        include_str!("../../scripts/add_watermark.py"),
+    ),
+    (
+        "adjust_image.py",
+        include_str!("../../scripts/adjust_image.py"),
+    ),
+    (
+        "compare_images.py",
+        include_str!("../../scripts/compare_images.py"),
+    ),
+    (
+        "convert_image.py",
+        include_str!("../../scripts/convert_image.py"),
+    ),
+    (
+        "document_to_pdf.py",
+        include_str!("../../scripts/document_to_pdf.py"),
+    ),
+    (
+        "evaluate_algorithms.py",
+        include_str!("../../scripts/evaluate_algorithms.py"),
+    ),
+    (
+        "images_to_pdf.py",
+        include_str!("../../scripts/images_to_pdf.py"),
+    ),
+    ("pdf_info.py", include_str!("../../scripts/pdf_info.py")),
+    (
+        "pdf_to_images.py",
+        include_str!("../../scripts/pdf_to_images.py"),
+    ),
+    (
+        "preview_page.py",
+        include_str!("../../scripts/preview_page.py"),
+    ),
+    (
+        "process_pdf.py",
+        include_str!("../../scripts/process_pdf.py"),
+    ),
+    (
+        "process_pdf_to_images.py",
+        include_str!("../../scripts/process_pdf_to_images.py"),
+    ),
+    (
+        "process_zip.py",
+        include_str!("../../scripts/process_zip.py"),
+    ),
+    ("rasterize.py", include_str!("../../scripts/rasterize.py")),
+    (
+        "redact_regions.py",
+        include_str!("../../scripts/redact_regions.py"),
+    ),
+    (
+        "remove_watermark.py",
+        include_str!("../../scripts/remove_watermark.py"),
+    ),
+    ("thumbnails.py", include_str!("../../scripts/thumbnails.py")),
+];
+
+static SCRIPTS_DIR: OnceLock<std::result::Result<PathBuf, String>> = OnceLock::new();
+
+/// Directory the tools load their Python scripts from.
+///
+/// An existing `WATERMARK_SCRIPTS_DIR` or the config file's `scripts_dir`
+/// wins, so scripts can be edited without rebuilding. Otherwise the bundled
+/// copies are extracted once per process.
+pub fn scripts_dir() -> Result<PathBuf> {
+    SCRIPTS_DIR
+        .get_or_init(|| resolve_scripts_dir().map_err(|e| format!("{e:#}")))
+        .clone()
+        .map_err(anyhow::Error::msg)
+}
+
+/// Names of the bundled scripts, for the environment report.
+pub fn bundled_script_names() -> impl Iterator<Item = &'static str> {
+    BUNDLED_SCRIPTS.iter().map(|(name, _)| *name)
+}
+
+fn resolve_scripts_dir() -> Result<PathBuf> {
+    if let Ok(scripts_dir) = std::env::var("WATERMARK_SCRIPTS_DIR") {
+        let path = PathBuf::from(&scripts_dir);
+        if path.is_dir() {
+            info!(
+                "Using scripts from WATERMARK_SCRIPTS_DIR: {}",
+                path.display()
+            );
+            return Ok(path);
+        }
+    }
+    if let Some(path) = &config().scripts_dir {
+        if !path.is_dir() {
+            anyhow::bail!(
+                "scripts_dir from the config file does not exist: {}",
+                path.display()
+            );
+        }
+        info!("Using scripts from the config file: {}", path.display());
+        return Ok(path.clone());
+    }
+
+    let dir = cache_root().join("watermark-remover").join(format!(
+        "scripts-{}-{:016x}",
+        env!("CARGO_PKG_VERSION"),
+        bundle_hash()
+    ));
+    extract_scripts(&dir)?;
+    info!("Using bundled scripts extracted to {}", dir.display());
+    Ok(dir)
+}
+
+/// Write any missing or modified script into `dir`. Each file goes through a
+/// temp name and a rename so concurrent servers never see a partial script.
+fn extract_scripts(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create scripts directory {}", dir.display()))?;
+
+    for (name, contents) in BUNDLED_SCRIPTS {
+        let path = dir.join(name);
+        if std::fs::read(&path).is_ok_and(|existing| existing == contents.as_bytes()) {
+            continue;
+        }
+        let tmp = dir.join(format!(".{name}.{}.tmp", std::process::id()));
+        std::fs::write(&tmp, contents)
+            .and_then(|()| std::fs::rename(&tmp, &path))
+            .with_context(|| format!("Failed to extract {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// `$XDG_CACHE_HOME`, `~/.cache` or `%LOCALAPPDATA%`, falling back to the
+/// system temp dir.
+pub(super) fn cache_root() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    if cfg!(windows)
+        && let Some(dir) = std::env::var_os("LOCALAPPDATA")
+    {
+        return PathBuf::from(dir);
+    }
+    if let Some(home) = std::env::var_os("HOME").filter(|h| !h.is_empty()) {
+        return PathBuf::from(home).join(".cache");
+    }
+    std::env::temp_dir()
+}
+
+/// FNV-1a over every script name and body.
+fn bundle_hash() -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for (name, contents) in BUNDLED_SCRIPTS {
+        for byte in name.bytes().chain([0]).chain(contents.bytes()) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
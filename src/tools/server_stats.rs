@@ -0,0 +1,61 @@
+//! Server Stats tool - call counters, pipeline totals and current load
+
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde_json::json;
+
+use super::error::ToolResult;
+use super::subprocess::busy_process_slots;
+use super::subprocess::max_processes;
+use crate::jobs::JobManager;
+use crate::jobs::JobStatus;
+use crate::metrics;
+
+pub async fn handle_server_stats(_args: serde_json::Value, jobs: &JobManager) -> ToolResult {
+    let stats = metrics::snapshot();
+    let running_jobs = jobs
+        .list()
+        .iter()
+        .filter(|job| job.status == JobStatus::Running)
+        .count();
+    let busy = busy_process_slots();
+    let max = max_processes();
+
+    let lines: Vec<String> = stats
+        .tools
+        .iter()
+        .map(|(tool, s)| {
+            format!(
+                "  {tool}: {} calls, {} errors, avg {} ms, max {} ms",
+                s.calls,
+                s.errors,
+                s.total_ms / s.calls.max(1),
+                s.max_ms
+            )
+        })
+        .collect();
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!(
+                "Uptime: {}s\nTool calls: {} ({} errors)\nPages processed: {}\nBytes written: {}\nNotifications dropped: {}\nRunning jobs: {running_jobs}\nSubprocesses: {busy}/{max}\n{}",
+                stats.uptime_secs,
+                stats.tool_calls,
+                stats.tool_errors,
+                stats.pages_processed,
+                stats.bytes_written,
+                stats.notifications_dropped,
+                lines.join("\n")
+            ),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(json!({
+            "stats": stats,
+            "running_jobs": running_jobs,
+            "subprocesses": { "busy": busy, "max": max },
+        })),
+    })
+}
@@ -0,0 +1,406 @@
+//! Subprocess limits - bounds how many helper processes run at once and for how long
+//!
+//! Every python/poppler child started by a tool takes a slot from one
+//! process-wide semaphore first, so background jobs, watches and foreground
+//! calls together never run more than `WATERMARK_MAX_PROCESSES` children.
+//! Children also run in their own process group and are killed, together
+//! with anything they started, after `WATERMARK_PROCESS_TIMEOUT_SECS`.
+//!
+//! Each running group is tagged with the client whose call, job or watch
+//! started it, so `abort_all` only reaches that client's children, and with
+//! its background job, so `job_cancel` kills the job's children before the
+//! job's task is aborted.
+//!
+//! Their output is read line by line while they run: each line is logged
+//! under the `subprocess` target and forwarded to the client that started
+//! the call as a `notifications/message`, so long jobs can be followed live.
+
+use mcp_types::LoggingLevel;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::process::Output;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::process::Command;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tracing::debug;
+use tracing::warn;
+
+use super::error::ToolError;
+use crate::config::config;
+use crate::message_processor::NotificationSender;
+
+/// Default for `WATERMARK_PROCESS_TIMEOUT_SECS`: long enough for a few hundred
+/// pages at 300 DPI, short enough that a hung poppler call is noticed
+const DEFAULT_PROCESS_TIMEOUT_SECS: u64 = 1800;
+
+tokio::task_local! {
+    /// Client receiving the output of scripts run by the current task
+    static OUTPUT_CLIENT: NotificationSender;
+    /// Background job the current task runs for
+    static CURRENT_JOB: String;
+}
+
+static PROCESS_SLOTS: LazyLock<Arc<Semaphore>> =
+    LazyLock::new(|| Arc::new(Semaphore::new(max_processes())));
+
+/// Process groups of the children currently running, with what started
+/// each, for [`kill_client_process_groups`] and [`kill_job_process_groups`]
+static PROCESS_GROUPS: LazyLock<Mutex<HashMap<u32, GroupOwner>>> = LazyLock::new(Mutex::default);
+
+/// Client and background job a process group was started for
+struct GroupOwner {
+    client: Option<NotificationSender>,
+    job: Option<String>,
+}
+
+/// Limit from `WATERMARK_MAX_PROCESSES` or the config file's
+/// `max_processes`, defaulting to the number of CPUs.
+pub fn max_processes() -> usize {
+    std::env::var("WATERMARK_MAX_PROCESSES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .or(config().max_processes)
+        .filter(|&n: &usize| n > 0)
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4)
+        })
+}
+
+/// Run `fut`, forwarding the output of the scripts it runs to `client`.
+pub async fn forward_output<F: Future>(client: NotificationSender, fut: F) -> F::Output {
+    OUTPUT_CLIENT.scope(client, fut).await
+}
+
+/// Run `fut` as part of the background job `job_id`, so the children it
+/// starts are killed with the job.
+pub async fn run_as_job<F: Future>(job_id: String, fut: F) -> F::Output {
+    CURRENT_JOB.scope(job_id, fut).await
+}
+
+/// `fut` forwarding script output to the same client, and belonging to the
+/// same job, as the current task. Task-locals do not cross `tokio::spawn`,
+/// so wrap futures spawned on behalf of a tool call with this.
+pub fn inherit_output<F: Future>(fut: F) -> impl Future<Output = F::Output> {
+    let client = OUTPUT_CLIENT.try_with(NotificationSender::clone).ok();
+    let job = CURRENT_JOB.try_with(String::clone).ok();
+    async move {
+        let fut = async move {
+            match client {
+                Some(client) => forward_output(client, fut).await,
+                None => fut.await,
+            }
+        };
+        match job {
+            Some(job) => run_as_job(job, fut).await,
+            None => fut.await,
+        }
+    }
+}
+
+/// Log one line a script printed and forward it to the client, stdout at
+/// info level and stderr at warning level.
+pub fn log_output_line(script: &str, stream: OutputStream, line: &str) {
+    if line.trim().is_empty() {
+        return;
+    }
+    debug!(target: "subprocess", script = %script, "{line}");
+    let level = match stream {
+        OutputStream::Stdout => LoggingLevel::Info,
+        OutputStream::Stderr => LoggingLevel::Warning,
+    };
+    let _ = OUTPUT_CLIENT.try_with(|client| {
+        client.log(
+            level,
+            "subprocess",
+            serde_json::json!({ "script": script, "line": line }),
+        );
+    });
+}
+
+/// Which pipe a line of script output came from
+#[derive(Clone, Copy)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Wait for a free subprocess slot. The slot is released when the permit is
+/// dropped, so hold it for as long as the child is alive.
+pub async fn acquire_process_slot() -> OwnedSemaphorePermit {
+    if PROCESS_SLOTS.available_permits() == 0 {
+        debug!("All {} subprocess slots busy; waiting", max_processes());
+    }
+    PROCESS_SLOTS
+        .clone()
+        .acquire_owned()
+        .await
+        .expect("subprocess semaphore is never closed")
+}
+
+/// Number of subprocess slots currently held.
+pub fn busy_process_slots() -> usize {
+    max_processes().saturating_sub(PROCESS_SLOTS.available_permits())
+}
+
+/// Limit from `WATERMARK_PROCESS_TIMEOUT_SECS` or the config file's
+/// `process_timeout_secs`; `0` disables the timeout.
+pub fn process_timeout() -> Option<Duration> {
+    let secs = std::env::var("WATERMARK_PROCESS_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .or(config().process_timeout_secs)
+        .unwrap_or(DEFAULT_PROCESS_TIMEOUT_SECS);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Start the child in a new process group (so [`kill_process_group`] also
+/// reaches poppler/ghostscript processes it spawns) and kill it if its
+/// handle is dropped.
+pub fn isolate_process_group(cmd: &mut Command) {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd.kill_on_drop(true);
+}
+
+/// SIGKILL every process in the group led by `pid`. Outside Unix only the
+/// child itself is killed, through `kill_on_drop`.
+pub fn kill_process_group(pid: Option<u32>) {
+    #[cfg(unix)]
+    if let Some(pid) = pid.and_then(|pid| i32::try_from(pid).ok()) {
+        // SAFETY: kill(2) has no memory-safety preconditions
+        unsafe {
+            libc::kill(-pid, libc::SIGKILL);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = pid;
+}
+
+/// Registers a child's process group while it runs; dropping the guard
+/// unregisters it.
+pub struct ProcessGroupGuard(Option<u32>);
+
+/// Track the process group led by `pid`, as started by the current task's
+/// client and job, until the returned guard is dropped.
+pub fn track_process_group(pid: Option<u32>) -> ProcessGroupGuard {
+    if let Some(pid) = pid {
+        let owner = GroupOwner {
+            client: OUTPUT_CLIENT.try_with(NotificationSender::clone).ok(),
+            job: CURRENT_JOB.try_with(String::clone).ok(),
+        };
+        lock_process_groups().insert(pid, owner);
+    }
+    ProcessGroupGuard(pid)
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            lock_process_groups().remove(&pid);
+        }
+    }
+}
+
+/// SIGKILL every child started by a tool call, job or watch of the current
+/// task's client, together with the processes it started. Other clients'
+/// children are left alone. Returns how many groups were killed.
+pub fn kill_client_process_groups() -> usize {
+    let client = OUTPUT_CLIENT.try_with(NotificationSender::clone).ok();
+    kill_process_groups_where(|owner| match (&owner.client, &client) {
+        (Some(owner), Some(client)) => owner.same_client(client),
+        (owner, client) => owner.is_none() && client.is_none(),
+    })
+}
+
+/// SIGKILL every child the background job `job_id` started, together with
+/// the processes it started. Returns how many groups were killed.
+pub fn kill_job_process_groups(job_id: &str) -> usize {
+    kill_process_groups_where(|owner| owner.job.as_deref() == Some(job_id))
+}
+
+fn kill_process_groups_where(matches: impl Fn(&GroupOwner) -> bool) -> usize {
+    let groups: Vec<u32> = {
+        let mut tracked = lock_process_groups();
+        let groups: Vec<u32> = tracked
+            .iter()
+            .filter(|(_, owner)| matches(owner))
+            .map(|(&pid, _)| pid)
+            .collect();
+        for pid in &groups {
+            tracked.remove(pid);
+        }
+        groups
+    };
+    for &pid in &groups {
+        kill_process_group(Some(pid));
+    }
+    if !groups.is_empty() {
+        warn!("Killed {} subprocess groups", groups.len());
+    }
+    groups.len()
+}
+
+fn lock_process_groups() -> std::sync::MutexGuard<'static, HashMap<u32, GroupOwner>> {
+    PROCESS_GROUPS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Error returned when a child outlives [`process_timeout`]. It wraps a
+/// [`ToolError::Timeout`] so tools can report the cause.
+pub fn timeout_error(cmd: &Command, timeout: Duration) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        ToolError::Timeout {
+            script: command_name(cmd),
+            secs: timeout.as_secs(),
+        },
+    )
+}
+
+/// `cmd.output()` run inside a subprocess slot and bounded by
+/// [`process_timeout`], with the output logged as it arrives.
+pub async fn run_command(cmd: &mut Command) -> std::io::Result<Output> {
+    run_command_with_input(cmd, None).await
+}
+
+/// [`run_command`] writing `input` to the child's stdin, which is then
+/// closed. Lets data reach a script without a file on disk.
+pub async fn run_command_with_stdin(cmd: &mut Command, input: Vec<u8>) -> std::io::Result<Output> {
+    run_command_with_input(cmd, Some(input)).await
+}
+
+async fn run_command_with_input(
+    cmd: &mut Command,
+    input: Option<Vec<u8>>,
+) -> std::io::Result<Output> {
+    let _slot = acquire_process_slot().await;
+    let stdin_fed = input.is_some();
+
+    isolate_process_group(cmd);
+    let mut child = cmd
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| spawn_error(cmd, e))?;
+    let pid = child.id();
+    let _group = track_process_group(pid);
+
+    // Written concurrently with reading the output so a large image cannot
+    // fill both pipes; a child that exits early just ends the write
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        tokio::spawn(async move {
+            let _ = stdin.write_all(&input).await;
+        });
+    }
+
+    // Scripts fed through stdin answer with binary data on stdout, so
+    // only their stderr is text
+    let script = command_name(cmd);
+    let stdout_pipe = child.stdout.take();
+    let stderr_pipe = child.stderr.take();
+    let stdout_is_text = !stdin_fed;
+    let run = async {
+        let (stdout, stderr) = tokio::try_join!(
+            read_output(&script, OutputStream::Stdout, stdout_pipe, stdout_is_text),
+            read_output(&script, OutputStream::Stderr, stderr_pipe, true),
+        )?;
+        let status = child.wait().await?;
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    };
+
+    let Some(timeout) = process_timeout() else {
+        return run.await;
+    };
+    match tokio::time::timeout(timeout, run).await {
+        Ok(output) => output,
+        Err(_) => {
+            warn!(
+                "Subprocess {pid:?} timed out after {}s; killing",
+                timeout.as_secs()
+            );
+            kill_process_group(pid);
+            Err(timeout_error(cmd, timeout))
+        }
+    }
+}
+
+/// Everything `pipe` yields. Text output is also passed to
+/// [`log_output_line`] one line at a time as it arrives.
+async fn read_output(
+    script: &str,
+    stream: OutputStream,
+    pipe: Option<impl AsyncRead + Unpin>,
+    is_text: bool,
+) -> std::io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    let Some(pipe) = pipe else {
+        return Ok(output);
+    };
+    let mut reader = BufReader::new(pipe);
+    if !is_text {
+        reader.read_to_end(&mut output).await?;
+        return Ok(output);
+    }
+    loop {
+        let start = output.len();
+        if reader.read_until(b'\n', &mut output).await? == 0 {
+            return Ok(output);
+        }
+        let line = String::from_utf8_lossy(&output[start..]);
+        log_output_line(script, stream, line.trim_end());
+    }
+}
+
+/// A missing executable becomes [`ToolError::BackendMissing`]; other spawn
+/// failures pass through unchanged.
+pub fn spawn_error(cmd: &Command, e: std::io::Error) -> std::io::Error {
+    if e.kind() != std::io::ErrorKind::NotFound {
+        return e;
+    }
+    let program = cmd.as_std().get_program().to_string_lossy().to_string();
+    std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        ToolError::BackendMissing {
+            detail: format!("{program} could not be started ({e})"),
+            backend: program,
+        },
+    )
+}
+
+/// The script a python command runs, or the program itself.
+fn command_name(cmd: &Command) -> String {
+    let cmd = cmd.as_std();
+    let target = cmd
+        .get_args()
+        .next()
+        .filter(|arg| Path::new(arg).extension().is_some_and(|ext| ext == "py"))
+        .unwrap_or(cmd.get_program());
+    Path::new(target)
+        .file_name()
+        .unwrap_or(target)
+        .to_string_lossy()
+        .to_string()
+}
@@ -0,0 +1,401 @@
+//! Text layer - copies the source PDF's text onto the cleaned raster pages
+//!
+//! Cleaned pages are images, so the output PDF has nothing to search or
+//! copy. With `text_layer`, the text objects of each source page (its
+//! `BT`..`ET` blocks, with the transforms and fonts they use) are added to
+//! the matching output page in invisible rendering mode (`3 Tr`), scaled
+//! from the source page's box onto the image. Search and copy then find
+//! exactly the source's text, where it was; no OCR is involved.
+//!
+//! Text drawn inside form XObjects is not copied, and neither is text on
+//! pages that were dropped or failed.
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use lopdf::Dictionary;
+use lopdf::Document;
+use lopdf::Object;
+use lopdf::ObjectId;
+use lopdf::Stream;
+use lopdf::content::Content;
+use lopdf::content::Operation;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Operators kept from the source content: text objects, text state and
+/// the graphics state operators that position them
+const KEPT_OPERATORS: &[&str] = &[
+    "q", "Q", "cm", "BT", "ET", "Tc", "Tw", "Tz", "TL", "Tf", "Ts", "Td", "TD", "Tm", "T*", "Tj",
+    "TJ", "'", "\"",
+];
+
+/// Invisible text: neither filled nor stroked, but still selectable
+const INVISIBLE: i64 = 3;
+
+/// Add the text of `source`'s pages to the pages of `output`, which holds
+/// `pages` of the source in order, one per output page. Returns how many
+/// pages got any text. Blocking; both files are parsed and `output` is
+/// rewritten in one rename.
+pub(super) fn copy_text_layer(source: &Path, output: &Path, pages: &[u32]) -> Result<usize> {
+    let source_doc =
+        Document::load(source).with_context(|| format!("Error reading {}", source.display()))?;
+    if source_doc.is_encrypted() {
+        bail!("{} is encrypted", source.display());
+    }
+    let mut output_doc =
+        Document::load(output).with_context(|| format!("Error reading {}", output.display()))?;
+
+    let source_pages = source_doc.get_pages();
+    let output_pages: Vec<ObjectId> = output_doc.get_pages().into_values().collect();
+    if output_pages.len() != pages.len() {
+        bail!(
+            "{} has {} pages, but {} were written",
+            output.display(),
+            output_pages.len(),
+            pages.len()
+        );
+    }
+
+    // Fonts shared by several pages are copied once
+    let mut copied = HashMap::new();
+    let mut with_text = 0;
+    for (&page, &target_id) in pages.iter().zip(&output_pages) {
+        let Some(&source_id) = source_pages.get(&page) else {
+            continue;
+        };
+        let operations = text_operations(&source_doc, source_id)?;
+        if operations.is_empty() {
+            continue;
+        }
+        let fonts = page_fonts(&source_doc, source_id);
+        let fonts = copy_object(&source_doc, &mut output_doc, &fonts, &mut copied);
+
+        let Some(target_box) = page_box(&output_doc, target_id, b"MediaBox") else {
+            continue;
+        };
+        let source_box = page_box(&source_doc, source_id, b"CropBox")
+            .or_else(|| page_box(&source_doc, source_id, b"MediaBox"))
+            .unwrap_or([0.0, 0.0, 612.0, 792.0]);
+        let rotate = inherited(&source_doc, source_id, b"Rotate")
+            .and_then(|rotate| rotate.as_i64().ok())
+            .unwrap_or(0);
+
+        let mut content = vec![
+            Operation::new("q", vec![]),
+            Operation::new(
+                "cm",
+                page_transform(source_box, target_box, rotate)
+                    .into_iter()
+                    .map(|value| Object::Real(value as f32))
+                    .collect(),
+            ),
+        ];
+        content.extend(operations);
+        content.push(Operation::new("Q", vec![]));
+        let content = Content {
+            operations: content,
+        }
+        .encode()?;
+        let mut stream = Stream::new(Dictionary::new(), content);
+        let _ = stream.compress();
+        let stream_id = output_doc.add_object(stream);
+
+        add_to_page(&mut output_doc, target_id, stream_id, fonts)?;
+        with_text += 1;
+    }
+
+    if with_text > 0 {
+        let file_name = output.file_name().unwrap_or_default().to_string_lossy();
+        let tmp = output.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+        output_doc
+            .save(&tmp)
+            .and_then(|_| std::fs::rename(&tmp, output))
+            .with_context(|| format!("Error writing {}", output.display()))
+            .inspect_err(|_| {
+                let _ = std::fs::remove_file(&tmp);
+            })?;
+    }
+    Ok(with_text)
+}
+
+/// The page's text objects and the state they depend on, forced invisible.
+fn text_operations(document: &Document, page_id: ObjectId) -> Result<Vec<Operation>> {
+    let content = document.get_page_content(page_id)?;
+    let operations = Content::decode(&content)
+        .map(|content| content.operations)
+        .unwrap_or_default();
+    if !operations.iter().any(|op| op.operator == "BT") {
+        return Ok(Vec::new());
+    }
+
+    let mut kept = Vec::new();
+    let mut depth = 0usize;
+    for op in operations {
+        match op.operator.as_str() {
+            "BT" => {
+                kept.push(op);
+                kept.push(Operation::new("Tr", vec![Object::Integer(INVISIBLE)]));
+            }
+            "Tr" => kept.push(Operation::new("Tr", vec![Object::Integer(INVISIBLE)])),
+            // Unbalanced restores would pop the state set up around the text
+            "q" => {
+                depth += 1;
+                kept.push(op);
+            }
+            "Q" if depth > 0 => {
+                depth -= 1;
+                kept.push(op);
+            }
+            "Q" => {}
+            operator if KEPT_OPERATORS.contains(&operator) => kept.push(op),
+            _ => {}
+        }
+    }
+    kept.extend((0..depth).map(|_| Operation::new("Q", vec![])));
+    Ok(kept)
+}
+
+/// The page's `Font` resources, nearest definition first.
+fn page_fonts(document: &Document, page_id: ObjectId) -> Object {
+    let mut fonts = Dictionary::new();
+    let Ok((own, inherited)) = document.get_page_resources(page_id) else {
+        return Object::Dictionary(fonts);
+    };
+    let resources = own.into_iter().chain(
+        inherited
+            .iter()
+            .filter_map(|&id| document.get_dictionary(id).ok()),
+    );
+    for resources in resources {
+        let Ok(Object::Dictionary(page_fonts)) = resources
+            .get(b"Font")
+            .and_then(|fonts| document.dereference(fonts))
+            .map(|(_, fonts)| fonts)
+        else {
+            continue;
+        };
+        for (name, font) in page_fonts.iter() {
+            if !fonts.has(name) {
+                fonts.set(name.clone(), font.clone());
+            }
+        }
+    }
+    Object::Dictionary(fonts)
+}
+
+/// Copy `object` and everything it references from `source` into `target`,
+/// returning it with references renumbered. `copied` maps source object ids
+/// already copied to their new ids.
+fn copy_object(
+    source: &Document,
+    target: &mut Document,
+    object: &Object,
+    copied: &mut HashMap<ObjectId, ObjectId>,
+) -> Object {
+    match object {
+        Object::Reference(id) => {
+            if let Some(&new_id) = copied.get(id) {
+                return Object::Reference(new_id);
+            }
+            let new_id = target.new_object_id();
+            copied.insert(*id, new_id);
+            let copy = match source.get_object(*id) {
+                Ok(referenced) => copy_object(source, target, referenced, copied),
+                Err(_) => Object::Null,
+            };
+            target.objects.insert(new_id, copy);
+            Object::Reference(new_id)
+        }
+        Object::Array(items) => Object::Array(
+            items
+                .iter()
+                .map(|item| copy_object(source, target, item, copied))
+                .collect(),
+        ),
+        Object::Dictionary(dict) => {
+            Object::Dictionary(copy_dictionary(source, target, dict, copied))
+        }
+        Object::Stream(stream) => {
+            let dict = copy_dictionary(source, target, &stream.dict, copied);
+            Object::Stream(Stream::new(dict, stream.content.clone()))
+        }
+        other => other.clone(),
+    }
+}
+
+fn copy_dictionary(
+    source: &Document,
+    target: &mut Document,
+    dict: &Dictionary,
+    copied: &mut HashMap<ObjectId, ObjectId>,
+) -> Dictionary {
+    let mut copy = Dictionary::new();
+    for (key, value) in dict.iter() {
+        // A back reference into the source page tree would drag it along
+        if key != b"Parent" {
+            copy.set(key.clone(), copy_object(source, target, value, copied));
+        }
+    }
+    copy
+}
+
+/// Append `stream_id` to the page's contents and merge `fonts` into its
+/// resources.
+fn add_to_page(
+    document: &mut Document,
+    page_id: ObjectId,
+    stream_id: ObjectId,
+    fonts: Object,
+) -> Result<()> {
+    let resources_id = match document.get_dictionary(page_id)?.get(b"Resources") {
+        Ok(Object::Reference(id)) => Some(*id),
+        _ => None,
+    };
+    let page = document.get_dictionary_mut(page_id)?;
+    let mut contents = match page.get(b"Contents") {
+        Ok(Object::Array(items)) => items.clone(),
+        Ok(single) => vec![single.clone()],
+        Err(_) => Vec::new(),
+    };
+    contents.push(Object::Reference(stream_id));
+    page.set("Contents", Object::Array(contents));
+
+    let resources = match resources_id {
+        Some(id) => document.get_dictionary_mut(id)?,
+        None => {
+            let page = document.get_dictionary_mut(page_id)?;
+            if !matches!(page.get(b"Resources"), Ok(Object::Dictionary(_))) {
+                page.set("Resources", Dictionary::new());
+            }
+            page.get_mut(b"Resources")?.as_dict_mut()?
+        }
+    };
+    // The output pages only name images, so font names cannot clash
+    resources.set("Font", fonts);
+    Ok(())
+}
+
+/// Find `key` on the page or the nearest ancestor in the page tree.
+fn inherited<'a>(document: &'a Document, page_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+    let mut node = document.get_dictionary(page_id).ok()?;
+    // Guards against a cyclic page tree
+    for _ in 0..32 {
+        if let Ok(value) = node.get(key) {
+            return document.dereference(value).ok().map(|(_, value)| value);
+        }
+        let parent = node.get(b"Parent").ok()?.as_reference().ok()?;
+        node = document.get_dictionary(parent).ok()?;
+    }
+    None
+}
+
+/// A page box as `[x0, y0, x1, y1]` with x0 < x1 and y0 < y1.
+fn page_box(document: &Document, page_id: ObjectId, key: &[u8]) -> Option<[f64; 4]> {
+    let values: Vec<f64> = inherited(document, page_id, key)?
+        .as_array()
+        .ok()?
+        .iter()
+        .filter_map(|value| document.dereference(value).ok()?.1.as_float().ok())
+        .map(f64::from)
+        .collect();
+    let [x0, y0, x1, y1] = values[..] else {
+        return None;
+    };
+    Some([x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)])
+}
+
+/// `cm` operands mapping the source page's box, displayed with `rotate`
+/// degrees of clockwise rotation as the rasterizer renders it, onto the
+/// output page's box.
+fn page_transform(source: [f64; 4], target: [f64; 4], rotate: i64) -> [f64; 6] {
+    let [x0, y0, x1, y1] = source;
+    let (w, h) = (x1 - x0, y1 - y0);
+    let [tx, ty, tx1, ty1] = target;
+    let (width, height) = (tx1 - tx, ty1 - ty);
+    match rotate.rem_euclid(360) {
+        90 => [
+            0.0,
+            -height / w,
+            width / h,
+            0.0,
+            tx - y0 * width / h,
+            ty + x1 * height / w,
+        ],
+        180 => [
+            -width / w,
+            0.0,
+            0.0,
+            -height / h,
+            tx + x1 * width / w,
+            ty + y1 * height / h,
+        ],
+        270 => [
+            0.0,
+            height / w,
+            -width / h,
+            0.0,
+            tx + y1 * width / h,
+            ty - x0 * height / w,
+        ],
+        _ => [
+            width / w,
+            0.0,
+            0.0,
+            height / h,
+            tx - x0 * width / w,
+            ty - y0 * height / h,
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Where the matrix `[a, b, c, d, e, f]` puts the point `(x, y)`.
+    fn apply([a, b, c, d, e, f]: [f64; 6], (x, y): (f64, f64)) -> (f64, f64) {
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    fn assert_maps(matrix: [f64; 6], from: (f64, f64), to: (f64, f64)) {
+        let (x, y) = apply(matrix, from);
+        assert!(
+            (x - to.0).abs() < 1e-9 && (y - to.1).abs() < 1e-9,
+            "{from:?} went to {:?}, not {to:?}",
+            (x, y)
+        );
+    }
+
+    // A 100 x 200 point page whose box does not start at the origin
+    const SOURCE: [f64; 4] = [10.0, 20.0, 110.0, 220.0];
+
+    #[test]
+    fn upright_pages_are_scaled_and_moved() {
+        let matrix = page_transform(SOURCE, [0.0, 0.0, 50.0, 100.0], 0);
+        assert_maps(matrix, (10.0, 20.0), (0.0, 0.0));
+        assert_maps(matrix, (110.0, 220.0), (50.0, 100.0));
+        assert_maps(matrix, (60.0, 120.0), (25.0, 50.0));
+    }
+
+    #[test]
+    fn rotated_pages_turn_clockwise() {
+        // Shown turned a quarter, the page is 200 wide and 100 tall
+        let target = [0.0, 0.0, 400.0, 200.0];
+        let matrix = page_transform(SOURCE, target, 90);
+        // The top edge ends up on the right
+        assert_maps(matrix, (10.0, 220.0), (400.0, 200.0));
+        assert_maps(matrix, (110.0, 220.0), (400.0, 0.0));
+        assert_maps(matrix, (10.0, 20.0), (0.0, 200.0));
+
+        let matrix = page_transform(SOURCE, target, 270);
+        // The top edge ends up on the left
+        assert_maps(matrix, (10.0, 220.0), (0.0, 0.0));
+        assert_maps(matrix, (10.0, 20.0), (400.0, 0.0));
+        assert_eq!(matrix, page_transform(SOURCE, target, -90));
+
+        let matrix = page_transform(SOURCE, [0.0, 0.0, 100.0, 200.0], 180);
+        assert_maps(matrix, (10.0, 20.0), (100.0, 200.0));
+        assert_maps(matrix, (110.0, 220.0), (0.0, 0.0));
+    }
+}
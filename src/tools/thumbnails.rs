@@ -0,0 +1,87 @@
+//! Verification thumbnails - small before/after images of a cleaned PDF
+//!
+//! `process_pdf` with `thumbnails: true` attaches one sample page of the
+//! input and of the output as `image` content blocks, so the removal can be
+//! checked in the chat without opening the output file.
+
+use anyhow::Context;
+use anyhow::Result;
+use mcp_types::ContentBlock;
+use std::path::Path;
+use std::process::Stdio;
+use tracing::info;
+
+use super::error::ToolError;
+use super::image_content::image_content_from_file;
+use super::process_pdf::PageResult;
+use super::process_pdf::PageStatus;
+use super::python::python_command;
+use super::scripts::scripts_dir;
+use super::subprocess::run_command;
+use super::workspace::create_temp_dir;
+use super::workspace::remove_temp_dir;
+
+/// Longest side of a thumbnail in pixels
+const THUMBNAIL_SIZE: u32 = 256;
+
+/// The page to show: the first one a watermark was removed from, else the
+/// first one that made it into the output. Returned with its page number in
+/// the output, which differs once failed or dropped pages are left out.
+pub(super) fn sample_page(pages: &[PageResult]) -> Option<(u32, u32)> {
+    let output: Vec<&PageResult> = pages
+        .iter()
+        .filter(|page| !matches!(page.status, PageStatus::Failed | PageStatus::Dropped))
+        .collect();
+    let index = output
+        .iter()
+        .position(|page| page.status == PageStatus::WatermarkRemoved)
+        .or((!output.is_empty()).then_some(0))?;
+    Some((output[index].page, index as u32 + 1))
+}
+
+/// Before and after thumbnails of `page`, rendered from `before_pdf` and
+/// page `after_page` of `after_pdf`.
+pub(super) async fn page_thumbnails(
+    before_pdf: &Path,
+    after_pdf: &Path,
+    (page, after_page): (u32, u32),
+) -> Result<[ContentBlock; 2]> {
+    let dir = create_temp_dir("thumbnails").await?;
+    let result = render(before_pdf, after_pdf, page, after_page, &dir).await;
+    remove_temp_dir(&dir).await;
+    result
+}
+
+async fn render(
+    before_pdf: &Path,
+    after_pdf: &Path,
+    page: u32,
+    after_page: u32,
+    dir: &Path,
+) -> Result<[ContentBlock; 2]> {
+    info!("Rendering thumbnails of page {page}");
+    let output = run_command(
+        python_command()
+            .arg(scripts_dir()?.join("thumbnails.py"))
+            .arg(before_pdf)
+            .arg(after_pdf)
+            .arg(page.to_string())
+            .arg(dir)
+            .arg(format!("--size={THUMBNAIL_SIZE}"))
+            .arg(format!("--after-page={after_page}"))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .await
+    .context("Failed to execute thumbnails.py")?;
+    if !output.status.success() {
+        return Err(ToolError::script_failed("thumbnails.py", &output.stderr).into());
+    }
+
+    let before = dir.join(format!("page_{page:03}_before.png"));
+    let after = dir.join(format!("page_{page:03}_after.png"));
+    Ok([
+        ContentBlock::ImageContent(image_content_from_file(&before).await?),
+        ContentBlock::ImageContent(image_content_from_file(&after).await?),
+    ])
+}
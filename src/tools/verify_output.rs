@@ -0,0 +1,152 @@
+//! Output verification - re-reads a written PDF before it is reported done
+//!
+//! The PDF is parsed again from disk, its page count compared with the
+//! pages the run meant to write, and every page checked for what a viewer
+//! needs to draw it: readable content streams and image data that decodes.
+//! A truncated or corrupted file is reported in the tool result instead of
+//! being found by whoever opens it later.
+
+use anyhow::Result;
+use anyhow::bail;
+use lopdf::Document;
+use lopdf::Object;
+use lopdf::ObjectId;
+use serde::Serialize;
+use std::path::Path;
+
+/// Outcome of [`verify_pdf`].
+#[derive(Clone, Debug, Serialize)]
+pub(super) struct OutputVerification {
+    pub status: VerificationStatus,
+    /// Pages the PDF was read back with
+    pub page_count: usize,
+    /// Pages the run wrote: the input's pages less dropped and failed ones
+    pub expected_pages: usize,
+    /// Pages whose content or images could not be decoded
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub broken_pages: Vec<BrokenPage>,
+    /// Why the PDF could not be read at all, or its page count is wrong
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum VerificationStatus {
+    Passed,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(super) struct BrokenPage {
+    pub page: u32,
+    pub error: String,
+}
+
+impl OutputVerification {
+    pub(super) fn passed(&self) -> bool {
+        self.status == VerificationStatus::Passed
+    }
+}
+
+/// Read `pdf_path` back and check it holds `expected_pages` pages that can
+/// all be drawn.
+pub(super) async fn verify_pdf(pdf_path: &Path, expected_pages: usize) -> OutputVerification {
+    let path = pdf_path.to_path_buf();
+    let checked = tokio::task::spawn_blocking(move || check_pages(&path))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|checked| checked);
+    let (page_count, broken_pages, error) = match checked {
+        Ok((page_count, broken_pages)) => {
+            let error = (page_count != expected_pages).then(|| {
+                format!("the PDF has {page_count} pages, but {expected_pages} were written")
+            });
+            (page_count, broken_pages, error)
+        }
+        Err(e) => (0, Vec::new(), Some(format!("{e:#}"))),
+    };
+    let status = if error.is_none() && broken_pages.is_empty() {
+        VerificationStatus::Passed
+    } else {
+        VerificationStatus::Failed
+    };
+    OutputVerification {
+        status,
+        page_count,
+        expected_pages,
+        broken_pages,
+        error,
+    }
+}
+
+fn check_pages(pdf_path: &Path) -> Result<(usize, Vec<BrokenPage>)> {
+    let document = match Document::load(pdf_path) {
+        Ok(document) => document,
+        Err(e) => bail!("the PDF cannot be read: {e}"),
+    };
+    let pages = document.get_pages();
+    let broken = pages
+        .iter()
+        .filter_map(|(&page, &page_id)| {
+            check_page(&document, page_id)
+                .err()
+                .map(|error| BrokenPage { page, error })
+        })
+        .collect();
+    Ok((pages.len(), broken))
+}
+
+/// Whether the page's content streams and images decode.
+fn check_page(document: &Document, page_id: ObjectId) -> std::result::Result<(), String> {
+    let contents = document.get_page_contents(page_id);
+    if contents.is_empty() {
+        return Err("page has no content stream".to_string());
+    }
+    for id in contents {
+        let stream = document
+            .get_object(id)
+            .and_then(Object::as_stream)
+            .map_err(|e| format!("content stream {} is missing: {e}", id.0))?;
+        if stream.dict.get(b"Filter").is_ok() {
+            stream
+                .decompressed_content()
+                .map_err(|e| format!("content stream {} does not decode: {e}", id.0))?;
+        }
+    }
+
+    // Pages without an XObject resource dictionary simply have no images
+    let images = document.get_page_images(page_id).unwrap_or_default();
+    for image in images {
+        if image.width <= 0 || image.height <= 0 {
+            return Err(format!("image {} has no size", image.id.0));
+        }
+        let filters = image.filters.clone().unwrap_or_default();
+        match filters.as_slice() {
+            // A JPEG must run from its start to its end marker
+            [f] if f == "DCTDecode"
+                && !(image.content.starts_with(&[0xFF, 0xD8])
+                    && image.content.ends_with(&[0xFF, 0xD9])) =>
+            {
+                return Err(format!("JPEG image {} is truncated", image.id.0));
+            }
+            [] if image.content.is_empty() => {
+                return Err(format!("image {} has no data", image.id.0));
+            }
+            _ if filters.iter().all(|f| f == "FlateDecode") => {
+                let stream = document
+                    .get_object(image.id)
+                    .and_then(Object::as_stream)
+                    .map_err(|e| e.to_string())?;
+                match stream.decompressed_content() {
+                    Ok(data) if !data.is_empty() => {}
+                    Ok(_) => return Err(format!("image {} has no data", image.id.0)),
+                    Err(e) => return Err(format!("image {} does not decode: {e}", image.id.0)),
+                }
+            }
+            // Other encodings are passed through from the input untouched
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! Watch tools - start and stop folder watches that clean new PDFs
+
+use anyhow::Result;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::TextContent;
+use serde::Deserialize;
+use serde_json::json;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::error::parse_args;
+use super::overwrite::check_output_file;
+use super::overwrite::default_output;
+use super::process_pdf::process_pdf_to_pdf;
+use super::profiles::RemovalSettings;
+use super::profiles::resolve_settings;
+use crate::watch::WatchConfig;
+use crate::watch::WatchManager;
+
+#[derive(Deserialize)]
+struct StartWatchArgs {
+    dir: String,
+    output_dir: Option<String>,
+    interval_secs: Option<u64>,
+    process_existing: Option<bool>,
+    overwrite: Option<bool>,
+    auto_rename: Option<bool>,
+    profile: Option<String>,
+    #[serde(flatten)]
+    settings: RemovalSettings,
+}
+
+/// What a watch does when a cleaned PDF already exists.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OnConflict {
+    Fail,
+    Overwrite,
+    Rename,
+}
+
+#[derive(Deserialize)]
+struct StopWatchArgs {
+    watch_id: Option<String>,
+}
+
+pub async fn handle_start_watch(args: serde_json::Value, watches: &WatchManager) -> ToolResult {
+    let args: StartWatchArgs = parse_args(args)?;
+
+    let dir = PathBuf::from(&args.dir);
+    if !dir.is_dir() {
+        return Err(ToolError::not_found("Directory", dir));
+    }
+
+    let settings = resolve_settings(args.settings, args.profile.as_deref()).await?;
+
+    // Outputs go to a subdirectory by default; only top-level PDFs are watched
+    let output_dir = args
+        .output_dir
+        .map(PathBuf::from)
+        .unwrap_or_else(|| dir.join("cleaned"));
+    tokio::fs::create_dir_all(&output_dir).await?;
+
+    let config = WatchConfig {
+        dir,
+        output_dir: output_dir.clone(),
+        profile: args.profile,
+        interval: Duration::from_secs(args.interval_secs.unwrap_or(5).max(1)),
+        process_existing: args.process_existing.unwrap_or(false),
+    };
+
+    let conflicts = if args.overwrite.unwrap_or(false) {
+        OnConflict::Overwrite
+    } else if args.auto_rename.unwrap_or(false) {
+        OnConflict::Rename
+    } else {
+        OnConflict::Fail
+    };
+    let watch_id = watches.start(config, {
+        let output_dir = output_dir.clone();
+        move |pdf_path| clean_watched_pdf(pdf_path, output_dir.clone(), settings.clone(), conflicts)
+    });
+
+    let info = watches.status(&watch_id);
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!(
+                "Started watch {watch_id} on {}.\nNew PDFs are cleaned into {} and reported as notifications/message log notifications. Stop with stop_watch.",
+                args.dir,
+                output_dir.display()
+            ),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(serde_json::to_value(&info)?),
+    })
+}
+
+pub async fn handle_stop_watch(args: serde_json::Value, watches: &WatchManager) -> ToolResult {
+    let args: StopWatchArgs = parse_args(args)?;
+
+    let Some(watch_id) = args.watch_id else {
+        let all = watches.list();
+        let lines: Vec<String> = all
+            .iter()
+            .map(|w| {
+                format!(
+                    "  {} {} ({} processed, {} failed)",
+                    w.watch_id,
+                    w.dir.display(),
+                    w.processed,
+                    w.failed
+                )
+            })
+            .collect();
+        return Ok(CallToolResult {
+            content: vec![ContentBlock::TextContent(TextContent {
+                r#type: "text".to_string(),
+                text: format!(
+                    "{} active watches (pass watch_id to stop one)\n{}",
+                    all.len(),
+                    lines.join("\n")
+                ),
+                annotations: None,
+            })],
+            is_error: Some(false),
+            structured_content: Some(json!({ "watches": all })),
+        });
+    };
+
+    let Some(info) = watches.stop(&watch_id) else {
+        return Err(ToolError::invalid_args(format!(
+            "Unknown watch_id: {watch_id}"
+        )));
+    };
+
+    Ok(CallToolResult {
+        content: vec![ContentBlock::TextContent(TextContent {
+            r#type: "text".to_string(),
+            text: format!(
+                "Stopped watch {} ({} processed, {} failed)",
+                info.watch_id, info.processed, info.failed
+            ),
+            annotations: None,
+        })],
+        is_error: Some(false),
+        structured_content: Some(serde_json::to_value(&info)?),
+    })
+}
+
+/// Render, clean and re-merge one PDF picked up by a watch. Returns the path
+/// of the cleaned PDF.
+async fn clean_watched_pdf(
+    pdf_path: PathBuf,
+    output_dir: PathBuf,
+    settings: RemovalSettings,
+    conflicts: OnConflict,
+) -> Result<String> {
+    let stem = pdf_path.file_stem().unwrap_or_default().to_string_lossy();
+    let (output_path, _) = default_output(
+        output_dir.join(format!("{stem}_nowatermark.pdf")),
+        conflicts == OnConflict::Rename,
+        Path::exists,
+    );
+    check_output_file(&output_path, conflicts == OnConflict::Overwrite)?;
+
+    process_pdf_to_pdf(&pdf_path, &output_path, &settings, false).await?;
+
+    Ok(output_path.display().to_string())
+}
@@ -0,0 +1,292 @@
+//! WebDAV - `webdav://` and `webdavs://` URIs as tool inputs and outputs
+//!
+//! Plugs into [`object_storage`](super::object_storage) staging, so
+//! documents kept on Nextcloud, ownCloud or any other WebDAV server can be
+//! read and written like `s3://` objects. `webdavs://host/path` is fetched
+//! over HTTPS and `webdav://host/path` over plain HTTP. Requests go through
+//! `curl`; credentials come from `WATERMARK_WEBDAV_USER` and
+//! `WATERMARK_WEBDAV_PASSWORD`, or else from `~/.netrc`.
+
+use anyhow::Context;
+use std::path::Path;
+use std::process::Output;
+use std::process::Stdio;
+use tokio::process::Command;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::roots::percent_decode;
+use super::subprocess::run_command_with_stdin;
+
+/// Whether `path` is a WebDAV URI.
+pub(super) fn is_webdav(path: &str) -> bool {
+    path.starts_with("webdav://") || path.starts_with("webdavs://")
+}
+
+/// Copy `from` to `to`, one of which is a WebDAV URI. Collections are
+/// copied recursively when `recursive` is set.
+pub(super) async fn transfer(from: &str, to: &str, recursive: bool) -> ToolResult<()> {
+    if is_webdav(from) {
+        download(&http_url(from), Path::new(to), recursive).await
+    } else {
+        let url = http_url(to);
+        make_parent_collections(&url).await?;
+        upload(Path::new(from), &url, recursive).await
+    }
+}
+
+/// Whether a file already exists at `uri`.
+pub(super) async fn exists(uri: &str) -> ToolResult<bool> {
+    let output = curl(&["--head", &http_url(uri)]).await?;
+    Ok(output.status.success())
+}
+
+async fn download(url: &str, local: &Path, recursive: bool) -> ToolResult<()> {
+    if !recursive {
+        return check(curl(&["--output", &local.to_string_lossy(), url]).await?);
+    }
+
+    tokio::fs::create_dir_all(local).await?;
+    let collection = format!("{}/", url.trim_end_matches('/'));
+    let output = curl(&["--request", "PROPFIND", "--header", "Depth: 1", &collection]).await?;
+    let listing = String::from_utf8_lossy(&output.stdout).to_string();
+    check(output)?;
+
+    let own_path = url_path(&collection);
+    for (href, is_collection) in list_entries(&listing) {
+        let Some(name) = entry_name(&href, own_path) else {
+            continue;
+        };
+        let child = format!("{}{href}", url_origin(url));
+        Box::pin(download(&child, &local.join(name), is_collection)).await?;
+    }
+    Ok(())
+}
+
+async fn upload(local: &Path, url: &str, recursive: bool) -> ToolResult<()> {
+    if !recursive || !local.is_dir() {
+        return check(curl(&["--upload-file", &local.to_string_lossy(), url]).await?);
+    }
+
+    // MKCOL fails with 405 when the collection already exists
+    let collection = format!("{}/", url.trim_end_matches('/'));
+    curl(&["--request", "MKCOL", &collection]).await?;
+    let mut entries = tokio::fs::read_dir(local).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let child = format!("{collection}{}", percent_encode(&name));
+        Box::pin(upload(&entry.path(), &child, true)).await?;
+    }
+    Ok(())
+}
+
+/// Create the collections above `url`, which servers do not create on
+/// upload.
+async fn make_parent_collections(url: &str) -> ToolResult<()> {
+    let origin = url_origin(url);
+    let path = url_path(url).trim_end_matches('/');
+    let mut parent = String::new();
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    for segment in segments.iter().take(segments.len().saturating_sub(1)) {
+        parent.push('/');
+        parent.push_str(segment);
+        // Existing collections answer 405, which is fine
+        curl(&["--request", "MKCOL", &format!("{origin}{parent}/")]).await?;
+    }
+    Ok(())
+}
+
+/// Run `curl` with `args`, passing the credentials through its stdin so
+/// they do not show up in the process list.
+async fn curl(args: &[&str]) -> ToolResult<Output> {
+    let mut cmd = Command::new("curl");
+    cmd.args(["--silent", "--show-error", "--fail", "--config", "-"])
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let output = run_command_with_stdin(&mut cmd, credentials().into_bytes())
+        .await
+        .context("Failed to execute curl")?;
+    Ok(output)
+}
+
+fn check(output: Output) -> ToolResult<()> {
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(ToolError::SubprocessFailed {
+        script: "curl".to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    })
+}
+
+/// `curl --config` lines holding the credentials.
+fn credentials() -> String {
+    let user = std::env::var("WATERMARK_WEBDAV_USER").unwrap_or_default();
+    if user.is_empty() {
+        return "netrc-optional\n".to_string();
+    }
+    let password = std::env::var("WATERMARK_WEBDAV_PASSWORD").unwrap_or_default();
+    let quoted = format!("{user}:{password}")
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    format!("user = \"{quoted}\"\n")
+}
+
+fn http_url(uri: &str) -> String {
+    if let Some(rest) = uri.strip_prefix("webdavs://") {
+        format!("https://{rest}")
+    } else {
+        format!("http://{}", uri.strip_prefix("webdav://").unwrap_or(uri))
+    }
+}
+
+/// `https://host:port` of `url`.
+fn url_origin(url: &str) -> &str {
+    let after_scheme = url.find("://").map_or(0, |i| i + 3);
+    match url[after_scheme..].find('/') {
+        Some(i) => &url[..after_scheme + i],
+        None => url,
+    }
+}
+
+fn url_path(url: &str) -> &str {
+    &url[url_origin(url).len()..]
+}
+
+/// `href` and whether it is a collection, for every `response` of a
+/// PROPFIND multistatus body.
+fn list_entries(xml: &str) -> Vec<(String, bool)> {
+    xml.split("response>")
+        .filter_map(|response| {
+            let start = response.find("href>")? + "href>".len();
+            let end = start + response[start..].find('<')?;
+            let href = response[start..end].trim();
+            // Some servers answer with absolute URLs
+            let href = href.find("://").map_or(href, |_| url_path(href));
+            Some((href.to_string(), has_collection_element(response)))
+        })
+        .collect()
+}
+
+/// Whether a `response` holds a `<collection/>` element (under any
+/// namespace prefix), which its `resourcetype` has for a collection. Text
+/// such as a file name containing "collection" does not count.
+fn has_collection_element(response: &str) -> bool {
+    response.split('<').skip(1).any(|tag| {
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .next()
+            .unwrap_or_default();
+        name.rsplit(':').next() == Some("collection")
+    })
+}
+
+/// Local name of the listed entry `href`, when it is a direct child of the
+/// collection at `own_path`. Names that are not a plain file name once
+/// decoded, such as `..` or an encoded `/`, are refused so a listing cannot
+/// place files outside the download directory.
+fn entry_name(href: &str, own_path: &str) -> Option<String> {
+    let path = href.trim_end_matches('/');
+    let parent = own_path.trim_end_matches('/');
+    let (dir, name) = path.rsplit_once('/')?;
+    // Servers may encode the same path differently from our request
+    if percent_decode(dir)? != percent_decode(parent)? {
+        return None;
+    }
+    let name = percent_decode(name)?;
+    let plain = !matches!(name.as_str(), "" | "." | "..") && !name.contains(['/', '\\', '\0']);
+    plain.then_some(name)
+}
+
+fn percent_encode(name: &str) -> String {
+    let mut encoded = String::new();
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_origin_splits_off_the_path() {
+        assert_eq!(
+            url_origin("https://dav.example.com/a/b.pdf"),
+            "https://dav.example.com"
+        );
+        assert_eq!(
+            url_origin("https://dav.example.com"),
+            "https://dav.example.com"
+        );
+        assert_eq!(url_path("https://dav.example.com/a/b.pdf"), "/a/b.pdf");
+        assert_eq!(url_path("https://dav.example.com"), "");
+    }
+
+    #[test]
+    fn list_entries_reads_hrefs_and_collections() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response>
+    <d:href>/dav/scans/</d:href>
+    <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>https://dav.example.com/dav/scans/my-collection.pdf</d:href>
+    <d:propstat><d:prop><d:displayname>collection</d:displayname><d:resourcetype/></d:prop></d:propstat>
+  </d:response>
+  <D:response>
+    <D:href>/dav/scans/sub/</D:href>
+    <D:propstat><D:prop><D:resourcetype><D:collection /></D:resourcetype></D:prop></D:propstat>
+  </D:response>
+</d:multistatus>"#;
+        assert_eq!(
+            list_entries(xml),
+            vec![
+                ("/dav/scans/".to_string(), true),
+                ("/dav/scans/my-collection.pdf".to_string(), false),
+                ("/dav/scans/sub/".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn entry_name_keeps_plain_children() {
+        assert_eq!(
+            entry_name("/dav/scans/a%20b.pdf", "/dav/scans/"),
+            Some("a b.pdf".into())
+        );
+        assert_eq!(
+            entry_name("/dav/scans/sub/", "/dav/scans/"),
+            Some("sub".into())
+        );
+        assert_eq!(
+            entry_name("/dav/my%20scans/a.pdf", "/dav/my scans/"),
+            Some("a.pdf".into())
+        );
+        // The collection itself
+        assert_eq!(entry_name("/dav/scans/", "/dav/scans/"), None);
+    }
+
+    #[test]
+    fn entry_name_cannot_leave_the_directory() {
+        for href in [
+            "/dav/scans/..",
+            "/dav/scans/.",
+            "/dav/scans/..%2F..%2Fetc%2Fpasswd",
+            "/dav/scans/%2E%2E",
+            "/dav/scans/a%5Cb.pdf",
+            "/dav/scans/a%00.pdf",
+            "/dav/other/a.pdf",
+            "/dav/scans/sub/a.pdf",
+        ] {
+            assert_eq!(entry_name(href, "/dav/scans/"), None, "{href}");
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Job webhooks - tells an external system that a background job finished
+//!
+//! A `process_pdf` job started with `webhook_url` POSTs a JSON summary of
+//! the job (status, outputs and timings) to that URL once it succeeds,
+//! fails or is cancelled. Requests go through `curl` and are retried a few
+//! times; a webhook that cannot be delivered is logged and does not change
+//! the job's outcome.
+
+use serde_json::Value;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::info;
+use tracing::warn;
+
+use super::error::ToolError;
+use super::error::ToolResult;
+use super::subprocess::inherit_output;
+use super::subprocess::run_command_with_stdin;
+
+/// Delivery attempts before giving up
+const ATTEMPTS: u32 = 3;
+/// Seconds one attempt may take
+const TIMEOUT_SECS: &str = "30";
+
+/// Reject a `webhook_url` that is not an HTTP(S) URL with a host.
+pub(super) fn check_webhook_url(url: &str) -> ToolResult<()> {
+    if url_host(url).is_some() {
+        return Ok(());
+    }
+    Err(ToolError::ValidationFailed {
+        argument: "webhook_url".to_string(),
+        reason: format!("{url} is not an http:// or https:// URL with a host"),
+    })
+}
+
+/// Host of an `http://` or `https://` URL, or `None` for anything else: other
+/// schemes, an empty host or port, or whitespace and control characters
+/// that curl would not read the way we do.
+fn url_host(url: &str) -> Option<&str> {
+    if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return None;
+    }
+    let (scheme, rest) = url.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let (host, port) = match host_port.strip_prefix('[') {
+        // IPv6 literal
+        Some(literal) => {
+            let (host, after) = literal.split_once(']')?;
+            (host, after.strip_prefix(':'))
+        }
+        None => match host_port.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        },
+    };
+    if port.is_some_and(|port| port.parse::<u16>().is_err()) {
+        return None;
+    }
+    (!host.is_empty()).then_some(host)
+}
+
+/// POST `summary` to `url` in the background.
+pub fn post_job_webhook(url: String, summary: Value) {
+    tokio::spawn(inherit_output(async move {
+        let body = summary.to_string().into_bytes();
+        for attempt in 1..=ATTEMPTS {
+            match post(&url, body.clone()).await {
+                Ok(()) => {
+                    info!("Delivered job webhook to {url}");
+                    return;
+                }
+                Err(e) if attempt < ATTEMPTS => {
+                    warn!("Job webhook to {url} failed (attempt {attempt}): {e}");
+                    tokio::time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
+                }
+                Err(e) => warn!("Giving up on job webhook to {url}: {e}"),
+            }
+        }
+    }));
+}
+
+async fn post(url: &str, body: Vec<u8>) -> Result<(), String> {
+    let mut cmd = Command::new("curl");
+    cmd.args([
+        "--silent",
+        "--show-error",
+        "--fail",
+        // The URL is used as given, and redirects stay on HTTP(S)
+        "--globoff",
+        "--proto",
+        "=http,https",
+        "--proto-redir",
+        "=http,https",
+        "--max-time",
+        TIMEOUT_SECS,
+        "--header",
+        "Content-Type: application/json",
+        "--data-binary",
+        "@-",
+        "--url",
+        url,
+    ])
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped());
+    let output = run_command_with_stdin(&mut cmd, body)
+        .await
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_host_accepts_http_urls() {
+        assert_eq!(
+            url_host("https://hooks.example.com/jobs?x=1"),
+            Some("hooks.example.com")
+        );
+        assert_eq!(url_host("HTTP://10.0.0.5:8080"), Some("10.0.0.5"));
+        assert_eq!(url_host("http://user:pw@example.com/"), Some("example.com"));
+        assert_eq!(url_host("http://[::1]:9000/hook"), Some("::1"));
+    }
+
+    #[test]
+    fn url_host_rejects_other_urls() {
+        for url in [
+            "file:///etc/passwd",
+            "ftp://example.com/",
+            "http://",
+            "https:///path",
+            "http://:8080/",
+            "http://example.com:port/",
+            "http://example.com:99999/",
+            "http://[::1/",
+            "https://example.com/a b",
+            "https://example.com/\n-o /tmp/x",
+            "example.com",
+        ] {
+            assert_eq!(url_host(url), None, "{url}");
+        }
+    }
+}
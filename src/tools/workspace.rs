@@ -0,0 +1,220 @@
+//! Scratch workspace - one server-owned directory for intermediate files
+//!
+//! Every intermediate directory (rendered pages, inline input, previews,
+//! diffs, ZIP work dirs) is created under a single workspace root rather
+//! than next to the user's files. Entries older than the TTL are deleted at
+//! startup and then once an hour.
+//!
+//! With a private workspace the root and every directory in it are created
+//! owner-only (0700 on Unix) and named with a random suffix instead of the
+//! pid and time, so other local users can neither list nor guess the pages
+//! of a document being processed. The files themselves are not encrypted;
+//! put the workspace on an encrypted volume for that.
+
+use anyhow::Result;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tracing::info;
+use tracing::warn;
+
+use super::manifest::forget_entries;
+use super::manifest::record_intermediate_dir;
+use super::page_cache::page_cache_dir;
+use crate::config::config;
+
+/// Default for `WATERMARK_TEMP_TTL_HOURS`
+const DEFAULT_TEMP_TTL_HOURS: f64 = 24.0;
+
+/// How often the background task looks for expired entries
+const GC_INTERVAL: Duration = Duration::from_secs(3600);
+
+static DIR_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// `WATERMARK_WORKSPACE_DIR`, the config file's `workspace_dir`, or
+/// `watermark-remover-workspace` under the system temp dir.
+pub fn workspace_root() -> PathBuf {
+    std::env::var_os("WATERMARK_WORKSPACE_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config().workspace_dir.clone())
+        .unwrap_or_else(|| std::env::temp_dir().join("watermark-remover-workspace"))
+}
+
+/// Age after which workspace entries are deleted, from
+/// `WATERMARK_TEMP_TTL_HOURS` or the config file's `temp_ttl_hours`;
+/// `0` keeps them until `cleanup_temp` is called.
+pub fn temp_ttl() -> Option<Duration> {
+    let hours = std::env::var("WATERMARK_TEMP_TTL_HOURS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .or(config().temp_ttl_hours)
+        .unwrap_or(DEFAULT_TEMP_TTL_HOURS);
+    (hours > 0.0).then(|| Duration::from_secs_f64(hours * 3600.0))
+}
+
+/// `WATERMARK_PRIVATE_WORKSPACE`, then the config file's
+/// `private_workspace`.
+pub fn private_workspace() -> bool {
+    match std::env::var("WATERMARK_PRIVATE_WORKSPACE") {
+        Ok(value) if !value.trim().is_empty() => !matches!(value.trim(), "0" | "false" | "off"),
+        _ => config().private_workspace.unwrap_or(false),
+    }
+}
+
+/// Create a fresh directory in the workspace named
+/// `<purpose>_<pid>_<nanos>_<seq>`, or `<purpose>_<random>` in a private
+/// workspace, and record it in the intermediate directory manifest.
+pub async fn create_temp_dir(purpose: &str) -> Result<PathBuf> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let seq = DIR_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let root = workspace_root();
+    let dir = if private_workspace() {
+        let dir = root.join(format!("{purpose}_{}", random_suffix(nanos, seq)));
+        create_private_dir(&root)?;
+        create_private_dir(&dir)?;
+        dir
+    } else {
+        let dir = root.join(format!("{purpose}_{}_{nanos}_{seq}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await?;
+        dir
+    };
+    record_intermediate_dir(&dir, purpose).await;
+    Ok(dir)
+}
+
+/// Create `dir` (and its parents) readable only by the server's user, and
+/// tighten it if it already exists with looser permissions.
+pub(super) fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        use std::os::unix::fs::PermissionsExt;
+
+        builder.mode(0o700).create(dir)?;
+        if std::fs::metadata(dir)?.permissions().mode() & 0o077 != 0 {
+            std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+        }
+    }
+    #[cfg(not(unix))]
+    builder.create(dir)?;
+    Ok(())
+}
+
+/// 32 hex digits from the OS random source, or from std's randomly keyed
+/// hasher where there is none.
+fn random_suffix(nanos: u128, seq: u64) -> String {
+    use std::hash::BuildHasher;
+    use std::io::Read;
+
+    let mut bytes = [0u8; 16];
+    let from_os = std::fs::File::open("/dev/urandom")
+        .and_then(|mut random| random.read_exact(&mut bytes))
+        .is_ok();
+    if !from_os {
+        let state = std::collections::hash_map::RandomState::new();
+        bytes[..8].copy_from_slice(&state.hash_one((nanos, seq)).to_le_bytes());
+        bytes[8..].copy_from_slice(
+            &state
+                .hash_one((seq, nanos, std::process::id()))
+                .to_le_bytes(),
+        );
+    }
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Delete a directory made by [`create_temp_dir`] and drop it from the
+/// manifest. Failures are logged: a leftover directory is collected later.
+pub async fn remove_temp_dir(dir: &Path) {
+    if let Err(e) = tokio::fs::remove_dir_all(dir).await
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        warn!("Failed to remove {}: {e}", dir.display());
+    }
+    if let Ok(dir) = std::path::absolute(dir)
+        && let Err(e) = forget_entries(&[dir]).await
+    {
+        warn!("Failed to update the manifest: {e:#}");
+    }
+}
+
+/// Delete workspace entries and cached pages last modified more than
+/// [`temp_ttl`] ago. Returns the number of entries removed.
+pub async fn collect_garbage() -> Result<usize> {
+    let Some(ttl) = temp_ttl() else {
+        return Ok(0);
+    };
+    let mut removed = expire_entries(&workspace_root(), ttl).await?;
+    if let Some(cache_dir) = page_cache_dir() {
+        removed += expire_entries(&cache_dir, ttl).await?;
+    }
+    Ok(removed)
+}
+
+async fn expire_entries(root: &Path, ttl: Duration) -> Result<usize> {
+    let mut entries = match tokio::fs::read_dir(root).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+
+    let now = SystemTime::now();
+    let mut removed = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let Ok(meta) = entry.metadata().await else {
+            continue;
+        };
+        let age = meta
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+        if age < ttl {
+            continue;
+        }
+
+        let path = entry.path();
+        let result = if meta.is_dir() {
+            tokio::fs::remove_dir_all(&path).await
+        } else {
+            tokio::fs::remove_file(&path).await
+        };
+        match result {
+            Ok(()) => removed.push(path),
+            Err(e) => warn!("Failed to delete expired {}: {e}", path.display()),
+        }
+    }
+
+    if !removed.is_empty() {
+        forget_entries(&removed).await?;
+        info!(
+            "Deleted {} expired entries from {}",
+            removed.len(),
+            root.display()
+        );
+    }
+    Ok(removed.len())
+}
+
+/// Run [`collect_garbage`] now and then every [`GC_INTERVAL`] in the
+/// background.
+pub fn spawn_workspace_gc() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(GC_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = collect_garbage().await {
+                warn!("Workspace cleanup failed: {e:#}");
+            }
+        }
+    });
+}
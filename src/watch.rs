@@ -0,0 +1,274 @@
+//! Folder watch subsystem - polls directories and processes new PDFs
+//!
+//! A watch is a spawned task that scans one directory on an interval. Each
+//! new PDF is handed to the watch's processing callback once its size has
+//! stopped changing, and the outcome is reported to the client as a
+//! `notifications/message` log notification.
+
+use anyhow::Result;
+use mcp_types::LoggingLevel;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tokio::task::AbortHandle;
+use tracing::Instrument;
+use tracing::error_span;
+use tracing::info;
+use tracing::warn;
+
+use crate::message_processor::NotificationSender;
+use crate::tools::forward_output;
+
+/// Snapshot of a watch as reported by `start_watch` / `stop_watch`.
+#[derive(Clone, Serialize)]
+pub struct WatchInfo {
+    pub watch_id: String,
+    pub dir: PathBuf,
+    pub output_dir: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    pub interval_secs: u64,
+    pub started_at: u64,
+    pub processed: usize,
+    pub failed: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_file: Option<PathBuf>,
+}
+
+struct Watch {
+    info: WatchInfo,
+    abort: Option<AbortHandle>,
+}
+
+/// What to watch, as given to [`WatchManager::start`].
+pub struct WatchConfig {
+    pub dir: PathBuf,
+    pub output_dir: PathBuf,
+    pub profile: Option<String>,
+    pub interval: Duration,
+    /// Also process PDFs already present when the watch starts
+    pub process_existing: bool,
+}
+
+/// Shared table of watches. Cloning yields another handle to the same table.
+#[derive(Clone)]
+pub struct WatchManager {
+    watches: Arc<Mutex<HashMap<String, Watch>>>,
+    notifier: NotificationSender,
+}
+
+static WATCH_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+impl WatchManager {
+    pub fn new(notifier: NotificationSender) -> Self {
+        Self {
+            watches: Arc::default(),
+            notifier,
+        }
+    }
+
+    /// Start watching `config.dir` and return the watch id.
+    ///
+    /// `process` is called once per new PDF and returns a short description
+    /// of the output (usually its path) on success.
+    pub fn start<F, Fut>(&self, config: WatchConfig, process: F) -> String
+    where
+        F: Fn(PathBuf) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String>> + Send + 'static,
+    {
+        let watch_id = format!(
+            "watch_{:x}_{}",
+            unix_now(),
+            WATCH_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+
+        self.lock().insert(
+            watch_id.clone(),
+            Watch {
+                info: WatchInfo {
+                    watch_id: watch_id.clone(),
+                    dir: config.dir.clone(),
+                    output_dir: config.output_dir.clone(),
+                    profile: config.profile.clone(),
+                    interval_secs: config.interval.as_secs(),
+                    started_at: unix_now(),
+                    processed: 0,
+                    failed: 0,
+                    last_file: None,
+                },
+                abort: None,
+            },
+        );
+
+        // A watch outlives the request that started it, so its span is a root
+        let span = error_span!(parent: None, "watch", watch_id = %watch_id);
+        let handle = tokio::spawn(
+            forward_output(
+                self.notifier.clone(),
+                run_watch(watch_id.clone(), config, process, self.clone()),
+            )
+            .instrument(span),
+        );
+
+        if let Some(watch) = self.lock().get_mut(&watch_id) {
+            watch.abort = Some(handle.abort_handle());
+        }
+
+        info!("Started {watch_id}");
+        watch_id
+    }
+
+    /// Stop a watch and return its final state, or `None` if the id is unknown.
+    pub fn stop(&self, watch_id: &str) -> Option<WatchInfo> {
+        let watch = self.lock().remove(watch_id)?;
+        if let Some(abort) = watch.abort {
+            abort.abort();
+        }
+        info!("Stopped {watch_id}");
+        Some(watch.info)
+    }
+
+    /// Stop every watch, e.g. when the session that owns them ends.
+    pub fn stop_all(&self) {
+        let ids: Vec<String> = self.lock().keys().cloned().collect();
+        for watch_id in ids {
+            self.stop(&watch_id);
+        }
+    }
+
+    pub fn status(&self, watch_id: &str) -> Option<WatchInfo> {
+        self.lock().get(watch_id).map(|w| w.info.clone())
+    }
+
+    pub fn list(&self) -> Vec<WatchInfo> {
+        let mut watches: Vec<WatchInfo> = self.lock().values().map(|w| w.info.clone()).collect();
+        watches.sort_by(|a, b| a.watch_id.cmp(&b.watch_id));
+        watches
+    }
+
+    fn record(&self, watch_id: &str, file: &Path, ok: bool) {
+        if let Some(watch) = self.lock().get_mut(watch_id) {
+            if ok {
+                watch.info.processed += 1;
+            } else {
+                watch.info.failed += 1;
+            }
+            watch.info.last_file = Some(file.to_path_buf());
+        }
+    }
+
+    fn notify(&self, level: LoggingLevel, data: serde_json::Value) {
+        self.notifier.log(level, "watch", data);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Watch>> {
+        self.watches
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+async fn run_watch<F, Fut>(watch_id: String, config: WatchConfig, process: F, manager: WatchManager)
+where
+    F: Fn(PathBuf) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let mut handled: HashSet<PathBuf> = HashSet::new();
+    if !config.process_existing {
+        handled.extend(scan_pdfs(&config.dir).await.into_keys());
+    }
+
+    // Size seen on the previous scan; a file is only picked up once two
+    // consecutive scans agree, so half-copied PDFs are left alone
+    let mut pending: HashMap<PathBuf, u64> = HashMap::new();
+    let mut interval = tokio::time::interval(config.interval);
+
+    loop {
+        interval.tick().await;
+
+        let current = scan_pdfs(&config.dir).await;
+        pending.retain(|path, _| current.contains_key(path));
+
+        for (path, size) in current {
+            if handled.contains(&path) {
+                continue;
+            }
+            match pending.insert(path.clone(), size) {
+                Some(previous) if previous == size && size > 0 => {}
+                _ => continue,
+            }
+            pending.remove(&path);
+            handled.insert(path.clone());
+
+            info!("{watch_id}: processing {}", path.display());
+            match process(path.clone()).await {
+                Ok(output) => {
+                    manager.record(&watch_id, &path, true);
+                    manager.notify(
+                        LoggingLevel::Info,
+                        json!({
+                            "watch_id": watch_id,
+                            "file": path,
+                            "status": "succeeded",
+                            "output": output,
+                        }),
+                    );
+                }
+                Err(e) => {
+                    warn!("{watch_id}: failed to process {}: {e}", path.display());
+                    manager.record(&watch_id, &path, false);
+                    manager.notify(
+                        LoggingLevel::Error,
+                        json!({
+                            "watch_id": watch_id,
+                            "file": path,
+                            "status": "failed",
+                            "error": e.to_string(),
+                        }),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// PDFs directly inside `dir`, with their sizes.
+async fn scan_pdfs(dir: &Path) -> HashMap<PathBuf, u64> {
+    let mut pdfs = HashMap::new();
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return pdfs;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        let is_pdf = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"));
+        if !is_pdf {
+            continue;
+        }
+        if let Ok(meta) = entry.metadata().await
+            && meta.is_file()
+        {
+            pdfs.insert(path, meta.len());
+        }
+    }
+    pdfs
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}